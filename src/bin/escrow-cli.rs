@@ -0,0 +1,913 @@
+//! `escrow-cli`, lists, buys and cancels NFT listings and manages the platform
+//! account from the command line. Built with the `cli` feature.
+
+use clap::{crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use escrow_buy::{
+    client::EscrowClient,
+    events::parse_logs,
+    instruction::ExchangeOptionalAccounts,
+    payout::Payout,
+    price_feed::USD_PRICE_DECIMALS,
+    state::{EditionDrop, Escrow, Marketplace, VaultAccount},
+};
+use solana_clap_utils::{
+    input_parsers::{pubkey_of, value_of},
+    input_validators::{is_parsable, is_url_or_moniker, is_valid_pubkey, normalize_to_url_if_moniker},
+};
+use solana_cli_config::{Config, CONFIG_FILE};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use std::{error::Error, process::exit};
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+struct CliConfig {
+    client: EscrowClient,
+    keypair_path: String,
+    dry_run: bool,
+}
+
+impl CliConfig {
+    /// Reads the signer's keypair, only the commands sending a transaction need it.
+    fn keypair(&self) -> Result<Keypair, Box<dyn Error>> {
+        read_keypair_file(&self.keypair_path)
+            .map_err(|err| format!("unable to read keypair {}: {}", self.keypair_path, err).into())
+    }
+}
+
+fn pubkey_arg<'a, 'b>(name: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(name)
+        .value_name("PUBKEY")
+        .takes_value(true)
+        .validator(is_valid_pubkey)
+        .help(help)
+}
+
+fn escrow_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("escrow")
+        .value_name("ESCROW_ACCOUNT")
+        .required(true)
+        .validator(is_valid_pubkey)
+        .help("Escrow account of the listing")
+}
+
+fn drop_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("drop")
+        .value_name("DROP_ACCOUNT")
+        .required(true)
+        .validator(is_valid_pubkey)
+        .help("Account of the edition drop")
+}
+
+fn platform_account_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("platform_account")
+        .value_name("PLATFORM_ACCOUNT")
+        .required(true)
+        .validator(is_valid_pubkey)
+        .help("Platform account holding the treasury and the platform fee")
+}
+
+fn app<'a, 'b>(default_url: &'a str, default_keypair: &'a str) -> App<'a, 'b> {
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .long("url")
+                .short("u")
+                .value_name("URL_OR_MONIKER")
+                .takes_value(true)
+                .global(true)
+                .default_value(default_url)
+                .validator(is_url_or_moniker)
+                .help("JSON RPC URL of the cluster, or a moniker such as localhost"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .short("k")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .global(true)
+                .default_value(default_keypair)
+                .help("Keypair file of the signer, who also pays the fees"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("PROGRAM_ID")
+                .takes_value(true)
+                .global(true)
+                .env("ESCROW_PROGRAM_ID")
+                .validator(is_valid_pubkey)
+                .help("Address the escrow program is deployed at"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .global(true)
+                .help("Simulate the transaction and print its logs instead of sending it"),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List an NFT for sale")
+                .arg(pubkey_arg("token-account", "Token account holding the NFT").required(true))
+                .arg(pubkey_arg("mint", "Mint of the NFT").required(true))
+                .arg(pubkey_arg("platform-account", "Platform account of the marketplace").required(true))
+                .arg(
+                    Arg::with_name("price")
+                        .long("price")
+                        .value_name("LAMPORTS")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(is_parsable::<u64>)
+                        .help("Price of the NFT in lamports"),
+                )
+                .arg(
+                    Arg::with_name("delegate")
+                        .long("delegate")
+                        .help("Keep the NFT in the token account and approve the program as its delegate"),
+                )
+                .arg(
+                    Arg::with_name("freeze")
+                        .long("freeze")
+                        .conflicts_with("delegate")
+                        .help("Like --delegate, and freeze the token account while listed, for mints the program can freeze"),
+                )
+                .arg(
+                    Arg::with_name("proceeds-vault")
+                        .long("proceeds-vault")
+                        .help("Credit the proceeds to the seller's proceeds account, to be withdrawn later"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("buy")
+                .about("Buy a listed NFT")
+                .arg(escrow_arg())
+                .arg(pubkey_arg(
+                    "platform-account",
+                    "Platform account of the marketplace, required for listings that do not record it",
+                ))
+                .arg(pubkey_arg(
+                    "pass-token-account",
+                    "Token account holding a membership pass, for the discounted platform fee",
+                ))
+                .arg(pubkey_arg("referrer", "Partner that referred the sale, paid a share of the platform fee")),
+        )
+        .subcommand(
+            SubCommand::with_name("cancel")
+                .about("Cancel a listing and take the NFT back")
+                .arg(escrow_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("show-listing")
+                .about("Show the state of a listing")
+                .arg(escrow_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("list-listings")
+                .about("Show the open listings")
+                .arg(pubkey_arg("seller", "Only show the listings of this seller"))
+                .arg(pubkey_arg("mint", "Only show the listings of this mint"))
+                .arg(pubkey_arg("marketplace", "Only show the listings made on this platform account")),
+        )
+        .subcommand(
+            SubCommand::with_name("show-referrer")
+                .about("Show the earnings of a referrer")
+                .arg(
+                    Arg::with_name("referrer")
+                        .value_name("REFERRER")
+                        .required(true)
+                        .validator(is_valid_pubkey)
+                        .help("Referrer address"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show-royalties")
+                .about("Show the royalties held for a creator")
+                .arg(
+                    Arg::with_name("creator")
+                        .value_name("CREATOR")
+                        .required(true)
+                        .validator(is_valid_pubkey)
+                        .help("Creator address"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("claim-royalties")
+                .about("Claim the royalties held for the signer")
+                .arg(pubkey_arg("destination", "Account receiving the royalties, the signer by default")),
+        )
+        .subcommand(
+            SubCommand::with_name("show-proceeds")
+                .about("Show the proceeds credited to a seller")
+                .arg(
+                    Arg::with_name("seller")
+                        .value_name("SELLER")
+                        .required(true)
+                        .validator(is_valid_pubkey)
+                        .help("Seller address"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-proceeds")
+                .about("Withdraw the proceeds credited to the signer")
+                .arg(pubkey_arg("destination", "Account receiving the proceeds, the signer by default")),
+        )
+        .subcommand(
+            SubCommand::with_name("register-referrer")
+                .about("Register the signer as a referrer, so that referred sales pay it"),
+        )
+        .subcommand(
+            SubCommand::with_name("drop")
+                .about("Print edition drop operations")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("create")
+                        .about("Sell limited prints of a master edition, escrowing its token")
+                        .arg(pubkey_arg("token-account", "Token account holding the master edition token").required(true))
+                        .arg(pubkey_arg("mint", "Mint of the master edition").required(true))
+                        .arg(pubkey_arg("platform-account", "Platform account of the marketplace").required(true))
+                        .arg(
+                            Arg::with_name("price")
+                                .long("price")
+                                .value_name("LAMPORTS")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Price of one print in lamports"),
+                        )
+                        .arg(
+                            Arg::with_name("max_prints")
+                                .long("max-prints")
+                                .value_name("COUNT")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Number of prints for sale"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("buy")
+                        .about("Buy the next print of a drop, minted to a new mint")
+                        .arg(drop_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("cancel")
+                        .about("End a drop and take the master edition token back")
+                        .arg(drop_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show the state of a drop")
+                        .arg(drop_arg()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("platform")
+                .about("Platform account operations")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show the state of a platform account")
+                        .arg(platform_account_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("create-marketplace")
+                        .about("Create a marketplace with its own authority, treasury and fee")
+                        .arg(
+                            Arg::with_name("name")
+                                .long("name")
+                                .value_name("NAME")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Name of the marketplace, at most 32 bytes, unique per creator"),
+                        )
+                        .arg(pubkey_arg("treasury", "Account receiving the platform fee").required(true))
+                        .arg(pubkey_arg(
+                            "authority",
+                            "Authority changing the marketplace settings [default: the signer]",
+                        ))
+                        .arg(
+                            Arg::with_name("fee_bps")
+                                .long("fee-bps")
+                                .value_name("BASIS_POINTS")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Platform fee in basis points of the price"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set-fee")
+                        .about("Set the treasury and the fee of a platform account, signed by the admin")
+                        .arg(platform_account_arg())
+                        .arg(pubkey_arg("treasury", "Account receiving the platform fee").required(true))
+                        .arg(
+                            Arg::with_name("fee_bps")
+                                .long("fee-bps")
+                                .value_name("BASIS_POINTS")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Platform fee in basis points of the price"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set-pass-discount")
+                        .about("Set the fee paid by membership pass holders, signed by the admin")
+                        .arg(platform_account_arg())
+                        .arg(
+                            pubkey_arg("pass-collection", "Verified creator of the membership pass NFTs")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("fee_bps")
+                                .long("fee-bps")
+                                .value_name("BASIS_POINTS")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Platform fee of pass holders in basis points of the price"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set-referral-share")
+                        .about("Set the share of the platform fee paid to referrers, signed by the admin")
+                        .arg(platform_account_arg())
+                        .arg(
+                            Arg::with_name("share_bps")
+                                .long("share-bps")
+                                .value_name("BASIS_POINTS")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u64>)
+                                .help("Referrer share in basis points of the platform fee"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("pause")
+                        .about("Stop listing and buying on a platform account, signed by the admin")
+                        .arg(platform_account_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("resume")
+                        .about("Resume listing and buying on a paused platform account, signed by the admin")
+                        .arg(platform_account_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("escrow-royalties")
+                        .about("Hold the royalties of every sale in the creators' vaults, signed by the admin")
+                        .arg(platform_account_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("pay-royalties")
+                        .about("Pay royalties to the creators that can take them directly, signed by the admin")
+                        .arg(platform_account_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("force-delist")
+                        .about("Take a listing down and hand the NFT back to its seller, signed by the admin")
+                        .arg(escrow_arg())
+                        .arg(
+                            Arg::with_name("reason")
+                                .long("reason")
+                                .value_name("CODE")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<u8>)
+                                .help("Moderation reason code recorded in the event"),
+                        ),
+                ),
+        )
+}
+
+fn main() {
+    let cli_config = CONFIG_FILE
+        .as_ref()
+        .and_then(|config_file| Config::load(config_file).ok())
+        .unwrap_or_default();
+    let matches = app(&cli_config.json_rpc_url, &cli_config.keypair_path).get_matches();
+
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> CliResult {
+    let program_id = pubkey_of(matches, "program_id")
+        .ok_or("the program id is required, pass --program-id or set ESCROW_PROGRAM_ID")?;
+    let url = normalize_to_url_if_moniker(matches.value_of("url").unwrap());
+
+    let config = CliConfig {
+        client: EscrowClient::new(url, program_id),
+        keypair_path: matches.value_of("keypair").unwrap().to_string(),
+        dry_run: matches.is_present("dry_run"),
+    };
+
+    match matches.subcommand() {
+        ("list", Some(matches)) => command_list(&config, matches),
+        ("buy", Some(matches)) => command_buy(&config, matches),
+        ("cancel", Some(matches)) => command_cancel(&config, matches),
+        ("show-listing", Some(matches)) => command_show_listing(&config, matches),
+        ("list-listings", Some(matches)) => command_list_listings(&config, matches),
+        ("show-referrer", Some(matches)) => command_show_referrer(&config, matches),
+        ("show-royalties", Some(matches)) => command_show_royalties(&config, matches),
+        ("claim-royalties", Some(matches)) => command_claim_royalties(&config, matches),
+        ("show-proceeds", Some(matches)) => command_show_proceeds(&config, matches),
+        ("withdraw-proceeds", Some(matches)) => command_withdraw_proceeds(&config, matches),
+        ("register-referrer", Some(_)) => command_register_referrer(&config),
+        ("drop", Some(matches)) => match matches.subcommand() {
+            ("create", Some(matches)) => command_drop_create(&config, matches),
+            ("buy", Some(matches)) => command_drop_buy(&config, matches),
+            ("cancel", Some(matches)) => command_drop_cancel(&config, matches),
+            ("show", Some(matches)) => command_drop_show(&config, matches),
+            _ => unreachable!(),
+        },
+        ("platform", Some(matches)) => match matches.subcommand() {
+            ("show", Some(matches)) => command_platform_show(&config, matches),
+            ("create-marketplace", Some(matches)) => command_platform_create_marketplace(&config, matches),
+            ("set-fee", Some(matches)) => command_platform_set_fee(&config, matches),
+            ("set-pass-discount", Some(matches)) => command_platform_set_pass_discount(&config, matches),
+            ("set-referral-share", Some(matches)) => command_platform_set_referral_share(&config, matches),
+            ("pause", Some(matches)) => command_platform_set_paused(&config, matches, true),
+            ("resume", Some(matches)) => command_platform_set_paused(&config, matches, false),
+            ("escrow-royalties", Some(matches)) => command_platform_set_escrow_royalties(&config, matches, true),
+            ("pay-royalties", Some(matches)) => command_platform_set_escrow_royalties(&config, matches, false),
+            ("force-delist", Some(matches)) => command_platform_force_delist(&config, matches),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn command_list(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let token_account = pubkey_of(matches, "token-account").unwrap();
+    let mint = pubkey_of(matches, "mint").unwrap();
+    let platform_address = pubkey_of(matches, "platform-account").unwrap();
+    let price: u64 = value_of(matches, "price").unwrap();
+    let keypair = config.keypair()?;
+    let escrow_account = Keypair::new();
+
+    println!("Listing {} for {} lamports", mint, price);
+    println!("Escrow account: {}", escrow_account.pubkey());
+    let list_token_transaction = if matches.is_present("freeze") {
+        EscrowClient::list_token_frozen_transaction
+    } else if matches.is_present("delegate") {
+        EscrowClient::list_token_delegated_transaction
+    } else {
+        EscrowClient::list_token_transaction
+    };
+    let transaction = list_token_transaction(
+        &config.client,
+        &keypair,
+        &token_account,
+        &mint,
+        &escrow_account,
+        &platform_address,
+        price,
+        matches.is_present("proceeds-vault"),
+    )?;
+    send(config, &transaction)
+}
+
+fn command_buy(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let escrow_address = pubkey_of(matches, "escrow").unwrap();
+    let keypair = config.keypair()?;
+    let membership_pass = pubkey_of(matches, "pass-token-account")
+        .map(|token_account| config.client.get_membership_pass(&token_account))
+        .transpose()?;
+    let referrer = pubkey_of(matches, "referrer");
+
+    let escrow = config.client.get_escrow(&escrow_address)?;
+    print_escrow(&escrow_address, &escrow);
+    if !escrow.is_initialized {
+        return Err("the listing is closed".into());
+    }
+    let platform_address = match pubkey_of(matches, "platform-account") {
+        Some(platform_address) => platform_address,
+        None if escrow.marketplace != Pubkey::default() => escrow.marketplace,
+        None => return Err("the listing does not record its platform account, pass --platform-account".into()),
+    };
+    let platform = config.client.get_platform_account(&platform_address)?;
+    print_platform_account(&platform_address, &platform);
+    if escrow.usd_priced {
+        println!("  Price now: {} lamports", config.client.get_price(&escrow_address, &escrow)?);
+    }
+    let payout = config.client.get_payout(&escrow_address, &escrow, &platform, membership_pass.is_some())?;
+    print_payout(&payout);
+    if let Some(referrer) = referrer {
+        let referral_fee = platform.referral_fee(payout.platform_fee).ok_or("invalid referral share")?;
+        println!("  Referrer {}: {} lamports of the platform fee", referrer, referral_fee);
+    }
+
+    let transaction = config.client.exchange_transaction(
+        &keypair,
+        &escrow_address,
+        &platform_address,
+        ExchangeOptionalAccounts {
+            membership_pass,
+            referrer,
+            ..ExchangeOptionalAccounts::default()
+        },
+    )?;
+    send(config, &transaction)
+}
+
+fn command_cancel(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let escrow_address = pubkey_of(matches, "escrow").unwrap();
+    let keypair = config.keypair()?;
+
+    let escrow = config.client.get_escrow(&escrow_address)?;
+    print_escrow(&escrow_address, &escrow);
+
+    let transaction = config.client.cancel_transaction(&keypair, &escrow_address)?;
+    send(config, &transaction)
+}
+
+fn command_show_listing(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let escrow_address = pubkey_of(matches, "escrow").unwrap();
+    print_escrow(&escrow_address, &config.client.get_escrow(&escrow_address)?);
+    Ok(())
+}
+
+fn command_list_listings(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let seller = pubkey_of(matches, "seller");
+    let mint = pubkey_of(matches, "mint");
+    let marketplace = pubkey_of(matches, "marketplace");
+
+    let listings = config
+        .client
+        .get_listings(seller.as_ref(), mint.as_ref(), marketplace.as_ref())?;
+    for (address, escrow) in &listings {
+        print_escrow(address, escrow);
+        println!();
+    }
+    println!("{} listings", listings.len());
+    Ok(())
+}
+
+fn command_show_referrer(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let referrer = pubkey_of(matches, "referrer").unwrap();
+    println!("Referrer: {}", referrer);
+    match config.client.get_referrer_account(&referrer)? {
+        Some(earnings) => {
+            println!("  Sales: {}", earnings.sales);
+            println!("  Earned: {} lamports", earnings.total_earned);
+        }
+        None => println!("  Not registered"),
+    }
+    Ok(())
+}
+
+fn command_show_royalties(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let creator = pubkey_of(matches, "creator").unwrap();
+    println!("Creator: {}", creator);
+    match config.client.get_royalty_vault(&creator)? {
+        Some(vault) => {
+            println!("  Sales: {}", vault.sales);
+            println!("  Deposited: {} lamports", vault.total_deposited);
+            println!("  Claimed: {} lamports", vault.total_claimed);
+            println!("  Unclaimed: {} lamports", vault.unclaimed());
+        }
+        None => println!("  No royalties held"),
+    }
+    Ok(())
+}
+
+fn command_claim_royalties(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let keypair = config.keypair()?;
+    let destination = pubkey_of(matches, "destination").unwrap_or_else(|| keypair.pubkey());
+
+    let vault = config
+        .client
+        .get_royalty_vault(&keypair.pubkey())?
+        .ok_or("no royalties are held for the signer")?;
+    println!("Claiming {} lamports to {}", vault.unclaimed(), destination);
+
+    let transaction = config.client.claim_royalties_transaction(&keypair, &destination)?;
+    send(config, &transaction)
+}
+
+fn command_show_proceeds(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let seller = pubkey_of(matches, "seller").unwrap();
+    println!("Seller: {}", seller);
+    match config.client.get_seller_proceeds(&seller)? {
+        Some(proceeds) => {
+            println!("  Sales: {}", proceeds.sales);
+            println!("  Credited: {} lamports", proceeds.total_credited);
+            println!("  Withdrawn: {} lamports", proceeds.total_withdrawn);
+            println!("  Balance: {} lamports", proceeds.balance());
+        }
+        None => println!("  No proceeds credited"),
+    }
+    Ok(())
+}
+
+fn command_withdraw_proceeds(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let keypair = config.keypair()?;
+    let destination = pubkey_of(matches, "destination").unwrap_or_else(|| keypair.pubkey());
+
+    let proceeds = config
+        .client
+        .get_seller_proceeds(&keypair.pubkey())?
+        .ok_or("no proceeds are credited to the signer")?;
+    println!("Withdrawing {} lamports to {}", proceeds.balance(), destination);
+
+    let transaction = config.client.withdraw_proceeds_transaction(&keypair, &destination)?;
+    send(config, &transaction)
+}
+
+fn command_register_referrer(config: &CliConfig) -> CliResult {
+    let keypair = config.keypair()?;
+    if config.client.get_referrer_account(&keypair.pubkey())?.is_some() {
+        return Err("the signer is already registered as a referrer".into());
+    }
+    println!("Registering referrer {}", keypair.pubkey());
+
+    let transaction = config.client.register_referrer_transaction(&keypair)?;
+    send(config, &transaction)
+}
+
+fn command_drop_create(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let token_account = pubkey_of(matches, "token-account").unwrap();
+    let mint = pubkey_of(matches, "mint").unwrap();
+    let platform_address = pubkey_of(matches, "platform-account").unwrap();
+    let price: u64 = value_of(matches, "price").unwrap();
+    let max_prints: u64 = value_of(matches, "max_prints").unwrap();
+    let keypair = config.keypair()?;
+    let drop_account = Keypair::new();
+
+    println!("Selling {} prints of {} for {} lamports each", max_prints, mint, price);
+    println!("Drop account: {}", drop_account.pubkey());
+    let transaction = config.client.list_edition_drop_transaction(
+        &keypair,
+        &token_account,
+        &mint,
+        &drop_account,
+        &platform_address,
+        price,
+        max_prints,
+    )?;
+    send(config, &transaction)
+}
+
+fn command_drop_buy(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let drop_address = pubkey_of(matches, "drop").unwrap();
+    let keypair = config.keypair()?;
+    let new_mint = Keypair::new();
+
+    let drop = config.client.get_edition_drop(&drop_address)?;
+    print_edition_drop(&drop_address, &drop);
+    if !drop.is_initialized {
+        return Err("the drop has ended".into());
+    }
+    println!("Print mint: {}", new_mint.pubkey());
+
+    let transaction = config.client.buy_edition_transaction(&keypair, &new_mint, &drop_address)?;
+    send(config, &transaction)
+}
+
+fn command_drop_cancel(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let drop_address = pubkey_of(matches, "drop").unwrap();
+    let keypair = config.keypair()?;
+
+    print_edition_drop(&drop_address, &config.client.get_edition_drop(&drop_address)?);
+
+    let transaction = config.client.cancel_edition_drop_transaction(&keypair, &drop_address)?;
+    send(config, &transaction)
+}
+
+fn command_drop_show(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let drop_address = pubkey_of(matches, "drop").unwrap();
+    print_edition_drop(&drop_address, &config.client.get_edition_drop(&drop_address)?);
+    Ok(())
+}
+
+fn command_platform_show(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    match config.client.get_marketplace(&platform_address)? {
+        Some(marketplace) => print_marketplace(&platform_address, &marketplace),
+        None => print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?),
+    }
+    Ok(())
+}
+
+fn command_platform_create_marketplace(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let name = matches.value_of("name").unwrap();
+    let treasury = pubkey_of(matches, "treasury").unwrap();
+    let fee_bps: u64 = value_of(matches, "fee_bps").unwrap();
+    let keypair = config.keypair()?;
+    let authority = pubkey_of(matches, "authority").unwrap_or_else(|| keypair.pubkey());
+
+    let (transaction, marketplace) = config.client.create_marketplace_transaction(
+        &keypair,
+        &authority,
+        &treasury,
+        name,
+        fee_bps,
+    )?;
+    println!("Creating marketplace {}", name);
+    println!("Marketplace account: {}", marketplace);
+    println!("  Authority: {}", authority);
+    println!("  Treasury: {}", treasury);
+    println!("  Fee: {} bps", fee_bps);
+    send(config, &transaction)
+}
+
+fn command_platform_set_fee(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    let treasury = pubkey_of(matches, "treasury").unwrap();
+    let fee_bps: u64 = value_of(matches, "fee_bps").unwrap();
+    let keypair = config.keypair()?;
+
+    print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?);
+    println!("New treasury: {}", treasury);
+    println!("New fee: {} bps", fee_bps);
+
+    let transaction = config.client.update_platform_account_transaction(
+        &keypair,
+        &platform_address,
+        &treasury,
+        fee_bps,
+    )?;
+    send(config, &transaction)
+}
+
+fn command_platform_set_pass_discount(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    let pass_collection = pubkey_of(matches, "pass-collection").unwrap();
+    let fee_bps: u64 = value_of(matches, "fee_bps").unwrap();
+    let keypair = config.keypair()?;
+
+    print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?);
+    println!("New pass collection: {}", pass_collection);
+    println!("New pass fee: {} bps", fee_bps);
+
+    let transaction = config.client.set_pass_discount_transaction(
+        &keypair,
+        &platform_address,
+        &pass_collection,
+        fee_bps,
+    )?;
+    send(config, &transaction)
+}
+
+fn command_platform_set_referral_share(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    let share_bps: u64 = value_of(matches, "share_bps").unwrap();
+    let keypair = config.keypair()?;
+
+    print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?);
+    println!("New referral share: {} bps of the fee", share_bps);
+
+    let transaction = config
+        .client
+        .set_referral_share_transaction(&keypair, &platform_address, share_bps)?;
+    send(config, &transaction)
+}
+
+fn command_platform_set_paused(config: &CliConfig, matches: &ArgMatches, paused: bool) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    let keypair = config.keypair()?;
+
+    print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?);
+    println!("{}", if paused { "Pausing" } else { "Resuming" });
+
+    let transaction = config
+        .client
+        .set_paused_transaction(&keypair, &platform_address, paused)?;
+    send(config, &transaction)
+}
+
+fn command_platform_set_escrow_royalties(config: &CliConfig, matches: &ArgMatches, escrow_royalties: bool) -> CliResult {
+    let platform_address = pubkey_of(matches, "platform_account").unwrap();
+    let keypair = config.keypair()?;
+
+    print_platform_account(&platform_address, &config.client.get_platform_account(&platform_address)?);
+    println!(
+        "{}",
+        if escrow_royalties {
+            "Holding all royalties in the creators' vaults"
+        } else {
+            "Paying royalties to the creators directly"
+        }
+    );
+
+    let transaction = config
+        .client
+        .set_escrow_royalties_transaction(&keypair, &platform_address, escrow_royalties)?;
+    send(config, &transaction)
+}
+
+fn command_platform_force_delist(config: &CliConfig, matches: &ArgMatches) -> CliResult {
+    let escrow_address = pubkey_of(matches, "escrow").unwrap();
+    let reason: u8 = value_of(matches, "reason").unwrap();
+    let keypair = config.keypair()?;
+
+    print_escrow(&escrow_address, &config.client.get_escrow(&escrow_address)?);
+    println!("Reason: {}", reason);
+
+    let transaction = config
+        .client
+        .force_delist_transaction(&keypair, &escrow_address, reason)?;
+    send(config, &transaction)
+}
+
+/// Sends `transaction`, or only simulates it with `--dry-run`.
+fn send(config: &CliConfig, transaction: &Transaction) -> CliResult {
+    if !config.dry_run {
+        let signature = config.client.send_and_confirm(transaction)?;
+        println!("Signature: {}", signature);
+        return Ok(());
+    }
+
+    let result = config.client.simulate(transaction)?;
+    let logs = result.logs.unwrap_or_default();
+    println!("Simulation logs:");
+    for line in &logs {
+        println!("  {}", line);
+    }
+    for event in parse_logs(config.client.program_id(), &logs) {
+        println!("Event: {:?}", event);
+    }
+    match result.err {
+        Some(err) => Err(format!("simulation failed: {}", err).into()),
+        None => {
+            println!("Simulation succeeded, nothing was sent");
+            Ok(())
+        }
+    }
+}
+
+fn print_escrow(address: &Pubkey, escrow: &Escrow) {
+    println!("Listing: {}", address);
+    println!("  Open: {}", escrow.is_initialized);
+    println!("  Seller: {}", escrow.seller_pubkey);
+    println!("  Token account: {}", escrow.token_account_pubkey);
+    println!("  Mint: {}", escrow.mint_key);
+    if escrow.usd_priced {
+        println!(
+            "  Price: {}.{:06} USD",
+            escrow.expected_amount / 10u64.pow(USD_PRICE_DECIMALS),
+            escrow.expected_amount % 10u64.pow(USD_PRICE_DECIMALS)
+        );
+    } else {
+        println!("  Price: {} lamports", escrow.expected_amount);
+    }
+    if escrow.marketplace != Pubkey::default() {
+        println!("  Marketplace: {}", escrow.marketplace);
+    }
+    println!("  Listing mode: {:?}", escrow.listing_mode);
+    println!("  Token program: {:?}", escrow.token_program);
+    if escrow.proceeds_vault {
+        println!("  Proceeds credited to the seller's proceeds account");
+    }
+}
+
+fn print_edition_drop(address: &Pubkey, drop: &EditionDrop) {
+    println!("Drop: {}", address);
+    println!("  Open: {}", drop.is_initialized);
+    println!("  Creator: {}", drop.creator);
+    println!("  Master edition token account: {}", drop.master_token_account);
+    println!("  Master mint: {}", drop.master_mint);
+    println!("  Marketplace: {}", drop.marketplace);
+    println!("  Price: {} lamports", drop.price);
+    println!("  Prints sold: {} of {}", drop.prints_sold, drop.max_prints);
+}
+
+fn print_marketplace(address: &Pubkey, marketplace: &Marketplace) {
+    println!("Marketplace: {}", String::from_utf8_lossy(marketplace.name()));
+    println!("  Creator: {}", marketplace.creator);
+    println!("  Authority: {}", marketplace.authority);
+    print_platform_account(address, &marketplace.platform);
+}
+
+fn print_platform_account(address: &Pubkey, platform: &VaultAccount) {
+    println!("Platform account: {}", address);
+    println!("  Initialized: {}", platform.is_initialized);
+    println!("  Treasury: {}", platform.treasury_account);
+    println!("  Fee: {} bps", platform.base_percentage);
+    println!("  Paused: {}", platform.paused);
+    if platform.pass_collection != Pubkey::default() {
+        println!("  Pass collection: {}", platform.pass_collection);
+        println!("  Pass fee: {} bps", platform.pass_fee_basis_points);
+    }
+    println!("  Referral share: {} bps of the fee", platform.referral_basis_points);
+    println!("  Royalties escrowed: {}", platform.escrow_royalties);
+}
+
+fn print_payout(payout: &Payout) {
+    println!("Payout:");
+    for royalty in &payout.royalties {
+        println!("  Creator {}: {} lamports", royalty.creator, royalty.amount);
+    }
+    println!("  Platform fee: {} lamports", payout.platform_fee);
+    println!("  Seller: {} lamports", payout.seller_proceeds);
+}
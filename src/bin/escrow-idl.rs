@@ -0,0 +1,20 @@
+//! `escrow-idl`, prints the JSON interface description of the program, or writes
+//! it to the path given as argument. Built with the `idl` feature.
+//!
+//! `cargo run --features idl --bin escrow-idl -- idl/escrow_buy.json`
+
+use escrow_buy::idl::idl;
+use std::{env, fs, process::exit};
+
+fn main() {
+    let json = serde_json::to_string_pretty(&idl().to_json()).unwrap() + "\n";
+    match env::args().nth(1) {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, json) {
+                eprintln!("error: unable to write {}: {}", path, err);
+                exit(1);
+            }
+        }
+        None => print!("{}", json),
+    }
+}
@@ -0,0 +1,176 @@
+//! `escrow-indexer`, keeps a SQLite index of the escrow program's listings, sales
+//! and cancellations and queries it. Built with the `indexer` feature.
+
+use clap::{crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use escrow_buy::{
+    client::EscrowClient,
+    indexer::{Index, Indexer, Listing},
+};
+use solana_clap_utils::{
+    input_parsers::{pubkey_of, value_of},
+    input_validators::{is_parsable, is_url_or_moniker, is_valid_pubkey, normalize_to_url_if_moniker},
+};
+use solana_cli_config::{Config, CONFIG_FILE};
+use std::{error::Error, process::exit, thread::sleep, time::Duration};
+
+type IndexerResult = Result<(), Box<dyn Error>>;
+
+fn pubkey_arg<'a, 'b>(name: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .value_name("PUBKEY")
+        .required(true)
+        .validator(is_valid_pubkey)
+        .help(help)
+}
+
+fn app<'a, 'b>(default_url: &'a str) -> App<'a, 'b> {
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("database")
+                .long("database")
+                .short("d")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .default_value("escrow-index.sqlite")
+                .help("SQLite database holding the index"),
+        )
+        .subcommand(
+            SubCommand::with_name("sync")
+                .about("Index the program's transactions and accounts")
+                .arg(
+                    Arg::with_name("url")
+                        .long("url")
+                        .short("u")
+                        .value_name("URL_OR_MONIKER")
+                        .takes_value(true)
+                        .default_value(default_url)
+                        .validator(is_url_or_moniker)
+                        .help("JSON RPC URL of the cluster, or a moniker such as localhost"),
+                )
+                .arg(
+                    Arg::with_name("program_id")
+                        .long("program-id")
+                        .value_name("PROGRAM_ID")
+                        .takes_value(true)
+                        .env("ESCROW_PROGRAM_ID")
+                        .validator(is_valid_pubkey)
+                        .help("Address the escrow program is deployed at"),
+                )
+                .arg(
+                    Arg::with_name("poll_interval")
+                        .long("poll-interval")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .validator(is_parsable::<u64>)
+                        .help("Keep syncing, waiting this long between syncs"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("floor")
+                .about("Show the floor price of every collection, or of one")
+                .arg(pubkey_arg("collection", "First verified creator of the collection").required(false)),
+        )
+        .subcommand(
+            SubCommand::with_name("seller-listings")
+                .about("Show the active listings of a seller")
+                .arg(pubkey_arg("seller", "Seller of the listings")),
+        )
+        .subcommand(
+            SubCommand::with_name("mint-listings")
+                .about("Show the active listings and the sales of a mint")
+                .arg(pubkey_arg("mint", "Mint of the NFT")),
+        )
+}
+
+fn main() {
+    let cli_config = CONFIG_FILE
+        .as_ref()
+        .and_then(|config_file| Config::load(config_file).ok())
+        .unwrap_or_default();
+    let matches = app(&cli_config.json_rpc_url).get_matches();
+
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> IndexerResult {
+    let index = Index::open(matches.value_of("database").unwrap())?;
+
+    match matches.subcommand() {
+        ("sync", Some(matches)) => command_sync(index, matches),
+        ("floor", Some(matches)) => command_floor(&index, matches),
+        ("seller-listings", Some(matches)) => command_seller_listings(&index, matches),
+        ("mint-listings", Some(matches)) => command_mint_listings(&index, matches),
+        _ => unreachable!(),
+    }
+}
+
+fn command_sync(index: Index, matches: &ArgMatches) -> IndexerResult {
+    let program_id = pubkey_of(matches, "program_id")
+        .ok_or("the program id is required, pass --program-id or set ESCROW_PROGRAM_ID")?;
+    let url = normalize_to_url_if_moniker(matches.value_of("url").unwrap());
+    let poll_interval: Option<u64> = value_of(matches, "poll_interval");
+
+    let indexer = Indexer::new(EscrowClient::new(url, program_id), index);
+    loop {
+        indexer.sync()?;
+        println!("Synced, last transaction: {}", indexer.index().log_cursor()?.unwrap_or_default());
+        match poll_interval {
+            Some(seconds) => sleep(Duration::from_secs(seconds)),
+            None => return Ok(()),
+        }
+    }
+}
+
+fn command_floor(index: &Index, matches: &ArgMatches) -> IndexerResult {
+    match pubkey_of(matches, "collection") {
+        Some(collection) => match index.floor_price(&collection)? {
+            Some(floor) => println!("{}: {} lamports", collection, floor),
+            None => println!("{}: no active listings", collection),
+        },
+        None => {
+            for (collection, floor) in index.floor_prices()? {
+                println!("{}: {} lamports", collection, floor);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn command_seller_listings(index: &Index, matches: &ArgMatches) -> IndexerResult {
+    let seller = pubkey_of(matches, "seller").unwrap();
+    print_listings(&index.seller_listings(&seller)?);
+    Ok(())
+}
+
+fn command_mint_listings(index: &Index, matches: &ArgMatches) -> IndexerResult {
+    let mint = pubkey_of(matches, "mint").unwrap();
+    print_listings(&index.mint_listings(&mint)?);
+    for sale in index.mint_sales(&mint)? {
+        println!(
+            "Sale {} at slot {}: {} lamports from {} to {}",
+            sale.signature, sale.slot, sale.price, sale.seller, sale.buyer
+        );
+    }
+    Ok(())
+}
+
+fn print_listings(listings: &[Listing]) {
+    for listing in listings {
+        println!("Listing: {}", listing.escrow);
+        println!("  Seller: {}", listing.seller);
+        println!("  Mint: {}", listing.mint);
+        if let Some(collection) = listing.collection {
+            println!("  Collection: {}", collection);
+        }
+        println!("  Price: {} lamports", listing.price);
+        println!();
+    }
+    println!("{} listings", listings.len());
+}
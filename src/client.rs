@@ -0,0 +1,954 @@
+//! RPC client for the escrow program, enabled with the `client` feature.
+
+use metaplex_token_metadata::{
+    state::{Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::ClientError,
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_instruction, system_program, sysvar,
+};
+use solana_sdk::{
+    account::{from_account, Account},
+    commitment_config::CommitmentConfig,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use thiserror::Error;
+
+use crate::{
+    instruction::{self, ExchangeOptionalAccounts, MembershipPass},
+    payout::{compute_payout, Payout},
+    pda::{
+        find_associated_token_address, find_edition_address, find_marketplace_address, find_metadata_address,
+        find_referrer_address, find_royalty_vault_address, find_seller_proceeds_address, find_usd_listing_address,
+    },
+    price_feed::{oracle_program_id, PriceFeed},
+    state::{
+        account_type, AccountType, EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault,
+        SellerProceeds, TokenProgram, UsdListing, VaultAccount, ACCOUNT_HEADER_LEN, STATE_VERSION,
+    },
+};
+
+/// Rise of the lamport value of a listing priced in USD that
+/// [exchange_transaction](struct.EscrowClient.html#method.exchange_transaction)
+/// lets happen before the sale is processed, in basis points.
+pub const USD_PRICE_SLIPPAGE_BASIS_POINTS: u64 = 100;
+
+// offsets of the fields listings are searched by in a packed Escrow
+const ESCROW_SELLER_OFFSET: usize = ACCOUNT_HEADER_LEN;
+const ESCROW_MINT_OFFSET: usize = ACCOUNT_HEADER_LEN + 64;
+const ESCROW_MARKETPLACE_OFFSET: usize = ACCOUNT_HEADER_LEN + 104;
+// and in one of the layout before the header, which starts with the `is_initialized` flag
+const LEGACY_ESCROW_SELLER_OFFSET: usize = 1;
+const LEGACY_ESCROW_MINT_OFFSET: usize = 65;
+
+/// Errors returned by [EscrowClient](struct.EscrowClient.html).
+#[derive(Debug, Error)]
+pub enum EscrowClientError {
+    #[error(transparent)]
+    Rpc(Box<ClientError>),
+    #[error(transparent)]
+    Program(#[from] ProgramError),
+    #[error("account {0} does not exist")]
+    AccountNotFound(Pubkey),
+    #[error("account {0} is not owned by {1}")]
+    InvalidOwner(Pubkey, Pubkey),
+    #[error("the fees of the sale exceed its price")]
+    InvalidPayout,
+}
+
+impl From<ClientError> for EscrowClientError {
+    fn from(error: ClientError) -> Self {
+        // boxed, RPC errors are much larger than the other variants
+        Self::Rpc(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, EscrowClientError>;
+
+/// Fetches the escrow program's accounts and builds signed transactions for it,
+/// against any RPC endpoint.
+pub struct EscrowClient {
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+}
+
+impl EscrowClient {
+    /// Connects to `rpc_url`, reading and confirming at the `confirmed` commitment.
+    pub fn new<U: ToString>(rpc_url: U, program_id: Pubkey) -> Self {
+        Self::from_rpc_client(
+            RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            program_id,
+        )
+    }
+
+    pub fn from_rpc_client(rpc_client: RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            program_id,
+        }
+    }
+
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// Fetches and decodes an escrow account, initialized or not, of either layout.
+    pub fn get_escrow(&self, address: &Pubkey) -> Result<Escrow> {
+        let account = self.rpc_client.get_account(address)?;
+        if account.owner == self.program_id && account.data.len() == Escrow::LEGACY_LEN {
+            return Ok(Escrow::unpack_legacy(&account.data)?);
+        }
+        self.unpack_program_account(address, &account)
+    }
+
+    /// Fetches and decodes an edition drop, running or ended.
+    pub fn get_edition_drop(&self, address: &Pubkey) -> Result<EditionDrop> {
+        let account = self.rpc_client.get_account(address)?;
+        self.unpack_program_account(address, &account)
+    }
+
+    /// Fetches and decodes the fee settings of a platform account, the admin's
+    /// or a marketplace.
+    pub fn get_platform_account(&self, address: &Pubkey) -> Result<VaultAccount> {
+        let account = self.rpc_client.get_account(address)?;
+        self.unpack_platform_account(address, &account)
+    }
+
+    /// Fetches and decodes a marketplace, `None` when `address` is the platform
+    /// account of the admin instead.
+    pub fn get_marketplace(&self, address: &Pubkey) -> Result<Option<Marketplace>> {
+        let account = self.rpc_client.get_account(address)?;
+        if account_type(&account.data) != Some(AccountType::Marketplace) {
+            return Ok(None);
+        }
+        self.unpack_program_account(address, &account).map(Some)
+    }
+
+    /// Fetches the earnings of `referrer`, `None` while it is not registered.
+    pub fn get_referrer_account(&self, referrer: &Pubkey) -> Result<Option<ReferrerAccount>> {
+        let (address, _bump_seed) = find_referrer_address(&self.program_id, referrer);
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(&address, self.rpc_client.commitment())?
+            .value;
+        account
+            .map(|account| self.unpack_program_account(&address, &account))
+            .transpose()
+    }
+
+    /// Fetches the royalties held for `creator`, `None` before its first deposit.
+    pub fn get_royalty_vault(&self, creator: &Pubkey) -> Result<Option<RoyaltyVault>> {
+        let (address, _bump_seed) = find_royalty_vault_address(&self.program_id, creator);
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(&address, self.rpc_client.commitment())?
+            .value;
+        account
+            .map(|account| self.unpack_program_account(&address, &account))
+            .transpose()
+    }
+
+    /// Fetches the proceeds credited to `seller`, `None` before its first credited sale.
+    pub fn get_seller_proceeds(&self, seller: &Pubkey) -> Result<Option<SellerProceeds>> {
+        let (address, _bump_seed) = find_seller_proceeds_address(&self.program_id, seller);
+        let account = self
+            .rpc_client
+            .get_account_with_commitment(&address, self.rpc_client.commitment())?
+            .value;
+        account
+            .map(|account| self.unpack_program_account(&address, &account))
+            .transpose()
+    }
+
+    /// Fetches the open listings, optionally only those of `seller`, of `mint` and
+    /// made on `marketplace`.
+    pub fn get_listings(
+        &self,
+        seller: Option<&Pubkey>,
+        mint: Option<&Pubkey>,
+        marketplace: Option<&Pubkey>,
+    ) -> Result<Vec<(Pubkey, Escrow)>> {
+        // initialized escrow accounts of the current layout
+        let header = vec![AccountType::Escrow as u8, STATE_VERSION, 1];
+        let mut filters = vec![memcmp(0, header)];
+        if let Some(seller) = seller {
+            filters.push(memcmp(ESCROW_SELLER_OFFSET, seller.to_bytes().to_vec()));
+        }
+        if let Some(mint) = mint {
+            filters.push(memcmp(ESCROW_MINT_OFFSET, mint.to_bytes().to_vec()));
+        }
+        if let Some(marketplace) = marketplace {
+            filters.push(memcmp(ESCROW_MARKETPLACE_OFFSET, marketplace.to_bytes().to_vec()));
+        }
+        let mut listings = self.find_escrow_accounts(filters)?;
+
+        // initialized escrow accounts of the layout before the header, which record
+        // no marketplace
+        if marketplace.is_none() {
+            let mut filters = vec![RpcFilterType::DataSize(Escrow::LEGACY_LEN as u64), memcmp(0, vec![1])];
+            if let Some(seller) = seller {
+                filters.push(memcmp(LEGACY_ESCROW_SELLER_OFFSET, seller.to_bytes().to_vec()));
+            }
+            if let Some(mint) = mint {
+                filters.push(memcmp(LEGACY_ESCROW_MINT_OFFSET, mint.to_bytes().to_vec()));
+            }
+            listings.extend(self.find_legacy_escrow_accounts(filters)?);
+        }
+        Ok(listings)
+    }
+
+    /// Fetches every escrow account, including the closed listings that are
+    /// left behind uninitialized once sold or cancelled.
+    pub fn get_escrow_accounts(&self) -> Result<Vec<(Pubkey, Escrow)>> {
+        let header = vec![AccountType::Escrow as u8, STATE_VERSION];
+        let mut escrow_accounts = self.find_escrow_accounts(vec![memcmp(0, header)])?;
+        escrow_accounts.extend(
+            self.find_legacy_escrow_accounts(vec![RpcFilterType::DataSize(Escrow::LEGACY_LEN as u64)])?,
+        );
+        Ok(escrow_accounts)
+    }
+
+    fn find_escrow_accounts(&self, mut filters: Vec<RpcFilterType>) -> Result<Vec<(Pubkey, Escrow)>> {
+        filters.insert(0, RpcFilterType::DataSize(Escrow::LEN as u64));
+        let accounts = self.rpc_client.get_program_accounts_with_config(
+            &self.program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?;
+        accounts
+            .into_iter()
+            .map(|(address, account)| Ok((address, Escrow::unpack_unchecked(&account.data)?)))
+            .collect()
+    }
+
+    fn find_legacy_escrow_accounts(&self, filters: Vec<RpcFilterType>) -> Result<Vec<(Pubkey, Escrow)>> {
+        let accounts = self.rpc_client.get_program_accounts_with_config(
+            &self.program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?;
+        accounts
+            .into_iter()
+            .map(|(address, account)| Ok((address, Escrow::unpack_legacy(&account.data)?)))
+            .collect()
+    }
+
+    /// Fetches the Metaplex metadata of `mint`.
+    pub fn get_metadata(&self, mint: &Pubkey) -> Result<Metadata> {
+        let (metadata_key, _metadata_bump_seed) = find_metadata_address(mint);
+        let account = self.rpc_client.get_account(&metadata_key)?;
+        Ok(try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN)?)
+    }
+
+    /// Fetches the creators of `mint` from its Metaplex metadata. The sales take
+    /// their accounts in any order, along with their royalty vaults, see
+    /// [royalty_accounts](../instruction/fn.royalty_accounts.html).
+    pub fn get_creators(&self, mint: &Pubkey) -> Result<Vec<Pubkey>> {
+        let (metadata_key, _metadata_bump_seed) = find_metadata_address(mint);
+        let account = self.rpc_client.get_account(&metadata_key)?;
+        Ok(creators_from_metadata(&account.data)?)
+    }
+
+    /// Fetches the price feed of the listing in `escrow_address`, which is priced in USD.
+    pub fn get_usd_listing(&self, escrow_address: &Pubkey) -> Result<UsdListing> {
+        let (address, _bump_seed) = find_usd_listing_address(&self.program_id, escrow_address);
+        let account = self.rpc_client.get_account(&address)?;
+        self.unpack_program_account(&address, &account)
+    }
+
+    /// Lamports buying the listing `escrow` in `escrow_address` costs now: its price,
+    /// or the value of its USD price at the current price of its feed.
+    pub fn get_price(&self, escrow_address: &Pubkey, escrow: &Escrow) -> Result<u64> {
+        if !escrow.usd_priced {
+            return Ok(escrow.expected_amount);
+        }
+        let usd_listing = self.get_usd_listing(escrow_address)?;
+        self.usd_price_in_lamports(escrow, &usd_listing)
+    }
+
+    /// Computes how buying `escrow`, listed in `escrow_address`, through `platform`
+    /// would split its price, `pass_holder` when the buyer shows a membership pass.
+    /// A listing priced in USD is previewed at the current price of its feed.
+    pub fn get_payout(
+        &self,
+        escrow_address: &Pubkey,
+        escrow: &Escrow,
+        platform: &VaultAccount,
+        pass_holder: bool,
+    ) -> Result<Payout> {
+        let price = self.get_price(escrow_address, escrow)?;
+        let metadata = self.get_metadata(&escrow.mint_key)?;
+        compute_payout(
+            price,
+            metadata.data.seller_fee_basis_points,
+            &metadata.data.creators.unwrap_or_default(),
+            platform.fee_basis_points(pass_holder),
+        )
+        .ok_or(EscrowClientError::InvalidPayout)
+    }
+
+    /// Fetches the membership pass held in `token_account`.
+    pub fn get_membership_pass(&self, token_account: &Pubkey) -> Result<MembershipPass> {
+        let account = self.rpc_client.get_account(token_account)?;
+        if account.owner != spl_token::id() {
+            return Err(EscrowClientError::InvalidOwner(*token_account, spl_token::id()));
+        }
+        let token = spl_token::state::Account::unpack(&account.data)?;
+        Ok(MembershipPass {
+            token_account: *token_account,
+            mint: token.mint,
+        })
+    }
+
+    /// Fetches the token program of `mint`, SPL Token or Token-2022.
+    pub fn get_token_program(&self, mint: &Pubkey) -> Result<TokenProgram> {
+        let account = self.rpc_client.get_account(mint)?;
+        TokenProgram::from_id(&account.owner).ok_or(EscrowClientError::InvalidOwner(*mint, spl_token::id()))
+    }
+
+    /// Builds a transaction listing the NFT in `token_account` for `price` lamports
+    /// on `platform_account`. `escrow_account` is a new keypair, the program creates
+    /// the account. With `proceeds_vault` the proceeds of the sale are credited to the
+    /// seller's `SellerProceeds` instead of transferred to the seller.
+    #[allow(clippy::too_many_arguments)]
+    pub fn list_token_transaction(
+        &self,
+        seller: &dyn Signer,
+        token_account: &Pubkey,
+        mint: &Pubkey,
+        escrow_account: &dyn Signer,
+        platform_account: &Pubkey,
+        price: u64,
+        proceeds_vault: bool,
+    ) -> Result<Transaction> {
+        let instruction = instruction::list_token(
+            &self.program_id,
+            &seller.pubkey(),
+            token_account,
+            mint,
+            &escrow_account.pubkey(),
+            platform_account,
+            price,
+        )?;
+        self.sign_listing(instruction, mint, seller, escrow_account, proceeds_vault)
+    }
+
+    /// Builds a transaction listing the NFT in `token_account` like
+    /// [`Self::list_token_transaction`], but the NFT stays in the seller's token
+    /// account and the program is only approved as its delegate.
+    #[allow(clippy::too_many_arguments)]
+    pub fn list_token_delegated_transaction(
+        &self,
+        seller: &dyn Signer,
+        token_account: &Pubkey,
+        mint: &Pubkey,
+        escrow_account: &dyn Signer,
+        platform_account: &Pubkey,
+        price: u64,
+        proceeds_vault: bool,
+    ) -> Result<Transaction> {
+        let instruction = instruction::list_token_delegated(
+            &self.program_id,
+            &seller.pubkey(),
+            token_account,
+            mint,
+            &escrow_account.pubkey(),
+            platform_account,
+            price,
+        )?;
+        self.sign_listing(instruction, mint, seller, escrow_account, proceeds_vault)
+    }
+
+    /// Builds a transaction listing the NFT in `token_account` like
+    /// [`Self::list_token_delegated_transaction`], and freezing the token account
+    /// until the NFT is sold or the listing cancelled. The freeze authority of
+    /// `mint` must be the escrow authority PDA.
+    #[allow(clippy::too_many_arguments)]
+    pub fn list_token_frozen_transaction(
+        &self,
+        seller: &dyn Signer,
+        token_account: &Pubkey,
+        mint: &Pubkey,
+        escrow_account: &dyn Signer,
+        platform_account: &Pubkey,
+        price: u64,
+        proceeds_vault: bool,
+    ) -> Result<Transaction> {
+        let instruction = instruction::list_token_frozen(
+            &self.program_id,
+            &seller.pubkey(),
+            token_account,
+            mint,
+            &escrow_account.pubkey(),
+            platform_account,
+            price,
+        )?;
+        self.sign_listing(instruction, mint, seller, escrow_account, proceeds_vault)
+    }
+
+    /// Builds a transaction buying the NFT listed in `escrow_address`. The seller,
+    /// the treasury and the creators are resolved from the chain. A membership pass
+    /// of the taker or the seller in `optional_accounts` discounts the platform fee,
+    /// a referrer gets a share of it. The NFT of a delegated or frozen listing is sent to the
+    /// taker's associated token account, which is created when it doesn't exist. The proceeds of a listing in
+    /// proceeds-vault mode are credited to the seller's `SellerProceeds`. A listing
+    /// priced in USD is bought with `ExchangeUsd`, for at most its current lamport
+    /// value and [USD_PRICE_SLIPPAGE_BASIS_POINTS](constant.USD_PRICE_SLIPPAGE_BASIS_POINTS.html).
+    pub fn exchange_transaction(
+        &self,
+        taker: &dyn Signer,
+        escrow_address: &Pubkey,
+        platform_account: &Pubkey,
+        mut optional_accounts: ExchangeOptionalAccounts,
+    ) -> Result<Transaction> {
+        let escrow = self.get_escrow(escrow_address)?;
+        let (metadata_key, _metadata_bump_seed) = find_metadata_address(&escrow.mint_key);
+
+        let token_program = escrow.token_program.id();
+        let buyer_token_account = find_associated_token_address(&taker.pubkey(), &escrow.mint_key, &token_program);
+        let mut addresses = vec![*platform_account, metadata_key];
+        if escrow.listing_mode != ListingMode::Custody {
+            addresses.push(buyer_token_account);
+        }
+
+        let accounts = self.rpc_client.get_multiple_accounts(&addresses)?;
+        let platform = match &accounts[0] {
+            Some(account) => self.unpack_platform_account(platform_account, account)?,
+            None => return Err(EscrowClientError::AccountNotFound(*platform_account)),
+        };
+        // the program decides whether a creator is paid directly or through its vault
+        let creators = match &accounts[1] {
+            Some(account) => instruction::royalty_accounts(&self.program_id, &creators_from_metadata(&account.data)?),
+            None => return Err(EscrowClientError::AccountNotFound(metadata_key)),
+        };
+
+        let amount = 1;
+        let usd_price = if escrow.usd_priced {
+            let usd_listing = self.get_usd_listing(escrow_address)?;
+            let lamports = self.usd_price_in_lamports(&escrow, &usd_listing)?;
+            let slippage = (lamports as u128 * USD_PRICE_SLIPPAGE_BASIS_POINTS as u128 / 10000) as u64;
+            Some((usd_listing.price_feed, lamports.saturating_add(slippage)))
+        } else {
+            None
+        };
+
+        let mut instructions = Vec::new();
+        if escrow.listing_mode != ListingMode::Custody {
+            if accounts[2].is_none() {
+                instructions.push(create_associated_token_account(
+                    &taker.pubkey(),
+                    &taker.pubkey(),
+                    &escrow.mint_key,
+                    &token_program,
+                ));
+            }
+            optional_accounts.buyer_token_account = Some(buyer_token_account);
+        }
+        optional_accounts.seller_proceeds = escrow.proceeds_vault;
+
+        let instruction = if let Some((price_feed, max_lamports)) = usd_price {
+            instruction::exchange_usd(
+                &self.program_id,
+                &taker.pubkey(),
+                &escrow.token_account_pubkey,
+                &escrow.seller_pubkey,
+                &escrow.mint_key,
+                escrow_address,
+                platform_account,
+                &platform.treasury_account,
+                &price_feed,
+                &optional_accounts,
+                &creators,
+                amount,
+                max_lamports,
+            )?
+        } else if optional_accounts == ExchangeOptionalAccounts::default() {
+            instruction::exchange(
+                &self.program_id,
+                &taker.pubkey(),
+                &escrow.token_account_pubkey,
+                &escrow.seller_pubkey,
+                &escrow.mint_key,
+                escrow_address,
+                platform_account,
+                &platform.treasury_account,
+                &creators,
+                amount,
+            )?
+        } else {
+            instruction::exchange_with_options(
+                &self.program_id,
+                &taker.pubkey(),
+                &escrow.token_account_pubkey,
+                &escrow.seller_pubkey,
+                &escrow.mint_key,
+                escrow_address,
+                platform_account,
+                &platform.treasury_account,
+                &optional_accounts,
+                &creators,
+                amount,
+            )?
+        };
+        instructions.push(instruction::with_token_program(instruction, &token_program));
+        self.sign_instructions(&instructions, &[taker])
+    }
+
+    /// Builds a transaction handing the NFT listed in `escrow_address` back to the seller.
+    pub fn cancel_transaction(&self, seller: &dyn Signer, escrow_address: &Pubkey) -> Result<Transaction> {
+        let escrow = self.get_escrow(escrow_address)?;
+        let instruction = match escrow.listing_mode {
+            ListingMode::Frozen => instruction::cancel_frozen(
+                &self.program_id,
+                &seller.pubkey(),
+                &escrow.token_account_pubkey,
+                escrow_address,
+                &escrow.mint_key,
+            )?,
+            _ => instruction::cancel(
+                &self.program_id,
+                &seller.pubkey(),
+                &escrow.token_account_pubkey,
+                escrow_address,
+            )?,
+        };
+        self.sign(instruction::with_token_program(instruction, &escrow.token_program.id()), &[seller])
+    }
+
+    /// Builds a transaction starting a drop of up to `max_prints` prints of the
+    /// master edition of `master_mint`, held in `token_account`, for `price` lamports
+    /// each. `drop_account` is a new keypair, the program creates the account.
+    #[allow(clippy::too_many_arguments)]
+    pub fn list_edition_drop_transaction(
+        &self,
+        creator: &dyn Signer,
+        token_account: &Pubkey,
+        master_mint: &Pubkey,
+        drop_account: &dyn Signer,
+        platform_account: &Pubkey,
+        price: u64,
+        max_prints: u64,
+    ) -> Result<Transaction> {
+        let instruction = instruction::list_edition_drop(
+            &self.program_id,
+            &creator.pubkey(),
+            token_account,
+            master_mint,
+            &drop_account.pubkey(),
+            platform_account,
+            price,
+            max_prints,
+        )?;
+        self.sign(instruction, &[creator, drop_account])
+    }
+
+    /// Builds a transaction buying the next print of the drop in `drop_address`.
+    /// `new_mint` is a new keypair, the transaction creates it with one token in
+    /// the buyer's associated token account before the edition is printed to it.
+    pub fn buy_edition_transaction(
+        &self,
+        buyer: &dyn Signer,
+        new_mint: &dyn Signer,
+        drop_address: &Pubkey,
+    ) -> Result<Transaction> {
+        let drop = self.get_edition_drop(drop_address)?;
+        let (metadata_key, _metadata_bump_seed) = find_metadata_address(&drop.master_mint);
+        let (master_edition_key, _master_edition_bump_seed) = find_edition_address(&drop.master_mint);
+
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&[drop.marketplace, metadata_key, master_edition_key])?;
+        let platform = match &accounts[0] {
+            Some(account) => self.unpack_platform_account(&drop.marketplace, account)?,
+            None => return Err(EscrowClientError::AccountNotFound(drop.marketplace)),
+        };
+        let metadata: Metadata = match &accounts[1] {
+            Some(account) => try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN)?,
+            None => return Err(EscrowClientError::AccountNotFound(metadata_key)),
+        };
+        let master_edition: MasterEditionV2 = match &accounts[2] {
+            Some(account) => try_from_slice_checked(&account.data, Key::MasterEditionV2, MAX_MASTER_EDITION_LEN)?,
+            None => return Err(EscrowClientError::AccountNotFound(master_edition_key)),
+        };
+        let creators: Vec<Pubkey> = metadata
+            .data
+            .creators
+            .unwrap_or_default()
+            .iter()
+            .map(|creator| creator.address)
+            .collect();
+        let creators = instruction::royalty_accounts(&self.program_id, &creators);
+
+        let buyer_key = buyer.pubkey();
+        let mint_key = new_mint.pubkey();
+        let mint_rent = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+        let instructions = [
+            system_instruction::create_account(
+                &buyer_key,
+                &mint_key,
+                mint_rent,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(&spl_token::id(), &mint_key, &buyer_key, Some(&buyer_key), 0)?,
+            create_associated_token_account(&buyer_key, &buyer_key, &mint_key, &spl_token::id()),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint_key,
+                &find_associated_token_address(&buyer_key, &mint_key, &spl_token::id()),
+                &buyer_key,
+                &[],
+                1,
+            )?,
+            instruction::buy_edition(
+                &self.program_id,
+                &buyer_key,
+                drop_address,
+                &drop.creator,
+                &drop.master_mint,
+                &drop.master_token_account,
+                &mint_key,
+                &metadata.update_authority,
+                &drop.marketplace,
+                &platform.treasury_account,
+                &creators,
+                master_edition.supply + 1,
+            )?,
+        ];
+        self.sign_instructions(&instructions, &[buyer, new_mint])
+    }
+
+    /// Builds a transaction ending the drop in `drop_address` and handing the
+    /// master edition token back to its creator.
+    pub fn cancel_edition_drop_transaction(&self, creator: &dyn Signer, drop_address: &Pubkey) -> Result<Transaction> {
+        let drop = self.get_edition_drop(drop_address)?;
+        let instruction = instruction::cancel_edition_drop(
+            &self.program_id,
+            &creator.pubkey(),
+            &drop.master_token_account,
+            drop_address,
+        )?;
+        self.sign(instruction, &[creator])
+    }
+
+    /// Builds a transaction setting the treasury and the fee, in basis points, of
+    /// `platform_account`. Only the platform admin can sign it.
+    pub fn update_platform_account_transaction(
+        &self,
+        admin: &dyn Signer,
+        platform_account: &Pubkey,
+        treasury: &Pubkey,
+        fee_basis_points: u64,
+    ) -> Result<Transaction> {
+        let instruction = instruction::update_platform_account(
+            &self.program_id,
+            &admin.pubkey(),
+            platform_account,
+            treasury,
+            fee_basis_points,
+        )?;
+        self.sign(instruction, &[admin])
+    }
+
+    /// Builds a transaction pausing, or resuming, listing and buying on
+    /// `platform_account`. Only the platform admin can sign it.
+    pub fn set_paused_transaction(
+        &self,
+        admin: &dyn Signer,
+        platform_account: &Pubkey,
+        paused: bool,
+    ) -> Result<Transaction> {
+        let instruction = instruction::set_paused(&self.program_id, &admin.pubkey(), platform_account, paused)?;
+        self.sign(instruction, &[admin])
+    }
+
+    /// Builds a transaction discounting the platform fee to `pass_fee_basis_points`
+    /// for holders of an NFT verified by `pass_collection`. Only the platform admin
+    /// can sign it.
+    pub fn set_pass_discount_transaction(
+        &self,
+        admin: &dyn Signer,
+        platform_account: &Pubkey,
+        pass_collection: &Pubkey,
+        pass_fee_basis_points: u64,
+    ) -> Result<Transaction> {
+        let instruction = instruction::set_pass_discount(
+            &self.program_id,
+            &admin.pubkey(),
+            platform_account,
+            pass_collection,
+            pass_fee_basis_points,
+        )?;
+        self.sign(instruction, &[admin])
+    }
+
+    /// Builds a transaction paying referrers `referral_basis_points` of the platform
+    /// fee of the sales they refer. Only the platform admin can sign it.
+    pub fn set_referral_share_transaction(
+        &self,
+        admin: &dyn Signer,
+        platform_account: &Pubkey,
+        referral_basis_points: u64,
+    ) -> Result<Transaction> {
+        let instruction = instruction::set_referral_share(
+            &self.program_id,
+            &admin.pubkey(),
+            platform_account,
+            referral_basis_points,
+        )?;
+        self.sign(instruction, &[admin])
+    }
+
+    /// Builds a transaction depositing the royalties of every sale on the platform
+    /// account into the royalty vaults of the creators while `escrow_royalties` is
+    /// set. Only the platform admin can sign it.
+    pub fn set_escrow_royalties_transaction(
+        &self,
+        admin: &dyn Signer,
+        platform_account: &Pubkey,
+        escrow_royalties: bool,
+    ) -> Result<Transaction> {
+        let instruction = instruction::set_escrow_royalties(
+            &self.program_id,
+            &admin.pubkey(),
+            platform_account,
+            escrow_royalties,
+        )?;
+        self.sign(instruction, &[admin])
+    }
+
+    /// Builds a transaction moving the unclaimed royalties held for `creator` to
+    /// `destination`.
+    pub fn claim_royalties_transaction(&self, creator: &dyn Signer, destination: &Pubkey) -> Result<Transaction> {
+        let instruction = instruction::claim_royalties(&self.program_id, &creator.pubkey(), destination)?;
+        self.sign(instruction, &[creator])
+    }
+
+    /// Builds a transaction moving the proceeds credited to `seller` to `destination`.
+    pub fn withdraw_proceeds_transaction(&self, seller: &dyn Signer, destination: &Pubkey) -> Result<Transaction> {
+        let instruction = instruction::withdraw_proceeds(&self.program_id, &seller.pubkey(), destination)?;
+        self.sign(instruction, &[seller])
+    }
+
+    /// Builds a transaction registering `referrer`, which can be paid for referred
+    /// sales from then on.
+    pub fn register_referrer_transaction(&self, referrer: &dyn Signer) -> Result<Transaction> {
+        let instruction = instruction::register_referrer(&self.program_id, &referrer.pubkey())?;
+        self.sign(instruction, &[referrer])
+    }
+
+    /// Builds a transaction creating the marketplace `name` of `creator`, who pays
+    /// for the account. Returns the transaction and the marketplace address.
+    pub fn create_marketplace_transaction(
+        &self,
+        creator: &dyn Signer,
+        authority: &Pubkey,
+        treasury: &Pubkey,
+        name: &str,
+        fee_basis_points: u64,
+    ) -> Result<(Transaction, Pubkey)> {
+        let (marketplace, _marketplace_bump_seed) =
+            find_marketplace_address(&self.program_id, &creator.pubkey(), name);
+        let instruction = instruction::create_marketplace(
+            &self.program_id,
+            &creator.pubkey(),
+            authority,
+            treasury,
+            name,
+            fee_basis_points,
+        )?;
+        Ok((self.sign(instruction, &[creator])?, marketplace))
+    }
+
+    /// Builds a transaction taking down the listing in `escrow_address` and handing
+    /// the NFT back to its seller. Only the authority of the platform account the
+    /// listing was made on can sign it.
+    pub fn force_delist_transaction(
+        &self,
+        admin: &dyn Signer,
+        escrow_address: &Pubkey,
+        reason: u8,
+    ) -> Result<Transaction> {
+        let escrow = self.get_escrow(escrow_address)?;
+        let instruction = match escrow.listing_mode {
+            ListingMode::Delegate => instruction::force_delist_delegated(
+                &self.program_id,
+                &admin.pubkey(),
+                &escrow.token_account_pubkey,
+                escrow_address,
+                &escrow.marketplace,
+                &escrow.mint_key,
+                reason,
+            )?,
+            ListingMode::Frozen => instruction::force_delist_frozen(
+                &self.program_id,
+                &admin.pubkey(),
+                &escrow.token_account_pubkey,
+                escrow_address,
+                &escrow.marketplace,
+                &escrow.mint_key,
+                reason,
+            )?,
+            ListingMode::Custody => instruction::force_delist(
+                &self.program_id,
+                &admin.pubkey(),
+                &escrow.token_account_pubkey,
+                escrow_address,
+                &escrow.marketplace,
+                reason,
+            )?,
+        };
+        self.sign(instruction::with_token_program(instruction, &escrow.token_program.id()), &[admin])
+    }
+
+    /// Simulates `transaction` without sending it, the result holds its logs.
+    pub fn simulate(&self, transaction: &Transaction) -> Result<RpcSimulateTransactionResult> {
+        Ok(self.rpc_client.simulate_transaction(transaction)?.value)
+    }
+
+    /// Sends `transaction` and waits until it is confirmed.
+    pub fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        Ok(self.rpc_client.send_and_confirm_transaction(transaction)?)
+    }
+
+    /// Signs `instruction` with the latest blockhash, the first signer pays the fees.
+    fn sign(&self, instruction: Instruction, signers: &[&dyn Signer]) -> Result<Transaction> {
+        self.sign_instructions(&[instruction], signers)
+    }
+
+    /// Signs `instructions` in one transaction, like [`Self::sign`].
+    fn sign_instructions(&self, instructions: &[Instruction], signers: &[&dyn Signer]) -> Result<Transaction> {
+        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            &signers.to_vec(),
+            recent_blockhash,
+        ))
+    }
+
+    // the proceeds-vault mode of a listing is only set by the transaction listing it
+    fn sign_listing(
+        &self,
+        instruction: Instruction,
+        mint: &Pubkey,
+        seller: &dyn Signer,
+        escrow_account: &dyn Signer,
+        proceeds_vault: bool,
+    ) -> Result<Transaction> {
+        let token_program = self.get_token_program(mint)?;
+        let mut instructions = vec![instruction::with_token_program(instruction, &token_program.id())];
+        if proceeds_vault {
+            instructions.push(instruction::set_proceeds_vault(
+                &self.program_id,
+                &seller.pubkey(),
+                &escrow_account.pubkey(),
+                true,
+            )?);
+        }
+        self.sign_instructions(&instructions, &[seller, escrow_account])
+    }
+
+    fn unpack_platform_account(&self, address: &Pubkey, account: &Account) -> Result<VaultAccount> {
+        if account_type(&account.data) == Some(AccountType::Marketplace) {
+            let marketplace: Marketplace = self.unpack_program_account(address, account)?;
+            return Ok(marketplace.platform);
+        }
+        if account.owner == self.program_id && account.data.len() == VaultAccount::LEGACY_LEN {
+            return Ok(VaultAccount::unpack_legacy(&account.data)?);
+        }
+        self.unpack_program_account(address, account)
+    }
+
+    // the feed is read at the clock of the cluster, as the program reads it
+    fn usd_price_in_lamports(&self, escrow: &Escrow, usd_listing: &UsdListing) -> Result<u64> {
+        let clock_id = sysvar::clock::id();
+        let accounts = self.rpc_client.get_multiple_accounts(&[usd_listing.price_feed, clock_id])?;
+        let price_feed = match &accounts[0] {
+            Some(account) if account.owner == oracle_program_id() => PriceFeed::unpack(&account.data)?,
+            Some(_) => return Err(EscrowClientError::InvalidOwner(usd_listing.price_feed, oracle_program_id())),
+            None => return Err(EscrowClientError::AccountNotFound(usd_listing.price_feed)),
+        };
+        let clock: Clock = accounts[1]
+            .as_ref()
+            .and_then(from_account)
+            .ok_or(EscrowClientError::AccountNotFound(clock_id))?;
+        Ok(price_feed.lamports_for(escrow.expected_amount, &clock)?)
+    }
+
+    fn unpack_program_account<T: Pack>(&self, address: &Pubkey, account: &Account) -> Result<T> {
+        if account.owner != self.program_id {
+            return Err(EscrowClientError::InvalidOwner(*address, self.program_id));
+        }
+        Ok(T::unpack_unchecked(&account.data)?)
+    }
+}
+
+/// Creates the associated token account of `wallet` for `mint`, held by `token_program`.
+/// The instruction builder of the associated token account crate only knows SPL Token.
+fn create_associated_token_account(funder: &Pubkey, wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: spl_associated_token_account::id(),
+        accounts: vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(find_associated_token_address(wallet, mint, token_program), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: vec![],
+    }
+}
+
+fn memcmp(offset: usize, bytes: Vec<u8>) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp {
+        offset,
+        bytes: MemcmpEncodedBytes::Base58(bs58::encode(bytes).into_string()),
+        encoding: None,
+    })
+}
+
+fn creators_from_metadata(data: &[u8]) -> std::result::Result<Vec<Pubkey>, ProgramError> {
+    let metadata: Metadata = try_from_slice_checked(data, Key::MetadataV1, MAX_METADATA_LEN)?;
+    Ok(metadata
+        .data
+        .creators
+        .unwrap_or_default()
+        .iter()
+        .map(|creator| creator.address)
+        .collect())
+}
@@ -0,0 +1,62 @@
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+/// Errors specific to the escrow program, returned as `ProgramError::Custom`.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum EscrowError {
+    /// listing and buying are paused on the platform account
+    #[error("Marketplace is paused")]
+    MarketplacePaused,
+    /// the seller of a delegated listing revoked the approval or moved the token
+    #[error("Listing no longer holds the token")]
+    ListingInvalidated,
+    /// the mint or the token account uses a Token-2022 extension the escrow
+    /// cannot honor, such as non-transferable mints or confidential transfers
+    #[error("Token extension not supported")]
+    UnsupportedTokenExtension,
+    /// the price feed of a listing priced in USD is not trading, or its price was
+    /// published too many slots or seconds ago
+    #[error("Price feed is stale")]
+    StalePriceFeed,
+    /// the confidence interval of the price feed is too wide a share of its price
+    #[error("Price feed is too uncertain")]
+    UncertainPriceFeed,
+    /// the lamport value of a listing priced in USD is more than the taker agreed to pay
+    #[error("Price is above the taker's maximum")]
+    PriceAboveMaximum,
+    /// a creator account was passed more than once to a sale paying royalties
+    #[error("Creator account passed twice")]
+    DuplicateCreatorAccount,
+    /// a creator owed royalties on a sale was not among its accounts
+    #[error("Creator account missing")]
+    MissingCreatorAccount,
+    /// the royalty vault of a creator that cannot take a transfer, or of any creator
+    /// while the platform escrows royalties, was not among the accounts of a sale
+    #[error("Royalty vault missing")]
+    MissingRoyaltyVault,
+    /// the transfer fee of the mint of a delegated or frozen listing would withhold
+    /// all of the token from the taker, as any fee on a single token does
+    #[error("Transfer fee would withhold the token")]
+    TransferFeeWithholdsToken,
+    /// the token account of a custody listing has the Token-2022 ImmutableOwner
+    /// extension, as associated token accounts do, so it cannot be handed over to
+    /// the escrow authority; such a token is listed delegated or frozen instead
+    #[error("Token account owner cannot change")]
+    ImmutableOwner,
+    /// the proceeds-vault mode of a listing was set outside the transaction that
+    /// listed it, where it would change the accounts of sales already on their way
+    #[error("Proceeds vault mode is only set with the listing")]
+    ProceedsVaultAfterListing,
+    /// a listing priced in USD named a price account other than the SOL/USD feed
+    #[error("Price feed is not the SOL/USD feed")]
+    UnsupportedPriceFeed,
+    /// the referrer of a sale has not registered its referrer account
+    #[error("Referrer is not registered")]
+    UnregisteredReferrer,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(error: EscrowError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
@@ -0,0 +1,268 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{msg, pubkey::Pubkey};
+
+/// Marks a program log line as a serialized [EscrowEvent](enum.EscrowEvent.html).
+/// The rest of the line is the base64 encoded Borsh serialization of the event.
+pub const EVENT_LOG_PREFIX: &str = "EVENT:";
+
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+
+/// Royalty paid to one creator of a sold NFT, in lamports.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
+pub struct RoyaltyPayout {
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+/// Events emitted by the processor, one per successful instruction. New variants
+/// are only ever appended so that logs of older transactions keep decoding.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
+pub enum EscrowEvent {
+    Listed {
+        escrow: Pubkey,
+        seller: Pubkey,
+        mint: Pubkey,
+        token_account: Pubkey,
+        price: u64,
+    },
+
+    /// `price` is split into the royalties, the platform fee and what the seller gets.
+    Sold {
+        escrow: Pubkey,
+        seller: Pubkey,
+        buyer: Pubkey,
+        mint: Pubkey,
+        price: u64,
+        royalties: Vec<RoyaltyPayout>,
+        treasury: Pubkey,
+        platform_fee: u64,
+        seller_proceeds: u64,
+    },
+
+    Cancelled {
+        escrow: Pubkey,
+        seller: Pubkey,
+        mint: Pubkey,
+    },
+
+    ConfigUpdated {
+        platform_account: Pubkey,
+        treasury: Pubkey,
+        fee_basis_points: u64,
+    },
+
+    TokenSwapListed {
+        swap: Pubkey,
+        seller: Pubkey,
+        offered_mint: Pubkey,
+        expected_mint: Pubkey,
+        offered_amount: u64,
+        expected_amount: u64,
+    },
+
+    /// `remaining_offered_amount` is 0 once the swap is completely filled.
+    TokenSwapFilled {
+        swap: Pubkey,
+        taker: Pubkey,
+        amount: u64,
+        paid: u64,
+        remaining_offered_amount: u64,
+    },
+
+    TokenSwapCancelled {
+        swap: Pubkey,
+        seller: Pubkey,
+        refunded_amount: u64,
+    },
+
+    PausedSet {
+        platform_account: Pubkey,
+        paused: bool,
+    },
+
+    /// A listing taken down by the platform admin, `reason` is the moderation code
+    /// passed to `ForceDelist`.
+    ForceDelisted {
+        escrow: Pubkey,
+        seller: Pubkey,
+        mint: Pubkey,
+        reason: u8,
+    },
+
+    PassDiscountSet {
+        platform_account: Pubkey,
+        pass_collection: Pubkey,
+        pass_fee_basis_points: u64,
+    },
+
+    /// Emitted after `Sold` when the sale was referred, `amount` was carved out
+    /// of its platform fee.
+    ReferralPaid {
+        escrow: Pubkey,
+        referrer: Pubkey,
+        amount: u64,
+        total_earned: u64,
+    },
+
+    ReferralShareSet {
+        platform_account: Pubkey,
+        referral_basis_points: u64,
+    },
+
+    MarketplaceCreated {
+        marketplace: Pubkey,
+        creator: Pubkey,
+        authority: Pubkey,
+        treasury: Pubkey,
+        name: String,
+        fee_basis_points: u64,
+    },
+
+    EditionDropListed {
+        drop: Pubkey,
+        creator: Pubkey,
+        master_mint: Pubkey,
+        price: u64,
+        max_prints: u64,
+    },
+
+    /// A print of a drop sold, `price` is split by the primary sale rules into the
+    /// creator shares, the platform fee and what the lister gets.
+    EditionSold {
+        drop: Pubkey,
+        seller: Pubkey,
+        buyer: Pubkey,
+        mint: Pubkey,
+        edition: u64,
+        price: u64,
+        royalties: Vec<RoyaltyPayout>,
+        treasury: Pubkey,
+        platform_fee: u64,
+        seller_proceeds: u64,
+        /// 0 once the drop sold out and the master edition went back to its creator
+        remaining_prints: u64,
+    },
+
+    EditionDropCancelled {
+        drop: Pubkey,
+        creator: Pubkey,
+        prints_sold: u64,
+    },
+
+    /// A listing priced in USD, emitted instead of `Listed`. `usd_price` is in
+    /// millionths of a dollar, the `Sold` event of the listing holds the lamports paid.
+    UsdListed {
+        escrow: Pubkey,
+        seller: Pubkey,
+        mint: Pubkey,
+        token_account: Pubkey,
+        usd_price: u64,
+        price_feed: Pubkey,
+    },
+
+    /// The royalty of `creator` on a sale went into its royalty vault instead of
+    /// a transfer, emitted before the `Sold` or `EditionSold` event of the sale.
+    RoyaltyDeposited {
+        creator: Pubkey,
+        royalty_vault: Pubkey,
+        amount: u64,
+        total_deposited: u64,
+    },
+
+    RoyaltiesClaimed {
+        creator: Pubkey,
+        royalty_vault: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        total_claimed: u64,
+    },
+
+    EscrowRoyaltiesSet {
+        platform_account: Pubkey,
+        escrow_royalties: bool,
+    },
+
+    ProceedsVaultSet {
+        escrow: Pubkey,
+        seller: Pubkey,
+        proceeds_vault: bool,
+    },
+
+    /// The proceeds of a sale were credited to the `SellerProceeds` of the seller
+    /// instead of a transfer, emitted before the `Sold` event of the sale.
+    ProceedsCredited {
+        escrow: Pubkey,
+        seller: Pubkey,
+        seller_proceeds: Pubkey,
+        amount: u64,
+        total_credited: u64,
+    },
+
+    ProceedsWithdrawn {
+        seller: Pubkey,
+        seller_proceeds: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        total_withdrawn: u64,
+    },
+
+    ReferrerRegistered {
+        referrer: Pubkey,
+        referrer_account: Pubkey,
+    },
+}
+
+impl EscrowEvent {
+    /// Writes the event to the program log.
+    pub fn emit(&self) {
+        // writing into a Vec cannot fail
+        let data = self.try_to_vec().unwrap();
+        msg!("{}{}", EVENT_LOG_PREFIX, base64::encode(data));
+    }
+
+    /// Decodes the event in a single `Program log: ` line, if the line holds one.
+    pub fn from_log_line(line: &str) -> Option<Self> {
+        let encoded = line
+            .strip_prefix(PROGRAM_LOG_PREFIX)?
+            .strip_prefix(EVENT_LOG_PREFIX)?;
+        let data = base64::decode(encoded).ok()?;
+        Self::try_from_slice(&data).ok()
+    }
+}
+
+/// Decodes the events `program_id` emitted in the log messages of a transaction.
+/// Lines logged while another program is executing, such as a program calling
+/// this one or a program this one calls, are skipped.
+pub fn parse_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<EscrowEvent> {
+    let program_id = program_id.to_string();
+    let mut invoked: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let line = line.as_ref();
+        if let Some(rest) = line.strip_prefix("Program ") {
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(id), Some("invoke")) => {
+                    invoked.push(id);
+                    continue;
+                }
+                (Some(id), Some(status))
+                    if invoked.last() == Some(&id)
+                        && (status == "success" || status.starts_with("failed")) =>
+                {
+                    invoked.pop();
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        if invoked.last() != Some(&program_id.as_str()) {
+            continue;
+        }
+        if let Some(event) = EscrowEvent::from_log_line(line) {
+            events.push(event);
+        }
+    }
+    events
+}
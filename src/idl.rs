@@ -0,0 +1,1007 @@
+//! JSON interface description of the program, enabled with the `idl` feature.
+//!
+//! The account roles of every instruction are read from the instruction builders
+//! and the account layouts are checked against the `Pack` sizes while generating,
+//! so [idl](fn.idl.html) cannot describe a program other than the one compiled.
+//! The `escrow-idl` binary writes it to `idl/escrow_buy.json`.
+
+use serde_json::{json, Value};
+use solana_program::{
+    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, system_program, sysvar,
+};
+
+use crate::{
+    instruction::{
+        self, EscrowInstruction, ExchangeOptionalAccounts, MembershipPass, EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT,
+        EXCHANGE_OPTION_MEMBERSHIP_PASS, EXCHANGE_OPTION_REFERRER, EXCHANGE_OPTION_SELLER_PROCEEDS,
+        INSTRUCTION_VERSION, VERSIONED_INSTRUCTION_MARKER,
+    },
+    pda::{
+        ESCROW_AUTHORITY_SEED, MARKETPLACE_SEED, REFERRER_SEED, ROYALTY_VAULT_SEED, SELLER_PROCEEDS_SEED,
+        USD_LISTING_SEED, VAULT_SEED,
+    },
+    state::{
+        AccountType, EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault, SellerProceeds,
+        TokenProgram, TokenSwap, UsdListing, VaultAccount, ACCOUNT_HEADER_LEN, ACCOUNT_PADDING_LEN,
+        MARKETPLACE_NAME_LEN, STATE_VERSION,
+    },
+};
+
+/// Offset of the Borsh serialized arguments in versioned instruction data, after
+/// the marker, the version and the variant index.
+pub const INSTRUCTION_ARGS_OFFSET: usize = 3;
+
+/// A field of instruction data or of an account, `offset` is in bytes from the start.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdlField {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdlAccountMeta {
+    pub name: &'static str,
+    pub signer: bool,
+    pub writable: bool,
+    /// the account repeats for as many entries as there are, it is always last
+    pub variadic: bool,
+    /// fixed address of program and sysvar accounts
+    pub address: Option<Pubkey>,
+    /// seeds of accounts derived from the program id, described in words
+    pub pda: Option<&'static str>,
+    /// option flag or listing mode the account is passed with, absent otherwise
+    pub option: Option<&'static str>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdlInstruction {
+    pub name: &'static str,
+    /// Borsh variant index, the byte after the version
+    pub discriminant: u8,
+    pub args: Vec<IdlField>,
+    pub accounts: Vec<IdlAccountMeta>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdlAccountLayout {
+    pub name: &'static str,
+    pub account_type: u8,
+    pub size: usize,
+    pub fields: Vec<IdlField>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Idl {
+    pub instructions: Vec<IdlInstruction>,
+    pub accounts: Vec<IdlAccountLayout>,
+}
+
+/// Account description, by position, handed to [describe_instruction](fn.describe_instruction.html).
+struct AccountName(&'static str, Option<&'static str>, Option<&'static str>);
+
+fn account(name: &'static str) -> AccountName {
+    AccountName(name, None, None)
+}
+
+fn pda(name: &'static str, seeds: &'static str) -> AccountName {
+    AccountName(name, Some(seeds), None)
+}
+
+fn optional(AccountName(name, seeds, _): AccountName, option: &'static str) -> AccountName {
+    AccountName(name, seeds, Some(option))
+}
+
+/// Describes the program.
+///
+/// # Panics
+///
+/// When the descriptions below no longer match the builders or the `Pack` impls.
+pub fn idl() -> Idl {
+    Idl {
+        instructions: instructions(),
+        accounts: vec![
+            describe_layout(
+                "Escrow",
+                AccountType::Escrow,
+                Escrow::LEN,
+                &[
+                    ("seller_pubkey", "publicKey", 32),
+                    ("token_account_pubkey", "publicKey", 32),
+                    ("mint_key", "publicKey", 32),
+                    ("expected_amount", "u64", 8),
+                    ("marketplace", "publicKey", 32),
+                    ("listing_mode", "u8", 1),
+                    ("token_program", "u8", 1),
+                    ("usd_priced", "bool", 1),
+                    ("escrow_authority_bump_seed", "u8", 1),
+                    ("metadata_bump_seed", "u8", 1),
+                    ("proceeds_vault", "bool", 1),
+                    ("padding", "[u8; 26]", ACCOUNT_PADDING_LEN - 38),
+                ],
+            ),
+            describe_layout(
+                "VaultAccount",
+                AccountType::PlatformAccount,
+                VaultAccount::LEN,
+                &[
+                    ("treasury_account", "publicKey", 32),
+                    ("base_percentage", "u64", 8),
+                    ("paused", "bool", 1),
+                    ("pass_collection", "publicKey", 32),
+                    ("pass_fee_basis_points", "u64", 8),
+                    ("referral_basis_points", "u64", 8),
+                    ("escrow_royalties", "bool", 1),
+                    ("padding", "[u8; 14]", ACCOUNT_PADDING_LEN - 50),
+                ],
+            ),
+            describe_layout(
+                "TokenSwap",
+                AccountType::TokenSwap,
+                TokenSwap::LEN,
+                &[
+                    ("seller_pubkey", "publicKey", 32),
+                    ("vault_pubkey", "publicKey", 32),
+                    ("seller_receive_pubkey", "publicKey", 32),
+                    ("offered_mint", "publicKey", 32),
+                    ("expected_mint", "publicKey", 32),
+                    ("offered_amount", "u64", 8),
+                    ("expected_amount", "u64", 8),
+                    ("native_payout", "bool", 1),
+                    ("padding", "[u8; 63]", ACCOUNT_PADDING_LEN - 1),
+                ],
+            ),
+            describe_layout(
+                "ReferrerAccount",
+                AccountType::Referrer,
+                ReferrerAccount::LEN,
+                &[
+                    ("referrer", "publicKey", 32),
+                    ("total_earned", "u64", 8),
+                    ("sales", "u64", 8),
+                    ("padding", "[u8; 64]", ACCOUNT_PADDING_LEN),
+                ],
+            ),
+            describe_layout(
+                "Marketplace",
+                AccountType::Marketplace,
+                Marketplace::LEN,
+                &[
+                    ("authority", "publicKey", 32),
+                    ("creator", "publicKey", 32),
+                    ("name", "[u8; 32]", MARKETPLACE_NAME_LEN),
+                    ("treasury_account", "publicKey", 32),
+                    ("base_percentage", "u64", 8),
+                    ("paused", "bool", 1),
+                    ("pass_collection", "publicKey", 32),
+                    ("pass_fee_basis_points", "u64", 8),
+                    ("referral_basis_points", "u64", 8),
+                    ("escrow_royalties", "bool", 1),
+                    ("padding", "[u8; 63]", ACCOUNT_PADDING_LEN - 1),
+                ],
+            ),
+            describe_layout(
+                "EditionDrop",
+                AccountType::EditionDrop,
+                EditionDrop::LEN,
+                &[
+                    ("creator", "publicKey", 32),
+                    ("master_token_account", "publicKey", 32),
+                    ("master_mint", "publicKey", 32),
+                    ("marketplace", "publicKey", 32),
+                    ("price", "u64", 8),
+                    ("max_prints", "u64", 8),
+                    ("prints_sold", "u64", 8),
+                    ("padding", "[u8; 64]", ACCOUNT_PADDING_LEN),
+                ],
+            ),
+            describe_layout(
+                "UsdListing",
+                AccountType::UsdListing,
+                UsdListing::LEN,
+                &[
+                    ("escrow", "publicKey", 32),
+                    ("price_feed", "publicKey", 32),
+                    ("bump_seed", "u8", 1),
+                    ("padding", "[u8; 63]", ACCOUNT_PADDING_LEN - 1),
+                ],
+            ),
+            describe_layout(
+                "RoyaltyVault",
+                AccountType::RoyaltyVault,
+                RoyaltyVault::LEN,
+                &[
+                    ("creator", "publicKey", 32),
+                    ("total_deposited", "u64", 8),
+                    ("total_claimed", "u64", 8),
+                    ("sales", "u64", 8),
+                    ("padding", "[u8; 64]", ACCOUNT_PADDING_LEN),
+                ],
+            ),
+            describe_layout(
+                "SellerProceeds",
+                AccountType::SellerProceeds,
+                SellerProceeds::LEN,
+                &[
+                    ("seller", "publicKey", 32),
+                    ("total_credited", "u64", 8),
+                    ("total_withdrawn", "u64", 8),
+                    ("sales", "u64", 8),
+                    ("padding", "[u8; 64]", ACCOUNT_PADDING_LEN),
+                ],
+            ),
+        ],
+    }
+}
+
+fn instructions() -> Vec<IdlInstruction> {
+    // the builders only need distinct addresses to report the account roles
+    let program_id = Pubkey::new_from_array([1; 32]);
+    let key = |n: u8| Pubkey::new_from_array([n; 32]);
+    let amount = &[("amount", "u64", 8)];
+    let swap_amounts = &[("offered_amount", "u64", 8), ("expected_amount", "u64", 8)];
+    let escrow_authority = "[\"escrow\"]";
+    let vault = "[\"vault\", swap_account]";
+    let listing_vault = "[\"vault\", escrow_account]";
+    let metadata = "[\"metadata\", metadata_program, mint] of the metadata program";
+    let edition = "[\"metadata\", metadata_program, mint, \"edition\"] of the metadata program";
+    let pass_metadata = "[\"metadata\", metadata_program, pass_mint] of the metadata program";
+    let referrer_account = "[\"referrer\", referrer]";
+    let buyer_token_account = "[taker, token_program, mint] of the associated token program";
+    let usd_listing = "[\"usd_listing\", escrow_account]";
+    let royalty_vault = "[\"royalty_vault\", creator]";
+    let seller_proceeds = "[\"seller_proceeds\", seller]";
+
+    vec![
+        describe_instruction(
+            "ListToken",
+            &EscrowInstruction::ListToken { amount: 0 },
+            amount,
+            instruction::list_token(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0),
+            &[
+                account("seller"),
+                account("token_account"),
+                account("mint"),
+                account("escrow_account"),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "Exchange",
+            &EscrowInstruction::Exchange { amount: 0 },
+            amount,
+            instruction::exchange(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                &[key(9)],
+                0,
+            ),
+            &[
+                account("taker"),
+                account("token_account"),
+                account("seller"),
+                account("mint"),
+                account("escrow_account"),
+                account("token_program"),
+                account("system_program"),
+                pda("escrow_authority", escrow_authority),
+                pda("metadata", metadata),
+                account("platform_account"),
+                account("treasury"),
+                account("creators"),
+            ],
+            true,
+        ),
+        describe_instruction(
+            "Cancel",
+            &EscrowInstruction::Cancel,
+            &[],
+            instruction::cancel_frozen(&program_id, &key(2), &key(3), &key(4), &key(5)),
+            &[
+                account("seller"),
+                account("token_account"),
+                account("escrow_account"),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+                optional(account("mint"), "frozen"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "UpdatePlatformAccount",
+            &EscrowInstruction::UpdatePlatformAccount { amount: 0 },
+            amount,
+            instruction::update_platform_account(&program_id, &key(2), &key(3), &key(4), 0),
+            &[
+                account("admin"),
+                account("platform_account"),
+                account("rent"),
+                account("treasury"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListTokenSwap",
+            &EscrowInstruction::ListTokenSwap {
+                offered_amount: 0,
+                expected_amount: 0,
+            },
+            swap_amounts,
+            instruction::list_token_swap(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), 0, 0),
+            &[
+                account("seller"),
+                account("offered_token_account"),
+                account("receive_token_account"),
+                account("offered_mint"),
+                account("swap_account"),
+                pda("vault", vault),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ExchangeTokenSwap",
+            &EscrowInstruction::ExchangeTokenSwap {
+                amount: 0,
+                expected_amount: 0,
+            },
+            &[("amount", "u64", 8), ("expected_amount", "u64", 8)],
+            instruction::exchange_token_swap(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                0,
+                0,
+            ),
+            &[
+                account("taker"),
+                account("taker_sending_token_account"),
+                account("taker_receiving_token_account"),
+                account("seller_receive_token_account"),
+                account("seller"),
+                account("swap_account"),
+                pda("vault", vault),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+                account("platform_account"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "CancelTokenSwap",
+            &EscrowInstruction::CancelTokenSwap,
+            &[],
+            instruction::cancel_token_swap(&program_id, &key(2), &key(3), &key(4)),
+            &[
+                account("seller"),
+                account("refund_token_account"),
+                account("swap_account"),
+                pda("vault", vault),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "SetPaused",
+            &EscrowInstruction::SetPaused { paused: false },
+            &[("paused", "bool", 1)],
+            instruction::set_paused(&program_id, &key(2), &key(3), false),
+            &[account("admin"), account("platform_account")],
+            false,
+        ),
+        describe_instruction(
+            "ForceDelist",
+            &EscrowInstruction::ForceDelist { reason: 0 },
+            &[("reason", "u8", 1)],
+            instruction::force_delist_delegated(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0),
+            &[
+                account("admin"),
+                account("token_account"),
+                account("escrow_account"),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+                account("platform_account"),
+                optional(account("mint"), "delegate"),
+                optional(pda("vault", listing_vault), "delegate"),
+                optional(account("system_program"), "delegate"),
+                optional(account("rent"), "delegate"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ExchangeWithOptions",
+            &EscrowInstruction::ExchangeWithOptions { amount: 0, options: 0 },
+            &[("amount", "u64", 8), ("options", "u8", 1)],
+            writable_seller(instruction::exchange_with_options(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                &ExchangeOptionalAccounts {
+                    membership_pass: Some(MembershipPass {
+                        token_account: key(9),
+                        mint: key(10),
+                    }),
+                    referrer: Some(key(11)),
+                    buyer_token_account: Some(key(12)),
+                    seller_proceeds: true,
+                },
+                &[key(13)],
+                0,
+            )),
+            &[
+                account("taker"),
+                account("token_account"),
+                account("seller"),
+                account("mint"),
+                account("escrow_account"),
+                account("token_program"),
+                account("system_program"),
+                pda("escrow_authority", escrow_authority),
+                pda("metadata", metadata),
+                account("platform_account"),
+                account("treasury"),
+                optional(account("pass_token_account"), "membership_pass"),
+                optional(pda("pass_metadata", pass_metadata), "membership_pass"),
+                optional(account("referrer"), "referrer"),
+                optional(pda("referrer_account", referrer_account), "referrer"),
+                optional(pda("buyer_token_account", buyer_token_account), "buyer_token_account"),
+                optional(pda("seller_proceeds", seller_proceeds), "seller_proceeds"),
+                account("creators"),
+            ],
+            true,
+        ),
+        describe_instruction(
+            "SetPassDiscount",
+            &EscrowInstruction::SetPassDiscount { pass_fee_basis_points: 0 },
+            &[("pass_fee_basis_points", "u64", 8)],
+            instruction::set_pass_discount(&program_id, &key(2), &key(3), &key(4), 0),
+            &[account("admin"), account("platform_account"), account("pass_collection")],
+            false,
+        ),
+        describe_instruction(
+            "SetReferralShare",
+            &EscrowInstruction::SetReferralShare { referral_basis_points: 0 },
+            &[("referral_basis_points", "u64", 8)],
+            instruction::set_referral_share(&program_id, &key(2), &key(3), 0),
+            &[account("admin"), account("platform_account")],
+            false,
+        ),
+        describe_instruction(
+            "CreateMarketplace",
+            &EscrowInstruction::CreateMarketplace {
+                name: [0; MARKETPLACE_NAME_LEN],
+                fee_basis_points: 0,
+            },
+            &[("name", "[u8; 32]", MARKETPLACE_NAME_LEN), ("fee_basis_points", "u64", 8)],
+            instruction::create_marketplace(&program_id, &key(2), &key(3), &key(4), "name", 0),
+            &[
+                account("creator"),
+                pda("marketplace", "[\"marketplace\", creator, name]"),
+                account("authority"),
+                account("treasury"),
+                account("system_program"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListTokenDelegated",
+            &EscrowInstruction::ListTokenDelegated { amount: 0 },
+            amount,
+            instruction::list_token_delegated(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0),
+            &[
+                account("seller"),
+                account("token_account"),
+                account("mint"),
+                account("escrow_account"),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+                pda("escrow_authority", escrow_authority),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListTokenFrozen",
+            &EscrowInstruction::ListTokenFrozen { amount: 0 },
+            amount,
+            instruction::list_token_frozen(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0),
+            &[
+                account("seller"),
+                account("token_account"),
+                account("mint"),
+                account("escrow_account"),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+                pda("escrow_authority", escrow_authority),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListEditionDrop",
+            &EscrowInstruction::ListEditionDrop { price: 0, max_prints: 0 },
+            &[("price", "u64", 8), ("max_prints", "u64", 8)],
+            instruction::list_edition_drop(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0, 0),
+            &[
+                account("creator"),
+                account("master_token_account"),
+                account("master_mint"),
+                account("drop_account"),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+                pda("master_edition", edition),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "BuyEdition",
+            &EscrowInstruction::BuyEdition { edition: 0 },
+            &[("edition", "u64", 8)],
+            instruction::buy_edition(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                &key(9),
+                &key(10),
+                &[key(11)],
+                1,
+            ),
+            &[
+                account("buyer"),
+                account("drop_account"),
+                account("creator"),
+                account("new_mint"),
+                pda("new_metadata", "[\"metadata\", metadata_program, new_mint] of the metadata program"),
+                pda("new_edition", "[\"metadata\", metadata_program, new_mint, \"edition\"] of the metadata program"),
+                pda("master_edition", edition),
+                pda(
+                    "edition_marker",
+                    "[\"metadata\", metadata_program, master_mint, \"edition\", edition / 248] of the metadata program",
+                ),
+                account("master_token_account"),
+                pda("master_metadata", metadata),
+                account("update_authority"),
+                pda("escrow_authority", escrow_authority),
+                account("token_program"),
+                account("system_program"),
+                account("rent"),
+                account("metadata_program"),
+                account("platform_account"),
+                account("treasury"),
+                account("creators"),
+            ],
+            true,
+        ),
+        describe_instruction(
+            "CancelEditionDrop",
+            &EscrowInstruction::CancelEditionDrop,
+            &[],
+            instruction::cancel_edition_drop(&program_id, &key(2), &key(3), &key(4)),
+            &[
+                account("creator"),
+                account("master_token_account"),
+                account("drop_account"),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListTokenSwapForSol",
+            &EscrowInstruction::ListTokenSwapForSol {
+                offered_amount: 0,
+                expected_amount: 0,
+            },
+            swap_amounts,
+            instruction::list_token_swap_for_sol(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), 0, 0),
+            &[
+                account("seller"),
+                account("offered_token_account"),
+                account("seller_wallet"),
+                account("offered_mint"),
+                account("swap_account"),
+                pda("vault", vault),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ExchangeTokenSwapWithSol",
+            &EscrowInstruction::ExchangeTokenSwapWithSol {
+                amount: 0,
+                expected_amount: 0,
+            },
+            &[("amount", "u64", 8), ("expected_amount", "u64", 8)],
+            instruction::exchange_token_swap_with_sol(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                0,
+                0,
+            ),
+            &[
+                account("taker"),
+                account("temporary_wsol_account"),
+                account("taker_receiving_token_account"),
+                account("seller_receive_account"),
+                account("seller"),
+                account("swap_account"),
+                pda("vault", vault),
+                account("token_program"),
+                pda("escrow_authority", escrow_authority),
+                account("platform_account"),
+                account("system_program"),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ListTokenUsd",
+            &EscrowInstruction::ListTokenUsd { usd_price: 0 },
+            &[("usd_price", "u64", 8)],
+            instruction::list_token_usd(&program_id, &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), 0),
+            &[
+                account("seller"),
+                account("token_account"),
+                account("mint"),
+                account("escrow_account"),
+                account("rent"),
+                account("token_program"),
+                account("system_program"),
+                account("platform_account"),
+                account("price_feed"),
+                pda("usd_listing", usd_listing),
+            ],
+            false,
+        ),
+        describe_instruction(
+            "ExchangeUsd",
+            &EscrowInstruction::ExchangeUsd {
+                amount: 0,
+                max_lamports: 0,
+                options: 0,
+            },
+            &[("amount", "u64", 8), ("max_lamports", "u64", 8), ("options", "u8", 1)],
+            writable_seller(instruction::exchange_usd(
+                &program_id,
+                &key(2),
+                &key(3),
+                &key(4),
+                &key(5),
+                &key(6),
+                &key(7),
+                &key(8),
+                &key(9),
+                &ExchangeOptionalAccounts {
+                    membership_pass: Some(MembershipPass {
+                        token_account: key(10),
+                        mint: key(11),
+                    }),
+                    referrer: Some(key(12)),
+                    buyer_token_account: Some(key(13)),
+                    seller_proceeds: true,
+                },
+                &[key(14)],
+                0,
+                0,
+            )),
+            &[
+                account("taker"),
+                account("token_account"),
+                account("seller"),
+                account("mint"),
+                account("escrow_account"),
+                account("token_program"),
+                account("system_program"),
+                pda("escrow_authority", escrow_authority),
+                pda("metadata", metadata),
+                account("platform_account"),
+                account("treasury"),
+                pda("usd_listing", usd_listing),
+                account("price_feed"),
+                optional(account("pass_token_account"), "membership_pass"),
+                optional(pda("pass_metadata", pass_metadata), "membership_pass"),
+                optional(account("referrer"), "referrer"),
+                optional(pda("referrer_account", referrer_account), "referrer"),
+                optional(pda("buyer_token_account", buyer_token_account), "buyer_token_account"),
+                optional(pda("seller_proceeds", seller_proceeds), "seller_proceeds"),
+                account("creators"),
+            ],
+            true,
+        ),
+        describe_instruction(
+            "SetEscrowRoyalties",
+            &EscrowInstruction::SetEscrowRoyalties { escrow_royalties: false },
+            &[("escrow_royalties", "bool", 1)],
+            instruction::set_escrow_royalties(&program_id, &key(2), &key(3), false),
+            &[account("admin"), account("platform_account")],
+            false,
+        ),
+        describe_instruction(
+            "ClaimRoyalties",
+            &EscrowInstruction::ClaimRoyalties,
+            &[],
+            instruction::claim_royalties(&program_id, &key(2), &key(3)),
+            &[account("creator"), pda("royalty_vault", royalty_vault), account("destination")],
+            false,
+        ),
+        describe_instruction(
+            "SetProceedsVault",
+            &EscrowInstruction::SetProceedsVault { proceeds_vault: false },
+            &[("proceeds_vault", "bool", 1)],
+            instruction::set_proceeds_vault(&program_id, &key(2), &key(3), false),
+            &[account("seller"), account("escrow_account"), account("instructions")],
+            false,
+        ),
+        describe_instruction(
+            "WithdrawProceeds",
+            &EscrowInstruction::WithdrawProceeds,
+            &[],
+            instruction::withdraw_proceeds(&program_id, &key(2), &key(3)),
+            &[account("seller"), pda("seller_proceeds", seller_proceeds), account("destination")],
+            false,
+        ),
+        describe_instruction(
+            "RegisterReferrer",
+            &EscrowInstruction::RegisterReferrer,
+            &[],
+            instruction::register_referrer(&program_id, &key(2)),
+            &[account("referrer"), pda("referrer_account", referrer_account), account("system_program")],
+            false,
+        ),
+    ]
+}
+
+// the builders only leave the seller read-only when its proceeds are credited to
+// the seller proceeds PDA, which the description lists as an option of the sale
+fn writable_seller(
+    built: Result<Instruction, solana_program::program_error::ProgramError>,
+) -> Result<Instruction, solana_program::program_error::ProgramError> {
+    built.map(|mut instruction| {
+        instruction.accounts[2].is_writable = true;
+        instruction
+    })
+}
+
+fn describe_instruction(
+    name: &'static str,
+    variant: &EscrowInstruction,
+    args: &[(&'static str, &'static str, usize)],
+    built: Result<Instruction, solana_program::program_error::ProgramError>,
+    names: &[AccountName],
+    variadic: bool,
+) -> IdlInstruction {
+    let built = built.unwrap();
+    let data = variant.pack();
+    let args = describe_fields(INSTRUCTION_ARGS_OFFSET, args);
+    let args_len: usize = args.iter().map(|arg| arg.size).sum();
+    assert_eq!(
+        data.len(),
+        INSTRUCTION_ARGS_OFFSET + args_len,
+        "arguments of {} drifted from EscrowInstruction",
+        name
+    );
+    assert_eq!(
+        built.accounts.len(),
+        names.len(),
+        "accounts of {} drifted from its builder",
+        name
+    );
+
+    let last = names.len() - 1;
+    let accounts = built
+        .accounts
+        .iter()
+        .zip(names)
+        .enumerate()
+        .map(|(position, (meta, AccountName(account_name, seeds, option)))| IdlAccountMeta {
+            name: account_name,
+            signer: meta.is_signer,
+            writable: meta.is_writable,
+            variadic: variadic && position == last,
+            address: well_known_address(&meta.pubkey),
+            pda: *seeds,
+            option: *option,
+        })
+        .collect();
+
+    IdlInstruction {
+        name,
+        discriminant: data[2],
+        args,
+        accounts,
+    }
+}
+
+fn well_known_address(pubkey: &Pubkey) -> Option<Pubkey> {
+    [
+        sysvar::rent::id(),
+        sysvar::instructions::id(),
+        spl_token::id(),
+        system_program::id(),
+        metaplex_token_metadata::id(),
+    ]
+        .into_iter()
+        .find(|address| address == pubkey)
+}
+
+fn describe_layout(
+    name: &'static str,
+    account_type: AccountType,
+    size: usize,
+    fields: &[(&'static str, &'static str, usize)],
+) -> IdlAccountLayout {
+    let mut all_fields = vec![
+        ("account_type", "u8", 1),
+        ("version", "u8", 1),
+        ("is_initialized", "bool", 1),
+        ("reserved", "[u8; 5]", ACCOUNT_HEADER_LEN - 3),
+    ];
+    all_fields.extend_from_slice(fields);
+
+    let fields = describe_fields(0, &all_fields);
+    let described: usize = fields.iter().map(|field| field.size).sum();
+    assert_eq!(described, size, "layout of {} drifted from its Pack impl", name);
+
+    IdlAccountLayout {
+        name,
+        account_type: account_type as u8,
+        size,
+        fields,
+    }
+}
+
+fn describe_fields(start: usize, fields: &[(&'static str, &'static str, usize)]) -> Vec<IdlField> {
+    let mut offset = start;
+    fields
+        .iter()
+        .map(|&(name, ty, size)| {
+            let field = IdlField {
+                name,
+                ty,
+                offset,
+                size,
+            };
+            offset += size;
+            field
+        })
+        .collect()
+}
+
+impl Idl {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "instructionEncoding": {
+                "marker": VERSIONED_INSTRUCTION_MARKER,
+                "version": INSTRUCTION_VERSION,
+                "argsOffset": INSTRUCTION_ARGS_OFFSET,
+            },
+            "exchangeOptions": {
+                "membership_pass": EXCHANGE_OPTION_MEMBERSHIP_PASS,
+                "referrer": EXCHANGE_OPTION_REFERRER,
+                "buyer_token_account": EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT,
+                "seller_proceeds": EXCHANGE_OPTION_SELLER_PROCEEDS,
+            },
+            "listingModes": {
+                "custody": ListingMode::Custody as u8,
+                "delegate": ListingMode::Delegate as u8,
+                "frozen": ListingMode::Frozen as u8,
+            },
+            "tokenPrograms": {
+                "spl_token": TokenProgram::SplToken as u8,
+                "token_2022": TokenProgram::Token2022 as u8,
+            },
+            "stateVersion": STATE_VERSION,
+            "seeds": {
+                "escrowAuthority": String::from_utf8_lossy(ESCROW_AUTHORITY_SEED),
+                "vault": String::from_utf8_lossy(VAULT_SEED),
+                "referrer": String::from_utf8_lossy(REFERRER_SEED),
+                "marketplace": String::from_utf8_lossy(MARKETPLACE_SEED),
+                "usdListing": String::from_utf8_lossy(USD_LISTING_SEED),
+                "royaltyVault": String::from_utf8_lossy(ROYALTY_VAULT_SEED),
+                "sellerProceeds": String::from_utf8_lossy(SELLER_PROCEEDS_SEED),
+            },
+            "instructions": self.instructions.iter().map(IdlInstruction::to_json).collect::<Vec<_>>(),
+            "accounts": self.accounts.iter().map(IdlAccountLayout::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl IdlInstruction {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "discriminant": self.discriminant,
+            "args": self.args.iter().map(IdlField::to_json).collect::<Vec<_>>(),
+            "accounts": self.accounts.iter().map(IdlAccountMeta::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl IdlAccountMeta {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "signer": self.signer,
+            "writable": self.writable,
+        });
+        if self.variadic {
+            value["variadic"] = json!(true);
+        }
+        if let Some(address) = self.address {
+            value["address"] = json!(address.to_string());
+        }
+        if let Some(seeds) = self.pda {
+            value["pda"] = json!(seeds);
+        }
+        if let Some(option) = self.option {
+            value["option"] = json!(option);
+        }
+        value
+    }
+}
+
+impl IdlAccountLayout {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "accountType": self.account_type,
+            "size": self.size,
+            "fields": self.fields.iter().map(IdlField::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl IdlField {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "type": self.ty,
+            "offset": self.offset,
+            "size": self.size,
+        })
+    }
+}
@@ -0,0 +1,579 @@
+//! Order book of the escrow program kept in SQLite, enabled with the `indexer` feature.
+//!
+//! [Index](struct.Index.html) materializes listings, sales and cancellations from
+//! escrow accounts and program events, [Indexer](struct.Indexer.html) feeds it from an
+//! RPC endpoint by scanning the program accounts and the logs of its transactions.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::{path::Path, str::FromStr};
+use thiserror::Error;
+
+use crate::{
+    client::{EscrowClient, EscrowClientError},
+    events::{parse_logs, EscrowEvent},
+    state::Escrow,
+};
+
+/// Errors returned by the [Index](struct.Index.html) and the [Indexer](struct.Indexer.html).
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Client(#[from] EscrowClientError),
+    #[error("invalid value stored in the index: {0}")]
+    InvalidValue(String),
+}
+
+impl From<solana_client::client_error::ClientError> for IndexerError {
+    fn from(error: solana_client::client_error::ClientError) -> Self {
+        Self::Client(error.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, IndexerError>;
+
+/// State of a listing in the index.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ListingStatus {
+    Active,
+    Sold,
+    Cancelled,
+    /// taken down by the platform admin
+    Delisted,
+    /// the escrow account was found uninitialized before the transaction closing
+    /// it was indexed
+    Closed,
+}
+
+impl ListingStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Sold => "sold",
+            Self::Cancelled => "cancelled",
+            Self::Delisted => "delisted",
+            Self::Closed => "closed",
+        }
+    }
+
+    fn parse(status: &str) -> Result<Self> {
+        match status {
+            "active" => Ok(Self::Active),
+            "sold" => Ok(Self::Sold),
+            "cancelled" => Ok(Self::Cancelled),
+            "delisted" => Ok(Self::Delisted),
+            "closed" => Ok(Self::Closed),
+            _ => Err(IndexerError::InvalidValue(status.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Listing {
+    pub escrow: Pubkey,
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub price: u64,
+    /// first verified creator of the NFT's metadata, if it was resolved
+    pub collection: Option<Pubkey>,
+    pub status: ListingStatus,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sale {
+    pub signature: String,
+    pub slot: u64,
+    pub escrow: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub royalties: u64,
+    pub platform_fee: u64,
+    pub seller_proceeds: u64,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS listings (
+        escrow TEXT PRIMARY KEY,
+        seller TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        token_account TEXT NOT NULL,
+        price INTEGER NOT NULL,
+        collection TEXT,
+        status TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS listings_by_mint ON listings (mint, status);
+    CREATE INDEX IF NOT EXISTS listings_by_seller ON listings (seller, status);
+    CREATE INDEX IF NOT EXISTS listings_by_collection ON listings (collection, status, price);
+
+    CREATE TABLE IF NOT EXISTS sales (
+        signature TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        escrow TEXT NOT NULL,
+        seller TEXT NOT NULL,
+        buyer TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        price INTEGER NOT NULL,
+        royalties INTEGER NOT NULL,
+        platform_fee INTEGER NOT NULL,
+        seller_proceeds INTEGER NOT NULL,
+        PRIMARY KEY (signature, escrow)
+    );
+    CREATE INDEX IF NOT EXISTS sales_by_mint ON sales (mint);
+    CREATE INDEX IF NOT EXISTS sales_by_seller ON sales (seller);
+
+    CREATE TABLE IF NOT EXISTS cancellations (
+        signature TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        escrow TEXT NOT NULL,
+        seller TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        PRIMARY KEY (signature, escrow)
+    );
+
+    CREATE TABLE IF NOT EXISTS log_cursor (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        signature TEXT NOT NULL
+    );
+";
+
+/// Listings, sales and cancellations of the escrow program, keyed by mint and seller.
+pub struct Index {
+    connection: Connection,
+}
+
+impl Index {
+    /// Opens, or creates, the index stored at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Records the state of an escrow account. An uninitialized account is the
+    /// tombstone of a sold or cancelled listing, it closes the listing unless
+    /// the transaction that closed it was already indexed.
+    pub fn apply_account(&self, address: &Pubkey, escrow: &Escrow, collection: Option<&Pubkey>) -> Result<()> {
+        if !escrow.is_initialized {
+            self.connection.execute(
+                "UPDATE listings SET status = ?2 WHERE escrow = ?1 AND status = ?3",
+                params![
+                    address.to_string(),
+                    ListingStatus::Closed.as_str(),
+                    ListingStatus::Active.as_str()
+                ],
+            )?;
+            return Ok(());
+        }
+        self.connection.execute(
+            "INSERT INTO listings (escrow, seller, mint, token_account, price, collection, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (escrow) DO UPDATE SET
+                 price = excluded.price,
+                 collection = COALESCE(excluded.collection, listings.collection),
+                 status = excluded.status",
+            params![
+                address.to_string(),
+                escrow.seller_pubkey.to_string(),
+                escrow.mint_key.to_string(),
+                escrow.token_account_pubkey.to_string(),
+                to_sql_amount(escrow.expected_amount)?,
+                collection.map(|collection| collection.to_string()),
+                ListingStatus::Active.as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records an event emitted by the transaction `signature`, events have to be
+    /// applied in the order they were emitted.
+    pub fn apply_event(&self, signature: &str, slot: u64, event: &EscrowEvent) -> Result<()> {
+        match event {
+            EscrowEvent::Listed {
+                escrow,
+                seller,
+                mint,
+                token_account,
+                price,
+            } => {
+                // an account scan may already know the listing, and more about it
+                self.connection.execute(
+                    "INSERT INTO listings (escrow, seller, mint, token_account, price, status)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (escrow) DO NOTHING",
+                    params![
+                        escrow.to_string(),
+                        seller.to_string(),
+                        mint.to_string(),
+                        token_account.to_string(),
+                        to_sql_amount(*price)?,
+                        ListingStatus::Active.as_str(),
+                    ],
+                )?;
+            }
+            EscrowEvent::Sold {
+                escrow,
+                seller,
+                buyer,
+                mint,
+                price,
+                royalties,
+                platform_fee,
+                seller_proceeds,
+                ..
+            } => {
+                self.insert_sale(&Sale {
+                    signature: signature.to_string(),
+                    slot,
+                    escrow: *escrow,
+                    seller: *seller,
+                    buyer: *buyer,
+                    mint: *mint,
+                    price: *price,
+                    royalties: royalties.iter().map(|royalty| royalty.amount).sum(),
+                    platform_fee: *platform_fee,
+                    seller_proceeds: *seller_proceeds,
+                })?;
+                self.set_status(escrow, ListingStatus::Sold)?;
+            }
+            // a print is recorded as a sale of its new mint by the drop
+            EscrowEvent::EditionSold {
+                drop,
+                seller,
+                buyer,
+                mint,
+                price,
+                royalties,
+                platform_fee,
+                seller_proceeds,
+                ..
+            } => {
+                self.insert_sale(&Sale {
+                    signature: signature.to_string(),
+                    slot,
+                    escrow: *drop,
+                    seller: *seller,
+                    buyer: *buyer,
+                    mint: *mint,
+                    price: *price,
+                    royalties: royalties.iter().map(|royalty| royalty.amount).sum(),
+                    platform_fee: *platform_fee,
+                    seller_proceeds: *seller_proceeds,
+                })?;
+            }
+            EscrowEvent::Cancelled { escrow, seller, mint } => {
+                self.insert_cancellation(signature, slot, escrow, seller, mint)?;
+                self.set_status(escrow, ListingStatus::Cancelled)?;
+            }
+            EscrowEvent::ForceDelisted {
+                escrow,
+                seller,
+                mint,
+                ..
+            } => {
+                self.insert_cancellation(signature, slot, escrow, seller, mint)?;
+                self.set_status(escrow, ListingStatus::Delisted)?;
+            }
+            // token swaps and configuration changes are not part of the order book
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn insert_sale(&self, sale: &Sale) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR IGNORE INTO sales
+             (signature, slot, escrow, seller, buyer, mint, price, royalties, platform_fee, seller_proceeds)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                sale.signature,
+                to_sql_amount(sale.slot)?,
+                sale.escrow.to_string(),
+                sale.seller.to_string(),
+                sale.buyer.to_string(),
+                sale.mint.to_string(),
+                to_sql_amount(sale.price)?,
+                to_sql_amount(sale.royalties)?,
+                to_sql_amount(sale.platform_fee)?,
+                to_sql_amount(sale.seller_proceeds)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn insert_cancellation(
+        &self,
+        signature: &str,
+        slot: u64,
+        escrow: &Pubkey,
+        seller: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR IGNORE INTO cancellations (signature, slot, escrow, seller, mint)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                signature,
+                to_sql_amount(slot)?,
+                escrow.to_string(),
+                seller.to_string(),
+                mint.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn set_status(&self, escrow: &Pubkey, status: ListingStatus) -> Result<()> {
+        self.connection.execute(
+            "UPDATE listings SET status = ?2 WHERE escrow = ?1",
+            params![escrow.to_string(), status.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Last transaction whose logs were indexed.
+    pub fn log_cursor(&self) -> Result<Option<String>> {
+        Ok(self
+            .connection
+            .query_row("SELECT signature FROM log_cursor WHERE id = 0", [], |row| row.get(0))
+            .optional()?)
+    }
+
+    pub fn set_log_cursor(&self, signature: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO log_cursor (id, signature) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET signature = excluded.signature",
+            params![signature],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_listing(&self, escrow: &Pubkey) -> Result<Option<Listing>> {
+        let mut listings = self.query_listings("WHERE escrow = ?1", &escrow.to_string())?;
+        Ok(listings.pop())
+    }
+
+    /// Active listings of `seller`, cheapest first.
+    pub fn seller_listings(&self, seller: &Pubkey) -> Result<Vec<Listing>> {
+        self.query_listings("WHERE seller = ?1 AND status = 'active' ORDER BY price", &seller.to_string())
+    }
+
+    /// Active listings of `mint`, cheapest first.
+    pub fn mint_listings(&self, mint: &Pubkey) -> Result<Vec<Listing>> {
+        self.query_listings("WHERE mint = ?1 AND status = 'active' ORDER BY price", &mint.to_string())
+    }
+
+    /// Cheapest active listing of `collection`, in lamports.
+    pub fn floor_price(&self, collection: &Pubkey) -> Result<Option<u64>> {
+        let floor: Option<i64> = self.connection.query_row(
+            "SELECT MIN(price) FROM listings WHERE collection = ?1 AND status = 'active'",
+            params![collection.to_string()],
+            |row| row.get(0),
+        )?;
+        floor.map(from_sql_amount).transpose()
+    }
+
+    /// Floor price of every collection with active listings.
+    pub fn floor_prices(&self) -> Result<Vec<(Pubkey, u64)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT collection, MIN(price) FROM listings
+             WHERE collection IS NOT NULL AND status = 'active'
+             GROUP BY collection ORDER BY collection",
+        )?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        rows.map(|row| {
+            let (collection, floor) = row?;
+            Ok((parse_pubkey(&collection)?, from_sql_amount(floor)?))
+        })
+        .collect()
+    }
+
+    /// Sales of `mint`, oldest first.
+    pub fn mint_sales(&self, mint: &Pubkey) -> Result<Vec<Sale>> {
+        let mut statement = self.connection.prepare(
+            "SELECT signature, slot, escrow, seller, buyer, mint, price, royalties, platform_fee, seller_proceeds
+             FROM sales WHERE mint = ?1 ORDER BY slot",
+        )?;
+        let rows = statement.query_map(params![mint.to_string()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                [row.get::<_, String>(2)?, row.get(3)?, row.get(4)?, row.get(5)?],
+                [row.get::<_, i64>(6)?, row.get(7)?, row.get(8)?, row.get(9)?],
+            ))
+        })?;
+        rows.map(|row| {
+            let (signature, slot, [escrow, seller, buyer, mint], [price, royalties, platform_fee, seller_proceeds]) =
+                row?;
+            Ok(Sale {
+                signature,
+                slot: from_sql_amount(slot)?,
+                escrow: parse_pubkey(&escrow)?,
+                seller: parse_pubkey(&seller)?,
+                buyer: parse_pubkey(&buyer)?,
+                mint: parse_pubkey(&mint)?,
+                price: from_sql_amount(price)?,
+                royalties: from_sql_amount(royalties)?,
+                platform_fee: from_sql_amount(platform_fee)?,
+                seller_proceeds: from_sql_amount(seller_proceeds)?,
+            })
+        })
+        .collect()
+    }
+
+    fn query_listings(&self, condition: &str, value: &str) -> Result<Vec<Listing>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT escrow, seller, mint, token_account, price, collection, status FROM listings {}",
+            condition
+        ))?;
+        let rows = statement.query_map(params![value], |row| {
+            Ok((
+                [row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get(3)?],
+                row.get::<_, i64>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let ([escrow, seller, mint, token_account], price, collection, status) = row?;
+            Ok(Listing {
+                escrow: parse_pubkey(&escrow)?,
+                seller: parse_pubkey(&seller)?,
+                mint: parse_pubkey(&mint)?,
+                token_account: parse_pubkey(&token_account)?,
+                price: from_sql_amount(price)?,
+                collection: collection.as_deref().map(parse_pubkey).transpose()?,
+                status: ListingStatus::parse(&status)?,
+            })
+        })
+        .collect()
+    }
+}
+
+// SQLite integers are signed 64 bit
+fn to_sql_amount(amount: u64) -> Result<i64> {
+    i64::try_from(amount).map_err(|_| IndexerError::InvalidValue(amount.to_string()))
+}
+
+fn from_sql_amount(amount: i64) -> Result<u64> {
+    u64::try_from(amount).map_err(|_| IndexerError::InvalidValue(amount.to_string()))
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey> {
+    Pubkey::from_str(pubkey).map_err(|_| IndexerError::InvalidValue(pubkey.to_string()))
+}
+
+/// Keeps an [Index](struct.Index.html) up to date from an RPC endpoint.
+pub struct Indexer {
+    client: EscrowClient,
+    index: Index,
+}
+
+impl Indexer {
+    pub fn new(client: EscrowClient, index: Index) -> Self {
+        Self { client, index }
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Indexes the transactions since the last sync, then the current escrow accounts.
+    pub fn sync(&self) -> Result<()> {
+        self.sync_logs()?;
+        self.sync_accounts()
+    }
+
+    /// Records the state of every escrow account of the program, resolving the
+    /// collection of listings the index does not know yet.
+    pub fn sync_accounts(&self) -> Result<()> {
+        for (address, escrow) in self.client.get_escrow_accounts()? {
+            let known = self
+                .index
+                .get_listing(&address)?
+                .is_some_and(|listing| listing.collection.is_some());
+            let collection = if escrow.is_initialized && !known {
+                self.collection_of(&escrow.mint_key)
+            } else {
+                None
+            };
+            self.index.apply_account(&address, &escrow, collection.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Indexes the events of the program's transactions since the log cursor,
+    /// oldest first. Failed transactions emit no events and are skipped.
+    pub fn sync_logs(&self) -> Result<()> {
+        let until = self
+            .index
+            .log_cursor()?
+            .map(|signature| parse_signature(&signature))
+            .transpose()?;
+
+        // signatures come newest first, page back to the cursor
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self.client.rpc_client().get_signatures_for_address_with_config(
+                self.client.program_id(),
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    ..GetConfirmedSignaturesForAddress2Config::default()
+                },
+            )?;
+            let last = match page.last() {
+                Some(last) => parse_signature(&last.signature)?,
+                None => break,
+            };
+            signatures.extend(page.into_iter().filter(|status| status.err.is_none()).map(|status| status.signature));
+            before = Some(last);
+        }
+
+        for signature in signatures.iter().rev() {
+            let transaction = self
+                .client
+                .rpc_client()
+                .get_transaction(&parse_signature(signature)?, UiTransactionEncoding::Json)?;
+            let logs = transaction
+                .transaction
+                .meta
+                .and_then(|meta| meta.log_messages)
+                .unwrap_or_default();
+            for event in parse_logs(self.client.program_id(), &logs) {
+                self.index.apply_event(signature, transaction.slot, &event)?;
+            }
+            self.index.set_log_cursor(signature)?;
+        }
+        Ok(())
+    }
+
+    fn collection_of(&self, mint: &Pubkey) -> Option<Pubkey> {
+        let metadata = self.client.get_metadata(mint).ok()?;
+        metadata
+            .data
+            .creators?
+            .into_iter()
+            .find(|creator| creator.verified)
+            .map(|creator| creator.address)
+    }
+}
+
+fn parse_signature(signature: &str) -> Result<Signature> {
+    Signature::from_str(signature).map_err(|_| IndexerError::InvalidValue(signature.to_string()))
+}
@@ -1,18 +1,44 @@
 
-use solana_program::program_error::ProgramError;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
 use std::convert::TryInto;
-// use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSerialize};
+use crate::{
+    pda::{
+        find_edition_address, find_edition_marker_address, find_escrow_authority, find_marketplace_address,
+        find_metadata_address, find_referrer_address, find_royalty_vault_address, find_seller_proceeds_address,
+        find_usd_listing_address, find_vault_address,
+    },
+    state::MARKETPLACE_NAME_LEN,
+};
+
+/// First byte of versioned instruction data. Legacy instructions start with their
+/// tag instead, which never reaches this value.
+pub const VERSIONED_INSTRUCTION_MARKER: u8 = 0xff;
 
-// use crate::error::EscrowError::InvalidInstruction;
-// use std::io::Read;
+/// Version of the Borsh layout written by [EscrowInstruction::pack](enum.EscrowInstruction.html#method.pack).
+pub const INSTRUCTION_VERSION: u8 = 1;
 
-#[derive(Debug, PartialEq)]
+/// Instructions are encoded as `[VERSIONED_INSTRUCTION_MARKER, INSTRUCTION_VERSION]`
+/// followed by the Borsh serialization of the variant. The legacy encoding, a tag
+/// byte followed by little-endian amounts, is still accepted by `unpack`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
 pub enum EscrowInstruction {
 
+    /// Lists an NFT for `amount` lamports. The platform account the listing is
+    /// made on comes after the system program, and is refused while paused.
     ListToken {
         amount: u64,
     },
 
+    /// Buys a listed NFT. The creator accounts owed royalties come last, in any
+    /// order; creators whose share rounds to nothing may be left out. The
+    /// [RoyaltyVault](../state/struct.RoyaltyVault.html) PDA of a creator passed
+    /// among them takes its royalty when the creator cannot take a transfer.
     Exchange {
         /// the amount the taker expects to be paid in the other token, as a u64 because that's the max possible supply of a token
         amount: u64,
@@ -24,12 +50,270 @@ pub enum EscrowInstruction {
     UpdatePlatformAccount{
         amount: u64,
     },
+
+    /// Escrows `offered_amount` of any SPL token in a vault PDA, to be released
+    /// for `expected_amount` of another SPL token. The offer can be filled partially.
+    /// The admin's platform account comes after the system program; listing is
+    /// refused while it is paused.
+    ListTokenSwap {
+        offered_amount: u64,
+        expected_amount: u64,
+    },
+
+    /// Fills a token swap. The admin's platform account comes after the escrow
+    /// authority PDA; fills are refused while it is paused.
+    ExchangeTokenSwap {
+        /// the amount of the offered token the taker takes out of the vault
+        amount: u64,
+        /// the amount of the expected token the taker expects to pay for it
+        expected_amount: u64,
+    },
+
+    CancelTokenSwap,
+
+    /// Pauses, or resumes, listing and buying on a platform account. Sellers can
+    /// still cancel their listings while it is paused. Pausing the admin's platform
+    /// account also stops token swap fills.
+    SetPaused {
+        paused: bool,
+    },
+
+    /// Hands a listed NFT back to its seller without the seller's signature, signed
+    /// by the platform admin. `reason` is a moderation code recorded in the event.
+    /// A delegated or frozen listing takes the mint after the platform account, and
+    /// its escrow authority gives up its approval. Token-2022 lets it revoke; SPL
+    /// Token only takes a revoke from the owner, so the escrow authority spends the
+    /// approval through a vault PDA of `["vault", escrow_account]` that follows the
+    /// mint with the system program and the rent sysvar, paid by the admin and
+    /// closed again.
+    ForceDelist {
+        reason: u8,
+    },
+
+    /// `Exchange` with optional accounts. Each flag set in `options` adds its
+    /// accounts between the treasury and the creators, in the order of the flags.
+    ExchangeWithOptions {
+        amount: u64,
+        options: u8,
+    },
+
+    /// Sets the membership pass collection of a platform account, and the platform
+    /// fee of sales where the taker or the seller holds a pass, at most the base fee.
+    /// Signed by the admin.
+    SetPassDiscount {
+        pass_fee_basis_points: u64,
+    },
+
+    /// Sets the share of the platform fee, in basis points of the fee, paid to
+    /// the referrer of a sale. Signed by the admin.
+    SetReferralShare {
+        referral_basis_points: u64,
+    },
+
+    /// Creates a [Marketplace](../state/struct.Marketplace.html) platform account at
+    /// the PDA of the creator and `name`, with its own authority, treasury and fee.
+    /// Anyone can create one, listings made on it pay its treasury.
+    CreateMarketplace {
+        /// UTF-8 name, zero padded
+        name: [u8; MARKETPLACE_NAME_LEN],
+        fee_basis_points: u64,
+    },
+
+    /// `ListToken` keeping the token account in the seller's wallet: the seller
+    /// approves the escrow authority PDA as delegate of the token instead of handing
+    /// it the account. Takes the accounts of `ListToken`, followed by the escrow
+    /// authority PDA.
+    ListTokenDelegated {
+        amount: u64,
+    },
+
+    /// `ListTokenDelegated` for mints whose freeze authority is the escrow authority
+    /// PDA: the token account is also frozen until the listing is sold or cancelled,
+    /// so the seller cannot move the token meanwhile. Takes the accounts of
+    /// `ListTokenDelegated`. `Cancel` of such a listing takes the mint as last
+    /// account, to thaw the token account; `ForceDelist` takes it as for
+    /// `ListTokenDelegated`.
+    ListTokenFrozen {
+        amount: u64,
+    },
+
+    /// Starts a drop of up to `max_prints` print editions of a Metaplex master
+    /// edition, sold for `price` lamports each. The master edition token is handed
+    /// to the escrow authority PDA like a `ListToken` listing, into an
+    /// [EditionDrop](../state/struct.EditionDrop.html) account created by the program.
+    ListEditionDrop {
+        price: u64,
+        max_prints: u64,
+    },
+
+    /// Prints edition number `edition` of a drop's master edition to a new mint of
+    /// the buyer, holding one token, and pays its price by the primary sale rules.
+    /// The master edition token goes back to the creator with the last print.
+    BuyEdition {
+        edition: u64,
+    },
+
+    /// Ends a drop before it sells out, handing the master edition token back to
+    /// its creator.
+    CancelEditionDrop,
+
+    /// `ListTokenSwap` for wrapped SOL, paid out to the seller as native SOL. Takes
+    /// the accounts of `ListTokenSwap` with the seller in place of the receive token
+    /// account. Such a swap is only filled with `ExchangeTokenSwapWithSol`.
+    ListTokenSwapForSol {
+        offered_amount: u64,
+        expected_amount: u64,
+    },
+
+    /// `ExchangeTokenSwap` of a swap expecting wrapped SOL, paid with the taker's
+    /// native SOL. The taker's sending token account is a temporary wrapped SOL
+    /// account of the taker, which the program funds with the price, syncs and
+    /// closes back to the taker. Takes the accounts of `ExchangeTokenSwap`, with the
+    /// taker writable, followed by the system program.
+    ExchangeTokenSwapWithSol {
+        amount: u64,
+        expected_amount: u64,
+    },
+
+    /// `ListToken` priced in USD: `usd_price` is in millionths of a dollar, paid in
+    /// lamports at the price of a SOL/USD feed of the oracle program when the listing
+    /// sells. Takes the accounts of `ListToken`, followed by the price feed and the
+    /// [UsdListing](../state/struct.UsdListing.html) PDA of the escrow account, which
+    /// the program creates. Such a listing is only bought with `ExchangeUsd`.
+    ListTokenUsd {
+        usd_price: u64,
+    },
+
+    /// `ExchangeWithOptions` of a listing priced in USD, failing when its lamport
+    /// value at the current price of the feed is more than `max_lamports`. The
+    /// `UsdListing` PDA of the escrow account and its price feed follow the treasury,
+    /// before the accounts of `options`.
+    ExchangeUsd {
+        amount: u64,
+        max_lamports: u64,
+        options: u8,
+    },
+
+    /// Deposits the royalties of every sale on the platform account into the
+    /// royalty vaults of their creators while `escrow_royalties` is set, instead of
+    /// only those of creators that cannot take a transfer. Signed by the admin.
+    SetEscrowRoyalties {
+        escrow_royalties: bool,
+    },
+
+    /// Moves the unclaimed royalties of a [RoyaltyVault](../state/struct.RoyaltyVault.html)
+    /// to a destination account. Signed by the creator of the vault.
+    ClaimRoyalties,
+
+    /// Credits the proceeds of a listing to the [SellerProceeds](../state/struct.SellerProceeds.html)
+    /// PDA of its seller while `proceeds_vault` is set, instead of transferring them
+    /// to the seller. Signed by the seller, in the transaction listing the token,
+    /// after the listing instruction; the instructions sysvar follows the escrow
+    /// account. A sale of such a listing passes the PDA with `EXCHANGE_OPTION_SELLER_PROCEEDS`.
+    SetProceedsVault {
+        proceeds_vault: bool,
+    },
+
+    /// Moves the balance of a `SellerProceeds` to a destination account. Signed by
+    /// the seller.
+    WithdrawProceeds,
+
+    /// Creates the [ReferrerAccount](../state/struct.ReferrerAccount.html) PDA of
+    /// the referrer, which has to exist before a sale pays it. Signed and paid for
+    /// by the referrer.
+    RegisterReferrer,
+}
+
+/// `ExchangeWithOptions` flag: the membership pass token account of the taker or
+/// the seller follows, then the metadata of its mint.
+pub const EXCHANGE_OPTION_MEMBERSHIP_PASS: u8 = 1;
+
+/// `ExchangeWithOptions` flag: the referrer follows, then its
+/// [ReferrerAccount](../state/struct.ReferrerAccount.html) PDA, which the referrer
+/// created beforehand with `RegisterReferrer`.
+pub const EXCHANGE_OPTION_REFERRER: u8 = 2;
+
+/// `ExchangeWithOptions` flag: the associated token account of the taker for the
+/// mint follows, which receives the token of a delegated listing. It has to exist.
+pub const EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT: u8 = 4;
+
+/// `ExchangeWithOptions` flag: the [SellerProceeds](../state/struct.SellerProceeds.html)
+/// PDA of the seller follows, credited with the proceeds of a listing in
+/// proceeds-vault mode and created by the taker on the first credited sale. It is
+/// required for such a listing, whose seller account is then only read.
+pub const EXCHANGE_OPTION_SELLER_PROCEEDS: u8 = 8;
+
+/// Membership pass presented to get the discounted platform fee.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct MembershipPass {
+    /// token account of the taker or the seller holding the pass
+    pub token_account: Pubkey,
+    pub mint: Pubkey,
+}
+
+/// Optional accounts of an `ExchangeWithOptions` instruction.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct ExchangeOptionalAccounts {
+    pub membership_pass: Option<MembershipPass>,
+    /// partner that routed the taker to the listing, paid a share of the platform fee
+    pub referrer: Option<Pubkey>,
+    /// associated token account of the taker, required to buy a delegated listing
+    pub buyer_token_account: Option<Pubkey>,
+    /// pass the `SellerProceeds` PDA of the seller, required to buy a listing in
+    /// proceeds-vault mode
+    pub seller_proceeds: bool,
+}
+
+impl ExchangeOptionalAccounts {
+    /// `ExchangeWithOptions` flags of the accounts that are set.
+    pub fn options(&self) -> u8 {
+        let mut options = 0;
+        if self.membership_pass.is_some() {
+            options |= EXCHANGE_OPTION_MEMBERSHIP_PASS;
+        }
+        if self.referrer.is_some() {
+            options |= EXCHANGE_OPTION_REFERRER;
+        }
+        if self.buyer_token_account.is_some() {
+            options |= EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT;
+        }
+        if self.seller_proceeds {
+            options |= EXCHANGE_OPTION_SELLER_PROCEEDS;
+        }
+        options
+    }
 }
 
 
 impl EscrowInstruction {
+    /// Packs a [EscrowInstruction](enum.EscrowInstruction.html) into the versioned byte format.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = vec![VERSIONED_INSTRUCTION_MARKER, INSTRUCTION_VERSION];
+        // writing into a Vec cannot fail
+        self.serialize(&mut buf).unwrap();
+        buf
+    }
+
     /// Unpacks a byte buffer into a [EscrowInstruction](enum.EscrowInstruction.html).
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        match input.split_first() {
+            Some((&VERSIONED_INSTRUCTION_MARKER, rest)) => Self::unpack_versioned(rest),
+            _ => Self::unpack_legacy(input),
+        }
+    }
+
+    fn unpack_versioned(input: &[u8]) -> Result<Self, ProgramError> {
+        let (version, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        match *version {
+            INSTRUCTION_VERSION => {
+                Self::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)
+            }
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    /// Unpacks the tag-and-amount encoding used before instructions were versioned.
+    fn unpack_legacy(input: &[u8]) -> Result<Self, ProgramError> {
         let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
 
         Ok(match tag {
@@ -43,6 +327,15 @@ impl EscrowInstruction {
             3 => Self::UpdatePlatformAccount {
                 amount: Self::unpack_amount(rest)?,
             },
+            4 => Self::ListTokenSwap {
+                offered_amount: Self::unpack_amount(rest)?,
+                expected_amount: Self::unpack_amount(rest.get(8..).unwrap_or_default())?,
+            },
+            5 => Self::ExchangeTokenSwap {
+                amount: Self::unpack_amount(rest)?,
+                expected_amount: Self::unpack_amount(rest.get(8..).unwrap_or_default())?,
+            },
+            6 => Self::CancelTokenSwap,
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
@@ -55,4 +348,889 @@ impl EscrowInstruction {
             .ok_or(ProgramError::InvalidInstructionData)?;
         Ok(amount)
     }
-}
\ No newline at end of file
+}
+
+/// Creates a `ListToken` instruction. `escrow_account` is created by the program,
+/// so it has to sign along with the seller. Listing fails while `platform_account`
+/// is paused.
+pub fn list_token(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*escrow_account, true),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ListToken { amount }.pack(),
+    })
+}
+
+/// Creates a `ListTokenUsd` instruction, listing the NFT for `usd_price` millionths
+/// of a dollar paid at the price of `price_feed`.
+#[allow(clippy::too_many_arguments)]
+pub fn list_token_usd(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    price_feed: &Pubkey,
+    usd_price: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = list_token(
+        program_id,
+        seller,
+        token_account,
+        mint,
+        escrow_account,
+        platform_account,
+        usd_price,
+    )?;
+    let (usd_listing, _usd_listing_bump_seed) = find_usd_listing_address(program_id, escrow_account);
+    instruction.accounts.push(AccountMeta::new_readonly(*price_feed, false));
+    instruction.accounts.push(AccountMeta::new(usd_listing, false));
+    instruction.data = EscrowInstruction::ListTokenUsd { usd_price }.pack();
+    Ok(instruction)
+}
+
+/// Creates a `ListTokenDelegated` instruction, listing the NFT in `token_account`
+/// while it stays in the seller's wallet.
+pub fn list_token_delegated(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = list_token(
+        program_id,
+        seller,
+        token_account,
+        mint,
+        escrow_account,
+        platform_account,
+        amount,
+    )?;
+    let (pda, _nonce) = find_escrow_authority(program_id);
+    instruction.accounts.push(AccountMeta::new_readonly(pda, false));
+    instruction.data = EscrowInstruction::ListTokenDelegated { amount }.pack();
+    Ok(instruction)
+}
+
+/// Creates a `ListTokenFrozen` instruction, listing the NFT in `token_account`
+/// while it stays frozen in the seller's wallet. The freeze authority of `mint`
+/// must be the escrow authority PDA.
+pub fn list_token_frozen(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = list_token_delegated(
+        program_id,
+        seller,
+        token_account,
+        mint,
+        escrow_account,
+        platform_account,
+        amount,
+    )?;
+    instruction.data = EscrowInstruction::ListTokenFrozen { amount }.pack();
+    Ok(instruction)
+}
+
+/// Creates an `Exchange` instruction. `creators` are the creator addresses of the
+/// mint's metadata, in any order. Creators owed no royalties may be left out, see
+/// [royalty_accounts](fn.royalty_accounts.html) to pass their royalty vaults as well.
+#[allow(clippy::too_many_arguments)]
+pub fn exchange(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    token_account: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    treasury: &Pubkey,
+    creators: &[Pubkey],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = exchange_with_options(
+        program_id,
+        taker,
+        token_account,
+        seller,
+        mint,
+        escrow_account,
+        platform_account,
+        treasury,
+        &ExchangeOptionalAccounts::default(),
+        creators,
+        amount,
+    )?;
+    instruction.data = EscrowInstruction::Exchange { amount }.pack();
+    Ok(instruction)
+}
+
+/// Creates an `ExchangeWithOptions` instruction, an `Exchange` also passing the
+/// accounts set in `optional_accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn exchange_with_options(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    token_account: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    treasury: &Pubkey,
+    optional_accounts: &ExchangeOptionalAccounts,
+    creators: &[Pubkey],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let (pda, _nonce) = find_escrow_authority(program_id);
+    let (metadata, _metadata_bump_seed) = find_metadata_address(mint);
+
+    // proceeds credited to the seller's proceeds PDA leave the seller account alone
+    let seller_account = if optional_accounts.seller_proceeds {
+        AccountMeta::new_readonly(*seller, false)
+    } else {
+        AccountMeta::new(*seller, false)
+    };
+    let mut accounts = vec![
+        AccountMeta::new(*taker, true),
+        AccountMeta::new(*token_account, false),
+        seller_account,
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(metadata, false),
+        AccountMeta::new_readonly(*platform_account, false),
+        AccountMeta::new(*treasury, false),
+    ];
+
+    if let Some(pass) = &optional_accounts.membership_pass {
+        let (pass_metadata, _pass_metadata_bump_seed) = find_metadata_address(&pass.mint);
+        accounts.push(AccountMeta::new_readonly(pass.token_account, false));
+        accounts.push(AccountMeta::new_readonly(pass_metadata, false));
+    }
+    if let Some(referrer) = &optional_accounts.referrer {
+        let (referrer_account, _referrer_bump_seed) = find_referrer_address(program_id, referrer);
+        accounts.push(AccountMeta::new(*referrer, false));
+        accounts.push(AccountMeta::new(referrer_account, false));
+    }
+    if let Some(buyer_token_account) = &optional_accounts.buyer_token_account {
+        accounts.push(AccountMeta::new(*buyer_token_account, false));
+    }
+    if optional_accounts.seller_proceeds {
+        let (seller_proceeds, _seller_proceeds_bump_seed) = find_seller_proceeds_address(program_id, seller);
+        accounts.push(AccountMeta::new(seller_proceeds, false));
+    }
+    accounts.extend(creators.iter().map(|creator| AccountMeta::new(*creator, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ExchangeWithOptions {
+            amount,
+            options: optional_accounts.options(),
+        }
+        .pack(),
+    })
+}
+
+/// Creates an `ExchangeUsd` instruction buying a listing priced in USD for at most
+/// `max_lamports`. `price_feed` is the feed recorded in the listing's `UsdListing`.
+#[allow(clippy::too_many_arguments)]
+pub fn exchange_usd(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    token_account: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    treasury: &Pubkey,
+    price_feed: &Pubkey,
+    optional_accounts: &ExchangeOptionalAccounts,
+    creators: &[Pubkey],
+    amount: u64,
+    max_lamports: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = exchange_with_options(
+        program_id,
+        taker,
+        token_account,
+        seller,
+        mint,
+        escrow_account,
+        platform_account,
+        treasury,
+        optional_accounts,
+        creators,
+        amount,
+    )?;
+    let (usd_listing, _usd_listing_bump_seed) = find_usd_listing_address(program_id, escrow_account);
+    // right after the treasury
+    instruction.accounts.insert(11, AccountMeta::new_readonly(usd_listing, false));
+    instruction.accounts.insert(12, AccountMeta::new_readonly(*price_feed, false));
+    instruction.data = EscrowInstruction::ExchangeUsd {
+        amount,
+        max_lamports,
+        options: optional_accounts.options(),
+    }
+    .pack();
+    Ok(instruction)
+}
+
+/// Creates a `Cancel` instruction, handing the escrowed token account back to the seller.
+pub fn cancel(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    escrow_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (pda, _nonce) = find_escrow_authority(program_id);
+
+    let accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::Cancel.pack(),
+    })
+}
+
+/// Creates a `Cancel` instruction for a listing made with `ListTokenFrozen`,
+/// thawing the token account of `mint`.
+pub fn cancel_frozen(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = cancel(program_id, seller, token_account, escrow_account)?;
+    instruction.accounts.push(AccountMeta::new_readonly(*mint, false));
+    Ok(instruction)
+}
+
+/// Points an NFT listing instruction built by this module at `token_program`,
+/// the token program of the listed mint, for NFTs of Token-2022 rather than SPL Token.
+pub fn with_token_program(mut instruction: Instruction, token_program: &Pubkey) -> Instruction {
+    for account in instruction.accounts.iter_mut() {
+        if account.pubkey == spl_token::id() {
+            account.pubkey = *token_program;
+        }
+    }
+    instruction
+}
+
+/// Creates an `UpdatePlatformAccount` instruction setting the treasury and the
+/// platform fee, in basis points of at most 10000.
+pub fn update_platform_account(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    platform_account: &Pubkey,
+    treasury: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*platform_account, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(*treasury, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::UpdatePlatformAccount { amount }.pack(),
+    })
+}
+
+/// Creates a `ListTokenSwap` instruction. `swap_account` is created by the program,
+/// so it has to sign along with the seller. Listing fails while the admin's
+/// `platform_account` is paused.
+#[allow(clippy::too_many_arguments)]
+pub fn list_token_swap(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    offered_token_account: &Pubkey,
+    receive_token_account: &Pubkey,
+    offered_mint: &Pubkey,
+    swap_account: &Pubkey,
+    platform_account: &Pubkey,
+    offered_amount: u64,
+    expected_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let (vault, _vault_nonce) = find_vault_address(program_id, swap_account);
+
+    let accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*offered_token_account, false),
+        AccountMeta::new_readonly(*receive_token_account, false),
+        AccountMeta::new_readonly(*offered_mint, false),
+        AccountMeta::new(*swap_account, true),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ListTokenSwap { offered_amount, expected_amount }.pack(),
+    })
+}
+
+/// Creates an `ExchangeTokenSwap` instruction taking `amount` of the offered token
+/// for `expected_amount` of the expected one. Filling fails while the admin's
+/// `platform_account` is paused.
+#[allow(clippy::too_many_arguments)]
+pub fn exchange_token_swap(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    taker_sending_token_account: &Pubkey,
+    taker_receiving_token_account: &Pubkey,
+    seller_receive_token_account: &Pubkey,
+    seller: &Pubkey,
+    swap_account: &Pubkey,
+    platform_account: &Pubkey,
+    amount: u64,
+    expected_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let (vault, _vault_nonce) = find_vault_address(program_id, swap_account);
+    let (pda, _nonce) = find_escrow_authority(program_id);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*taker, true),
+        AccountMeta::new(*taker_sending_token_account, false),
+        AccountMeta::new(*taker_receiving_token_account, false),
+        AccountMeta::new(*seller_receive_token_account, false),
+        AccountMeta::new(*seller, false),
+        AccountMeta::new(*swap_account, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ExchangeTokenSwap { amount, expected_amount }.pack(),
+    })
+}
+
+/// Creates a `ListTokenSwapForSol` instruction, listing `offered_amount` of the
+/// offered token for `expected_amount` lamports paid to the seller's wallet.
+#[allow(clippy::too_many_arguments)]
+pub fn list_token_swap_for_sol(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    offered_token_account: &Pubkey,
+    offered_mint: &Pubkey,
+    swap_account: &Pubkey,
+    platform_account: &Pubkey,
+    offered_amount: u64,
+    expected_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = list_token_swap(
+        program_id,
+        seller,
+        offered_token_account,
+        seller,
+        offered_mint,
+        swap_account,
+        platform_account,
+        offered_amount,
+        expected_amount,
+    )?;
+    instruction.accounts[2].is_writable = true;
+    instruction.data = EscrowInstruction::ListTokenSwapForSol { offered_amount, expected_amount }.pack();
+    Ok(instruction)
+}
+
+/// Creates an `ExchangeTokenSwapWithSol` instruction, paying for `amount` of the
+/// offered token with `expected_amount` lamports through `temporary_wsol_account`,
+/// a wrapped SOL account of the taker closed by the program. `seller_receive_account` is the
+/// seller's wallet when the swap pays out native SOL.
+#[allow(clippy::too_many_arguments)]
+pub fn exchange_token_swap_with_sol(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    temporary_wsol_account: &Pubkey,
+    taker_receiving_token_account: &Pubkey,
+    seller_receive_account: &Pubkey,
+    seller: &Pubkey,
+    swap_account: &Pubkey,
+    platform_account: &Pubkey,
+    amount: u64,
+    expected_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = exchange_token_swap(
+        program_id,
+        taker,
+        temporary_wsol_account,
+        taker_receiving_token_account,
+        seller_receive_account,
+        seller,
+        swap_account,
+        platform_account,
+        amount,
+        expected_amount,
+    )?;
+    instruction.accounts[0].is_writable = true;
+    instruction.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    instruction.data = EscrowInstruction::ExchangeTokenSwapWithSol { amount, expected_amount }.pack();
+    Ok(instruction)
+}
+
+/// Creates a `CancelTokenSwap` instruction, refunding what is left in the vault.
+pub fn cancel_token_swap(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    refund_token_account: &Pubkey,
+    swap_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (vault, _vault_nonce) = find_vault_address(program_id, swap_account);
+    let (pda, _nonce) = find_escrow_authority(program_id);
+
+    let accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*refund_token_account, false),
+        AccountMeta::new(*swap_account, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::CancelTokenSwap.pack(),
+    })
+}
+
+/// Creates a `SetPaused` instruction, signed by the platform admin.
+pub fn set_paused(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    platform_account: &Pubkey,
+    paused: bool,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SetPaused { paused }.pack(),
+    })
+}
+
+/// Creates a `ForceDelist` instruction, signed by the authority of the platform
+/// account the listing was made on, returning the escrowed token account to the
+/// seller recorded in `escrow_account`.
+pub fn force_delist(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    reason: u8,
+) -> Result<Instruction, ProgramError> {
+    let (pda, _nonce) = find_escrow_authority(program_id);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ForceDelist { reason }.pack(),
+    })
+}
+
+/// Creates a `ForceDelist` instruction for a listing made with `ListTokenDelegated`,
+/// clearing the approval of the escrow authority over the token account of `mint`.
+/// An SPL Token approval is spent through a vault of the listing, which the admin
+/// pays for and gets back within the instruction.
+pub fn force_delist_delegated(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    mint: &Pubkey,
+    reason: u8,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = force_delist(program_id, admin, token_account, escrow_account, platform_account, reason)?;
+    let (vault, _vault_nonce) = find_vault_address(program_id, escrow_account);
+    instruction.accounts[0].is_writable = true;
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(vault, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ]);
+    Ok(instruction)
+}
+
+/// Creates a `ForceDelist` instruction for a listing made with `ListTokenFrozen`,
+/// thawing the token account of `mint` and clearing the approval like
+/// [`force_delist_delegated`].
+pub fn force_delist_frozen(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    token_account: &Pubkey,
+    escrow_account: &Pubkey,
+    platform_account: &Pubkey,
+    mint: &Pubkey,
+    reason: u8,
+) -> Result<Instruction, ProgramError> {
+    force_delist_delegated(program_id, admin, token_account, escrow_account, platform_account, mint, reason)
+}
+
+/// Creates a `SetPassDiscount` instruction, signed by the platform admin. Holders
+/// of NFTs with `pass_collection` as verified creator pay `pass_fee_basis_points`.
+pub fn set_pass_discount(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    platform_account: &Pubkey,
+    pass_collection: &Pubkey,
+    pass_fee_basis_points: u64,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*platform_account, false),
+        AccountMeta::new_readonly(*pass_collection, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SetPassDiscount { pass_fee_basis_points }.pack(),
+    })
+}
+
+/// Creates a `SetReferralShare` instruction, signed by the platform admin.
+pub fn set_referral_share(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    platform_account: &Pubkey,
+    referral_basis_points: u64,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SetReferralShare { referral_basis_points }.pack(),
+    })
+}
+
+/// Creates a `SetEscrowRoyalties` instruction, signed by the platform admin.
+pub fn set_escrow_royalties(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    platform_account: &Pubkey,
+    escrow_royalties: bool,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*platform_account, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SetEscrowRoyalties { escrow_royalties }.pack(),
+    })
+}
+
+/// Creates a `ClaimRoyalties` instruction moving the unclaimed royalties of
+/// `creator` to `destination`, which may be the creator itself.
+pub fn claim_royalties(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    destination: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (royalty_vault, _royalty_vault_bump_seed) = find_royalty_vault_address(program_id, creator);
+    let accounts = vec![
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new(royalty_vault, false),
+        AccountMeta::new(*destination, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ClaimRoyalties.pack(),
+    })
+}
+
+/// Creates a `SetProceedsVault` instruction, signed by the seller of the listing,
+/// to follow the instruction listing it in the same transaction.
+pub fn set_proceeds_vault(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    escrow_account: &Pubkey,
+    proceeds_vault: bool,
+) -> Result<Instruction, ProgramError> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*seller, true),
+        AccountMeta::new(*escrow_account, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SetProceedsVault { proceeds_vault }.pack(),
+    })
+}
+
+/// Creates a `WithdrawProceeds` instruction moving the proceeds credited to
+/// `seller` to `destination`, which may be the seller itself.
+pub fn withdraw_proceeds(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    destination: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (seller_proceeds, _seller_proceeds_bump_seed) = find_seller_proceeds_address(program_id, seller);
+    let accounts = vec![
+        AccountMeta::new_readonly(*seller, true),
+        AccountMeta::new(seller_proceeds, false),
+        AccountMeta::new(*destination, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::WithdrawProceeds.pack(),
+    })
+}
+
+/// Creates a `RegisterReferrer` instruction creating the referrer account of
+/// `referrer`, which pays for it.
+pub fn register_referrer(program_id: &Pubkey, referrer: &Pubkey) -> Result<Instruction, ProgramError> {
+    let (referrer_account, _referrer_bump_seed) = find_referrer_address(program_id, referrer);
+    let accounts = vec![
+        AccountMeta::new(*referrer, true),
+        AccountMeta::new(referrer_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::RegisterReferrer.pack(),
+    })
+}
+
+/// Creator accounts paying royalties to `creators` through the builders of the
+/// sales: each creator followed by its [RoyaltyVault](../state/struct.RoyaltyVault.html)
+/// PDA, so the program can deposit the royalty of a creator that cannot take a
+/// transfer, or of every creator while the platform escrows royalties.
+pub fn royalty_accounts(program_id: &Pubkey, creators: &[Pubkey]) -> Vec<Pubkey> {
+    creators
+        .iter()
+        .flat_map(|creator| [*creator, find_royalty_vault_address(program_id, creator).0])
+        .collect()
+}
+
+/// Creates a `CreateMarketplace` instruction, paid for by `creator`. `authority`
+/// signs the later configuration changes of the marketplace. `name` is at most
+/// `MARKETPLACE_NAME_LEN` bytes.
+pub fn create_marketplace(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    authority: &Pubkey,
+    treasury: &Pubkey,
+    name: &str,
+    fee_basis_points: u64,
+) -> Result<Instruction, ProgramError> {
+    if name.len() > MARKETPLACE_NAME_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut padded_name = [0; MARKETPLACE_NAME_LEN];
+    padded_name[..name.len()].copy_from_slice(name.as_bytes());
+    let (marketplace, _marketplace_bump_seed) = find_marketplace_address(program_id, creator, name);
+
+    let accounts = vec![
+        AccountMeta::new(*creator, true),
+        AccountMeta::new(marketplace, false),
+        AccountMeta::new_readonly(*authority, false),
+        AccountMeta::new_readonly(*treasury, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::CreateMarketplace {
+            name: padded_name,
+            fee_basis_points,
+        }
+        .pack(),
+    })
+}
+
+/// Creates a `ListEditionDrop` instruction for the master edition token of
+/// `master_mint` held in `token_account`. `drop_account` is created by the program,
+/// so it has to sign along with the creator.
+#[allow(clippy::too_many_arguments)]
+pub fn list_edition_drop(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    token_account: &Pubkey,
+    master_mint: &Pubkey,
+    drop_account: &Pubkey,
+    platform_account: &Pubkey,
+    price: u64,
+    max_prints: u64,
+) -> Result<Instruction, ProgramError> {
+    let (master_edition, _master_edition_bump_seed) = find_edition_address(master_mint);
+
+    let accounts = vec![
+        AccountMeta::new(*creator, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new_readonly(*master_mint, false),
+        AccountMeta::new(*drop_account, true),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*platform_account, false),
+        AccountMeta::new_readonly(master_edition, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ListEditionDrop { price, max_prints }.pack(),
+    })
+}
+
+/// Creates a `BuyEdition` instruction printing edition number `edition` to
+/// `new_mint`, a mint with the buyer as mint authority and one token minted.
+/// `update_authority` is the update authority of the master edition's metadata,
+/// `creators` the creator addresses of that metadata, in any order, as for `exchange`.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_edition(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    drop_account: &Pubkey,
+    creator: &Pubkey,
+    master_mint: &Pubkey,
+    master_token_account: &Pubkey,
+    new_mint: &Pubkey,
+    update_authority: &Pubkey,
+    platform_account: &Pubkey,
+    treasury: &Pubkey,
+    creators: &[Pubkey],
+    edition: u64,
+) -> Result<Instruction, ProgramError> {
+    let (pda, _nonce) = find_escrow_authority(program_id);
+    let (new_metadata, _new_metadata_bump_seed) = find_metadata_address(new_mint);
+    let (new_edition, _new_edition_bump_seed) = find_edition_address(new_mint);
+    let (master_edition, _master_edition_bump_seed) = find_edition_address(master_mint);
+    let (edition_marker, _edition_marker_bump_seed) = find_edition_marker_address(master_mint, edition);
+    let (master_metadata, _master_metadata_bump_seed) = find_metadata_address(master_mint);
+
+    let mut accounts = vec![
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new(*drop_account, false),
+        AccountMeta::new(*creator, false),
+        AccountMeta::new(*new_mint, false),
+        AccountMeta::new(new_metadata, false),
+        AccountMeta::new(new_edition, false),
+        AccountMeta::new(master_edition, false),
+        AccountMeta::new(edition_marker, false),
+        AccountMeta::new(*master_token_account, false),
+        AccountMeta::new_readonly(master_metadata, false),
+        AccountMeta::new_readonly(*update_authority, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(metaplex_token_metadata::id(), false),
+        AccountMeta::new_readonly(*platform_account, false),
+        AccountMeta::new(*treasury, false),
+    ];
+    accounts.extend(creators.iter().map(|creator| AccountMeta::new(*creator, false)));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::BuyEdition { edition }.pack(),
+    })
+}
+
+/// Creates a `CancelEditionDrop` instruction, handing the master edition token
+/// in `master_token_account` back to the creator.
+pub fn cancel_edition_drop(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    master_token_account: &Pubkey,
+    drop_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (pda, _nonce) = find_escrow_authority(program_id);
+
+    let accounts = vec![
+        AccountMeta::new(*creator, true),
+        AccountMeta::new(*master_token_account, false),
+        AccountMeta::new(*drop_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(pda, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::CancelEditionDrop.pack(),
+    })
+}
@@ -1,5 +1,18 @@
+pub mod error;
 pub mod state;
 pub mod processor;
 pub mod instruction;
+pub mod events;
+pub mod payout;
+pub mod pda;
+pub mod price_feed;
+pub mod token;
+pub mod view;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "idl")]
+pub mod idl;
+#[cfg(feature = "indexer")]
+pub mod indexer;
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
\ No newline at end of file
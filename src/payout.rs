@@ -0,0 +1,73 @@
+use metaplex_token_metadata::state::Creator;
+
+use crate::events::RoyaltyPayout;
+
+/// How the price of a sold NFT is split between its creators, the platform
+/// treasury and the seller, in lamports.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Payout {
+    /// one entry per creator of the metadata, in the same order
+    pub royalties: Vec<RoyaltyPayout>,
+    pub platform_fee: u64,
+    pub seller_proceeds: u64,
+}
+
+/// Splits `price` the way `Exchange` pays it out: `seller_fee_basis_points` of the
+/// price go to the creators by their share, `platform_fee_basis_points` of the price
+/// to the treasury and the rest to the seller. Every part is rounded down, so the
+/// seller gets what rounding leaves over. `None` if the fees exceed the price.
+pub fn compute_payout(
+    price: u64,
+    seller_fee_basis_points: u16,
+    creators: &[Creator],
+    platform_fee_basis_points: u64,
+) -> Option<Payout> {
+    let total_royalty = (seller_fee_basis_points as u64).checked_mul(price)? / 10000;
+
+    let mut seller_proceeds = price;
+    let mut royalties = Vec::with_capacity(creators.len());
+    for creator in creators {
+        let amount = (creator.share as u64).checked_mul(total_royalty)? / 100;
+        seller_proceeds = seller_proceeds.checked_sub(amount)?;
+        royalties.push(RoyaltyPayout {
+            creator: creator.address,
+            amount,
+        });
+    }
+
+    let platform_fee = price.checked_mul(platform_fee_basis_points)? / 10000;
+    seller_proceeds = seller_proceeds.checked_sub(platform_fee)?;
+
+    Some(Payout {
+        royalties,
+        platform_fee,
+        seller_proceeds,
+    })
+}
+
+/// Splits the `price` of a print edition the way `BuyEdition` pays it out, by the
+/// primary sale rules: `platform_fee_basis_points` of the price go to the treasury
+/// and the rest to the creators by their share. Every part is rounded down, the
+/// seller, the lister of the drop, gets what rounding leaves over and everything
+/// when there are no creators. `None` if the fee exceeds the price.
+pub fn compute_primary_payout(price: u64, creators: &[Creator], platform_fee_basis_points: u64) -> Option<Payout> {
+    let platform_fee = price.checked_mul(platform_fee_basis_points)? / 10000;
+    let proceeds = price.checked_sub(platform_fee)?;
+
+    let mut seller_proceeds = proceeds;
+    let mut royalties = Vec::with_capacity(creators.len());
+    for creator in creators {
+        let amount = (creator.share as u64).checked_mul(proceeds)? / 100;
+        seller_proceeds = seller_proceeds.checked_sub(amount)?;
+        royalties.push(RoyaltyPayout {
+            creator: creator.address,
+            amount,
+        });
+    }
+
+    Some(Payout {
+        royalties,
+        platform_fee,
+        seller_proceeds,
+    })
+}
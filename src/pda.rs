@@ -0,0 +1,148 @@
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed of the pda that holds custody of every escrowed token account
+pub const ESCROW_AUTHORITY_SEED: &[u8] = b"escrow";
+
+/// Seed prefix of the vault token account of a token swap
+pub const VAULT_SEED: &[u8] = b"vault";
+
+/// Seed prefix of the account tracking the earnings of a referrer
+pub const REFERRER_SEED: &[u8] = b"referrer";
+
+/// Seed prefix of a marketplace platform account
+pub const MARKETPLACE_SEED: &[u8] = b"marketplace";
+
+/// Seed prefix of the account recording the price feed of a listing priced in USD
+pub const USD_LISTING_SEED: &[u8] = b"usd_listing";
+
+/// Seed prefix of the account holding the royalties of a creator
+pub const ROYALTY_VAULT_SEED: &[u8] = b"royalty_vault";
+
+/// Seed prefix of the account holding the proceeds credited to a seller
+pub const SELLER_PROCEEDS_SEED: &[u8] = b"seller_proceeds";
+
+/// Finds the pda that escrowed token accounts are handed to.
+pub fn find_escrow_authority(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ESCROW_AUTHORITY_SEED], program_id)
+}
+
+/// Recreates the escrow authority pda from the bump seed `find_escrow_authority`
+/// returned, which costs a fraction of searching for it again.
+pub fn create_escrow_authority(program_id: &Pubkey, bump_seed: u8) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_program_address(&[ESCROW_AUTHORITY_SEED, &[bump_seed]], program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+/// Finds the vault token account of the token swap stored in `swap_account`.
+pub fn find_vault_address(program_id: &Pubkey, swap_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, swap_account.as_ref()], program_id)
+}
+
+/// Finds the [ReferrerAccount](../state/struct.ReferrerAccount.html) of `referrer`.
+pub fn find_referrer_address(program_id: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REFERRER_SEED, referrer.as_ref()], program_id)
+}
+
+/// Finds the [RoyaltyVault](../state/struct.RoyaltyVault.html) of `creator`.
+pub fn find_royalty_vault_address(program_id: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROYALTY_VAULT_SEED, creator.as_ref()], program_id)
+}
+
+/// Finds the [SellerProceeds](../state/struct.SellerProceeds.html) of `seller`.
+pub fn find_seller_proceeds_address(program_id: &Pubkey, seller: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SELLER_PROCEEDS_SEED, seller.as_ref()], program_id)
+}
+
+/// Finds the [Marketplace](../state/struct.Marketplace.html) `creator` created
+/// under `name`.
+pub fn find_marketplace_address(program_id: &Pubkey, creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MARKETPLACE_SEED, creator.as_ref(), name.as_bytes()], program_id)
+}
+
+/// Finds the [UsdListing](../state/struct.UsdListing.html) of the listing stored
+/// in `escrow_account`.
+pub fn find_usd_listing_address(program_id: &Pubkey, escrow_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[USD_LISTING_SEED, escrow_account.as_ref()], program_id)
+}
+
+/// Recreates the [UsdListing](../state/struct.UsdListing.html) address of
+/// `escrow_account` from its recorded bump seed.
+pub fn create_usd_listing_address(
+    program_id: &Pubkey,
+    escrow_account: &Pubkey,
+    bump_seed: u8,
+) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_program_address(&[USD_LISTING_SEED, escrow_account.as_ref(), &[bump_seed]], program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+/// Finds the Metaplex metadata account of `mint`, which holds its royalty settings.
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    let metadata_program_id = metaplex_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            metadata_program_id.as_ref(),
+            mint.as_ref(),
+        ],
+        &metadata_program_id,
+    )
+}
+
+/// Recreates the Metaplex metadata account of `mint` from the bump seed
+/// `find_metadata_address` returned.
+pub fn create_metadata_address(mint: &Pubkey, bump_seed: u8) -> Result<Pubkey, ProgramError> {
+    let metadata_program_id = metaplex_token_metadata::id();
+    Pubkey::create_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            metadata_program_id.as_ref(),
+            mint.as_ref(),
+            &[bump_seed],
+        ],
+        &metadata_program_id,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+/// Finds the Metaplex edition account of `mint`, the master edition of an
+/// original or the edition of a print.
+pub fn find_edition_address(mint: &Pubkey) -> (Pubkey, u8) {
+    let metadata_program_id = metaplex_token_metadata::id();
+    Pubkey::find_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            metadata_program_id.as_ref(),
+            mint.as_ref(),
+            metaplex_token_metadata::state::EDITION.as_bytes(),
+        ],
+        &metadata_program_id,
+    )
+}
+
+/// Finds the Metaplex marker account of the prints of `master_mint` that
+/// records whether print number `edition` was made.
+pub fn find_edition_marker_address(master_mint: &Pubkey, edition: u64) -> (Pubkey, u8) {
+    let metadata_program_id = metaplex_token_metadata::id();
+    let marker_number = (edition / metaplex_token_metadata::state::EDITION_MARKER_BIT_SIZE).to_string();
+    Pubkey::find_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            metadata_program_id.as_ref(),
+            master_mint.as_ref(),
+            metaplex_token_metadata::state::EDITION.as_bytes(),
+            marker_number.as_bytes(),
+        ],
+        &metadata_program_id,
+    )
+}
+
+/// Finds the associated token account of `wallet` for `mint`, held by `token_program`,
+/// SPL Token or Token-2022.
+pub fn find_associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &spl_associated_token_account::id(),
+    )
+    .0
+}
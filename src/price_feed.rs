@@ -0,0 +1,159 @@
+//! Reader of Pyth-style price accounts, the SOL/USD feed that listings priced in
+//! USD are paid at.
+//!
+//! Only the header and the aggregate price of a price account are read, the
+//! prices of the individual publishers that follow are ignored.
+
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, program_error::ProgramError, pubkey::Pubkey,
+};
+use std::str::FromStr;
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::error::EscrowError;
+
+/// First bytes of every account of the oracle program.
+pub const PRICE_ACCOUNT_MAGIC: u32 = 0xa1b2c3d4;
+
+/// Layout version of the price accounts read here.
+pub const PRICE_ACCOUNT_VERSION: u32 = 2;
+
+/// Account type of a price account, as opposed to mapping and product accounts.
+pub const PRICE_ACCOUNT_TYPE: u32 = 3;
+
+/// Size of a price account of the oracle program.
+pub const PRICE_ACCOUNT_LEN: usize = 3312;
+
+/// Bytes of a price account read by [PriceFeed::unpack](struct.PriceFeed.html#method.unpack),
+/// up to the end of the aggregate price.
+pub const PRICE_FEED_READ_LEN: usize = 240;
+
+/// Aggregate price status of a feed whose price can be used.
+pub const PRICE_STATUS_TRADING: u32 = 1;
+
+/// Slots after which a published price is too old to pay a sale with.
+pub const MAX_PRICE_AGE_SLOTS: u64 = 25;
+
+/// Seconds after which a published price is too old to pay a sale with.
+pub const MAX_PRICE_AGE_SECONDS: i64 = 60;
+
+/// Widest confidence interval of a usable price, in basis points of the price.
+pub const MAX_CONFIDENCE_BASIS_POINTS: u64 = 200;
+
+/// Decimals of a USD price, which is in millionths of a dollar.
+pub const USD_PRICE_DECIMALS: u32 = 6;
+
+/// Decimals of SOL, in lamports.
+const LAMPORTS_DECIMALS: i32 = 9;
+
+/// Program owning the price accounts listings can be priced with.
+pub fn oracle_program_id() -> Pubkey {
+    Pubkey::from_str("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH").unwrap()
+}
+
+/// SOL/USD price account of the oracle program, the only feed listings are priced with.
+pub fn sol_usd_price_feed_id() -> Pubkey {
+    Pubkey::from_str("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG").unwrap()
+}
+
+/// Aggregate price of a feed, `price * 10^exponent` dollars per SOL.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PriceFeed {
+    pub exponent: i32,
+    /// unix timestamp the aggregate price was published at
+    pub timestamp: i64,
+    pub price: i64,
+    /// confidence interval of `price`, in the same unit
+    pub confidence: u64,
+    pub status: u32,
+    /// slot the aggregate price was published at
+    pub publish_slot: u64,
+}
+
+impl PriceFeed {
+    /// Reads the price account `data`, failing for accounts of another type or version.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let data = data.get(..PRICE_FEED_READ_LEN).ok_or(ProgramError::InvalidAccountData)?;
+        let data = array_ref![data, 0, PRICE_FEED_READ_LEN];
+        let (magic, version, account_type, _size, _price_type, exponent, _components, timestamp, _links, aggregate) =
+            array_refs![data, 4, 4, 4, 4, 4, 4, 72, 8, 104, 32];
+        if u32::from_le_bytes(*magic) != PRICE_ACCOUNT_MAGIC
+            || u32::from_le_bytes(*version) != PRICE_ACCOUNT_VERSION
+            || u32::from_le_bytes(*account_type) != PRICE_ACCOUNT_TYPE
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (price, confidence, status, _corporate_action, publish_slot) = array_refs![aggregate, 8, 8, 4, 4, 8];
+        Ok(PriceFeed {
+            exponent: i32::from_le_bytes(*exponent),
+            timestamp: i64::from_le_bytes(*timestamp),
+            price: i64::from_le_bytes(*price),
+            confidence: u64::from_le_bytes(*confidence),
+            status: u32::from_le_bytes(*status),
+            publish_slot: u64::from_le_bytes(*publish_slot),
+        })
+    }
+
+    /// Writes the feed into the price account `dst`, leaving the fields it does not
+    /// hold untouched. Used to build feeds locally.
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, PRICE_FEED_READ_LEN];
+        let (magic, version, account_type, size, _price_type, exponent, _components, timestamp, _links, aggregate) =
+            mut_array_refs![dst, 4, 4, 4, 4, 4, 4, 72, 8, 104, 32];
+        *magic = PRICE_ACCOUNT_MAGIC.to_le_bytes();
+        *version = PRICE_ACCOUNT_VERSION.to_le_bytes();
+        *account_type = PRICE_ACCOUNT_TYPE.to_le_bytes();
+        *size = (PRICE_ACCOUNT_LEN as u32).to_le_bytes();
+        *exponent = self.exponent.to_le_bytes();
+        *timestamp = self.timestamp.to_le_bytes();
+        let (price, confidence, status, _corporate_action, publish_slot) =
+            mut_array_refs![aggregate, 8, 8, 4, 4, 8];
+        *price = self.price.to_le_bytes();
+        *confidence = self.confidence.to_le_bytes();
+        *status = self.status.to_le_bytes();
+        *publish_slot = self.publish_slot.to_le_bytes();
+    }
+
+    /// Reads the price account `price_feed`, which has to be the SOL/USD feed of the
+    /// oracle program. Any other feed would price the listing in another asset.
+    pub fn load(price_feed: &AccountInfo) -> Result<Self, ProgramError> {
+        if *price_feed.key != sol_usd_price_feed_id() {
+            return Err(EscrowError::UnsupportedPriceFeed.into());
+        }
+        if *price_feed.owner != oracle_program_id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Self::unpack(&price_feed.try_borrow_data()?)
+    }
+
+    /// Lamports worth `usd_price` millionths of a dollar at the price of the feed,
+    /// rounded up so the seller is never short-changed. The price has to be trading,
+    /// recent at `clock` and known within `MAX_CONFIDENCE_BASIS_POINTS`.
+    pub fn lamports_for(&self, usd_price: u64, clock: &Clock) -> Result<u64, ProgramError> {
+        if self.status != PRICE_STATUS_TRADING
+            || clock.slot.saturating_sub(self.publish_slot) > MAX_PRICE_AGE_SLOTS
+            || clock.unix_timestamp.saturating_sub(self.timestamp) > MAX_PRICE_AGE_SECONDS
+        {
+            return Err(EscrowError::StalePriceFeed.into());
+        }
+        if self.price <= 0
+            || self.confidence as u128 * 10000 > self.price as u128 * MAX_CONFIDENCE_BASIS_POINTS as u128
+        {
+            return Err(EscrowError::UncertainPriceFeed.into());
+        }
+        self.convert(usd_price).ok_or(ProgramError::InvalidAccountData)
+    }
+
+    // lamports = usd_price / 10^USD_PRICE_DECIMALS / (price * 10^exponent) * 10^LAMPORTS_DECIMALS
+    fn convert(&self, usd_price: u64) -> Option<u64> {
+        let scale = (LAMPORTS_DECIMALS - USD_PRICE_DECIMALS as i32).checked_sub(self.exponent)?;
+        let price = u128::try_from(self.price).ok()?;
+        let (numerator, denominator) = if scale >= 0 {
+            ((usd_price as u128).checked_mul(10u128.checked_pow(scale as u32)?)?, price)
+        } else {
+            (usd_price as u128, price.checked_mul(10u128.checked_pow(scale.unsigned_abs())?)?)
+        };
+        u64::try_from(numerator.div_ceil(denominator)).ok()
+    }
+}
@@ -1,20 +1,55 @@
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    instruction::Instruction,
     msg, 
+    program_option::COption,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
-    program_pack::{IsInitialized, Pack},
+    program_pack::Pack,
     pubkey::Pubkey,
-    sysvar::{rent::Rent, Sysvar},
-    system_instruction::{transfer, create_account},
+    sysvar::{
+        clock::Clock,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        rent::Rent,
+        Sysvar,
+    },
+    system_instruction::{allocate, assign, transfer, create_account},
 
 };
 
-use metaplex_token_metadata::state::Metadata;
+use metaplex_token_metadata::{
+    instruction::mint_new_edition_from_master_edition_via_token,
+    state::{MasterEditionV2, Metadata},
+};
 use std::str::FromStr;
 use spl_token::state::Account as TokenAccount;
-use crate::{ instruction::EscrowInstruction, state::{Escrow , VaultAccount} };
+use crate::{
+    error::EscrowError,
+    events::{EscrowEvent, RoyaltyPayout},
+    instruction::{
+        EscrowInstruction, EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT, EXCHANGE_OPTION_MEMBERSHIP_PASS,
+        EXCHANGE_OPTION_REFERRER, EXCHANGE_OPTION_SELLER_PROCEEDS,
+    },
+    payout::{compute_payout, compute_primary_payout},
+    pda::{
+        create_escrow_authority, create_metadata_address, create_usd_listing_address, find_associated_token_address,
+        find_edition_address, find_escrow_authority, find_marketplace_address, find_metadata_address, find_referrer_address,
+        find_royalty_vault_address, find_seller_proceeds_address, find_usd_listing_address, find_vault_address,
+        ESCROW_AUTHORITY_SEED, MARKETPLACE_SEED, REFERRER_SEED, ROYALTY_VAULT_SEED, SELLER_PROCEEDS_SEED,
+        USD_LISTING_SEED, VAULT_SEED,
+    },
+    price_feed::PriceFeed,
+    state::{
+        account_type, AccountType, EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault,
+        SellerProceeds, TokenProgram, TokenSwap, UsdListing, VaultAccount, MARKETPLACE_NAME_LEN,
+    },
+    token,
+    view::{
+        self, AccountView, EditionDropView, EscrowView, MarketplaceView, PlatformView, ReferrerAccountView,
+        RoyaltyVaultView, SellerProceedsView, TokenSwapView, UsdListingView, VaultAccountView,
+    },
+};
 pub struct Processor;
 impl Processor {
     pub fn process(
@@ -42,6 +77,98 @@ impl Processor {
                 msg!("Instruction: Update platform accounts");
                 Self::process_val_accounts(accounts, amount, program_id)
             }
+            EscrowInstruction::ListTokenSwap { offered_amount, expected_amount } => {
+                msg!("Instruction: ListTokenSwap");
+                Self::process_init_token_swap(accounts, offered_amount, expected_amount, program_id)
+            }
+            EscrowInstruction::ExchangeTokenSwap { amount, expected_amount } => {
+                msg!("Instruction: ExchangeTokenSwap");
+                Self::process_token_swap_exchange(accounts, amount, expected_amount, program_id)
+            }
+            EscrowInstruction::CancelTokenSwap => {
+                msg!("Instruction: CancelTokenSwap");
+                Self::process_cancel_token_swap(accounts, program_id)
+            }
+            EscrowInstruction::SetPaused { paused } => {
+                msg!("Instruction: SetPaused");
+                Self::process_set_paused(accounts, paused, program_id)
+            }
+            EscrowInstruction::ForceDelist { reason } => {
+                msg!("Instruction: ForceDelist");
+                Self::process_force_delist(accounts, reason, program_id)
+            }
+            EscrowInstruction::ExchangeWithOptions { amount, options } => {
+                msg!("Instruction: ExchangeWithOptions");
+                Self::process_exchange_with_options(accounts, amount, options, program_id)
+            }
+            EscrowInstruction::SetPassDiscount { pass_fee_basis_points } => {
+                msg!("Instruction: SetPassDiscount");
+                Self::process_set_pass_discount(accounts, pass_fee_basis_points, program_id)
+            }
+            EscrowInstruction::SetReferralShare { referral_basis_points } => {
+                msg!("Instruction: SetReferralShare");
+                Self::process_set_referral_share(accounts, referral_basis_points, program_id)
+            }
+            EscrowInstruction::CreateMarketplace { name, fee_basis_points } => {
+                msg!("Instruction: CreateMarketplace");
+                Self::process_create_marketplace(accounts, name, fee_basis_points, program_id)
+            }
+            EscrowInstruction::ListTokenDelegated { amount } => {
+                msg!("Instruction: ListTokenDelegated");
+                Self::process_list_token_delegated(accounts, amount, program_id)
+            }
+            EscrowInstruction::ListTokenFrozen { amount } => {
+                msg!("Instruction: ListTokenFrozen");
+                Self::process_list_token_frozen(accounts, amount, program_id)
+            }
+            EscrowInstruction::ListEditionDrop { price, max_prints } => {
+                msg!("Instruction: ListEditionDrop");
+                Self::process_list_edition_drop(accounts, price, max_prints, program_id)
+            }
+            EscrowInstruction::BuyEdition { edition } => {
+                msg!("Instruction: BuyEdition");
+                Self::process_buy_edition(accounts, edition, program_id)
+            }
+            EscrowInstruction::CancelEditionDrop => {
+                msg!("Instruction: CancelEditionDrop");
+                Self::process_cancel_edition_drop(accounts, program_id)
+            }
+            EscrowInstruction::ListTokenSwapForSol { offered_amount, expected_amount } => {
+                msg!("Instruction: ListTokenSwapForSol");
+                Self::process_list_token_swap_for_sol(accounts, offered_amount, expected_amount, program_id)
+            }
+            EscrowInstruction::ExchangeTokenSwapWithSol { amount, expected_amount } => {
+                msg!("Instruction: ExchangeTokenSwapWithSol");
+                Self::process_exchange_token_swap_with_sol(accounts, amount, expected_amount, program_id)
+            }
+            EscrowInstruction::ListTokenUsd { usd_price } => {
+                msg!("Instruction: ListTokenUsd");
+                Self::process_list_token_usd(accounts, usd_price, program_id)
+            }
+            EscrowInstruction::ExchangeUsd { amount, max_lamports, options } => {
+                msg!("Instruction: ExchangeUsd");
+                Self::process_exchange_usd(accounts, amount, max_lamports, options, program_id)
+            }
+            EscrowInstruction::SetEscrowRoyalties { escrow_royalties } => {
+                msg!("Instruction: SetEscrowRoyalties");
+                Self::process_set_escrow_royalties(accounts, escrow_royalties, program_id)
+            }
+            EscrowInstruction::ClaimRoyalties => {
+                msg!("Instruction: ClaimRoyalties");
+                Self::process_claim_royalties(accounts, program_id)
+            }
+            EscrowInstruction::SetProceedsVault { proceeds_vault } => {
+                msg!("Instruction: SetProceedsVault");
+                Self::process_set_proceeds_vault(accounts, proceeds_vault, program_id)
+            }
+            EscrowInstruction::WithdrawProceeds => {
+                msg!("Instruction: WithdrawProceeds");
+                Self::process_withdraw_proceeds(accounts, program_id)
+            }
+            EscrowInstruction::RegisterReferrer => {
+                msg!("Instruction: RegisterReferrer");
+                Self::process_register_referrer(accounts, program_id)
+            }
         }
     }
 
@@ -52,6 +179,47 @@ impl Processor {
         accounts: &[AccountInfo],
         amount: u64,
         program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list(accounts, amount, ListingMode::Custody, false, program_id)
+    }
+
+    /// Lists a token like [`Self::process_init_escrow`] for `usd_price` millionths of
+    /// a dollar, paid in lamports at the price of the feed passed after the accounts
+    /// of the listing.
+    pub fn process_list_token_usd(
+        accounts: &[AccountInfo],
+        usd_price: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list(accounts, usd_price, ListingMode::Custody, true, program_id)
+    }
+
+    /// Lists a token that stays in the seller's token account, with the escrow
+    /// authority PDA approved as its delegate.
+    pub fn process_list_token_delegated(
+        accounts: &[AccountInfo],
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list(accounts, amount, ListingMode::Delegate, false, program_id)
+    }
+
+    /// Lists a token like [`Self::process_list_token_delegated`] and freezes its
+    /// token account, for mints whose freeze authority is the escrow authority PDA.
+    pub fn process_list_token_frozen(
+        accounts: &[AccountInfo],
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list(accounts, amount, ListingMode::Frozen, false, program_id)
+    }
+
+    fn list(
+        accounts: &[AccountInfo],
+        amount: u64,
+        listing_mode: ListingMode,
+        usd_priced: bool,
+        program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let initializer = next_account_info(account_info_iter)?;
@@ -71,18 +239,22 @@ impl Processor {
 
         let system_program = next_account_info(account_info_iter)?;
 
-
-        // mint validation check
-        if *mint_key.owner != spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
+        // no new listings while the marketplace is paused
+        let platform_account = next_account_info(account_info_iter)?;
+        let (platform_info, _authority) = Self::load_platform(platform_account, program_id)?;
+        if platform_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
         }
-        
-        let token_account_state = spl_token::state::Account::unpack(
-            &**token_account.data.borrow()
-        ).unwrap();
+
+        // mint validation check, the mint and the token account belong to the
+        // passed token program, SPL Token or Token-2022
+        let listed_token_program = token::token_program_of(mint_key, token_program)?;
+        token::token_program_of(token_account, token_program)?;
+        let mint_info = token::unpack_mint(mint_key)?;
+        let token_account_state = token::unpack_token_account(token_account)?;
 
         // check if the token account have balance
-        if token_account_state.amount != (1 as u64){
+        if token_account_state.amount != 1 {
             msg!("invalid NFT data ** ..");
             return Err(ProgramError::InvalidAccountData);
         }
@@ -91,13 +263,29 @@ impl Processor {
             msg!("invalid NFT data ** ..");
             return Err(ProgramError::InvalidAccountData);
         }
+
+        match listing_mode {
+            // a custody listing hands over its token account without a transfer, by
+            // making the escrow authority its owner
+            ListingMode::Custody => {
+                if token::has_immutable_owner(token_account)? {
+                    return Err(EscrowError::ImmutableOwner.into());
+                }
+            }
+            // the token of a delegated or frozen listing is transferred to the taker,
+            // who receives it net of the transfer fee of the mint
+            ListingMode::Delegate | ListingMode::Frozen => {
+                if token::transfer_fee(mint_key, token_account_state.amount)? >= token_account_state.amount {
+                    return Err(EscrowError::TransferFeeWithholdsToken.into());
+                }
+            }
+        }
         
         invoke(
             &create_account(
                 initializer.key, 
                 escrow_account.key, 
-                Rent::default().
-                minimum_balance(
+                rent.minimum_balance(
                     Escrow::LEN 
                 ),
                 Escrow::LEN as u64, 
@@ -112,12 +300,16 @@ impl Processor {
     
 
         // check listing amount > 0
-        if amount <= (0 as u64) {
+        if amount == 0 {
             return Err(ProgramError::InvalidInstructionData);
         }
 
         let mut escrow_info = Escrow::unpack_unchecked(&escrow_account.try_borrow_data()?)?;
 
+        // get a pda for escrow program, the listing records the bump seeds of its
+        // pdas so that the sale and the cancellation need not search for them again
+        let (pda, nonce) = find_escrow_authority(program_id);
+        let (_metadata_key, metadata_bump_seed) = find_metadata_address(mint_key.key);
 
         // set the state for escrow account
         escrow_info.is_initialized = true;
@@ -125,29 +317,179 @@ impl Processor {
         escrow_info.token_account_pubkey = *token_account.key;
         escrow_info.mint_key = *mint_key.key;
         escrow_info.expected_amount = amount;
+        escrow_info.marketplace = *platform_account.key;
+        escrow_info.listing_mode = listing_mode;
+        escrow_info.token_program = listed_token_program;
+        escrow_info.usd_priced = usd_priced;
+        escrow_info.escrow_authority_bump_seed = nonce;
+        escrow_info.metadata_bump_seed = metadata_bump_seed;
+        escrow_info.proceeds_vault = false;
         Escrow::pack(escrow_info, &mut escrow_account.try_borrow_mut_data()?)?;
 
-        // get a pda for escrow program
-        let (pda, _nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
+        match listing_mode {
+            // transfer the authority of token account from initializer to pda
+            ListingMode::Custody => invoke(
+                &spl_token_2022::instruction::set_authority(
+                    token_program.key,
+                    token_account.key,
+                    Some(&pda),
+                    spl_token_2022::instruction::AuthorityType::AccountOwner,
+                    initializer.key,
+                    &[],
+                )?,
+                &[
+                    token_account.clone(),
+                    initializer.clone(),
+                    token_program.clone(),
+                ],
+            )?,
+            // let the pda move the token out of the seller's token account
+            ListingMode::Delegate | ListingMode::Frozen => {
+                let pda_account = next_account_info(account_info_iter)?;
+                if *pda_account.key != pda || token_account_state.owner != *initializer.key {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                // only the pda can thaw the token account it freezes
+                if listing_mode == ListingMode::Frozen && mint_info.freeze_authority != COption::Some(pda) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                invoke(
+                    &spl_token_2022::instruction::approve(
+                        token_program.key,
+                        token_account.key,
+                        &pda,
+                        initializer.key,
+                        &[],
+                        1,
+                    )?,
+                    &[
+                        token_account.clone(),
+                        pda_account.clone(),
+                        initializer.clone(),
+                        token_program.clone(),
+                    ],
+                )?;
+                if listing_mode == ListingMode::Frozen {
+                    Self::invoke_as_custodian(
+                        &spl_token_2022::instruction::freeze_account(
+                            token_program.key,
+                            token_account.key,
+                            mint_key.key,
+                            &pda,
+                            &[],
+                        )?,
+                        &[
+                            token_account.clone(),
+                            mint_key.clone(),
+                            pda_account.clone(),
+                            token_program.clone(),
+                        ],
+                        nonce,
+                    )?;
+                }
+            }
+        }
+
+        // the price feed of a listing priced in USD is recorded next to its escrow account
+        if usd_priced {
+            let price_feed = next_account_info(account_info_iter)?;
+            let usd_listing_account = next_account_info(account_info_iter)?;
+            Self::create_usd_listing(
+                initializer,
+                escrow_account,
+                price_feed,
+                usd_listing_account,
+                system_program,
+                rent,
+                program_id,
+            )?;
+            EscrowEvent::UsdListed {
+                escrow: *escrow_account.key,
+                seller: *initializer.key,
+                mint: *mint_key.key,
+                token_account: *token_account.key,
+                usd_price: amount,
+                price_feed: *price_feed.key,
+            }
+            .emit();
+            return Ok(());
+        }
 
-        // transfer the authority of token account from initializer to pda
-        let owner_change_ix = spl_token::instruction::set_authority(
-            token_program.key,
-            token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            initializer.key,
-            &[],
-        )?;
-        invoke(
-            &owner_change_ix,
+        EscrowEvent::Listed {
+            escrow: *escrow_account.key,
+            seller: *initializer.key,
+            mint: *mint_key.key,
+            token_account: *token_account.key,
+            price: amount,
+        }
+        .emit();
+        Ok(())
+    }
+
+    /// Creates the [UsdListing](../state/struct.UsdListing.html) PDA of
+    /// `escrow_account`, recording `price_feed`, a price account of the oracle program.
+    fn create_usd_listing<'a>(
+        payer: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        price_feed: &AccountInfo<'a>,
+        usd_listing_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        rent: &Rent,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        PriceFeed::load(price_feed)?;
+        let (usd_listing_key, usd_listing_bump_seed) = find_usd_listing_address(program_id, escrow_account.key);
+        if *usd_listing_account.key != usd_listing_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        invoke_signed(
+            &create_account(
+                payer.key,
+                usd_listing_account.key,
+                rent.minimum_balance(UsdListing::LEN),
+                UsdListing::LEN as u64,
+                program_id,
+            ),
             &[
-                token_account.clone(),
-                initializer.clone(),
-                token_program.clone(),
+                payer.clone(),
+                usd_listing_account.clone(),
+                system_program.clone(),
             ],
+            &[&[USD_LISTING_SEED, escrow_account.key.as_ref(), &[usd_listing_bump_seed]]],
         )?;
-        Ok(())
+
+        let usd_listing = UsdListing {
+            is_initialized: true,
+            escrow: *escrow_account.key,
+            price_feed: *price_feed.key,
+            bump_seed: usd_listing_bump_seed,
+        };
+        UsdListing::pack(usd_listing, &mut usd_listing_account.try_borrow_mut_data()?)
+    }
+
+    /// Lamport value of the USD price of a listing at the current price of the feed
+    /// recorded in its [UsdListing](../state/struct.UsdListing.html).
+    fn usd_price_in_lamports(
+        escrow_account: &AccountInfo,
+        escrow_info: &EscrowView,
+        usd_listing_account: &AccountInfo,
+        price_feed: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        if usd_listing_account.owner != program_id {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let usd_listing_data = usd_listing_account.try_borrow_data()?;
+        let usd_listing = view::load::<UsdListingView>(&usd_listing_data)?;
+        let usd_listing_key = create_usd_listing_address(program_id, escrow_account.key, usd_listing.bump_seed)?;
+        if *usd_listing_account.key != usd_listing_key
+            || usd_listing.escrow != *escrow_account.key
+            || usd_listing.price_feed != *price_feed.key
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        PriceFeed::load(price_feed)?.lamports_for(escrow_info.expected_amount(), &Clock::get()?)
     }
 
     
@@ -156,8 +498,47 @@ impl Processor {
         amount_expected_by_taker: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        Self::process_exchange_with_options(accounts, amount_expected_by_taker, 0, program_id)
+    }
+
+    /// `options` flags which optional accounts follow the treasury, see
+    /// [EscrowInstruction::ExchangeWithOptions](../instruction/enum.EscrowInstruction.html).
+    pub fn process_exchange_with_options(
+        accounts: &[AccountInfo],
+        amount_expected_by_taker: u64,
+        options: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::exchange(accounts, amount_expected_by_taker, options, None, program_id)
+    }
+
+    /// Buys a listing priced in USD like [`Self::process_exchange_with_options`],
+    /// for at most `max_lamports`.
+    pub fn process_exchange_usd(
+        accounts: &[AccountInfo],
+        amount_expected_by_taker: u64,
+        max_lamports: u64,
+        options: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::exchange(accounts, amount_expected_by_taker, options, Some(max_lamports), program_id)
+    }
+
+    fn exchange(
+        accounts: &[AccountInfo],
+        amount_expected_by_taker: u64,
+        options: u8,
+        max_lamports: Option<u64>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let known_options = EXCHANGE_OPTION_MEMBERSHIP_PASS
+            | EXCHANGE_OPTION_REFERRER
+            | EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT
+            | EXCHANGE_OPTION_SELLER_PROCEEDS;
+        if options & !known_options != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-        msg!("enter");
         let account_info_iter = &mut accounts.iter();
         let taker = next_account_info(account_info_iter)?;
 
@@ -167,14 +548,6 @@ impl Processor {
         }
         //let takers_token_to_receive_account = next_account_info(account_info_iter)?;
         let pdas_token_account = next_account_info(account_info_iter)?;
-        let pdas_token_account_info =
-            TokenAccount::unpack(&pdas_token_account.try_borrow_data()?)?;
-        let (pda, nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
-
-        // validation check for amount
-        if amount_expected_by_taker != pdas_token_account_info.amount {
-            return Err(ProgramError::InvalidInstructionData);
-        }
 
         let initializers_main_account = next_account_info(account_info_iter)?;
         let mint_key = next_account_info(account_info_iter)?;
@@ -184,7 +557,12 @@ impl Processor {
         if escrow_account.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
-        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
+        // the listing is read and closed in place, it takes part in none of the
+        // instructions invoked below
+        let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+        let mut legacy_listing = None;
+        let escrow_info = Self::load_listing(&mut escrow_data, &mut legacy_listing)?;
+        let (pda, nonce) = Self::listing_escrow_authority(escrow_info, program_id)?;
 
         // validate data using Escrow state
         if escrow_info.token_account_pubkey != *pdas_token_account.key {
@@ -200,9 +578,31 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let amomunt_expected_by_user = escrow_info.expected_amount.clone();
+        // the taker of a delegated or frozen listing receives the token net of the
+        // transfer fee of the mint, which may have been set after the listing
+        let (pdas_token_account_info, transfer_fee) = match escrow_info.listing_mode() {
+            ListingMode::Custody => (token::unpack_token_account(pdas_token_account)?, 0),
+            ListingMode::Delegate | ListingMode::Frozen => {
+                let pdas_token_account_info = Self::delegated_token(pdas_token_account, escrow_info, &pda)?;
+                let transfer_fee = token::transfer_fee(mint_key, pdas_token_account_info.amount)?;
+                (pdas_token_account_info, transfer_fee)
+            }
+        };
+        let received_amount = pdas_token_account_info
+            .amount
+            .checked_sub(transfer_fee)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if received_amount == 0 && transfer_fee != 0 {
+            return Err(EscrowError::TransferFeeWithholdsToken.into());
+        }
+
+        // validation check for amount
+        if received_amount == 0 || amount_expected_by_taker != received_amount {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
         let token_program = next_account_info(account_info_iter)?;
+        Self::check_token_program(token_program, escrow_info)?;
         let system_program = next_account_info(account_info_iter)?;
         let pda_account = next_account_info(account_info_iter)?;
         let metadata_info = next_account_info(account_info_iter)?;
@@ -210,100 +610,157 @@ impl Processor {
         // platform state account for valAccount struct
         let val_acc = next_account_info(account_info_iter)?;
 
-        // check if owner of platform account is the program
-        if *val_acc.owner != *program_id {
-            return Err(ProgramError::IncorrectProgramId);
-        }
-
         // platform team and treasury accounts
         let platform_treasury = next_account_info(account_info_iter)?;
 
         // get the percentages from the platform state account
-        let val_acccount_info = VaultAccount::unpack(&val_acc.try_borrow_data()?)?;
+        let (val_acccount_info, _authority) = Self::load_platform(val_acc, program_id)?;
+
+        if val_acccount_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
+        }
+
+        // the sale pays the fee of the marketplace the token was listed on, listings
+        // made before it was recorded pay the platform account of the admin
+        if escrow_info.marketplace == Pubkey::default() {
+            if account_type(&val_acc.try_borrow_data()?) == Some(AccountType::Marketplace) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        } else if escrow_info.marketplace != *val_acc.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
 
         // validation checks for treasury and team accounts
         if val_acccount_info.treasury_account != *platform_treasury.key {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // a listing priced in USD is paid at the current lamport value of its price,
+        // which the taker caps, and is only bought that way
+        let size = match (escrow_info.usd_priced(), max_lamports) {
+            (true, Some(max_lamports)) => {
+                let usd_listing_account = next_account_info(account_info_iter)?;
+                let price_feed = next_account_info(account_info_iter)?;
+                let lamports = Self::usd_price_in_lamports(
+                    escrow_account,
+                    escrow_info,
+                    usd_listing_account,
+                    price_feed,
+                    program_id,
+                )?;
+                if lamports > max_lamports {
+                    return Err(EscrowError::PriceAboveMaximum.into());
+                }
+                lamports
+            }
+            (false, None) => escrow_info.expected_amount(),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // a membership pass of the taker or the seller discounts the platform fee
+        let pass_holder = options & EXCHANGE_OPTION_MEMBERSHIP_PASS != 0;
+        if pass_holder {
+            let pass_token_account = next_account_info(account_info_iter)?;
+            let pass_metadata = next_account_info(account_info_iter)?;
+            Self::check_membership_pass(
+                pass_token_account,
+                pass_metadata,
+                &val_acccount_info.pass_collection,
+                &[*taker.key, *initializers_main_account.key],
+            )?;
+        }
+
+        // a partner that routed the taker to the listing gets a share of the platform fee
+        let referrer = if options & EXCHANGE_OPTION_REFERRER != 0 {
+            let referrer_info = next_account_info(account_info_iter)?;
+            let referrer_account = next_account_info(account_info_iter)?;
+            if *referrer_info.key == *taker.key || *referrer_info.key == *initializers_main_account.key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some((referrer_info, referrer_account))
+        } else {
+            None
+        };
+
+        // the token of a delegated or frozen listing is moved into the taker's associated
+        // token account, a listing in custody hands over the whole token account
+        let buyer_token_account = if options & EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT != 0 {
+            let buyer_token_account = next_account_info(account_info_iter)?;
+            let buyer_token_account_key = find_associated_token_address(
+                taker.key,
+                &escrow_info.mint_key,
+                &escrow_info.token_program().id(),
+            );
+            if *buyer_token_account.key != buyer_token_account_key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some(buyer_token_account)
+        } else {
+            None
+        };
+        if buyer_token_account.is_some() != (escrow_info.listing_mode() != ListingMode::Custody) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the proceeds of a listing in proceeds-vault mode are credited to the proceeds
+        // PDA of the seller, and only then
+        let seller_proceeds = if options & EXCHANGE_OPTION_SELLER_PROCEEDS != 0 {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        if seller_proceeds.is_some() != escrow_info.proceeds_vault() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
 
         // fetch onchain metadata account 
-        const PREFIX: &str = "metadata";
-        let metadata_program_id = Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").unwrap();
-        // seeds for metadata pda
-        let metadata_seeds = &[
-            PREFIX.as_bytes(),
-            metadata_program_id.as_ref(),
-            escrow_info.mint_key.as_ref(),
-        ];
-    
-        let (metadata_key, _metadata_bump_seed) =
-            Pubkey::find_program_address(metadata_seeds, &metadata_program_id);
+        let metadata_key = Self::listing_metadata_address(escrow_info)?;
 
         // validation check for correct accounts send from the client side
         if *metadata_info.key != metadata_key{
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let size = escrow_info.expected_amount;
-
         // unpack the metadata from the metadata pda
         let metadata = Metadata::from_account_info(metadata_info)?;
 
-        // seller fee basis points from the metadata
-        let fees = metadata.data.seller_fee_basis_points;
-        let total_fee = ((fees as u64)*size)/10000;
-
-        let mut remaining_fee = size;
-        match metadata.data.creators {
-            Some(creators) => {
-                for creator in creators {
-                    let pct = creator.share as u64;
-                    let creator_fee = (pct*(total_fee))/100;
-                    remaining_fee = remaining_fee - creator_fee;
-
-                    let creator_acc_web = next_account_info(account_info_iter)?;
-
-                    if *creator_acc_web.key != creator.address {
-                        return Err(ProgramError::InvalidAccountData);
-                    }
-
-                    // send the royalties to the creators of the NFT
-                    if creator_fee > 0 {
-
-                        invoke(
-                            &transfer(
-                                taker.key,
-                                creator_acc_web.key,
-                                creator_fee,   
-                            ),
-                            &[
-                                taker.clone(),
-                                creator_acc_web.clone(),
-                                taker.clone(),
-                                system_program.clone(),
-                            ],
-                        )?;
-                    }
-                }
-            }
-            None => {
-                msg!("No creators found in metadata");
-            }
+        if metadata.data.creators.is_none() {
+            msg!("No creators found in metadata");
         }
+        let creators = metadata.data.creators.unwrap_or_default();
+
+        // split the price between the creators, the platform and the seller
+        let payout = compute_payout(
+            size,
+            metadata.data.seller_fee_basis_points,
+            &creators,
+            val_acccount_info.fee_basis_points(pass_holder),
+        )
+        .ok_or(ProgramError::InvalidAccountData)?;
+        let referral_fee = match referrer {
+            Some(_) => val_acccount_info
+                .referral_fee(payout.platform_fee)
+                .ok_or(ProgramError::InvalidAccountData)?,
+            None => 0,
+        };
+
+        // send the royalties to the creators of the NFT
+        Self::pay_royalties(
+            taker,
+            system_program,
+            &payout.royalties,
+            account_info_iter.as_slice(),
+            val_acccount_info.escrow_royalties(),
+            program_id,
+        )?;
 
-        // get platform treasury + team % and convert it to SOL according
-        // to the sale amount of the NFT
-        // let base_percentge = val_acccount_info.base_percentage;
-
-        let platform_fee = (size * val_acccount_info.base_percentage) / 10000;
-
-        // transer SOL to platform fee account
+        // transer SOL to platform fee account, less the referrer's share
         invoke(
             &transfer(
             taker.key,
             platform_treasury.key,
-            platform_fee.clone(),   
+            payout.platform_fee - referral_fee,
             ),
             &[
                 taker.clone(),
@@ -313,57 +770,404 @@ impl Processor {
             ],
         )?;
 
-        // calculate the remaining amount
-        remaining_fee = remaining_fee - platform_fee;
+        match seller_proceeds {
+            Some(seller_proceeds) => {
+                let total_credited = Self::credit_proceeds(
+                    taker,
+                    seller_proceeds,
+                    system_program,
+                    initializers_main_account.key,
+                    payout.seller_proceeds,
+                    program_id,
+                )?;
+                EscrowEvent::ProceedsCredited {
+                    escrow: *escrow_account.key,
+                    seller: *initializers_main_account.key,
+                    seller_proceeds: *seller_proceeds.key,
+                    amount: payout.seller_proceeds,
+                    total_credited,
+                }
+                .emit();
+            }
+            None => {
+                // transfer the remaining SOL to the seller
+                let transfer_to_initializer_ix = transfer(
+                    taker.key,
+                    initializers_main_account.key,
+                    payout.seller_proceeds,
+                );
+
+                invoke(
+                    &transfer_to_initializer_ix,
+                    &[
+                        taker.clone(),
+                        initializers_main_account.clone(),
+                        taker.clone(),
+                        system_program.clone(),
+                    ],
+                )?;
+            }
+        }
 
-        // transfer the remaining SOL to the seller
-        let transfer_to_initializer_ix = transfer(
-            taker.key,
-            initializers_main_account.key,
-            remaining_fee,   
-        );
+        // the sold token leaves the frozen token account, which is not frozen again
+        if escrow_info.listing_mode() == ListingMode::Frozen {
+            Self::thaw(token_program, pdas_token_account, mint_key, pda_account, &pda, nonce)?;
+        }
+        match buyer_token_account {
+            Some(buyer_token_account) => {
+                let decimals = token::unpack_mint(mint_key)?.decimals;
+                // a fee is asserted, so that the taker gets no less than it agreed to
+                let transfer_instruction = if transfer_fee == 0 {
+                    spl_token_2022::instruction::transfer_checked(
+                        token_program.key,
+                        pdas_token_account.key,
+                        mint_key.key,
+                        buyer_token_account.key,
+                        &pda,
+                        &[],
+                        pdas_token_account_info.amount,
+                        decimals,
+                    )?
+                } else {
+                    spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+                        token_program.key,
+                        pdas_token_account.key,
+                        mint_key.key,
+                        buyer_token_account.key,
+                        &pda,
+                        &[],
+                        pdas_token_account_info.amount,
+                        decimals,
+                        transfer_fee,
+                    )?
+                };
+                Self::invoke_as_custodian(
+                    &transfer_instruction,
+                    &[
+                        pdas_token_account.clone(),
+                        mint_key.clone(),
+                        buyer_token_account.clone(),
+                        pda_account.clone(),
+                        token_program.clone(),
+                    ],
+                    nonce,
+                )?
+            }
+            // transfer ownership authority of token account to the buyer
+            None => Self::release_token_account(
+                token_program,
+                pdas_token_account,
+                taker.key,
+                pda_account,
+                &pda,
+                nonce,
+            )?,
+        }
+
+        // set the state to is_initialized false
+        escrow_info.header_mut().set_initialized(false);
+
+        EscrowEvent::Sold {
+            escrow: *escrow_account.key,
+            seller: *initializers_main_account.key,
+            buyer: *taker.key,
+            mint: *mint_key.key,
+            price: size,
+            royalties: payout.royalties,
+            treasury: *platform_treasury.key,
+            platform_fee: payout.platform_fee,
+            seller_proceeds: payout.seller_proceeds,
+        }
+        .emit();
+        if let Some(legacy_listing) = legacy_listing {
+            legacy_listing.store_legacy(&mut escrow_data)?;
+        }
+
+        if let Some((referrer_info, referrer_account)) = referrer {
+            let total_earned = Self::pay_referral(
+                taker,
+                referrer_info,
+                referrer_account,
+                system_program,
+                referral_fee,
+                program_id,
+            )?;
+            EscrowEvent::ReferralPaid {
+                escrow: *escrow_account.key,
+                referrer: *referrer_info.key,
+                amount: referral_fee,
+                total_earned,
+            }
+            .emit();
+        }
+
+        Ok(())
+    }
+
+    /// Pays every royalty of a sale from `payer` to its creator, whose account is
+    /// looked up by key among `creator_accounts` in whatever order they were passed.
+    /// Creators owed nothing may be left out, no account may be passed twice.
+    ///
+    /// The royalty goes into the royalty vault of the creator instead when the
+    /// platform escrows royalties, or when the vault was passed and the creator
+    /// cannot take a transfer.
+    fn pay_royalties<'a>(
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        royalties: &[RoyaltyPayout],
+        creator_accounts: &[AccountInfo<'a>],
+        escrow_royalties: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        for (index, creator_account) in creator_accounts.iter().enumerate() {
+            if creator_accounts[..index].iter().any(|other| other.key == creator_account.key) {
+                return Err(EscrowError::DuplicateCreatorAccount.into());
+            }
+        }
+
+        // any other account passed is a royalty vault, whose address is only derived
+        // for royalties that do not go to the creator directly
+        let has_vaults = creator_accounts
+            .iter()
+            .any(|account| !royalties.iter().any(|royalty| royalty.creator == *account.key));
+
+        for royalty in royalties.iter().filter(|royalty| royalty.amount > 0) {
+            let creator_account = creator_accounts
+                .iter()
+                .find(|creator_account| *creator_account.key == royalty.creator);
+            let pays_directly = match creator_account {
+                Some(creator_account) if !escrow_royalties => {
+                    Self::accepts_transfer(creator_account, royalty.amount, has_vaults)?
+                }
+                _ => false,
+            };
+
+            let royalty_vault = if pays_directly {
+                None
+            } else {
+                let (royalty_vault_key, royalty_vault_bump_seed) = find_royalty_vault_address(program_id, &royalty.creator);
+                creator_accounts
+                    .iter()
+                    .find(|creator_account| *creator_account.key == royalty_vault_key)
+                    .map(|royalty_vault| (royalty_vault, royalty_vault_bump_seed))
+            };
+            // without its vault, a creator of the system program is paid in any case
+            let pays_directly = match creator_account {
+                Some(creator_account) if !escrow_royalties && !pays_directly && royalty_vault.is_none() => {
+                    Self::accepts_transfer(creator_account, royalty.amount, false)?
+                }
+                _ => pays_directly,
+            };
+            match (creator_account, royalty_vault) {
+                (Some(creator_account), _) if pays_directly => {
+                    invoke(
+                        &transfer(payer.key, creator_account.key, royalty.amount),
+                        &[
+                            payer.clone(),
+                            creator_account.clone(),
+                            system_program.clone(),
+                        ],
+                    )?;
+                }
+                (_, Some((royalty_vault, royalty_vault_bump_seed))) => {
+                    let total_deposited = Self::deposit_royalty(
+                        payer,
+                        royalty_vault,
+                        system_program,
+                        &royalty.creator,
+                        royalty_vault_bump_seed,
+                        royalty.amount,
+                        program_id,
+                    )?;
+                    EscrowEvent::RoyaltyDeposited {
+                        creator: royalty.creator,
+                        royalty_vault: *royalty_vault.key,
+                        amount: royalty.amount,
+                        total_deposited,
+                    }
+                    .emit();
+                }
+                (None, None) => return Err(EscrowError::MissingCreatorAccount.into()),
+                (Some(_), None) => return Err(EscrowError::MissingRoyaltyVault.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether a transfer of `amount` lamports to `creator_account` goes through: the
+    /// system program only credits its own accounts, and the rent rules refuse to
+    /// leave an account below its rent-exempt minimum. Without a vault to fall back
+    /// on, an account of the system program is paid in any case, as it always was.
+    fn accepts_transfer(creator_account: &AccountInfo, amount: u64, has_vault: bool) -> Result<bool, ProgramError> {
+        if !solana_program::system_program::check_id(creator_account.owner) || creator_account.executable {
+            return Ok(false);
+        }
+        if !has_vault {
+            return Ok(true);
+        }
+        let lamports = creator_account
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(Rent::get()?.is_exempt(lamports, creator_account.data_len()))
+    }
+
+    /// Deposits `amount` lamports of `payer` into the royalty vault of `creator`,
+    /// which the payer creates on the first deposit. Returns the creator's total
+    /// deposits.
+    fn deposit_royalty<'a>(
+        payer: &AccountInfo<'a>,
+        royalty_vault: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        creator: &Pubkey,
+        royalty_vault_bump_seed: u8,
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        if royalty_vault.owner != program_id {
+            Self::create_pda_account(
+                payer,
+                royalty_vault,
+                system_program,
+                RoyaltyVault::LEN,
+                &[ROYALTY_VAULT_SEED, creator.as_ref(), &[royalty_vault_bump_seed]],
+                program_id,
+            )?;
+        }
 
         invoke(
-            &transfer_to_initializer_ix,
+            &transfer(payer.key, royalty_vault.key, amount),
             &[
-                taker.clone(),
-                initializers_main_account.clone(),
-                taker.clone(),
+                payer.clone(),
+                royalty_vault.clone(),
                 system_program.clone(),
             ],
         )?;
 
-        // transfer ownership authority of token account to the buyer
-        let transfer_nft = spl_token::instruction::set_authority(
-            token_program.key,
-            pdas_token_account.key,
-            Some(taker.key),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            &pda,
-            &[],
-        )?;
-        invoke_signed(
-            &transfer_nft,
+        // the deposits are added up in place
+        let mut royalty_vault_data = royalty_vault.try_borrow_mut_data()?;
+        let vault = if view::load_unchecked::<RoyaltyVaultView>(&royalty_vault_data)?.header().is_initialized() {
+            view::load_mut::<RoyaltyVaultView>(&mut royalty_vault_data)?
+        } else {
+            let vault = view::init::<RoyaltyVaultView>(&mut royalty_vault_data)?;
+            vault.creator = *creator;
+            vault
+        };
+        let total_deposited = vault
+            .total_deposited()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        vault.set_total_deposited(total_deposited);
+        vault.set_sales(vault.sales().checked_add(1).ok_or(ProgramError::InvalidAccountData)?);
+
+        Ok(total_deposited)
+    }
+
+    /// Credits `amount` lamports of `payer` to the proceeds PDA of `seller`, which the
+    /// payer creates on the first credited sale. Returns the seller's total credits.
+    fn credit_proceeds<'a>(
+        payer: &AccountInfo<'a>,
+        seller_proceeds: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        seller: &Pubkey,
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        let (seller_proceeds_key, seller_proceeds_bump_seed) = find_seller_proceeds_address(program_id, seller);
+        if *seller_proceeds.key != seller_proceeds_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if seller_proceeds.owner != program_id {
+            Self::create_pda_account(
+                payer,
+                seller_proceeds,
+                system_program,
+                SellerProceeds::LEN,
+                &[SELLER_PROCEEDS_SEED, seller.as_ref(), &[seller_proceeds_bump_seed]],
+                program_id,
+            )?;
+        }
+
+        invoke(
+            &transfer(payer.key, seller_proceeds.key, amount),
             &[
-                pdas_token_account.clone(),
-                pda_account.clone(),
-                token_program.clone(),
+                payer.clone(),
+                seller_proceeds.clone(),
+                system_program.clone(),
             ],
-            &[&[&b"escrow"[..], &[nonce]]],
         )?;
 
-        // unpack the escrow account
-        // and set the state to is_initialized false
-        let mut escrow_update_info = Escrow::unpack_unchecked(&escrow_account.try_borrow_data()?)?;
+        // the credits are added up in place
+        let mut seller_proceeds_data = seller_proceeds.try_borrow_mut_data()?;
+        let proceeds = if view::load_unchecked::<SellerProceedsView>(&seller_proceeds_data)?.header().is_initialized() {
+            view::load_mut::<SellerProceedsView>(&mut seller_proceeds_data)?
+        } else {
+            let proceeds = view::init::<SellerProceedsView>(&mut seller_proceeds_data)?;
+            proceeds.seller = *seller;
+            proceeds
+        };
+        let total_credited = proceeds
+            .total_credited()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        proceeds.set_total_credited(total_credited);
+        proceeds.set_sales(proceeds.sales().checked_add(1).ok_or(ProgramError::InvalidAccountData)?);
+
+        Ok(total_credited)
+    }
+
+    /// Pays `amount` lamports of the taker to `referrer` and adds them to its
+    /// earnings in `referrer_account`, which the taker creates on the first
+    /// referred sale. Returns the referrer's total earnings.
+    fn pay_referral<'a>(
+        taker: &AccountInfo<'a>,
+        referrer: &AccountInfo<'a>,
+        referrer_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        let (referrer_account_key, _referrer_bump_seed) = find_referrer_address(program_id, referrer.key);
+        if *referrer_account.key != referrer_account_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // only referrers that registered beforehand are paid, so a taker cannot
+        // name a second wallet of its own on the spot
+        if referrer_account.owner != program_id
+            || !view::load_unchecked::<ReferrerAccountView>(&referrer_account.try_borrow_data()?)?
+                .header()
+                .is_initialized()
+        {
+            return Err(EscrowError::UnregisteredReferrer.into());
+        }
 
-        escrow_update_info.is_initialized = false;
-        escrow_update_info.seller_pubkey = *initializers_main_account.key;
-        escrow_update_info.token_account_pubkey = *pdas_token_account.key;
-        escrow_update_info.mint_key = *mint_key.key;
-        escrow_update_info.expected_amount = amomunt_expected_by_user;
-        Escrow::pack(escrow_update_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        if amount > 0 {
+            invoke(
+                &transfer(taker.key, referrer.key, amount),
+                &[
+                    taker.clone(),
+                    referrer.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
 
-        Ok(())
+        // the earnings are added up in place
+        let mut referrer_data = referrer_account.try_borrow_mut_data()?;
+        let earnings = view::load_mut::<ReferrerAccountView>(&mut referrer_data)?;
+        if earnings.referrer != *referrer.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let total_earned = earnings
+            .total_earned()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        earnings.set_total_earned(total_earned);
+        earnings.set_sales(earnings.sales().checked_add(1).ok_or(ProgramError::InvalidAccountData)?);
+
+        Ok(total_earned)
     }
 
 
@@ -382,11 +1186,10 @@ impl Processor {
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        // unpack the escrow state for some validation checks
-        let escrow_info = Escrow::unpack(&escrow_account.try_borrow_data()?)?;
-
-        let mint_key_data = escrow_info.mint_key.clone();
-        let amomunt_expected_by_user = escrow_info.expected_amount.clone();
+        // read the escrow state in place for some validation checks
+        let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+        let mut legacy_listing = None;
+        let escrow_info = Self::load_listing(&mut escrow_data, &mut legacy_listing)?;
 
         // check if the user cancelling the listing is actually
         // the user who have listed it
@@ -397,61 +1200,212 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let (pda, nonce) = Pubkey::find_program_address(&[b"escrow"], program_id);
+        let (pda, nonce) = Self::listing_escrow_authority(escrow_info, program_id)?;
         let token_program = next_account_info(account_info_iter)?;
+        Self::check_token_program(token_program, escrow_info)?;
         let pda_account = next_account_info(account_info_iter)?;
 
-        // transfer the ownership authority of the
-        // token account back to the user who have initialized the escorw
-        let cancel_listing_ix = spl_token::instruction::set_authority(
-            token_program.key,
-            pdas_token_account.key,
-            Some(user.key),
-            spl_token::instruction::AuthorityType::AccountOwner,
-            &pda,
-            &[],
-        )?;
-        invoke_signed(
-            &cancel_listing_ix,
-            &[
-                pdas_token_account.clone(),
-                pda_account.clone(),
-                token_program.clone(),
-            ],
-            &[&[&b"escrow"[..], &[nonce]]],
-        )?;
+        match escrow_info.listing_mode() {
+            // transfer the ownership authority of the
+            // token account back to the user who have initialized the escorw
+            ListingMode::Custody => Self::release_token_account(
+                token_program,
+                pdas_token_account,
+                user.key,
+                pda_account,
+                &pda,
+                nonce,
+            )?,
+            // thaw the token account, a frozen one still holds the token and the approval
+            ListingMode::Frozen => {
+                let mint_account = next_account_info(account_info_iter)?;
+                if *mint_account.key != escrow_info.mint_key {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                Self::thaw(token_program, pdas_token_account, mint_account, pda_account, &pda, nonce)?;
+                invoke(
+                    &spl_token_2022::instruction::revoke(
+                        token_program.key,
+                        pdas_token_account.key,
+                        user.key,
+                        &[],
+                    )?,
+                    &[
+                        pdas_token_account.clone(),
+                        user.clone(),
+                        token_program.clone(),
+                    ],
+                )?;
+            }
+            // take back the approval, unless the seller already revoked it or
+            // moved the token elsewhere
+            ListingMode::Delegate => {
+                if Self::delegated_token(pdas_token_account, escrow_info, &pda).is_ok() {
+                    invoke(
+                        &spl_token_2022::instruction::revoke(
+                            token_program.key,
+                            pdas_token_account.key,
+                            user.key,
+                            &[],
+                        )?,
+                        &[
+                            pdas_token_account.clone(),
+                            user.clone(),
+                            token_program.clone(),
+                        ],
+                    )?;
+                }
+            }
+        }
 
         // set the escorw state is_initialized to false
-        let mut escrow_update_info = Escrow::unpack_unchecked(&escrow_account.try_borrow_data()?)?;
+        escrow_info.header_mut().set_initialized(false);
 
-        escrow_update_info.is_initialized = false;
-
-        Escrow::pack(escrow_update_info, &mut escrow_account.try_borrow_mut_data()?)?;
+        EscrowEvent::Cancelled {
+            escrow: *escrow_account.key,
+            seller: *user.key,
+            mint: escrow_info.mint_key,
+        }
+        .emit();
+        if let Some(legacy_listing) = legacy_listing {
+            legacy_listing.store_legacy(&mut escrow_data)?;
+        }
 
         Ok(())
     }
 
-    pub fn process_val_accounts(
-        accounts:&[AccountInfo],
-        amount: u64,
+    /// Hands the escrowed token account back to the seller on behalf of the
+    /// authority of the platform account it was listed on, for listings moderation
+    /// takes down.
+    pub fn process_force_delist(
+        accounts: &[AccountInfo],
+        reason: u8,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
 
-        msg!("fees percent {:?}", amount);
+        let pdas_token_account = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
 
+        // check if owner of escrow account is the program
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+        let mut legacy_listing = None;
+        let escrow_info = Self::load_listing(&mut escrow_data, &mut legacy_listing)?;
+        if escrow_info.token_account_pubkey != *pdas_token_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (pda, nonce) = Self::listing_escrow_authority(escrow_info, program_id)?;
+        let token_program = next_account_info(account_info_iter)?;
+        Self::check_token_program(token_program, escrow_info)?;
+        let pda_account = next_account_info(account_info_iter)?;
+
+        // listings made before the marketplace was recorded are moderated by the admin
+        if escrow_info.marketplace == Pubkey::default() {
+            Self::check_platform_authority(admin, &Self::platform_admin())?;
+        } else {
+            let platform_account = next_account_info(account_info_iter)?;
+            if *platform_account.key != escrow_info.marketplace {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let (_platform_info, authority) = Self::load_platform(platform_account, program_id)?;
+            Self::check_platform_authority(admin, &authority)?;
+        }
+
+        // the token account goes back to the seller recorded in the listing, a
+        // delegated or frozen listing never left the seller's wallet and only loses
+        // the approval of the pda
+        if escrow_info.listing_mode() == ListingMode::Custody {
+            Self::release_token_account(
+                token_program,
+                pdas_token_account,
+                &escrow_info.seller_pubkey,
+                pda_account,
+                &pda,
+                nonce,
+            )?;
+        } else {
+            let mint_account = next_account_info(account_info_iter)?;
+            if *mint_account.key != escrow_info.mint_key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if escrow_info.listing_mode() == ListingMode::Frozen {
+                Self::thaw(token_program, pdas_token_account, mint_account, pda_account, &pda, nonce)?;
+            }
+
+            let approved = token::unpack_token_account(pdas_token_account)
+                .ok()
+                .filter(|token_account| token_account.delegate == COption::Some(pda));
+            match approved {
+                // Token-2022 lets the pda give up its approval as delegate
+                Some(_) if escrow_info.token_program() == TokenProgram::Token2022 => {
+                    Self::invoke_as_custodian(
+                        &spl_token_2022::instruction::revoke(token_program.key, pdas_token_account.key, &pda, &[])?,
+                        &[
+                            pdas_token_account.clone(),
+                            pda_account.clone(),
+                            token_program.clone(),
+                        ],
+                        nonce,
+                    )?;
+                }
+                // SPL Token only takes a revoke from the owner, the pda spends the
+                // approval instead when the account holds all of it
+                Some(token_account) if token_account.delegated_amount <= token_account.amount
+                    && !token_account.is_frozen() =>
+                {
+                    Self::spend_spl_token_approval(
+                        admin,
+                        pdas_token_account,
+                        mint_account,
+                        escrow_account,
+                        token_program,
+                        pda_account,
+                        &pda,
+                        nonce,
+                        token_account.delegated_amount,
+                        account_info_iter,
+                        program_id,
+                    )?;
+                }
+                _ => (),
+            }
+        }
+
+        escrow_info.header_mut().set_initialized(false);
+
+        EscrowEvent::ForceDelisted {
+            escrow: *escrow_account.key,
+            seller: escrow_info.seller_pubkey,
+            mint: escrow_info.mint_key,
+            reason,
+        }
+        .emit();
+        if let Some(legacy_listing) = legacy_listing {
+            legacy_listing.store_legacy(&mut escrow_data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn process_val_accounts(
+        accounts:&[AccountInfo],
+        amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // the fee is taken out of the price, it cannot be more than all of it
+        if amount > 10000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-        //update authority of platform
-        let admin_update_auth = Pubkey::from_str("J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq").unwrap();
         let account_info_iter = &mut accounts.iter();
         let user = next_account_info(account_info_iter)?;
 
-        // validation check if the user calling this instruction
-        // actually holds the authority for updating the platform account
-        if admin_update_auth != *user.key {
-            msg!("wrong update auth.....");
-            return Err(ProgramError::InvalidAccountData);
-        }        
-
         let platfrom_account = next_account_info(account_info_iter)?;
 
         // check if program owns platfrom_account account
@@ -466,19 +1420,1624 @@ impl Processor {
             return Err(ProgramError::InvalidInstructionData);
         }
 
+        // the admin sets up its own platform account with the first update, a
+        // marketplace is created initialized and updated by its own authority.
+        // Nothing set up here is kept unless the authority signed for it below
+        {
+            let mut data = platfrom_account.try_borrow_mut_data()?;
+            if data.len() == VaultAccount::LEGACY_LEN {
+                let platform = VaultAccount::unpack_legacy(&data)?;
+                VaultAccount { is_initialized: true, ..platform }.pack_legacy(&mut data)?;
+            } else if account_type(&data) != Some(AccountType::Marketplace)
+                && !view::load_unchecked::<VaultAccountView>(&data)?.header().is_initialized()
+            {
+                view::init::<VaultAccountView>(&mut data)?;
+            }
+        }
+
+        let account_update_info = Self::update_platform(user, platfrom_account, program_id, |platform| {
+            platform.treasury_account = *treasury_acc.key;
+            platform.set_base_percentage(amount);
+            Ok(())
+        })?;
+
+        msg!("fee percentage : {:?}", account_update_info.base_percentage());
+
+        EscrowEvent::ConfigUpdated {
+            platform_account: *platfrom_account.key,
+            treasury: account_update_info.treasury_account,
+            fee_basis_points: account_update_info.base_percentage(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_set_paused(
+        accounts: &[AccountInfo],
+        paused: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        Self::update_platform(admin, platform_account, program_id, |platform| {
+            platform.set_paused(paused);
+            Ok(())
+        })?;
+
+        EscrowEvent::PausedSet {
+            platform_account: *platform_account.key,
+            paused,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_set_pass_discount(
+        accounts: &[AccountInfo],
+        pass_fee_basis_points: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        let pass_collection = next_account_info(account_info_iter)?;
+        Self::update_platform(admin, platform_account, program_id, |platform| {
+            // a discount, not a surcharge on the fee of everyone else
+            if pass_fee_basis_points > platform.base_percentage() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            platform.pass_collection = *pass_collection.key;
+            platform.set_pass_fee_basis_points(pass_fee_basis_points);
+            Ok(())
+        })?;
+
+        EscrowEvent::PassDiscountSet {
+            platform_account: *platform_account.key,
+            pass_collection: *pass_collection.key,
+            pass_fee_basis_points,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_set_referral_share(
+        accounts: &[AccountInfo],
+        referral_basis_points: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        Self::update_platform(admin, platform_account, program_id, |platform| {
+            // the share is taken out of the platform fee, it cannot be more than all of it
+            if referral_basis_points > 10000 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            platform.set_referral_basis_points(referral_basis_points);
+            Ok(())
+        })?;
+
+        EscrowEvent::ReferralShareSet {
+            platform_account: *platform_account.key,
+            referral_basis_points,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_set_escrow_royalties(
+        accounts: &[AccountInfo],
+        escrow_royalties: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        Self::update_platform(admin, platform_account, program_id, |platform| {
+            platform.set_escrow_royalties(escrow_royalties);
+            Ok(())
+        })?;
+
+        EscrowEvent::EscrowRoyaltiesSet {
+            platform_account: *platform_account.key,
+            escrow_royalties,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Moves the royalties deposited into the vault of the signing creator and not
+    /// claimed yet to the destination account. The vault keeps its rent-exempt
+    /// minimum and its totals.
+    pub fn process_claim_royalties(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        let royalty_vault = next_account_info(account_info_iter)?;
+        let destination = next_account_info(account_info_iter)?;
+
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if royalty_vault.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if destination.key == royalty_vault.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut royalty_vault_data = royalty_vault.try_borrow_mut_data()?;
+        let vault = view::load_mut::<RoyaltyVaultView>(&mut royalty_vault_data)?;
+        if vault.creator != *creator.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let amount = vault.total_deposited().saturating_sub(vault.total_claimed());
+        if amount == 0 {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        let total_claimed = vault.total_deposited();
+        vault.set_total_claimed(total_claimed);
+
+        Self::move_lamports(royalty_vault, destination, amount)?;
+
+        EscrowEvent::RoyaltiesClaimed {
+            creator: *creator.key,
+            royalty_vault: *royalty_vault.key,
+            destination: *destination.key,
+            amount,
+            total_claimed,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Sets the proceeds-vault mode of a listing. The mode changes which accounts a
+    /// sale takes, so it is only set along with the listing, after it in the same
+    /// transaction.
+    pub fn process_set_proceeds_vault(
+        accounts: &[AccountInfo],
+        proceeds_vault: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let seller = next_account_info(account_info_iter)?;
+        let escrow_account = next_account_info(account_info_iter)?;
+        let instructions_sysvar = next_account_info(account_info_iter)?;
+
+        if !seller.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if !Self::listed_in_transaction(escrow_account.key, instructions_sysvar, program_id)? {
+            return Err(EscrowError::ProceedsVaultAfterListing.into());
+        }
+        let mut escrow_data = escrow_account.try_borrow_mut_data()?;
+        let escrow_info = view::load_mut::<EscrowView>(&mut escrow_data)?;
+        if escrow_info.seller_pubkey != *seller.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        escrow_info.set_proceeds_vault(proceeds_vault);
+
+        EscrowEvent::ProceedsVaultSet {
+            escrow: *escrow_account.key,
+            seller: *seller.key,
+            proceeds_vault,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Whether an instruction of this program before the current one in the
+    /// transaction lists a token in `escrow_account`, as the instructions sysvar
+    /// records them.
+    fn listed_in_transaction(
+        escrow_account: &Pubkey,
+        instructions_sysvar: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        for index in 0..load_current_index_checked(instructions_sysvar)? {
+            let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+            if instruction.program_id != *program_id {
+                continue;
+            }
+            let lists = matches!(
+                EscrowInstruction::unpack(&instruction.data),
+                Ok(EscrowInstruction::ListToken { .. }
+                    | EscrowInstruction::ListTokenDelegated { .. }
+                    | EscrowInstruction::ListTokenFrozen { .. }
+                    | EscrowInstruction::ListTokenUsd { .. })
+            );
+            // the escrow account comes fourth in every listing instruction
+            if lists && instruction.accounts.get(3).map(|account| account.pubkey) == Some(*escrow_account) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Moves the balance of the proceeds PDA of the signing seller to the destination
+    /// account. The PDA keeps its rent-exempt minimum and its totals.
+    pub fn process_withdraw_proceeds(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let seller = next_account_info(account_info_iter)?;
+        let seller_proceeds = next_account_info(account_info_iter)?;
+        let destination = next_account_info(account_info_iter)?;
+
+        if !seller.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if seller_proceeds.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if destination.key == seller_proceeds.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut seller_proceeds_data = seller_proceeds.try_borrow_mut_data()?;
+        let proceeds = view::load_mut::<SellerProceedsView>(&mut seller_proceeds_data)?;
+        if proceeds.seller != *seller.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let amount = proceeds.total_credited().saturating_sub(proceeds.total_withdrawn());
+        if amount == 0 {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        let total_withdrawn = proceeds.total_credited();
+        proceeds.set_total_withdrawn(total_withdrawn);
+
+        Self::move_lamports(seller_proceeds, destination, amount)?;
+
+        EscrowEvent::ProceedsWithdrawn {
+            seller: *seller.key,
+            seller_proceeds: *seller_proceeds.key,
+            destination: *destination.key,
+            amount,
+            total_withdrawn,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_register_referrer(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let referrer = next_account_info(account_info_iter)?;
+        let referrer_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        if !referrer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let (referrer_account_key, referrer_bump_seed) = find_referrer_address(program_id, referrer.key);
+        if *referrer_account.key != referrer_account_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if referrer_account.owner == program_id {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        Self::create_pda_account(
+            referrer,
+            referrer_account,
+            system_program,
+            ReferrerAccount::LEN,
+            &[REFERRER_SEED, referrer.key.as_ref(), &[referrer_bump_seed]],
+            program_id,
+        )?;
+
+        let mut referrer_data = referrer_account.try_borrow_mut_data()?;
+        let earnings = view::init::<ReferrerAccountView>(&mut referrer_data)?;
+        earnings.referrer = *referrer.key;
+
+        EscrowEvent::ReferrerRegistered {
+            referrer: *referrer.key,
+            referrer_account: *referrer_account.key,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Moves `amount` lamports out of `from`, an account of the program, which
+    /// needs no system program to debit it.
+    fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+        let from_lamports = from.lamports().checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+        let to_lamports = to.lamports().checked_add(amount).ok_or(ProgramError::InvalidAccountData)?;
+        **from.try_borrow_mut_lamports()? = from_lamports;
+        **to.try_borrow_mut_lamports()? = to_lamports;
+        Ok(())
+    }
+
+    /// Checks that `pass_token_account` holds an NFT of the membership pass
+    /// collection, one with `pass_collection` as verified creator, for one of `holders`.
+    fn check_membership_pass(
+        pass_token_account: &AccountInfo,
+        pass_metadata: &AccountInfo,
+        pass_collection: &Pubkey,
+        holders: &[Pubkey],
+    ) -> ProgramResult {
+        // the platform has no membership pass
+        if *pass_collection == Pubkey::default() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if *pass_token_account.owner != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let pass_token = TokenAccount::unpack(&pass_token_account.try_borrow_data()?)?;
+        if pass_token.amount == 0 || !holders.contains(&pass_token.owner) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (pass_metadata_key, _pass_metadata_bump_seed) = find_metadata_address(&pass_token.mint);
+        if *pass_metadata.key != pass_metadata_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let metadata = Metadata::from_account_info(pass_metadata)?;
+        let verified = metadata
+            .data
+            .creators
+            .unwrap_or_default()
+            .iter()
+            .any(|creator| creator.verified && creator.address == *pass_collection);
+        if !verified {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    /// Creates a marketplace platform account, with its own authority, treasury
+    /// and fee, at the PDA of the creator and `name`.
+    pub fn process_create_marketplace(
+        accounts: &[AccountInfo],
+        name: [u8; MARKETPLACE_NAME_LEN],
+        fee_basis_points: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let marketplace_account = next_account_info(account_info_iter)?;
+        let authority = next_account_info(account_info_iter)?;
+        let treasury = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // a UTF-8 name followed by nothing but its zero padding, so that every
+        // name has a single encoding and a single address
+        let name_len = name.iter().position(|byte| *byte == 0).unwrap_or(MARKETPLACE_NAME_LEN);
+        let (name_bytes, padding) = name.split_at(name_len);
+        if name_len == 0 || padding.iter().any(|byte| *byte != 0) || fee_basis_points > 10000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let name_str = std::str::from_utf8(name_bytes).map_err(|_| ProgramError::InvalidInstructionData)?;
+        let (marketplace_key, marketplace_bump_seed) =
+            find_marketplace_address(program_id, creator.key, name_str);
+        if *marketplace_account.key != marketplace_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::create_pda_account(
+            creator,
+            marketplace_account,
+            system_program,
+            Marketplace::LEN,
+            &[MARKETPLACE_SEED, creator.key.as_ref(), name_bytes, &[marketplace_bump_seed]],
+            program_id,
+        )?;
+
+        let marketplace = Marketplace {
+            is_initialized: true,
+            authority: *authority.key,
+            creator: *creator.key,
+            name,
+            platform: VaultAccount {
+                is_initialized: true,
+                treasury_account: *treasury.key,
+                base_percentage: fee_basis_points,
+                paused: false,
+                pass_collection: Pubkey::default(),
+                pass_fee_basis_points: 0,
+                referral_basis_points: 0,
+                escrow_royalties: false,
+            },
+        };
+        Marketplace::pack(marketplace, &mut marketplace_account.try_borrow_mut_data()?)?;
+
+        EscrowEvent::MarketplaceCreated {
+            marketplace: *marketplace_account.key,
+            creator: *creator.key,
+            authority: *authority.key,
+            treasury: *treasury.key,
+            name: name_str.to_string(),
+            fee_basis_points,
+        }
+        .emit();
 
-        // unpack the platfrom_account state, to store data into
-        let mut account_update_info = VaultAccount::unpack_unchecked(&platfrom_account.try_borrow_data()?)?;
+        Ok(())
+    }
 
-        account_update_info.is_initialized = true;
-        account_update_info.treasury_account = *treasury_acc.key;
-        account_update_info.base_percentage = amount;
+    /// Reads the fee settings of a platform account in place, the admin's
+    /// [VaultAccount](../state/struct.VaultAccount.html) or a
+    /// [Marketplace](../state/struct.Marketplace.html), along with the key
+    /// allowed to change them.
+    fn load_platform(
+        platform_account: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<(PlatformView, Pubkey), ProgramError> {
+        if platform_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = platform_account.try_borrow_data()?;
+        match account_type(&data) {
+            Some(AccountType::Marketplace) => {
+                let marketplace = view::load::<MarketplaceView>(&data)?;
+                Ok((marketplace.platform, marketplace.authority))
+            }
+            // the admin's platform account may still have the layout from before the header
+            _ if data.len() == VaultAccount::LEGACY_LEN => {
+                let platform = VaultAccountView::from_legacy(&data)?;
+                if !platform.header().is_initialized() {
+                    return Err(ProgramError::UninitializedAccount);
+                }
+                Ok((platform.platform, Self::platform_admin()))
+            }
+            _ => Ok((view::load::<VaultAccountView>(&data)?.platform, Self::platform_admin())),
+        }
+    }
 
-        msg!("fee percentage : {:?}", account_update_info.base_percentage);
+    /// Changes the fee settings of a platform account in place with `update`, once
+    /// `admin` proved it is the key allowed to change them. Returns the new settings.
+    /// The admin's platform account of the layout before the header is copied out
+    /// and written back, refused for settings that layout cannot hold.
+    fn update_platform(
+        admin: &AccountInfo,
+        platform_account: &AccountInfo,
+        program_id: &Pubkey,
+        update: impl FnOnce(&mut PlatformView) -> ProgramResult,
+    ) -> Result<PlatformView, ProgramError> {
+        if platform_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut data = platform_account.try_borrow_mut_data()?;
+        match account_type(&data) {
+            Some(AccountType::Marketplace) => {
+                let marketplace = view::load_mut::<MarketplaceView>(&mut data)?;
+                Self::check_platform_authority(admin, &marketplace.authority)?;
+                update(&mut marketplace.platform)?;
+                Ok(marketplace.platform)
+            }
+            _ if data.len() == VaultAccount::LEGACY_LEN => {
+                let mut platform = VaultAccountView::from_legacy(&data)?;
+                if !platform.header().is_initialized() {
+                    return Err(ProgramError::UninitializedAccount);
+                }
+                Self::check_platform_authority(admin, &Self::platform_admin())?;
+                update(&mut platform.platform)?;
+                platform.store_legacy(&mut data)?;
+                Ok(platform.platform)
+            }
+            _ => {
+                let platform = view::load_mut::<VaultAccountView>(&mut data)?;
+                Self::check_platform_authority(admin, &Self::platform_admin())?;
+                update(&mut platform.platform)?;
+                Ok(platform.platform)
+            }
+        }
+    }
 
-        // pack data into the platform account
-        VaultAccount::pack(account_update_info, &mut platfrom_account.try_borrow_mut_data()?)?;
+    /// Admin of the platform accounts that are not marketplaces.
+    fn platform_admin() -> Pubkey {
+        // update authority of platform
+        Pubkey::from_str("J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq").unwrap()
+    }
 
+    /// Checks that `admin` is the `authority` of a platform account and signed the instruction.
+    fn check_platform_authority(admin: &AccountInfo, authority: &Pubkey) -> ProgramResult {
+        // validation check if the user calling this instruction
+        // actually holds the authority for updating the platform account
+        if *authority != *admin.key {
+            msg!("wrong update auth.....");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !admin.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn process_init_token_swap(
+        accounts: &[AccountInfo],
+        offered_amount: u64,
+        expected_amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list_token_swap(accounts, offered_amount, expected_amount, false, program_id)
+    }
+
+    /// Lists a token swap for wrapped SOL, paid out to the seller's wallet as
+    /// native SOL.
+    pub fn process_list_token_swap_for_sol(
+        accounts: &[AccountInfo],
+        offered_amount: u64,
+        expected_amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::list_token_swap(accounts, offered_amount, expected_amount, true, program_id)
+    }
+
+    fn list_token_swap(
+        accounts: &[AccountInfo],
+        offered_amount: u64,
+        expected_amount: u64,
+        native_payout: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        // initializer is signer validation check
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let offered_token_account = next_account_info(account_info_iter)?;
+        let receive_token_account = next_account_info(account_info_iter)?;
+        let offered_mint = next_account_info(account_info_iter)?;
+        let swap_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_info)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // swaps are not listed on a marketplace, no new ones while the admin's
+        // platform account is paused
+        let platform_account = next_account_info(account_info_iter)?;
+        if account_type(&platform_account.try_borrow_data()?) == Some(AccountType::Marketplace) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (platform_info, _authority) = Self::load_platform(platform_account, program_id)?;
+        if platform_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
+        }
+
+        // check offered and expected amounts > 0
+        if offered_amount == 0 || expected_amount == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // mint validation check
+        if *offered_mint.owner != spl_token::id() || *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // the offered tokens must come from the initializer's account of the offered mint
+        let offered_token_state = TokenAccount::unpack(&offered_token_account.try_borrow_data()?)?;
+        if offered_token_state.mint != *offered_mint.key
+            || offered_token_state.owner != *initializer.key
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if offered_token_state.amount < offered_amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        // the payments are sent to the initializer's account of the expected mint,
+        // or to the initializer's wallet when they are paid out in native SOL
+        let expected_mint = if native_payout {
+            if *receive_token_account.key != *initializer.key
+                || *offered_mint.key == spl_token::native_mint::id()
+            {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            spl_token::native_mint::id()
+        } else {
+            let receive_token_state = TokenAccount::unpack(&receive_token_account.try_borrow_data()?)?;
+            if receive_token_state.owner != *initializer.key
+                || receive_token_state.mint == *offered_mint.key
+            {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            receive_token_state.mint
+        };
+
+        // the vault is a PDA of the swap account, so every swap gets its own vault
+        let (vault_key, vault_nonce) = find_vault_address(program_id, swap_account.key);
+        if vault_key != *vault_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        invoke(
+            &create_account(
+                initializer.key,
+                swap_account.key,
+                rent.minimum_balance(TokenSwap::LEN),
+                TokenSwap::LEN as u64,
+                program_id,
+            ),
+            &[
+                initializer.clone(),
+                swap_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+
+        // create the vault token account, held by the escrow pda
+        let (pda, nonce) = find_escrow_authority(program_id);
+        invoke_signed(
+            &create_account(
+                initializer.key,
+                vault_account.key,
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                token_program.key,
+            ),
+            &[
+                initializer.clone(),
+                vault_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[VAULT_SEED, swap_account.key.as_ref(), &[vault_nonce]]],
+        )?;
+        invoke(
+            &spl_token::instruction::initialize_account2(
+                token_program.key,
+                vault_account.key,
+                offered_mint.key,
+                &pda,
+            )?,
+            &[
+                vault_account.clone(),
+                offered_mint.clone(),
+                rent_info.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // move the offered tokens into the vault
+        invoke(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                offered_token_account.key,
+                vault_account.key,
+                initializer.key,
+                &[],
+                offered_amount,
+            )?,
+            &[
+                offered_token_account.clone(),
+                vault_account.clone(),
+                initializer.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        let mut swap_data = swap_account.try_borrow_mut_data()?;
+        let swap_info = view::init::<TokenSwapView>(&mut swap_data)?;
+
+        // set the state for swap account
+        swap_info.seller_pubkey = *initializer.key;
+        swap_info.vault_pubkey = *vault_account.key;
+        swap_info.seller_receive_pubkey = *receive_token_account.key;
+        swap_info.offered_mint = *offered_mint.key;
+        swap_info.expected_mint = expected_mint;
+        swap_info.set_offered_amount(offered_amount);
+        swap_info.set_expected_amount(expected_amount);
+        swap_info.set_native_payout(native_payout);
+        swap_info.escrow_authority_bump_seed = nonce;
+
+        EscrowEvent::TokenSwapListed {
+            swap: *swap_account.key,
+            seller: *initializer.key,
+            offered_mint: swap_info.offered_mint,
+            expected_mint: swap_info.expected_mint,
+            offered_amount,
+            expected_amount,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn process_token_swap_exchange(
+        accounts: &[AccountInfo],
+        amount: u64,
+        amount_expected_by_taker: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::fill_token_swap(accounts, amount, amount_expected_by_taker, false, program_id)
+    }
+
+    /// Fills a token swap expecting wrapped SOL with the taker's native SOL, wrapped
+    /// in a temporary token account of the taker for the fill.
+    pub fn process_exchange_token_swap_with_sol(
+        accounts: &[AccountInfo],
+        amount: u64,
+        amount_expected_by_taker: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::fill_token_swap(accounts, amount, amount_expected_by_taker, true, program_id)
+    }
+
+    fn fill_token_swap(
+        accounts: &[AccountInfo],
+        amount: u64,
+        amount_expected_by_taker: u64,
+        pay_with_sol: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let taker = next_account_info(account_info_iter)?;
+
+        // check if the taker is the singer for this instruction
+        if !taker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let takers_sending_token_account = next_account_info(account_info_iter)?;
+        let takers_receiving_token_account = next_account_info(account_info_iter)?;
+        let initializers_receive_token_account = next_account_info(account_info_iter)?;
+        let initializers_main_account = next_account_info(account_info_iter)?;
+        let swap_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        if *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // swaps are not listed on a marketplace, they stop with the admin's platform account
+        let platform_account = next_account_info(account_info_iter)?;
+        if account_type(&platform_account.try_borrow_data()?) == Some(AccountType::Marketplace) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (platform_info, _authority) = Self::load_platform(platform_account, program_id)?;
+        if platform_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
+        }
+
+        // check if owner of swap account is the program
+        if swap_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut swap_data = swap_account.try_borrow_mut_data()?;
+        let swap_info = view::load_mut::<TokenSwapView>(&mut swap_data)?;
+
+        // validate data using TokenSwap state
+        if swap_info.vault_pubkey != *vault_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if swap_info.seller_receive_pubkey != *initializers_receive_token_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if swap_info.seller_pubkey != *initializers_main_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if *taker.key == *initializers_main_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the taker must agree on the price of the fill
+        let price = swap_info
+            .price_for(amount)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        if price != amount_expected_by_taker {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if pay_with_sol {
+            let system_program = next_account_info(account_info_iter)?;
+            Self::pay_token_swap_with_sol(
+                swap_info,
+                price,
+                taker,
+                takers_sending_token_account,
+                initializers_receive_token_account,
+                token_program,
+                system_program,
+            )?;
+        } else {
+            // a swap paid out in native SOL has no token account to receive the payment
+            if swap_info.native_payout() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            // pay the seller in the expected token
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program.key,
+                    takers_sending_token_account.key,
+                    initializers_receive_token_account.key,
+                    taker.key,
+                    &[],
+                    price,
+                )?,
+                &[
+                    takers_sending_token_account.clone(),
+                    initializers_receive_token_account.clone(),
+                    taker.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        // release the offered tokens from the vault to the taker
+        let (pda, nonce) = Self::recorded_escrow_authority(swap_info.escrow_authority_bump_seed, program_id)?;
+        Self::invoke_as_custodian(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                vault_account.key,
+                takers_receiving_token_account.key,
+                &pda,
+                &[],
+                amount,
+            )?,
+            &[
+                vault_account.clone(),
+                takers_receiving_token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )?;
+
+        swap_info.set_offered_amount(swap_info.offered_amount() - amount);
+        swap_info.set_expected_amount(swap_info.expected_amount() - price);
+
+        // the last fill closes the vault and the swap
+        if swap_info.offered_amount() == 0 {
+            Self::close_vault(
+                token_program,
+                vault_account,
+                initializers_main_account,
+                pda_account,
+                &pda,
+                nonce,
+            )?;
+            swap_info.header_mut().set_initialized(false);
+        }
+
+        EscrowEvent::TokenSwapFilled {
+            swap: *swap_account.key,
+            taker: *taker.key,
+            amount,
+            paid: price,
+            remaining_offered_amount: swap_info.offered_amount(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Pays `price` lamports of the taker to the seller of a swap expecting wrapped
+    /// SOL: straight to the seller's wallet when the swap pays out native SOL, else
+    /// wrapped in the taker's temporary account and transferred to the seller's
+    /// token account. The temporary account is closed back to the taker either way.
+    fn pay_token_swap_with_sol<'a>(
+        swap_info: &TokenSwapView,
+        price: u64,
+        taker: &AccountInfo<'a>,
+        temporary_account: &AccountInfo<'a>,
+        seller_receive_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if swap_info.expected_mint != spl_token::native_mint::id() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let temporary_state = TokenAccount::unpack(&temporary_account.try_borrow_data()?)?;
+        if temporary_state.mint != spl_token::native_mint::id() || temporary_state.owner != *taker.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if swap_info.native_payout() {
+            invoke(
+                &transfer(taker.key, seller_receive_account.key, price),
+                &[taker.clone(), seller_receive_account.clone(), system_program.clone()],
+            )?;
+        } else {
+            invoke(
+                &transfer(taker.key, temporary_account.key, price),
+                &[taker.clone(), temporary_account.clone(), system_program.clone()],
+            )?;
+            invoke(
+                &spl_token::instruction::sync_native(token_program.key, temporary_account.key)?,
+                &[temporary_account.clone(), token_program.clone()],
+            )?;
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program.key,
+                    temporary_account.key,
+                    seller_receive_account.key,
+                    taker.key,
+                    &[],
+                    price,
+                )?,
+                &[
+                    temporary_account.clone(),
+                    seller_receive_account.clone(),
+                    taker.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+        }
+
+        invoke(
+            &spl_token::instruction::close_account(
+                token_program.key,
+                temporary_account.key,
+                taker.key,
+                taker.key,
+                &[],
+            )?,
+            &[
+                temporary_account.clone(),
+                taker.clone(),
+                taker.clone(),
+                token_program.clone(),
+            ],
+        )
+    }
+
+    pub fn process_cancel_token_swap(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let user = next_account_info(account_info_iter)?;
+        if !user.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let refund_token_account = next_account_info(account_info_iter)?;
+        let swap_account = next_account_info(account_info_iter)?;
+        let vault_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        if *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // check if owner of swap account is the program
+        if swap_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut swap_data = swap_account.try_borrow_mut_data()?;
+        let swap_info = view::load_mut::<TokenSwapView>(&mut swap_data)?;
+
+        // check if the user cancelling the swap is actually
+        // the user who have listed it
+        if swap_info.seller_pubkey != *user.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if swap_info.vault_pubkey != *vault_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // return what is left in the vault and close it
+        let (pda, nonce) = Self::recorded_escrow_authority(swap_info.escrow_authority_bump_seed, program_id)?;
+        Self::invoke_as_custodian(
+            &spl_token::instruction::transfer(
+                token_program.key,
+                vault_account.key,
+                refund_token_account.key,
+                &pda,
+                &[],
+                swap_info.offered_amount(),
+            )?,
+            &[
+                vault_account.clone(),
+                refund_token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )?;
+        Self::close_vault(token_program, vault_account, user, pda_account, &pda, nonce)?;
+
+        EscrowEvent::TokenSwapCancelled {
+            swap: *swap_account.key,
+            seller: *user.key,
+            refunded_amount: swap_info.offered_amount(),
+        }
+        .emit();
+
+        swap_info.header_mut().set_initialized(false);
+        swap_info.set_offered_amount(0);
+        swap_info.set_expected_amount(0);
+
+        Ok(())
+    }
+
+    /// Escrows the master edition token of a Metaplex master edition for a drop of
+    /// its print editions.
+    pub fn process_list_edition_drop(
+        accounts: &[AccountInfo],
+        price: u64,
+        max_prints: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let token_account = next_account_info(account_info_iter)?;
+        let master_mint = next_account_info(account_info_iter)?;
+        let drop_account = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        let master_edition_info = next_account_info(account_info_iter)?;
+
+        // no new drops while the marketplace is paused
+        let (platform_info, _authority) = Self::load_platform(platform_account, program_id)?;
+        if platform_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
+        }
+
+        if price == 0 || max_prints == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // the token metadata program prints editions of SPL Token mints only
+        if *master_mint.owner != spl_token::id() || *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let token_account_info = TokenAccount::unpack(&token_account.try_borrow_data()?)?;
+        if token_account_info.amount != 1
+            || token_account_info.mint != *master_mint.key
+            || token_account_info.owner != *creator.key
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the master edition has to allow the prints of the drop on top of those made before
+        let (master_edition_key, _master_edition_bump_seed) = find_edition_address(master_mint.key);
+        if *master_edition_info.key != master_edition_key
+            || *master_edition_info.owner != metaplex_token_metadata::id()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let master_edition = MasterEditionV2::from_account_info(master_edition_info)?;
+        if let Some(max_supply) = master_edition.max_supply {
+            let supply = master_edition.supply.checked_add(max_prints).ok_or(ProgramError::InvalidInstructionData)?;
+            if supply > max_supply {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+
+        invoke(
+            &create_account(
+                creator.key,
+                drop_account.key,
+                rent.minimum_balance(EditionDrop::LEN),
+                EditionDrop::LEN as u64,
+                program_id,
+            ),
+            &[
+                creator.clone(),
+                drop_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+
+        let mut drop_data = drop_account.try_borrow_mut_data()?;
+        let drop_info = view::init::<EditionDropView>(&mut drop_data)?;
+        drop_info.creator = *creator.key;
+        drop_info.master_token_account = *token_account.key;
+        drop_info.master_mint = *master_mint.key;
+        drop_info.marketplace = *platform_account.key;
+        drop_info.set_price(price);
+        drop_info.set_max_prints(max_prints);
+
+        // the pda holds the master edition token and signs the prints
+        let (pda, nonce) = find_escrow_authority(program_id);
+        drop_info.escrow_authority_bump_seed = nonce;
+        invoke(
+            &spl_token::instruction::set_authority(
+                token_program.key,
+                token_account.key,
+                Some(&pda),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                creator.key,
+                &[],
+            )?,
+            &[
+                token_account.clone(),
+                creator.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        EscrowEvent::EditionDropListed {
+            drop: *drop_account.key,
+            creator: *creator.key,
+            master_mint: *master_mint.key,
+            price,
+            max_prints,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Prints an edition of a drop's master edition to the buyer's new mint, paying
+    /// its price to the creators of the master edition, the treasury and the lister.
+    pub fn process_buy_edition(
+        accounts: &[AccountInfo],
+        edition: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let buyer = next_account_info(account_info_iter)?;
+        if !buyer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let drop_account = next_account_info(account_info_iter)?;
+        let seller = next_account_info(account_info_iter)?;
+        let new_mint = next_account_info(account_info_iter)?;
+        let new_metadata = next_account_info(account_info_iter)?;
+        let new_edition = next_account_info(account_info_iter)?;
+        let master_edition = next_account_info(account_info_iter)?;
+        let edition_marker = next_account_info(account_info_iter)?;
+        let master_token_account = next_account_info(account_info_iter)?;
+        let master_metadata_info = next_account_info(account_info_iter)?;
+        let update_authority = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let metadata_program = next_account_info(account_info_iter)?;
+        let platform_account = next_account_info(account_info_iter)?;
+        let platform_treasury = next_account_info(account_info_iter)?;
+        if *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if drop_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut drop_data = drop_account.try_borrow_mut_data()?;
+        let drop_info = view::load_mut::<EditionDropView>(&mut drop_data)?;
+        if drop_info.creator != *seller.key
+            || drop_info.master_token_account != *master_token_account.key
+            || drop_info.marketplace != *platform_account.key
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if drop_info.prints_sold() >= drop_info.max_prints() || edition == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if *metadata_program.key != metaplex_token_metadata::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let (platform_info, _authority) = Self::load_platform(platform_account, program_id)?;
+        if platform_info.paused() {
+            return Err(EscrowError::MarketplacePaused.into());
+        }
+        if platform_info.treasury_account != *platform_treasury.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // the prints inherit the metadata, and its update authority, of the master edition
+        let (master_metadata_key, _metadata_bump_seed) = find_metadata_address(&drop_info.master_mint);
+        if *master_metadata_info.key != master_metadata_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let master_metadata = Metadata::from_account_info(master_metadata_info)?;
+        if master_metadata.update_authority != *update_authority.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // a print is a primary sale, its creators share what the platform fee leaves
+        let creators = master_metadata.data.creators.unwrap_or_default();
+        let payout = compute_primary_payout(drop_info.price(), &creators, platform_info.fee_basis_points(false))
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Self::pay_royalties(
+            buyer,
+            system_program,
+            &payout.royalties,
+            account_info_iter.as_slice(),
+            platform_info.escrow_royalties(),
+            program_id,
+        )?;
+        invoke(
+            &transfer(buyer.key, platform_treasury.key, payout.platform_fee),
+            &[buyer.clone(), platform_treasury.clone(), system_program.clone()],
+        )?;
+        invoke(
+            &transfer(buyer.key, seller.key, payout.seller_proceeds),
+            &[buyer.clone(), seller.clone(), system_program.clone()],
+        )?;
+
+        // the pda proves it holds the master edition token
+        let (pda, nonce) = Self::recorded_escrow_authority(drop_info.escrow_authority_bump_seed, program_id)?;
+        Self::invoke_as_custodian(
+            &mint_new_edition_from_master_edition_via_token(
+                *metadata_program.key,
+                *new_metadata.key,
+                *new_edition.key,
+                *master_edition.key,
+                *new_mint.key,
+                *buyer.key,
+                *buyer.key,
+                pda,
+                *master_token_account.key,
+                *update_authority.key,
+                *master_metadata_info.key,
+                drop_info.master_mint,
+                edition,
+            ),
+            &[
+                new_metadata.clone(),
+                new_edition.clone(),
+                master_edition.clone(),
+                new_mint.clone(),
+                edition_marker.clone(),
+                buyer.clone(),
+                pda_account.clone(),
+                master_token_account.clone(),
+                update_authority.clone(),
+                master_metadata_info.clone(),
+                token_program.clone(),
+                system_program.clone(),
+                rent_info.clone(),
+                metadata_program.clone(),
+            ],
+            nonce,
+        )?;
+
+        // the last print ends the drop and returns the master edition to its creator
+        drop_info.set_prints_sold(drop_info.prints_sold() + 1);
+        if drop_info.prints_sold() == drop_info.max_prints() {
+            Self::release_token_account(
+                token_program,
+                master_token_account,
+                &drop_info.creator,
+                pda_account,
+                &pda,
+                nonce,
+            )?;
+            drop_info.header_mut().set_initialized(false);
+        }
+
+        EscrowEvent::EditionSold {
+            drop: *drop_account.key,
+            seller: drop_info.creator,
+            buyer: *buyer.key,
+            mint: *new_mint.key,
+            edition,
+            price: drop_info.price(),
+            royalties: payout.royalties,
+            treasury: *platform_treasury.key,
+            platform_fee: payout.platform_fee,
+            seller_proceeds: payout.seller_proceeds,
+            remaining_prints: drop_info.max_prints() - drop_info.prints_sold(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Ends a drop and hands the master edition token back to its creator.
+    pub fn process_cancel_edition_drop(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let master_token_account = next_account_info(account_info_iter)?;
+        let drop_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        if *token_program.key != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if drop_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut drop_data = drop_account.try_borrow_mut_data()?;
+        let drop_info = view::load_mut::<EditionDropView>(&mut drop_data)?;
+        if drop_info.creator != *creator.key || drop_info.master_token_account != *master_token_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (pda, nonce) = Self::recorded_escrow_authority(drop_info.escrow_authority_bump_seed, program_id)?;
+        Self::release_token_account(token_program, master_token_account, creator.key, pda_account, &pda, nonce)?;
+
+        drop_info.header_mut().set_initialized(false);
+
+        EscrowEvent::EditionDropCancelled {
+            drop: *drop_account.key,
+            creator: *creator.key,
+            prints_sold: drop_info.prints_sold(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Borrows the escrow account of an initialized listing in place. The account of
+    /// a listing made before the account header is too short for that, it is copied
+    /// into `legacy_listing` instead, for the caller to write back with
+    /// [EscrowView::store_legacy](../view/struct.EscrowView.html#method.store_legacy).
+    fn load_listing<'a>(
+        data: &'a mut [u8],
+        legacy_listing: &'a mut Option<EscrowView>,
+    ) -> Result<&'a mut EscrowView, ProgramError> {
+        if data.len() == Escrow::LEGACY_LEN {
+            return Ok(legacy_listing.insert(EscrowView::from_legacy(data)?));
+        }
+        view::load_mut::<EscrowView>(data)
+    }
+
+    /// Escrow authority pda of a listing and its bump seed, recreated from the bump
+    /// seed the listing recorded. Listings made before it was recorded search for it.
+    fn listing_escrow_authority(escrow_info: &EscrowView, program_id: &Pubkey) -> Result<(Pubkey, u8), ProgramError> {
+        Self::recorded_escrow_authority(escrow_info.escrow_authority_bump_seed, program_id)
+    }
+
+    /// Escrow authority pda and its bump seed, from the `bump_seed` a listing, swap
+    /// or drop recorded, or searched for when it is 0.
+    fn recorded_escrow_authority(bump_seed: u8, program_id: &Pubkey) -> Result<(Pubkey, u8), ProgramError> {
+        match bump_seed {
+            0 => Ok(find_escrow_authority(program_id)),
+            bump_seed => Ok((create_escrow_authority(program_id, bump_seed)?, bump_seed)),
+        }
+    }
+
+    /// Metadata account of the mint of a listing, recreated from the bump seed the
+    /// listing recorded like [`Self::listing_escrow_authority`].
+    fn listing_metadata_address(escrow_info: &EscrowView) -> Result<Pubkey, ProgramError> {
+        match escrow_info.metadata_bump_seed {
+            0 => Ok(find_metadata_address(&escrow_info.mint_key).0),
+            bump_seed => create_metadata_address(&escrow_info.mint_key, bump_seed),
+        }
+    }
+
+    /// Creates the PDA `account` of `space` bytes owned by `owner`, signed with its
+    /// `seeds`. Lamports sent to the address beforehand would make `create_account`
+    /// fail, so `payer` only tops them up to the rent exemption.
+    fn create_pda_account<'a>(
+        payer: &AccountInfo<'a>,
+        account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        space: usize,
+        seeds: &[&[u8]],
+        owner: &Pubkey,
+    ) -> ProgramResult {
+        let top_up = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+        if top_up > 0 {
+            invoke(
+                &transfer(payer.key, account.key, top_up),
+                &[
+                    payer.clone(),
+                    account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        invoke_signed(
+            &allocate(account.key, space as u64),
+            &[account.clone(), system_program.clone()],
+            &[seeds],
+        )?;
+        invoke_signed(
+            &assign(account.key, owner),
+            &[account.clone(), system_program.clone()],
+            &[seeds],
+        )
+    }
+
+    /// Invokes `instruction` signed by the `b"escrow"` pda, the custodian of every
+    /// escrowed token account, NFT listings and token swap vaults alike.
+    fn invoke_as_custodian(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        nonce: u8,
+    ) -> ProgramResult {
+        invoke_signed(instruction, account_infos, &[&[ESCROW_AUTHORITY_SEED, &[nonce]]])
+    }
+
+    /// Reads the token account of a delegated or frozen listing, which must still
+    /// belong to the seller, hold the token and have the pda approved as its delegate.
+    fn delegated_token(
+        token_account: &AccountInfo,
+        escrow_info: &EscrowView,
+        pda: &Pubkey,
+    ) -> Result<spl_token_2022::state::Account, ProgramError> {
+        if *token_account.owner != escrow_info.token_program().id() {
+            return Err(EscrowError::ListingInvalidated.into());
+        }
+        let token_account_info =
+            token::unpack_token_account(token_account).map_err(|_| EscrowError::ListingInvalidated)?;
+        if token_account_info.owner != escrow_info.seller_pubkey
+            || token_account_info.mint != escrow_info.mint_key
+            || token_account_info.amount < 1
+            || token_account_info.delegate != COption::Some(*pda)
+            || token_account_info.delegated_amount < 1
+            || token_account_info.is_frozen() != (escrow_info.listing_mode() == ListingMode::Frozen)
+        {
+            return Err(EscrowError::ListingInvalidated.into());
+        }
+        Ok(token_account_info)
+    }
+
+    /// Checks `token_program` is the token program of the listed NFT.
+    fn check_token_program(token_program: &AccountInfo, escrow_info: &EscrowView) -> ProgramResult {
+        if *token_program.key != escrow_info.token_program().id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(())
+    }
+
+    /// Thaws a token account the pda froze as freeze authority of `mint`.
+    fn thaw<'a>(
+        token_program: &AccountInfo<'a>,
+        token_account: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        pda_account: &AccountInfo<'a>,
+        pda: &Pubkey,
+        nonce: u8,
+    ) -> ProgramResult {
+        Self::invoke_as_custodian(
+            &spl_token_2022::instruction::thaw_account(token_program.key, token_account.key, mint.key, pda, &[])?,
+            &[
+                token_account.clone(),
+                mint.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )
+    }
+
+    /// Clears the approval of the pda over the SPL Token account of a listing, which
+    /// only takes a revoke from the owner: the pda moves the `delegated_amount` it
+    /// was approved for into a vault of the listing and back, which leaves it with
+    /// no approval, then closes the vault. The vault, the system program and the rent
+    /// sysvar come next in `account_info_iter`; `payer` gets the vault rent back.
+    #[allow(clippy::too_many_arguments)]
+    fn spend_spl_token_approval<'a, 'b>(
+        payer: &AccountInfo<'a>,
+        token_account: &AccountInfo<'a>,
+        mint: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        pda_account: &AccountInfo<'a>,
+        pda: &Pubkey,
+        nonce: u8,
+        delegated_amount: u64,
+        account_info_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let vault_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+
+        let (vault_key, vault_nonce) = find_vault_address(program_id, escrow_account.key);
+        if vault_key != *vault_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::create_pda_account(
+            payer,
+            vault_account,
+            system_program,
+            TokenAccount::LEN,
+            &[VAULT_SEED, escrow_account.key.as_ref(), &[vault_nonce]],
+            token_program.key,
+        )?;
+        invoke(
+            &spl_token::instruction::initialize_account2(token_program.key, vault_account.key, mint.key, pda)?,
+            &[
+                vault_account.clone(),
+                mint.clone(),
+                rent_info.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // spending all of the approval as delegate clears it
+        for (source, destination) in [(token_account, vault_account), (vault_account, token_account)] {
+            Self::invoke_as_custodian(
+                &spl_token::instruction::transfer(
+                    token_program.key,
+                    source.key,
+                    destination.key,
+                    pda,
+                    &[],
+                    delegated_amount,
+                )?,
+                &[
+                    source.clone(),
+                    destination.clone(),
+                    pda_account.clone(),
+                    token_program.clone(),
+                ],
+                nonce,
+            )?;
+        }
+
+        Self::invoke_as_custodian(
+            &spl_token::instruction::close_account(token_program.key, vault_account.key, payer.key, pda, &[])?,
+            &[
+                vault_account.clone(),
+                payer.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )
+    }
+
+    /// Hands the ownership of an escrowed token account back from the pda to `new_owner`.
+    fn release_token_account<'a>(
+        token_program: &AccountInfo<'a>,
+        token_account: &AccountInfo<'a>,
+        new_owner: &Pubkey,
+        pda_account: &AccountInfo<'a>,
+        pda: &Pubkey,
+        nonce: u8,
+    ) -> ProgramResult {
+        Self::invoke_as_custodian(
+            &spl_token_2022::instruction::set_authority(
+                token_program.key,
+                token_account.key,
+                Some(new_owner),
+                spl_token_2022::instruction::AuthorityType::AccountOwner,
+                pda,
+                &[],
+            )?,
+            &[
+                token_account.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )
+    }
+
+    /// Closes an empty vault token account, sending its rent to `destination`.
+    fn close_vault<'a>(
+        token_program: &AccountInfo<'a>,
+        vault_account: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        pda_account: &AccountInfo<'a>,
+        pda: &Pubkey,
+        nonce: u8,
+    ) -> ProgramResult {
+        Self::invoke_as_custodian(
+            &spl_token::instruction::close_account(
+                token_program.key,
+                vault_account.key,
+                destination.key,
+                pda,
+                &[],
+            )?,
+            &[
+                vault_account.clone(),
+                destination.clone(),
+                pda_account.clone(),
+                token_program.clone(),
+            ],
+            nonce,
+        )
+    }
+}
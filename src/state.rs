@@ -6,6 +6,119 @@ use solana_program::{
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 
+/// Layout version written into the header of every program account.
+pub const STATE_VERSION: u8 = 1;
+
+/// Length of the header every program account starts with: the account type,
+/// the layout version, the `is_initialized` flag and reserved bytes, which keeps
+/// the fields after it 8-byte aligned.
+pub const ACCOUNT_HEADER_LEN: usize = 8;
+
+/// Zeroed bytes at the end of every program account, kept for future fields.
+pub const ACCOUNT_PADDING_LEN: usize = 64;
+
+/// Discriminator stored in the first byte of every program account, so an
+/// account of one type can never be read as another.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+pub enum AccountType {
+    /// a freshly created, still zeroed account
+    Uninitialized,
+    Escrow,
+    PlatformAccount,
+    TokenSwap,
+    Referrer,
+    Marketplace,
+    EditionDrop,
+    UsdListing,
+    RoyaltyVault,
+    SellerProceeds,
+}
+
+/// Account type in the header of `data`, `None` for a zeroed account or an
+/// unknown type.
+pub fn account_type(data: &[u8]) -> Option<AccountType> {
+    match data.first()? {
+        1 => Some(AccountType::Escrow),
+        2 => Some(AccountType::PlatformAccount),
+        3 => Some(AccountType::TokenSwap),
+        4 => Some(AccountType::Referrer),
+        5 => Some(AccountType::Marketplace),
+        6 => Some(AccountType::EditionDrop),
+        7 => Some(AccountType::UsdListing),
+        8 => Some(AccountType::RoyaltyVault),
+        9 => Some(AccountType::SellerProceeds),
+        _ => None,
+    }
+}
+
+fn pack_header(dst: &mut [u8; ACCOUNT_HEADER_LEN], account_type: AccountType, is_initialized: bool) {
+    let (account_type_dst, version_dst, is_initialized_dst, reserved_dst) =
+        mut_array_refs![dst, 1, 1, 1, 5];
+    account_type_dst[0] = account_type as u8;
+    version_dst[0] = STATE_VERSION;
+    is_initialized_dst[0] = is_initialized as u8;
+    *reserved_dst = [0; 5];
+}
+
+/// Validates the header against `account_type` and returns the `is_initialized` flag.
+/// A header that is still all zeroes is read as an uninitialized account of any type.
+fn unpack_header(src: &[u8; ACCOUNT_HEADER_LEN], account_type: AccountType) -> Result<bool, ProgramError> {
+    if *src == [0; ACCOUNT_HEADER_LEN] {
+        return Ok(false);
+    }
+    let (account_type_src, version, is_initialized, _reserved) = array_refs![src, 1, 1, 1, 5];
+    if account_type_src[0] != account_type as u8 || version[0] != STATE_VERSION {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    match is_initialized {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// How a listed token is held until it is sold or the listing is cancelled.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+pub enum ListingMode {
+    /// the escrow authority PDA owns the token account
+    Custody,
+    /// the seller keeps the token account and approves the escrow authority PDA
+    /// as delegate of the token, the listing is void once the approval is gone
+    Delegate,
+    /// `Delegate`, with the token account frozen by the escrow authority PDA as
+    /// freeze authority of the mint until the listing is sold or cancelled
+    Frozen,
+}
+
+/// Token program of a listed NFT.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+pub enum TokenProgram {
+    SplToken,
+    Token2022,
+}
+
+impl TokenProgram {
+    pub fn from_id(program_id: &Pubkey) -> Option<Self> {
+        if *program_id == spl_token::id() {
+            Some(TokenProgram::SplToken)
+        } else if *program_id == spl_token_2022::id() {
+            Some(TokenProgram::Token2022)
+        } else {
+            None
+        }
+    }
+
+    pub fn id(&self) -> Pubkey {
+        match self {
+            TokenProgram::SplToken => spl_token::id(),
+            TokenProgram::Token2022 => spl_token_2022::id(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Escrow {
     pub is_initialized: bool,
@@ -13,7 +126,84 @@ pub struct Escrow {
     pub token_account_pubkey: Pubkey,
     pub mint_key: Pubkey,
     pub expected_amount: u64,
+    /// platform account the token was listed on, the default pubkey for
+    /// listings made before it was recorded
+    pub marketplace: Pubkey,
+    pub listing_mode: ListingMode,
+    /// program of the mint and the token account, SPL Token for listings made
+    /// before it was recorded
+    pub token_program: TokenProgram,
+    /// `expected_amount` is in millionths of a dollar, paid in lamports at the
+    /// price of the feed recorded in the [UsdListing](struct.UsdListing.html) of the listing
+    pub usd_priced: bool,
+    /// bump seed of the escrow authority PDA, 0 for listings made before it was recorded
+    pub escrow_authority_bump_seed: u8,
+    /// bump seed of the metadata PDA of `mint_key`, 0 for listings made before it was recorded
+    pub metadata_bump_seed: u8,
+    /// the proceeds of the sale are credited to the [SellerProceeds](struct.SellerProceeds.html)
+    /// of the seller, to be withdrawn later, instead of being transferred to the seller
+    pub proceeds_vault: bool,
 }
+impl Escrow {
+    /// Length of the escrow accounts of listings made before the account header,
+    /// which hold the `is_initialized` flag, the seller, the token account, the
+    /// mint and the price only.
+    pub const LEGACY_LEN: usize = 105;
+
+    /// Reads an escrow account of `LEGACY_LEN`, initialized or not. Such a listing
+    /// holds its token in custody, is of an SPL Token mint listed on the platform
+    /// account of the admin, is priced in lamports and has no bump seeds recorded.
+    pub fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != Escrow::LEGACY_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, Escrow::LEGACY_LEN];
+        let (is_initialized, seller_pubkey, token_account_pubkey, mint_key, expected_amount) =
+            array_refs![src, 1, 32, 32, 32, 8];
+        Ok(Escrow {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            seller_pubkey: Pubkey::new_from_array(*seller_pubkey),
+            token_account_pubkey: Pubkey::new_from_array(*token_account_pubkey),
+            mint_key: Pubkey::new_from_array(*mint_key),
+            expected_amount: u64::from_le_bytes(*expected_amount),
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        })
+    }
+
+    /// Writes a listing read by `unpack_legacy` back into its `LEGACY_LEN` account,
+    /// refused for a listing the legacy layout cannot hold.
+    pub fn pack_legacy(&self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != Escrow::LEGACY_LEN
+            || self.marketplace != Pubkey::default()
+            || self.listing_mode != ListingMode::Custody
+            || self.token_program != TokenProgram::SplToken
+            || self.usd_priced
+            || self.proceeds_vault
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let dst = array_mut_ref![dst, 0, Escrow::LEGACY_LEN];
+        let (is_initialized_dst, seller_pubkey_dst, token_account_pubkey_dst, mint_key_dst, expected_amount_dst) =
+            mut_array_refs![dst, 1, 32, 32, 32, 8];
+        is_initialized_dst[0] = self.is_initialized as u8;
+        seller_pubkey_dst.copy_from_slice(self.seller_pubkey.as_ref());
+        token_account_pubkey_dst.copy_from_slice(self.token_account_pubkey.as_ref());
+        mint_key_dst.copy_from_slice(self.mint_key.as_ref());
+        *expected_amount_dst = self.expected_amount.to_le_bytes();
+        Ok(())
+    }
+}
+
 impl Sealed for Escrow {}
 impl IsInitialized for Escrow {
     fn is_initialized(&self) -> bool {
@@ -21,21 +211,25 @@ impl IsInitialized for Escrow {
     }
 }
 impl Pack for Escrow {
-    const LEN: usize = 105;
+    const LEN: usize = 176;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, Escrow::LEN];
         let (
-            is_initialized,
+            header,
             seller_pubkey,
             token_account_pubkey,
             mint_key,
             expected_amount,
-        ) = array_refs![src, 1, 32, 32, 32, 8];
-        let is_initialized = match is_initialized {
-            [0] => false,
-            [1] => true,
-            _ => return Err(ProgramError::InvalidAccountData),
-        };
+            marketplace,
+            listing_mode,
+            token_program,
+            usd_priced,
+            escrow_authority_bump_seed,
+            metadata_bump_seed,
+            proceeds_vault,
+            _padding,
+        ) = array_refs![src, ACCOUNT_HEADER_LEN, 32, 32, 32, 8, 32, 1, 1, 1, 1, 1, 1, ACCOUNT_PADDING_LEN - 38];
+        let is_initialized = unpack_header(header, AccountType::Escrow)?;
         Ok(Escrow {
             is_initialized,
             seller_pubkey: Pubkey::new_from_array(*seller_pubkey),
@@ -44,29 +238,76 @@ impl Pack for Escrow {
                 *mint_key,
             ),
             expected_amount: u64::from_le_bytes(*expected_amount),
+            marketplace: Pubkey::new_from_array(*marketplace),
+            listing_mode: match listing_mode {
+                [0] => ListingMode::Custody,
+                [1] => ListingMode::Delegate,
+                [2] => ListingMode::Frozen,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            token_program: match token_program {
+                [0] => TokenProgram::SplToken,
+                [1] => TokenProgram::Token2022,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            usd_priced: match usd_priced {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            escrow_authority_bump_seed: escrow_authority_bump_seed[0],
+            metadata_bump_seed: metadata_bump_seed[0],
+            proceeds_vault: match proceeds_vault {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
         })
     }
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let dst = array_mut_ref![dst, 0, Escrow::LEN];
         let (
-            is_initialized_dst,
+            header_dst,
             seller_pubkey_dst,
             token_account_pubkey_dst,
             mint_key_dst,
             expected_amount_dst,
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 8];
+            marketplace_dst,
+            listing_mode_dst,
+            token_program_dst,
+            usd_priced_dst,
+            escrow_authority_bump_seed_dst,
+            metadata_bump_seed_dst,
+            proceeds_vault_dst,
+            padding_dst,
+        ) = mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 32, 32, 8, 32, 1, 1, 1, 1, 1, 1, ACCOUNT_PADDING_LEN - 38];
         let Escrow {
             is_initialized,
             seller_pubkey,
             token_account_pubkey,
             mint_key,
             expected_amount,
+            marketplace,
+            listing_mode,
+            token_program,
+            usd_priced,
+            escrow_authority_bump_seed,
+            metadata_bump_seed,
+            proceeds_vault,
         } = self;
-        is_initialized_dst[0] = *is_initialized as u8;
+        pack_header(header_dst, AccountType::Escrow, *is_initialized);
         seller_pubkey_dst.copy_from_slice(seller_pubkey.as_ref());
         token_account_pubkey_dst.copy_from_slice(token_account_pubkey.as_ref());
         mint_key_dst.copy_from_slice(mint_key.as_ref());
         *expected_amount_dst = expected_amount.to_le_bytes();
+        marketplace_dst.copy_from_slice(marketplace.as_ref());
+        listing_mode_dst[0] = *listing_mode as u8;
+        token_program_dst[0] = *token_program as u8;
+        usd_priced_dst[0] = *usd_priced as u8;
+        escrow_authority_bump_seed_dst[0] = *escrow_authority_bump_seed;
+        metadata_bump_seed_dst[0] = *metadata_bump_seed;
+        proceeds_vault_dst[0] = *proceeds_vault as u8;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 38];
     }
 }
 
@@ -75,6 +316,93 @@ pub struct VaultAccount {
     pub is_initialized: bool,
     pub treasury_account: Pubkey,
     pub base_percentage: u64,
+    /// while set, tokens can neither be listed nor bought, only cancelled
+    pub paused: bool,
+    /// verified creator of the membership pass collection, the default pubkey
+    /// when there is none
+    pub pass_collection: Pubkey,
+    /// platform fee, in basis points, of sales where the taker or the seller
+    /// holds a membership pass
+    pub pass_fee_basis_points: u64,
+    /// share of the platform fee, in basis points of the fee, paid to the
+    /// referrer of a sale
+    pub referral_basis_points: u64,
+    /// while set, the royalties of every sale are deposited into the
+    /// [RoyaltyVault](struct.RoyaltyVault.html) of their creator, not only those of
+    /// creators that cannot take a transfer
+    pub escrow_royalties: bool,
+}
+
+impl VaultAccount {
+    /// Length of the platform accounts written before the account header, which
+    /// hold the `is_initialized` flag, the treasury and the fee only.
+    pub const LEGACY_LEN: usize = 41;
+
+    /// Reads a platform account of `LEGACY_LEN`, initialized or not. Such an
+    /// account is not paused, has no membership pass or referral share and pays
+    /// royalties directly.
+    pub fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != VaultAccount::LEGACY_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, VaultAccount::LEGACY_LEN];
+        let (is_initialized, treasury_account, base_percentage) = array_refs![src, 1, 32, 8];
+        Ok(VaultAccount {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            treasury_account: Pubkey::new_from_array(*treasury_account),
+            base_percentage: u64::from_le_bytes(*base_percentage),
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        })
+    }
+
+    /// Writes settings read by `unpack_legacy` back into its `LEGACY_LEN` account,
+    /// refused for settings the legacy layout cannot hold. The admin sets those on
+    /// a platform account of the current layout.
+    pub fn pack_legacy(&self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != VaultAccount::LEGACY_LEN
+            || self.paused
+            || self.pass_collection != Pubkey::default()
+            || self.pass_fee_basis_points != 0
+            || self.referral_basis_points != 0
+            || self.escrow_royalties
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let dst = array_mut_ref![dst, 0, VaultAccount::LEGACY_LEN];
+        let (is_initialized_dst, treasury_account_dst, base_percentage_dst) = mut_array_refs![dst, 1, 32, 8];
+        is_initialized_dst[0] = self.is_initialized as u8;
+        treasury_account_dst.copy_from_slice(self.treasury_account.as_ref());
+        *base_percentage_dst = self.base_percentage.to_le_bytes();
+        Ok(())
+    }
+
+    /// Platform fee, in basis points, of a sale, `pass_holder` when the taker or
+    /// the seller proved they hold a membership pass. The pass fee is never more
+    /// than the base fee, which may have been lowered after the discount was set.
+    pub fn fee_basis_points(&self, pass_holder: bool) -> u64 {
+        if pass_holder && self.pass_collection != Pubkey::default() {
+            self.pass_fee_basis_points.min(self.base_percentage)
+        } else {
+            self.base_percentage
+        }
+    }
+
+    /// Part of `platform_fee` paid to the referrer of a sale, `None` if the
+    /// referral share is more than the whole fee.
+    pub fn referral_fee(&self, platform_fee: u64) -> Option<u64> {
+        if self.referral_basis_points > 10000 {
+            return None;
+        }
+        Some(platform_fee.checked_mul(self.referral_basis_points)? / 10000)
+    }
 }
 
 impl Sealed for VaultAccount {}
@@ -84,15 +412,30 @@ impl IsInitialized for VaultAccount {
     }
 }
 impl Pack for VaultAccount {
-    const LEN: usize = 41;
+    const LEN: usize = 112;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let src = array_ref![src, 0, VaultAccount::LEN];
+        // the fields after `base_percentage` were carved out of the padding, accounts
+        // written before them are not paused, have no membership pass or referral share
+        // and pay royalties directly
         let (
-            is_initialized,
+            header,
             treasury_account,
             base_percentage,
-        ) = array_refs![src, 1, 32, 8];
-        let is_initialized = match is_initialized {
+            paused,
+            pass_collection,
+            pass_fee_basis_points,
+            referral_basis_points,
+            escrow_royalties,
+            _padding,
+        ) = array_refs![src, ACCOUNT_HEADER_LEN, 32, 8, 1, 32, 8, 8, 1, ACCOUNT_PADDING_LEN - 50];
+        let is_initialized = unpack_header(header, AccountType::PlatformAccount)?;
+        let paused = match paused {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let escrow_royalties = match escrow_royalties {
             [0] => false,
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
@@ -101,25 +444,613 @@ impl Pack for VaultAccount {
             is_initialized,
             treasury_account: Pubkey::new_from_array(*treasury_account),
             base_percentage: u64::from_le_bytes(*base_percentage),
-
+            paused,
+            pass_collection: Pubkey::new_from_array(*pass_collection),
+            pass_fee_basis_points: u64::from_le_bytes(*pass_fee_basis_points),
+            referral_basis_points: u64::from_le_bytes(*referral_basis_points),
+            escrow_royalties,
         }) 
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let dst = array_mut_ref![dst, 0, VaultAccount::LEN];
         let ( 
-            is_initialized_dst,
+            header_dst,
             treasury_account_dst,
             base_percentage_dst,
-        ) = mut_array_refs![dst, 1, 32, 8];
+            paused_dst,
+            pass_collection_dst,
+            pass_fee_basis_points_dst,
+            referral_basis_points_dst,
+            escrow_royalties_dst,
+            padding_dst,
+        ) = mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 8, 1, 32, 8, 8, 1, ACCOUNT_PADDING_LEN - 50];
         let VaultAccount {
             is_initialized,
             treasury_account,
             base_percentage,
+            paused,
+            pass_collection,
+            pass_fee_basis_points,
+            referral_basis_points,
+            escrow_royalties,
         } = self;
-        is_initialized_dst[0] = *is_initialized as u8;
+        pack_header(header_dst, AccountType::PlatformAccount, *is_initialized);
         treasury_account_dst.copy_from_slice(treasury_account.as_ref());
         *base_percentage_dst = base_percentage.to_le_bytes();
+        paused_dst[0] = *paused as u8;
+        pass_collection_dst.copy_from_slice(pass_collection.as_ref());
+        *pass_fee_basis_points_dst = pass_fee_basis_points.to_le_bytes();
+        *referral_basis_points_dst = referral_basis_points.to_le_bytes();
+        escrow_royalties_dst[0] = *escrow_royalties as u8;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 50];
+
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct TokenSwap {
+    pub is_initialized: bool,
+    pub seller_pubkey: Pubkey,
+    /// token account of the offered mint, owned by the token program at
+    /// the vault PDA and held by the escrow PDA
+    pub vault_pubkey: Pubkey,
+    /// seller's token account for the expected mint, receives the payments
+    pub seller_receive_pubkey: Pubkey,
+    pub offered_mint: Pubkey,
+    pub expected_mint: Pubkey,
+    /// amount of the offered token still held in the vault
+    pub offered_amount: u64,
+    /// amount of the expected token still owed for the remaining offer
+    pub expected_amount: u64,
+    /// the expected token is wrapped SOL paid out to the seller as native SOL,
+    /// `seller_receive_pubkey` is then the seller's wallet
+    pub native_payout: bool,
+    /// bump seed of the escrow authority PDA, 0 for swaps listed before it was recorded
+    pub escrow_authority_bump_seed: u8,
+}
+
+impl TokenSwap {
+    /// Amount of the expected token a taker pays for `amount` of the offered token.
+    /// Partial fills are priced at the remaining rate rounded up, so the seller is never
+    /// short-changed, and the final fill pays exactly what is still owed. A partial fill
+    /// that would leave offered tokens in the vault with nothing owed for them is refused.
+    pub fn price_for(&self, amount: u64) -> Option<u64> {
+        swap_price(self.offered_amount, self.expected_amount, amount)
+    }
+}
+
+/// [TokenSwap::price_for](struct.TokenSwap.html#method.price_for) of a swap with
+/// `offered_amount` and `expected_amount` left.
+pub(crate) fn swap_price(offered_amount: u64, expected_amount: u64, amount: u64) -> Option<u64> {
+    if amount == 0 || amount > offered_amount {
+        return None;
+    }
+    if amount == offered_amount {
+        return Some(expected_amount);
+    }
+    let numerator = (amount as u128).checked_mul(expected_amount as u128)?;
+    let price = u64::try_from(numerator.div_ceil(offered_amount as u128)).ok()?;
+    if price >= expected_amount {
+        return None;
+    }
+    Some(price)
+}
+
+impl Sealed for TokenSwap {}
+impl IsInitialized for TokenSwap {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for TokenSwap {
+    const LEN: usize = 248;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, TokenSwap::LEN];
+        let (
+            header,
+            seller_pubkey,
+            vault_pubkey,
+            seller_receive_pubkey,
+            offered_mint,
+            expected_mint,
+            offered_amount,
+            expected_amount,
+            native_payout,
+            escrow_authority_bump_seed,
+            _padding,
+        ) = array_refs![src, ACCOUNT_HEADER_LEN, 32, 32, 32, 32, 32, 8, 8, 1, 1, ACCOUNT_PADDING_LEN - 2];
+        let is_initialized = unpack_header(header, AccountType::TokenSwap)?;
+        Ok(TokenSwap {
+            is_initialized,
+            seller_pubkey: Pubkey::new_from_array(*seller_pubkey),
+            vault_pubkey: Pubkey::new_from_array(*vault_pubkey),
+            seller_receive_pubkey: Pubkey::new_from_array(*seller_receive_pubkey),
+            offered_mint: Pubkey::new_from_array(*offered_mint),
+            expected_mint: Pubkey::new_from_array(*expected_mint),
+            offered_amount: u64::from_le_bytes(*offered_amount),
+            expected_amount: u64::from_le_bytes(*expected_amount),
+            native_payout: match native_payout {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            escrow_authority_bump_seed: escrow_authority_bump_seed[0],
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, TokenSwap::LEN];
+        let (
+            header_dst,
+            seller_pubkey_dst,
+            vault_pubkey_dst,
+            seller_receive_pubkey_dst,
+            offered_mint_dst,
+            expected_mint_dst,
+            offered_amount_dst,
+            expected_amount_dst,
+            native_payout_dst,
+            escrow_authority_bump_seed_dst,
+            padding_dst,
+        ) = mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 32, 32, 32, 32, 8, 8, 1, 1, ACCOUNT_PADDING_LEN - 2];
+        let TokenSwap {
+            is_initialized,
+            seller_pubkey,
+            vault_pubkey,
+            seller_receive_pubkey,
+            offered_mint,
+            expected_mint,
+            offered_amount,
+            expected_amount,
+            native_payout,
+            escrow_authority_bump_seed,
+        } = self;
+        pack_header(header_dst, AccountType::TokenSwap, *is_initialized);
+        seller_pubkey_dst.copy_from_slice(seller_pubkey.as_ref());
+        vault_pubkey_dst.copy_from_slice(vault_pubkey.as_ref());
+        seller_receive_pubkey_dst.copy_from_slice(seller_receive_pubkey.as_ref());
+        offered_mint_dst.copy_from_slice(offered_mint.as_ref());
+        expected_mint_dst.copy_from_slice(expected_mint.as_ref());
+        *offered_amount_dst = offered_amount.to_le_bytes();
+        *expected_amount_dst = expected_amount.to_le_bytes();
+        native_payout_dst[0] = *native_payout as u8;
+        escrow_authority_bump_seed_dst[0] = *escrow_authority_bump_seed;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 2];
+    }
+}
+
+/// Cumulative earnings of a referrer, at the PDA of `[b"referrer", referrer]`.
+/// Created by the first sale the referrer routes to the marketplace.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ReferrerAccount {
+    pub is_initialized: bool,
+    pub referrer: Pubkey,
+    /// lamports paid to the referrer over all sales
+    pub total_earned: u64,
+    /// number of sales referred
+    pub sales: u64,
+}
+impl Sealed for ReferrerAccount {}
+impl IsInitialized for ReferrerAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for ReferrerAccount {
+    const LEN: usize = 120;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ReferrerAccount::LEN];
+        let (header, referrer, total_earned, sales, _padding) =
+            array_refs![src, ACCOUNT_HEADER_LEN, 32, 8, 8, ACCOUNT_PADDING_LEN];
+        let is_initialized = unpack_header(header, AccountType::Referrer)?;
+        Ok(ReferrerAccount {
+            is_initialized,
+            referrer: Pubkey::new_from_array(*referrer),
+            total_earned: u64::from_le_bytes(*total_earned),
+            sales: u64::from_le_bytes(*sales),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ReferrerAccount::LEN];
+        let (header_dst, referrer_dst, total_earned_dst, sales_dst, padding_dst) =
+            mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 8, 8, ACCOUNT_PADDING_LEN];
+        let ReferrerAccount {
+            is_initialized,
+            referrer,
+            total_earned,
+            sales,
+        } = self;
+        pack_header(header_dst, AccountType::Referrer, *is_initialized);
+        referrer_dst.copy_from_slice(referrer.as_ref());
+        *total_earned_dst = total_earned.to_le_bytes();
+        *sales_dst = sales.to_le_bytes();
+        *padding_dst = [0; ACCOUNT_PADDING_LEN];
+    }
+}
+
+/// Longest marketplace name, in bytes.
+pub const MARKETPLACE_NAME_LEN: usize = 32;
+
+/// Platform account of a marketplace anyone can create, at the PDA of
+/// `[b"marketplace", creator, name]`. Its fees work like those of a
+/// [VaultAccount](struct.VaultAccount.html), changed by its own authority
+/// instead of the platform admin.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Marketplace {
+    pub is_initialized: bool,
+    /// signs the configuration changes and the force delistings of the marketplace
+    pub authority: Pubkey,
+    pub creator: Pubkey,
+    /// UTF-8 name, zero padded
+    pub name: [u8; MARKETPLACE_NAME_LEN],
+    /// fee settings, `platform.is_initialized` follows `is_initialized`
+    pub platform: VaultAccount,
+}
+
+impl Marketplace {
+    /// Name of the marketplace without its zero padding.
+    pub fn name(&self) -> &[u8] {
+        let len = self.name.iter().position(|byte| *byte == 0).unwrap_or(MARKETPLACE_NAME_LEN);
+        &self.name[..len]
+    }
+}
+
+impl Sealed for Marketplace {}
+impl IsInitialized for Marketplace {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for Marketplace {
+    const LEN: usize = 257;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Marketplace::LEN];
+        let (
+            header,
+            authority,
+            creator,
+            name,
+            treasury_account,
+            base_percentage,
+            paused,
+            pass_collection,
+            pass_fee_basis_points,
+            referral_basis_points,
+            escrow_royalties,
+            _padding,
+        ) = array_refs![src, ACCOUNT_HEADER_LEN, 32, 32, MARKETPLACE_NAME_LEN, 32, 8, 1, 32, 8, 8, 1, ACCOUNT_PADDING_LEN - 1];
+        let is_initialized = unpack_header(header, AccountType::Marketplace)?;
+        let paused = match paused {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let escrow_royalties = match escrow_royalties {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(Marketplace {
+            is_initialized,
+            authority: Pubkey::new_from_array(*authority),
+            creator: Pubkey::new_from_array(*creator),
+            name: *name,
+            platform: VaultAccount {
+                is_initialized,
+                treasury_account: Pubkey::new_from_array(*treasury_account),
+                base_percentage: u64::from_le_bytes(*base_percentage),
+                paused,
+                pass_collection: Pubkey::new_from_array(*pass_collection),
+                pass_fee_basis_points: u64::from_le_bytes(*pass_fee_basis_points),
+                referral_basis_points: u64::from_le_bytes(*referral_basis_points),
+                escrow_royalties,
+            },
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Marketplace::LEN];
+        let (
+            header_dst,
+            authority_dst,
+            creator_dst,
+            name_dst,
+            treasury_account_dst,
+            base_percentage_dst,
+            paused_dst,
+            pass_collection_dst,
+            pass_fee_basis_points_dst,
+            referral_basis_points_dst,
+            escrow_royalties_dst,
+            padding_dst,
+        ) = mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 32, MARKETPLACE_NAME_LEN, 32, 8, 1, 32, 8, 8, 1, ACCOUNT_PADDING_LEN - 1];
+        let Marketplace {
+            is_initialized,
+            authority,
+            creator,
+            name,
+            platform,
+        } = self;
+        pack_header(header_dst, AccountType::Marketplace, *is_initialized);
+        authority_dst.copy_from_slice(authority.as_ref());
+        creator_dst.copy_from_slice(creator.as_ref());
+        *name_dst = *name;
+        treasury_account_dst.copy_from_slice(platform.treasury_account.as_ref());
+        *base_percentage_dst = platform.base_percentage.to_le_bytes();
+        paused_dst[0] = platform.paused as u8;
+        pass_collection_dst.copy_from_slice(platform.pass_collection.as_ref());
+        *pass_fee_basis_points_dst = platform.pass_fee_basis_points.to_le_bytes();
+        *referral_basis_points_dst = platform.referral_basis_points.to_le_bytes();
+        escrow_royalties_dst[0] = platform.escrow_royalties as u8;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 1];
+    }
+}
+
+/// Limited run of print editions of a Metaplex master edition, whose token is
+/// held by the escrow authority PDA until the prints sell out or the drop is
+/// cancelled.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct EditionDrop {
+    pub is_initialized: bool,
+    /// lister of the drop, gets the master edition token back at its end
+    pub creator: Pubkey,
+    /// token account holding the master edition token, owned by the escrow authority PDA
+    pub master_token_account: Pubkey,
+    pub master_mint: Pubkey,
+    /// platform account the drop was listed on
+    pub marketplace: Pubkey,
+    /// price of one print in lamports
+    pub price: u64,
+    pub max_prints: u64,
+    pub prints_sold: u64,
+    /// bump seed of the escrow authority PDA, 0 for drops listed before it was recorded
+    pub escrow_authority_bump_seed: u8,
+}
+impl Sealed for EditionDrop {}
+impl IsInitialized for EditionDrop {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for EditionDrop {
+    const LEN: usize = 224;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, EditionDrop::LEN];
+        let (
+            header,
+            creator,
+            master_token_account,
+            master_mint,
+            marketplace,
+            price,
+            max_prints,
+            prints_sold,
+            escrow_authority_bump_seed,
+            _padding,
+        ) = array_refs![src, ACCOUNT_HEADER_LEN, 32, 32, 32, 32, 8, 8, 8, 1, ACCOUNT_PADDING_LEN - 1];
+        let is_initialized = unpack_header(header, AccountType::EditionDrop)?;
+        Ok(EditionDrop {
+            is_initialized,
+            creator: Pubkey::new_from_array(*creator),
+            master_token_account: Pubkey::new_from_array(*master_token_account),
+            master_mint: Pubkey::new_from_array(*master_mint),
+            marketplace: Pubkey::new_from_array(*marketplace),
+            price: u64::from_le_bytes(*price),
+            max_prints: u64::from_le_bytes(*max_prints),
+            prints_sold: u64::from_le_bytes(*prints_sold),
+            escrow_authority_bump_seed: escrow_authority_bump_seed[0],
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, EditionDrop::LEN];
+        let (
+            header_dst,
+            creator_dst,
+            master_token_account_dst,
+            master_mint_dst,
+            marketplace_dst,
+            price_dst,
+            max_prints_dst,
+            prints_sold_dst,
+            escrow_authority_bump_seed_dst,
+            padding_dst,
+        ) = mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 32, 32, 32, 8, 8, 8, 1, ACCOUNT_PADDING_LEN - 1];
+        let EditionDrop {
+            is_initialized,
+            creator,
+            master_token_account,
+            master_mint,
+            marketplace,
+            price,
+            max_prints,
+            prints_sold,
+            escrow_authority_bump_seed,
+        } = self;
+        pack_header(header_dst, AccountType::EditionDrop, *is_initialized);
+        creator_dst.copy_from_slice(creator.as_ref());
+        master_token_account_dst.copy_from_slice(master_token_account.as_ref());
+        master_mint_dst.copy_from_slice(master_mint.as_ref());
+        marketplace_dst.copy_from_slice(marketplace.as_ref());
+        *price_dst = price.to_le_bytes();
+        *max_prints_dst = max_prints.to_le_bytes();
+        *prints_sold_dst = prints_sold.to_le_bytes();
+        escrow_authority_bump_seed_dst[0] = *escrow_authority_bump_seed;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 1];
+    }
+}
+
+/// Price feed of a listing priced in USD, at the PDA of `[b"usd_listing", escrow]`.
+/// Created along with the listing, whose `expected_amount` is then its price in
+/// millionths of a dollar.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct UsdListing {
+    pub is_initialized: bool,
+    pub escrow: Pubkey,
+    /// SOL/USD price account of the oracle program the listing is paid at
+    pub price_feed: Pubkey,
+    pub bump_seed: u8,
+}
+impl Sealed for UsdListing {}
+impl IsInitialized for UsdListing {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for UsdListing {
+    const LEN: usize = 136;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, UsdListing::LEN];
+        let (header, escrow, price_feed, bump_seed, _padding) =
+            array_refs![src, ACCOUNT_HEADER_LEN, 32, 32, 1, ACCOUNT_PADDING_LEN - 1];
+        let is_initialized = unpack_header(header, AccountType::UsdListing)?;
+        Ok(UsdListing {
+            is_initialized,
+            escrow: Pubkey::new_from_array(*escrow),
+            price_feed: Pubkey::new_from_array(*price_feed),
+            bump_seed: bump_seed[0],
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, UsdListing::LEN];
+        let (header_dst, escrow_dst, price_feed_dst, bump_seed_dst, padding_dst) =
+            mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 32, 1, ACCOUNT_PADDING_LEN - 1];
+        let UsdListing {
+            is_initialized,
+            escrow,
+            price_feed,
+            bump_seed,
+        } = self;
+        pack_header(header_dst, AccountType::UsdListing, *is_initialized);
+        escrow_dst.copy_from_slice(escrow.as_ref());
+        price_feed_dst.copy_from_slice(price_feed.as_ref());
+        bump_seed_dst[0] = *bump_seed;
+        *padding_dst = [0; ACCOUNT_PADDING_LEN - 1];
+    }
+}
+
+/// Royalties held for a creator that cannot take them as a transfer, at the PDA of
+/// `[b"royalty_vault", creator]`. The lamports deposited and not yet claimed are
+/// held by the account on top of its rent-exempt minimum.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RoyaltyVault {
+    pub is_initialized: bool,
+    pub creator: Pubkey,
+    /// lamports deposited over all sales
+    pub total_deposited: u64,
+    /// lamports the creator claimed over all claims
+    pub total_claimed: u64,
+    /// number of sales that deposited royalties
+    pub sales: u64,
+}
 
+impl RoyaltyVault {
+    /// Lamports the creator can claim.
+    pub fn unclaimed(&self) -> u64 {
+        self.total_deposited.saturating_sub(self.total_claimed)
     }
-}
\ No newline at end of file
+}
+
+impl Sealed for RoyaltyVault {}
+impl IsInitialized for RoyaltyVault {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for RoyaltyVault {
+    const LEN: usize = 128;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, RoyaltyVault::LEN];
+        let (header, creator, total_deposited, total_claimed, sales, _padding) =
+            array_refs![src, ACCOUNT_HEADER_LEN, 32, 8, 8, 8, ACCOUNT_PADDING_LEN];
+        let is_initialized = unpack_header(header, AccountType::RoyaltyVault)?;
+        Ok(RoyaltyVault {
+            is_initialized,
+            creator: Pubkey::new_from_array(*creator),
+            total_deposited: u64::from_le_bytes(*total_deposited),
+            total_claimed: u64::from_le_bytes(*total_claimed),
+            sales: u64::from_le_bytes(*sales),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RoyaltyVault::LEN];
+        let (header_dst, creator_dst, total_deposited_dst, total_claimed_dst, sales_dst, padding_dst) =
+            mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 8, 8, 8, ACCOUNT_PADDING_LEN];
+        let RoyaltyVault {
+            is_initialized,
+            creator,
+            total_deposited,
+            total_claimed,
+            sales,
+        } = self;
+        pack_header(header_dst, AccountType::RoyaltyVault, *is_initialized);
+        creator_dst.copy_from_slice(creator.as_ref());
+        *total_deposited_dst = total_deposited.to_le_bytes();
+        *total_claimed_dst = total_claimed.to_le_bytes();
+        *sales_dst = sales.to_le_bytes();
+        *padding_dst = [0; ACCOUNT_PADDING_LEN];
+    }
+}
+
+/// Proceeds of the sales of a seller whose listings credit them instead of
+/// transferring them, at the PDA of `[b"seller_proceeds", seller]`. The lamports
+/// credited and not yet withdrawn are held by the account on top of its
+/// rent-exempt minimum.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SellerProceeds {
+    pub is_initialized: bool,
+    pub seller: Pubkey,
+    /// lamports credited over all sales
+    pub total_credited: u64,
+    /// lamports the seller withdrew over all withdrawals
+    pub total_withdrawn: u64,
+    /// number of sales that credited proceeds
+    pub sales: u64,
+}
+
+impl SellerProceeds {
+    /// Lamports the seller can withdraw.
+    pub fn balance(&self) -> u64 {
+        self.total_credited.saturating_sub(self.total_withdrawn)
+    }
+}
+
+impl Sealed for SellerProceeds {}
+impl IsInitialized for SellerProceeds {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+impl Pack for SellerProceeds {
+    const LEN: usize = 128;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, SellerProceeds::LEN];
+        let (header, seller, total_credited, total_withdrawn, sales, _padding) =
+            array_refs![src, ACCOUNT_HEADER_LEN, 32, 8, 8, 8, ACCOUNT_PADDING_LEN];
+        let is_initialized = unpack_header(header, AccountType::SellerProceeds)?;
+        Ok(SellerProceeds {
+            is_initialized,
+            seller: Pubkey::new_from_array(*seller),
+            total_credited: u64::from_le_bytes(*total_credited),
+            total_withdrawn: u64::from_le_bytes(*total_withdrawn),
+            sales: u64::from_le_bytes(*sales),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, SellerProceeds::LEN];
+        let (header_dst, seller_dst, total_credited_dst, total_withdrawn_dst, sales_dst, padding_dst) =
+            mut_array_refs![dst, ACCOUNT_HEADER_LEN, 32, 8, 8, 8, ACCOUNT_PADDING_LEN];
+        let SellerProceeds {
+            is_initialized,
+            seller,
+            total_credited,
+            total_withdrawn,
+            sales,
+        } = self;
+        pack_header(header_dst, AccountType::SellerProceeds, *is_initialized);
+        seller_dst.copy_from_slice(seller.as_ref());
+        *total_credited_dst = total_credited.to_le_bytes();
+        *total_withdrawn_dst = total_withdrawn.to_le_bytes();
+        *sales_dst = sales.to_le_bytes();
+        *padding_dst = [0; ACCOUNT_PADDING_LEN];
+    }
+}
@@ -0,0 +1,102 @@
+//! Reads NFT mints and token accounts of either token program, SPL Token or
+//! Token-2022, whose extensions the escrow has to take into account.
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    program_error::ProgramError,
+    program_pack::Pack,
+    sysvar::Sysvar,
+};
+use spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, ExtensionType, StateWithExtensions},
+    pod::pod_from_bytes,
+    state::{Account, Mint},
+};
+
+use crate::{error::EscrowError, state::TokenProgram};
+
+// extensions start after the base account and the account type byte, for mints
+// as well as for token accounts
+const EXTENSIONS_OFFSET: usize = Account::LEN + 1;
+
+// numbered as by Token-2022, which added NonTransferable after the version of
+// spl-token-2022 the program is built with
+const NON_TRANSFERABLE_EXTENSION: u16 = 9;
+
+/// Token program that owns `info`, `IncorrectProgramId` when it is neither
+/// SPL Token nor Token-2022 or not `token_program`.
+pub fn token_program_of(info: &AccountInfo, token_program: &AccountInfo) -> Result<TokenProgram, ProgramError> {
+    if info.owner != token_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    TokenProgram::from_id(info.owner).ok_or(ProgramError::IncorrectProgramId)
+}
+
+/// Unpacks the token account in `info`, refusing accounts with confidential
+/// transfer state, whose balance the escrow cannot see.
+pub fn unpack_token_account(info: &AccountInfo) -> Result<Account, ProgramError> {
+    let data = info.try_borrow_data()?;
+    let account = StateWithExtensions::<Account>::unpack(&data)?.base;
+    if extension(&data, u16::from(ExtensionType::ConfidentialTransferAccount))?.is_some() {
+        return Err(EscrowError::UnsupportedTokenExtension.into());
+    }
+    Ok(account)
+}
+
+/// Unpacks the mint in `info`, refusing non-transferable mints, which cannot
+/// change hands.
+pub fn unpack_mint(info: &AccountInfo) -> Result<Mint, ProgramError> {
+    let data = info.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?.base;
+    if extension(&data, NON_TRANSFERABLE_EXTENSION)?.is_some() {
+        return Err(EscrowError::UnsupportedTokenExtension.into());
+    }
+    Ok(mint)
+}
+
+/// Tokens the transfer fee extension of the mint in `info` withholds from a
+/// transfer of `amount` in the current epoch, 0 for mints without one.
+pub fn transfer_fee(info: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    match transfer_fee_config(&info.try_borrow_data()?)? {
+        Some(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ProgramError::InvalidAccountData),
+        None => Ok(0),
+    }
+}
+
+/// Whether the token account in `info` has the ImmutableOwner extension, which
+/// Token-2022 gives every associated token account, so its owner cannot be changed.
+pub fn has_immutable_owner(info: &AccountInfo) -> Result<bool, ProgramError> {
+    Ok(extension(&info.try_borrow_data()?, u16::from(ExtensionType::ImmutableOwner))?.is_some())
+}
+
+/// Transfer fee extension of the packed mint `data`, if it has one.
+pub fn transfer_fee_config(data: &[u8]) -> Result<Option<TransferFeeConfig>, ProgramError> {
+    extension(data, u16::from(ExtensionType::TransferFeeConfig))?
+        .map(|value| pod_from_bytes::<TransferFeeConfig>(value).copied())
+        .transpose()
+}
+
+/// Value of the extension of `extension_type` in packed mint or token account
+/// `data`. The type-length-value entries are walked without interpreting the
+/// others, so extensions newer than the program don't make the account unreadable.
+fn extension(data: &[u8], extension_type: u16) -> Result<Option<&[u8]>, ProgramError> {
+    let mut offset = EXTENSIONS_OFFSET;
+    while offset + 4 <= data.len() {
+        let entry_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let value = data
+            .get(offset + 4..offset + 4 + length)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if entry_type == u16::from(ExtensionType::Uninitialized) {
+            break;
+        }
+        if entry_type == extension_type {
+            return Ok(Some(value));
+        }
+        offset += 4 + length;
+    }
+    Ok(None)
+}
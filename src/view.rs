@@ -0,0 +1,665 @@
+//! Zero-copy views of the program accounts.
+//!
+//! A view is a `#[repr(C)]` struct with the byte layout the [state](../state/index.html)
+//! types pack, cast straight from the account data so that a handler reads and
+//! changes the fields it needs in place instead of copying the whole account in
+//! and out. Every field has an alignment of 1, integers are stored as little endian
+//! byte arrays, so a view can be cast from any account data without undefined
+//! behaviour, including the fields that were carved out of the padding of a layout
+//! at an odd offset.
+//!
+//! Public keys and bump seeds are plain fields, the fields stored in another
+//! encoding are read and written through methods named after them.
+
+use bytemuck::{Pod, Zeroable};
+use solana_program::{program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
+
+use crate::state::{
+    AccountType, EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault, SellerProceeds,
+    TokenProgram, TokenSwap, UsdListing, VaultAccount, ACCOUNT_HEADER_LEN, ACCOUNT_PADDING_LEN, MARKETPLACE_NAME_LEN,
+    STATE_VERSION,
+    swap_price,
+};
+
+/// Little endian `u64` with an alignment of 1.
+#[derive(Debug, Default, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct PodU64([u8; 8]);
+
+impl PodU64 {
+    pub fn get(&self) -> u64 {
+        u64::from_le_bytes(self.0)
+    }
+
+    pub fn set(&mut self, value: u64) {
+        self.0 = value.to_le_bytes();
+    }
+}
+
+impl From<u64> for PodU64 {
+    fn from(value: u64) -> Self {
+        PodU64(value.to_le_bytes())
+    }
+}
+
+/// Header every program account starts with.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct AccountHeader {
+    pub account_type: u8,
+    pub version: u8,
+    is_initialized: u8,
+    pub reserved: [u8; 5],
+}
+
+impl AccountHeader {
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized == 1
+    }
+
+    pub fn set_initialized(&mut self, is_initialized: bool) {
+        self.is_initialized = is_initialized as u8;
+    }
+}
+
+/// View of a program account of `ACCOUNT_TYPE`, starting with its header.
+pub trait AccountView: Pod {
+    const ACCOUNT_TYPE: AccountType;
+
+    fn header(&self) -> &AccountHeader {
+        bytemuck::from_bytes(&bytemuck::bytes_of(self)[..ACCOUNT_HEADER_LEN])
+    }
+
+    fn header_mut(&mut self) -> &mut AccountHeader {
+        bytemuck::from_bytes_mut(&mut bytemuck::bytes_of_mut(self)[..ACCOUNT_HEADER_LEN])
+    }
+
+    /// Checks the fields not every byte value is valid for, like unpacking the
+    /// account does.
+    fn check(&self) -> Result<(), ProgramError> {
+        Ok(())
+    }
+}
+
+/// Borrows `data` as the view of an account of type `T`, initialized or still
+/// zeroed. Fails for an account of another type or layout version.
+pub fn load_unchecked<T: AccountView>(data: &[u8]) -> Result<&T, ProgramError> {
+    let view: &T = data
+        .get(..size_of::<T>())
+        .and_then(|data| bytemuck::try_from_bytes(data).ok())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    check_header(view.header(), T::ACCOUNT_TYPE)?;
+    view.check()?;
+    Ok(view)
+}
+
+/// Borrows `data` as the view of an initialized account of type `T`.
+pub fn load<T: AccountView>(data: &[u8]) -> Result<&T, ProgramError> {
+    let view = load_unchecked::<T>(data)?;
+    if !view.header().is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(view)
+}
+
+/// Mutably borrows `data` like [load_unchecked](fn.load_unchecked.html).
+pub fn load_mut_unchecked<T: AccountView>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    load_unchecked::<T>(data)?;
+    // the checks above passed on the same bytes
+    Ok(bytemuck::from_bytes_mut(&mut data[..size_of::<T>()]))
+}
+
+/// Mutably borrows `data` like [load](fn.load.html).
+pub fn load_mut<T: AccountView>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    load::<T>(data)?;
+    Ok(bytemuck::from_bytes_mut(&mut data[..size_of::<T>()]))
+}
+
+/// Initializes the uninitialized account `data` as an account of type `T` with
+/// zeroed fields and returns its view for the caller to fill in.
+pub fn init<T: AccountView>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    let view = load_mut_unchecked::<T>(data)?;
+    if view.header().is_initialized() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    *view = T::zeroed();
+    let header = view.header_mut();
+    header.account_type = T::ACCOUNT_TYPE as u8;
+    header.version = STATE_VERSION;
+    header.set_initialized(true);
+    Ok(view)
+}
+
+// a header that is still all zeroes is an uninitialized account of any type
+fn check_header(header: &AccountHeader, account_type: AccountType) -> Result<(), ProgramError> {
+    if *header == AccountHeader::zeroed() {
+        return Ok(());
+    }
+    if header.account_type != account_type as u8 || header.version != STATE_VERSION || header.is_initialized > 1 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+fn check_bool(value: u8) -> Result<(), ProgramError> {
+    if value > 1 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// View of an [Escrow](../state/struct.Escrow.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct EscrowView {
+    pub header: AccountHeader,
+    pub seller_pubkey: Pubkey,
+    pub token_account_pubkey: Pubkey,
+    pub mint_key: Pubkey,
+    expected_amount: PodU64,
+    pub marketplace: Pubkey,
+    listing_mode: u8,
+    token_program: u8,
+    usd_priced: u8,
+    pub escrow_authority_bump_seed: u8,
+    pub metadata_bump_seed: u8,
+    proceeds_vault: u8,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 38],
+}
+
+impl EscrowView {
+    pub fn expected_amount(&self) -> u64 {
+        self.expected_amount.get()
+    }
+
+    pub fn set_expected_amount(&mut self, expected_amount: u64) {
+        self.expected_amount.set(expected_amount);
+    }
+
+    pub fn listing_mode(&self) -> ListingMode {
+        match self.listing_mode {
+            1 => ListingMode::Delegate,
+            2 => ListingMode::Frozen,
+            _ => ListingMode::Custody,
+        }
+    }
+
+    pub fn set_listing_mode(&mut self, listing_mode: ListingMode) {
+        self.listing_mode = listing_mode as u8;
+    }
+
+    pub fn token_program(&self) -> TokenProgram {
+        match self.token_program {
+            1 => TokenProgram::Token2022,
+            _ => TokenProgram::SplToken,
+        }
+    }
+
+    pub fn set_token_program(&mut self, token_program: TokenProgram) {
+        self.token_program = token_program as u8;
+    }
+
+    pub fn usd_priced(&self) -> bool {
+        self.usd_priced == 1
+    }
+
+    pub fn set_usd_priced(&mut self, usd_priced: bool) {
+        self.usd_priced = usd_priced as u8;
+    }
+
+    pub fn proceeds_vault(&self) -> bool {
+        self.proceeds_vault == 1
+    }
+
+    pub fn set_proceeds_vault(&mut self, proceeds_vault: bool) {
+        self.proceeds_vault = proceeds_vault as u8;
+    }
+
+    /// Copies an initialized listing out of an escrow account of the layout before
+    /// the account header, read as [Escrow::unpack_legacy](../state/struct.Escrow.html#method.unpack_legacy)
+    /// reads it. Such an account is too short to be viewed in place.
+    pub fn from_legacy(data: &[u8]) -> Result<Self, ProgramError> {
+        let escrow = Escrow::unpack_legacy(data)?;
+        if !escrow.is_initialized {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        let mut view = Self::zeroed();
+        escrow.pack_into_slice(bytemuck::bytes_of_mut(&mut view));
+        Ok(view)
+    }
+
+    /// Writes a listing copied out by `from_legacy` back into its account.
+    pub fn store_legacy(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        Escrow::unpack_unchecked(bytemuck::bytes_of(self))?.pack_legacy(data)
+    }
+}
+
+impl AccountView for EscrowView {
+    const ACCOUNT_TYPE: AccountType = AccountType::Escrow;
+
+    fn check(&self) -> Result<(), ProgramError> {
+        if self.listing_mode > ListingMode::Frozen as u8 || self.token_program > TokenProgram::Token2022 as u8 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        check_bool(self.usd_priced)?;
+        check_bool(self.proceeds_vault)
+    }
+}
+
+/// Fee settings of a platform account, laid out alike in the
+/// [VaultAccountView](struct.VaultAccountView.html) of the admin and in a
+/// [MarketplaceView](struct.MarketplaceView.html).
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct PlatformView {
+    pub treasury_account: Pubkey,
+    base_percentage: PodU64,
+    paused: u8,
+    pub pass_collection: Pubkey,
+    pass_fee_basis_points: PodU64,
+    referral_basis_points: PodU64,
+    escrow_royalties: u8,
+}
+
+impl PlatformView {
+    pub fn base_percentage(&self) -> u64 {
+        self.base_percentage.get()
+    }
+
+    pub fn set_base_percentage(&mut self, base_percentage: u64) {
+        self.base_percentage.set(base_percentage);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused == 1
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused as u8;
+    }
+
+    pub fn pass_fee_basis_points(&self) -> u64 {
+        self.pass_fee_basis_points.get()
+    }
+
+    pub fn set_pass_fee_basis_points(&mut self, pass_fee_basis_points: u64) {
+        self.pass_fee_basis_points.set(pass_fee_basis_points);
+    }
+
+    pub fn referral_basis_points(&self) -> u64 {
+        self.referral_basis_points.get()
+    }
+
+    pub fn set_referral_basis_points(&mut self, referral_basis_points: u64) {
+        self.referral_basis_points.set(referral_basis_points);
+    }
+
+    pub fn escrow_royalties(&self) -> bool {
+        self.escrow_royalties == 1
+    }
+
+    pub fn set_escrow_royalties(&mut self, escrow_royalties: bool) {
+        self.escrow_royalties = escrow_royalties as u8;
+    }
+
+    /// Platform fee of a sale, as [VaultAccount::fee_basis_points](../state/struct.VaultAccount.html#method.fee_basis_points)
+    /// computes it.
+    pub fn fee_basis_points(&self, pass_holder: bool) -> u64 {
+        if pass_holder && self.pass_collection != Pubkey::default() {
+            self.pass_fee_basis_points().min(self.base_percentage())
+        } else {
+            self.base_percentage()
+        }
+    }
+
+    /// Referrer's part of `platform_fee`, as [VaultAccount::referral_fee](../state/struct.VaultAccount.html#method.referral_fee)
+    /// computes it.
+    pub fn referral_fee(&self, platform_fee: u64) -> Option<u64> {
+        if self.referral_basis_points() > 10000 {
+            return None;
+        }
+        Some(platform_fee.checked_mul(self.referral_basis_points())? / 10000)
+    }
+
+    fn check(&self) -> Result<(), ProgramError> {
+        check_bool(self.paused)?;
+        check_bool(self.escrow_royalties)
+    }
+}
+
+/// View of a [VaultAccount](../state/struct.VaultAccount.html), the platform
+/// account of the admin. Its fee settings are read and changed through the
+/// [PlatformView](struct.PlatformView.html) it dereferences to.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct VaultAccountView {
+    pub header: AccountHeader,
+    pub platform: PlatformView,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 50],
+}
+
+impl VaultAccountView {
+    /// Copies a platform account of the layout before the account header out, read
+    /// as [VaultAccount::unpack_legacy](../state/struct.VaultAccount.html#method.unpack_legacy)
+    /// reads it, initialized or not. Such an account is too short to be viewed in place.
+    pub fn from_legacy(data: &[u8]) -> Result<Self, ProgramError> {
+        let platform = VaultAccount::unpack_legacy(data)?;
+        let mut view = Self::zeroed();
+        platform.pack_into_slice(bytemuck::bytes_of_mut(&mut view));
+        Ok(view)
+    }
+
+    /// Writes a platform account copied out by `from_legacy` back into its account,
+    /// refused for settings the legacy layout cannot hold.
+    pub fn store_legacy(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        VaultAccount::unpack_unchecked(bytemuck::bytes_of(self))?.pack_legacy(data)
+    }
+}
+
+impl Deref for VaultAccountView {
+    type Target = PlatformView;
+
+    fn deref(&self) -> &PlatformView {
+        &self.platform
+    }
+}
+
+impl DerefMut for VaultAccountView {
+    fn deref_mut(&mut self) -> &mut PlatformView {
+        &mut self.platform
+    }
+}
+
+impl AccountView for VaultAccountView {
+    const ACCOUNT_TYPE: AccountType = AccountType::PlatformAccount;
+
+    fn check(&self) -> Result<(), ProgramError> {
+        self.platform.check()
+    }
+}
+
+/// View of a [TokenSwap](../state/struct.TokenSwap.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct TokenSwapView {
+    pub header: AccountHeader,
+    pub seller_pubkey: Pubkey,
+    pub vault_pubkey: Pubkey,
+    pub seller_receive_pubkey: Pubkey,
+    pub offered_mint: Pubkey,
+    pub expected_mint: Pubkey,
+    offered_amount: PodU64,
+    expected_amount: PodU64,
+    native_payout: u8,
+    pub escrow_authority_bump_seed: u8,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 2],
+}
+
+impl TokenSwapView {
+    pub fn offered_amount(&self) -> u64 {
+        self.offered_amount.get()
+    }
+
+    pub fn set_offered_amount(&mut self, offered_amount: u64) {
+        self.offered_amount.set(offered_amount);
+    }
+
+    pub fn expected_amount(&self) -> u64 {
+        self.expected_amount.get()
+    }
+
+    pub fn set_expected_amount(&mut self, expected_amount: u64) {
+        self.expected_amount.set(expected_amount);
+    }
+
+    pub fn native_payout(&self) -> bool {
+        self.native_payout == 1
+    }
+
+    pub fn set_native_payout(&mut self, native_payout: bool) {
+        self.native_payout = native_payout as u8;
+    }
+
+    /// Amount of the expected token a taker pays for `amount` of the offered token,
+    /// as [TokenSwap::price_for](../state/struct.TokenSwap.html#method.price_for) prices it.
+    pub fn price_for(&self, amount: u64) -> Option<u64> {
+        swap_price(self.offered_amount(), self.expected_amount(), amount)
+    }
+}
+
+impl AccountView for TokenSwapView {
+    const ACCOUNT_TYPE: AccountType = AccountType::TokenSwap;
+
+    fn check(&self) -> Result<(), ProgramError> {
+        check_bool(self.native_payout)
+    }
+}
+
+/// View of a [ReferrerAccount](../state/struct.ReferrerAccount.html).
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ReferrerAccountView {
+    pub header: AccountHeader,
+    pub referrer: Pubkey,
+    total_earned: PodU64,
+    sales: PodU64,
+    pub padding: [u8; ACCOUNT_PADDING_LEN],
+}
+
+impl ReferrerAccountView {
+    pub fn total_earned(&self) -> u64 {
+        self.total_earned.get()
+    }
+
+    pub fn set_total_earned(&mut self, total_earned: u64) {
+        self.total_earned.set(total_earned);
+    }
+
+    pub fn sales(&self) -> u64 {
+        self.sales.get()
+    }
+
+    pub fn set_sales(&mut self, sales: u64) {
+        self.sales.set(sales);
+    }
+}
+
+impl AccountView for ReferrerAccountView {
+    const ACCOUNT_TYPE: AccountType = AccountType::Referrer;
+}
+
+/// View of a [Marketplace](../state/struct.Marketplace.html) account, which
+/// dereferences to its fee settings like a [VaultAccountView](struct.VaultAccountView.html).
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct MarketplaceView {
+    pub header: AccountHeader,
+    pub authority: Pubkey,
+    pub creator: Pubkey,
+    pub name: [u8; MARKETPLACE_NAME_LEN],
+    pub platform: PlatformView,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 1],
+}
+
+impl Deref for MarketplaceView {
+    type Target = PlatformView;
+
+    fn deref(&self) -> &PlatformView {
+        &self.platform
+    }
+}
+
+impl DerefMut for MarketplaceView {
+    fn deref_mut(&mut self) -> &mut PlatformView {
+        &mut self.platform
+    }
+}
+
+impl AccountView for MarketplaceView {
+    const ACCOUNT_TYPE: AccountType = AccountType::Marketplace;
+
+    fn check(&self) -> Result<(), ProgramError> {
+        self.platform.check()
+    }
+}
+
+/// View of an [EditionDrop](../state/struct.EditionDrop.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct EditionDropView {
+    pub header: AccountHeader,
+    pub creator: Pubkey,
+    pub master_token_account: Pubkey,
+    pub master_mint: Pubkey,
+    pub marketplace: Pubkey,
+    price: PodU64,
+    max_prints: PodU64,
+    prints_sold: PodU64,
+    pub escrow_authority_bump_seed: u8,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 1],
+}
+
+impl EditionDropView {
+    pub fn price(&self) -> u64 {
+        self.price.get()
+    }
+
+    pub fn set_price(&mut self, price: u64) {
+        self.price.set(price);
+    }
+
+    pub fn max_prints(&self) -> u64 {
+        self.max_prints.get()
+    }
+
+    pub fn set_max_prints(&mut self, max_prints: u64) {
+        self.max_prints.set(max_prints);
+    }
+
+    pub fn prints_sold(&self) -> u64 {
+        self.prints_sold.get()
+    }
+
+    pub fn set_prints_sold(&mut self, prints_sold: u64) {
+        self.prints_sold.set(prints_sold);
+    }
+}
+
+impl AccountView for EditionDropView {
+    const ACCOUNT_TYPE: AccountType = AccountType::EditionDrop;
+}
+
+/// View of a [UsdListing](../state/struct.UsdListing.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct UsdListingView {
+    pub header: AccountHeader,
+    pub escrow: Pubkey,
+    pub price_feed: Pubkey,
+    pub bump_seed: u8,
+    pub padding: [u8; ACCOUNT_PADDING_LEN - 1],
+}
+
+impl AccountView for UsdListingView {
+    const ACCOUNT_TYPE: AccountType = AccountType::UsdListing;
+}
+
+/// View of a [RoyaltyVault](../state/struct.RoyaltyVault.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct RoyaltyVaultView {
+    pub header: AccountHeader,
+    pub creator: Pubkey,
+    total_deposited: PodU64,
+    total_claimed: PodU64,
+    sales: PodU64,
+    pub padding: [u8; ACCOUNT_PADDING_LEN],
+}
+
+impl RoyaltyVaultView {
+    pub fn total_deposited(&self) -> u64 {
+        self.total_deposited.get()
+    }
+
+    pub fn set_total_deposited(&mut self, total_deposited: u64) {
+        self.total_deposited.set(total_deposited);
+    }
+
+    pub fn total_claimed(&self) -> u64 {
+        self.total_claimed.get()
+    }
+
+    pub fn set_total_claimed(&mut self, total_claimed: u64) {
+        self.total_claimed.set(total_claimed);
+    }
+
+    pub fn sales(&self) -> u64 {
+        self.sales.get()
+    }
+
+    pub fn set_sales(&mut self, sales: u64) {
+        self.sales.set(sales);
+    }
+}
+
+impl AccountView for RoyaltyVaultView {
+    const ACCOUNT_TYPE: AccountType = AccountType::RoyaltyVault;
+}
+
+/// View of a [SellerProceeds](../state/struct.SellerProceeds.html) account.
+#[derive(Debug, PartialEq, Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct SellerProceedsView {
+    pub header: AccountHeader,
+    pub seller: Pubkey,
+    total_credited: PodU64,
+    total_withdrawn: PodU64,
+    sales: PodU64,
+    pub padding: [u8; ACCOUNT_PADDING_LEN],
+}
+
+impl SellerProceedsView {
+    pub fn total_credited(&self) -> u64 {
+        self.total_credited.get()
+    }
+
+    pub fn set_total_credited(&mut self, total_credited: u64) {
+        self.total_credited.set(total_credited);
+    }
+
+    pub fn total_withdrawn(&self) -> u64 {
+        self.total_withdrawn.get()
+    }
+
+    pub fn set_total_withdrawn(&mut self, total_withdrawn: u64) {
+        self.total_withdrawn.set(total_withdrawn);
+    }
+
+    pub fn sales(&self) -> u64 {
+        self.sales.get()
+    }
+
+    pub fn set_sales(&mut self, sales: u64) {
+        self.sales.set(sales);
+    }
+}
+
+impl AccountView for SellerProceedsView {
+    const ACCOUNT_TYPE: AccountType = AccountType::SellerProceeds;
+}
+
+// every view covers exactly the packed layout of its account
+const _: () = assert!(size_of::<AccountHeader>() == ACCOUNT_HEADER_LEN);
+const _: () = assert!(size_of::<EscrowView>() == Escrow::LEN);
+const _: () = assert!(size_of::<VaultAccountView>() == VaultAccount::LEN);
+const _: () = assert!(size_of::<TokenSwapView>() == TokenSwap::LEN);
+const _: () = assert!(size_of::<ReferrerAccountView>() == ReferrerAccount::LEN);
+const _: () = assert!(size_of::<MarketplaceView>() == Marketplace::LEN);
+const _: () = assert!(size_of::<EditionDropView>() == EditionDrop::LEN);
+const _: () = assert!(size_of::<UsdListingView>() == UsdListing::LEN);
+const _: () = assert!(size_of::<RoyaltyVaultView>() == RoyaltyVault::LEN);
+const _: () = assert!(size_of::<SellerProceedsView>() == SellerProceeds::LEN);
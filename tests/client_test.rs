@@ -0,0 +1,761 @@
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use async_trait::async_trait;
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{
+        Creator, Data, Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+    };
+    use serde_json::{json, Value};
+    use std::{collections::HashMap, str::FromStr};
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_client::{
+        client_error::Result as ClientResult,
+        rpc_client::{RpcClient, RpcClientConfig},
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_program::{clock::Clock, hash::Hash, program_pack::Pack, pubkey::Pubkey, sysvar};
+    use solana_sdk::{
+        account::{create_account_for_test, Account},
+        commitment_config::CommitmentConfig,
+        signature::{Keypair, Signer},
+    };
+
+    use escrow_buy::{
+        client::{EscrowClient, EscrowClientError},
+        instruction::{
+            EscrowInstruction, ExchangeOptionalAccounts, MembershipPass, EXCHANGE_OPTION_MEMBERSHIP_PASS,
+            EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT, EXCHANGE_OPTION_REFERRER,
+        },
+        pda,
+        price_feed::{oracle_program_id, sol_usd_price_feed_id, PriceFeed, PRICE_ACCOUNT_LEN, PRICE_STATUS_TRADING},
+        state::{EditionDrop, Escrow, ListingMode, TokenProgram, UsdListing, VaultAccount},
+    };
+
+    fn program_account<T: Pack>(state: T, owner: &Pubkey) -> Account {
+        let mut data = vec![0; T::LEN];
+        T::pack(state, &mut data).unwrap();
+        Account {
+            lamports: 1_000_000,
+            data,
+            owner: *owner,
+            ..Account::default()
+        }
+    }
+
+    fn encode(pubkey: &Pubkey, account: &Account) -> UiAccount {
+        UiAccount::encode(pubkey, account, UiAccountEncoding::Base64, None, None)
+    }
+
+    // what the RPC answers for an account, or accounts, at slot 1
+    fn rpc_response(value: Value) -> Value {
+        json!({ "context": { "slot": 1 }, "value": value })
+    }
+
+    // answers the account requests from `accounts`, like a node holding only them,
+    // for the requests that need more than one answer
+    struct AccountsSender {
+        accounts: HashMap<Pubkey, Account>,
+    }
+
+    impl AccountsSender {
+        fn encode_account(&self, address: &Value) -> Option<UiAccount> {
+            let address = Pubkey::from_str(address.as_str().unwrap()).unwrap();
+            self.accounts.get(&address).map(|account| encode(&address, account))
+        }
+    }
+
+    #[async_trait]
+    impl RpcSender for AccountsSender {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            Ok(match request {
+                RpcRequest::GetAccountInfo => rpc_response(json!(self.encode_account(&params[0]))),
+                RpcRequest::GetMultipleAccounts => {
+                    let addresses = params[0].as_array().unwrap();
+                    let accounts: Vec<_> = addresses.iter().map(|address| self.encode_account(address)).collect();
+                    rpc_response(json!(accounts))
+                }
+                RpcRequest::GetVersion => json!({ "solana-core": "1.10.12" }),
+                RpcRequest::GetLatestBlockhash => rpc_response(json!({
+                    "blockhash": Hash::default().to_string(),
+                    "lastValidBlockHeight": 0,
+                })),
+                _ => unimplemented!("{}", request),
+            })
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "accounts".to_string()
+        }
+    }
+
+    fn escrow_state(seller: Pubkey) -> Escrow {
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: seller,
+            token_account_pubkey: Pubkey::new_unique(),
+            mint_key: Pubkey::new_unique(),
+            expected_amount: 123,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+    }
+
+    #[test]
+    fn get_escrow_test() {
+        let program_id = Pubkey::new_unique();
+        let escrow_address = Pubkey::new_unique();
+        let escrow = escrow_state(Pubkey::new_unique());
+
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            rpc_response(json!(encode(&escrow_address, &program_account(escrow, &program_id)))),
+        );
+        let client = EscrowClient::from_rpc_client(
+            RpcClient::new_mock_with_mocks("succeeds", mocks),
+            program_id,
+        );
+        assert_eq!(client.get_escrow(&escrow_address).unwrap(), escrow);
+
+        // accounts of other programs are refused
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            rpc_response(json!(encode(&escrow_address, &program_account(escrow, &Pubkey::new_unique())))),
+        );
+        let client = EscrowClient::from_rpc_client(
+            RpcClient::new_mock_with_mocks("succeeds", mocks),
+            program_id,
+        );
+        assert!(matches!(
+            client.get_escrow(&escrow_address),
+            Err(EscrowClientError::InvalidOwner(..))
+        ));
+    }
+
+    // the exchange resolves the seller, treasury and creators on its own
+    #[test]
+    fn exchange_transaction_test() {
+        let program_id = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let escrow_address = Pubkey::new_unique();
+        let platform_address = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let creators = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let escrow = escrow_state(Pubkey::new_unique());
+
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&escrow.mint_key);
+        let mut metadata_data = Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: escrow.mint_key,
+            data: Data {
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                uri: "uri".to_string(),
+                seller_fee_basis_points: 500,
+                creators: Some(
+                    creators
+                        .iter()
+                        .map(|address| Creator {
+                            address: *address,
+                            verified: true,
+                            share: 50,
+                        })
+                        .collect(),
+                ),
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .try_to_vec()
+        .unwrap();
+        metadata_data.resize(MAX_METADATA_LEN, 0);
+        let metadata = Account {
+            lamports: 1_000_000,
+            data: metadata_data,
+            owner: metaplex_token_metadata::id(),
+            ..Account::default()
+        };
+
+        // the mocked answers are used up by the first request
+        let client = || {
+            let mut mocks = HashMap::new();
+            mocks.insert(
+                RpcRequest::GetAccountInfo,
+                rpc_response(json!(encode(&escrow_address, &program_account(escrow, &program_id)))),
+            );
+            mocks.insert(
+                RpcRequest::GetMultipleAccounts,
+                rpc_response(json!([
+                    encode(&platform_address, &program_account(platform, &program_id)),
+                    encode(&metadata_key, &metadata),
+                ])),
+            );
+            EscrowClient::from_rpc_client(
+                RpcClient::new_mock_with_mocks("succeeds", mocks),
+                program_id,
+            )
+        };
+
+        let transaction = client()
+            .exchange_transaction(&taker, &escrow_address, &platform_address, ExchangeOptionalAccounts::default())
+            .unwrap();
+        transaction.verify().unwrap();
+
+        let instruction = &transaction.message.instructions[0];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                taker.pubkey(),
+                escrow.token_account_pubkey,
+                escrow.seller_pubkey,
+                escrow.mint_key,
+                escrow_address,
+                spl_token::id(),
+                solana_program::system_program::id(),
+                pda::find_escrow_authority(&program_id).0,
+                metadata_key,
+                platform_address,
+                treasury,
+                creators[0],
+                pda::find_royalty_vault_address(&program_id, &creators[0]).0,
+                creators[1],
+                pda::find_royalty_vault_address(&program_id, &creators[1]).0,
+            ]
+        );
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::Exchange { amount: 1 }
+        );
+
+        // a membership pass and a referrer go between the treasury and the creators
+        let pass = MembershipPass {
+            token_account: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+        };
+        let referrer = Pubkey::new_unique();
+        let optional_accounts = ExchangeOptionalAccounts {
+            membership_pass: Some(pass),
+            referrer: Some(referrer),
+            buyer_token_account: None,
+            seller_proceeds: false,
+        };
+        let transaction = client()
+            .exchange_transaction(&taker, &escrow_address, &platform_address, optional_accounts)
+            .unwrap();
+        let instruction = &transaction.message.instructions[0];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(
+            keys[11..],
+            [
+                pass.token_account,
+                pda::find_metadata_address(&pass.mint).0,
+                referrer,
+                pda::find_referrer_address(&program_id, &referrer).0,
+                creators[0],
+                pda::find_royalty_vault_address(&program_id, &creators[0]).0,
+                creators[1],
+                pda::find_royalty_vault_address(&program_id, &creators[1]).0,
+            ]
+        );
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::ExchangeWithOptions {
+                amount: 1,
+                options: EXCHANGE_OPTION_MEMBERSHIP_PASS | EXCHANGE_OPTION_REFERRER,
+            }
+        );
+    }
+
+    // the NFT of a delegated listing goes to the taker's associated token account,
+    // created in the same transaction when it doesn't exist yet
+    #[test]
+    fn exchange_delegated_transaction_test() {
+        let program_id = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let escrow_address = Pubkey::new_unique();
+        let platform_address = Pubkey::new_unique();
+        let escrow = Escrow {
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            ..escrow_state(Pubkey::new_unique())
+        };
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&escrow.mint_key);
+        let mut metadata_data = Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: escrow.mint_key,
+            data: Data {
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                uri: "uri".to_string(),
+                seller_fee_basis_points: 0,
+                creators: None,
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .try_to_vec()
+        .unwrap();
+        metadata_data.resize(MAX_METADATA_LEN, 0);
+        let metadata = Account {
+            lamports: 1_000_000,
+            data: metadata_data,
+            owner: metaplex_token_metadata::id(),
+            ..Account::default()
+        };
+        let buyer_token_account = spl_associated_token_account::get_associated_token_address(
+            &taker.pubkey(),
+            &escrow.mint_key,
+        );
+
+        let client = |buyer_token: Option<Account>| {
+            let mut mocks = HashMap::new();
+            mocks.insert(
+                RpcRequest::GetAccountInfo,
+                rpc_response(json!(encode(&escrow_address, &program_account(escrow, &program_id)))),
+            );
+            mocks.insert(
+                RpcRequest::GetMultipleAccounts,
+                rpc_response(json!([
+                    encode(&platform_address, &program_account(platform, &program_id)),
+                    encode(&metadata_key, &metadata),
+                    buyer_token.map(|account| encode(&buyer_token_account, &account)),
+                ])),
+            );
+            EscrowClient::from_rpc_client(
+                RpcClient::new_mock_with_mocks("succeeds", mocks),
+                program_id,
+            )
+        };
+
+        let transaction = client(None)
+            .exchange_transaction(&taker, &escrow_address, &platform_address, ExchangeOptionalAccounts::default())
+            .unwrap();
+        transaction.verify().unwrap();
+        assert_eq!(transaction.message.instructions.len(), 2);
+        let create = &transaction.message.instructions[0];
+        assert_eq!(
+            transaction.message.account_keys[create.program_id_index as usize],
+            spl_associated_token_account::id()
+        );
+
+        let instruction = &transaction.message.instructions[1];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(keys[11..], [buyer_token_account]);
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::ExchangeWithOptions {
+                amount: 1,
+                options: EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT,
+            }
+        );
+
+        // an existing associated token account is used as it is
+        let existing = Account {
+            lamports: 1_000_000,
+            data: vec![0; spl_token::state::Account::LEN],
+            owner: spl_token::id(),
+            ..Account::default()
+        };
+        let transaction = client(Some(existing))
+            .exchange_transaction(&taker, &escrow_address, &platform_address, ExchangeOptionalAccounts::default())
+            .unwrap();
+        assert_eq!(transaction.message.instructions.len(), 1);
+    }
+
+    // a delegated Token-2022 NFT goes to the taker's associated token account of
+    // that token program
+    #[test]
+    fn exchange_token_2022_transaction_test() {
+        let program_id = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let escrow_address = Pubkey::new_unique();
+        let platform_address = Pubkey::new_unique();
+        let escrow = Escrow {
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::Token2022,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            ..escrow_state(Pubkey::new_unique())
+        };
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&escrow.mint_key);
+        let mut metadata_data = Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: escrow.mint_key,
+            data: Data {
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                uri: "uri".to_string(),
+                seller_fee_basis_points: 0,
+                creators: None,
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .try_to_vec()
+        .unwrap();
+        metadata_data.resize(MAX_METADATA_LEN, 0);
+        let metadata = Account {
+            lamports: 1_000_000,
+            data: metadata_data,
+            owner: metaplex_token_metadata::id(),
+            ..Account::default()
+        };
+        // a mint without extensions, so without a transfer fee
+        let mint = program_account(
+            spl_token_2022::state::Mint {
+                supply: 1,
+                is_initialized: true,
+                ..spl_token_2022::state::Mint::default()
+            },
+            &spl_token_2022::id(),
+        );
+        let buyer_token_account =
+            pda::find_associated_token_address(&taker.pubkey(), &escrow.mint_key, &spl_token_2022::id());
+
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            rpc_response(json!(encode(&escrow_address, &program_account(escrow, &program_id)))),
+        );
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            rpc_response(json!([
+                encode(&platform_address, &program_account(platform, &program_id)),
+                encode(&metadata_key, &metadata),
+                None::<UiAccount>,
+                encode(&escrow.mint_key, &mint),
+            ])),
+        );
+        let client = EscrowClient::from_rpc_client(RpcClient::new_mock_with_mocks("succeeds", mocks), program_id);
+
+        let transaction = client
+            .exchange_transaction(&taker, &escrow_address, &platform_address, ExchangeOptionalAccounts::default())
+            .unwrap();
+        transaction.verify().unwrap();
+        let keys = |instruction: &solana_sdk::instruction::CompiledInstruction| -> Vec<Pubkey> {
+            instruction
+                .accounts
+                .iter()
+                .map(|index| transaction.message.account_keys[*index as usize])
+                .collect()
+        };
+
+        let create = &transaction.message.instructions[0];
+        assert_eq!(keys(create)[1], buyer_token_account);
+        assert_eq!(keys(create)[5], spl_token_2022::id());
+
+        let instruction = &transaction.message.instructions[1];
+        assert_eq!(keys(instruction)[5], spl_token_2022::id());
+        assert_eq!(keys(instruction)[11..], [buyer_token_account]);
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::ExchangeWithOptions {
+                amount: 1,
+                options: EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT,
+            }
+        );
+    }
+
+    // the print is minted to a new mint of the buyer, numbered after the editions
+    // the master edition already printed
+    #[test]
+    fn buy_edition_transaction_test() {
+        let program_id = Pubkey::new_unique();
+        let buyer = Keypair::new();
+        let new_mint = Keypair::new();
+        let drop_address = Pubkey::new_unique();
+        let artist = Pubkey::new_unique();
+        let update_authority = Pubkey::new_unique();
+        let drop = EditionDrop {
+            is_initialized: true,
+            creator: artist,
+            master_token_account: Pubkey::new_unique(),
+            master_mint: Pubkey::new_unique(),
+            marketplace: Pubkey::new_unique(),
+            price: 1000,
+            max_prints: 10,
+            prints_sold: 4,
+            escrow_authority_bump_seed: 0,
+        };
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&drop.master_mint);
+        let mut metadata_data = Metadata {
+            key: Key::MetadataV1,
+            update_authority,
+            mint: drop.master_mint,
+            data: Data {
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                uri: "uri".to_string(),
+                seller_fee_basis_points: 500,
+                creators: Some(vec![Creator {
+                    address: artist,
+                    verified: true,
+                    share: 100,
+                }]),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .try_to_vec()
+        .unwrap();
+        metadata_data.resize(MAX_METADATA_LEN, 0);
+        let metadata = Account {
+            lamports: 1_000_000,
+            data: metadata_data,
+            owner: metaplex_token_metadata::id(),
+            ..Account::default()
+        };
+        let (master_edition_key, _master_edition_bump_seed) = pda::find_edition_address(&drop.master_mint);
+        let mut master_edition_data = MasterEditionV2 {
+            key: Key::MasterEditionV2,
+            supply: 6,
+            max_supply: Some(20),
+        }
+        .try_to_vec()
+        .unwrap();
+        master_edition_data.resize(MAX_MASTER_EDITION_LEN, 0);
+        let master_edition = Account {
+            lamports: 1_000_000,
+            data: master_edition_data,
+            owner: metaplex_token_metadata::id(),
+            ..Account::default()
+        };
+
+        let mut mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            rpc_response(json!(encode(&drop_address, &program_account(drop, &program_id)))),
+        );
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            rpc_response(json!([
+                encode(&drop.marketplace, &program_account(platform, &program_id)),
+                encode(&metadata_key, &metadata),
+                encode(&master_edition_key, &master_edition),
+            ])),
+        );
+        let client = EscrowClient::from_rpc_client(RpcClient::new_mock_with_mocks("succeeds", mocks), program_id);
+
+        let transaction = client.buy_edition_transaction(&buyer, &new_mint, &drop_address).unwrap();
+        transaction.verify().unwrap();
+        assert_eq!(transaction.message.instructions.len(), 5);
+
+        let instruction = &transaction.message.instructions[4];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(keys[0], buyer.pubkey());
+        assert_eq!(keys[3], new_mint.pubkey());
+        assert_eq!(keys[10], update_authority);
+        assert_eq!(keys[17], platform.treasury_account);
+        assert_eq!(keys[18..], [artist, pda::find_royalty_vault_address(&program_id, &artist).0]);
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::BuyEdition { edition: 7 }
+        );
+    }
+
+    // a listing priced in USD is bought with ExchangeUsd, at the current price of
+    // its feed, which the payout preview is computed at too
+    #[test]
+    fn exchange_usd_transaction_test() {
+        let program_id = Pubkey::new_unique();
+        let taker = Keypair::new();
+        let escrow_address = Pubkey::new_unique();
+        let platform_address = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let price_feed_address = sol_usd_price_feed_id();
+        // 25 dollars
+        let escrow = Escrow {
+            expected_amount: 25_000_000,
+            usd_priced: true,
+            ..escrow_state(Pubkey::new_unique())
+        };
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let (usd_listing_address, usd_listing_bump_seed) = pda::find_usd_listing_address(&program_id, &escrow_address);
+        let usd_listing = UsdListing {
+            is_initialized: true,
+            escrow: escrow_address,
+            price_feed: price_feed_address,
+            bump_seed: usd_listing_bump_seed,
+        };
+        let clock = Clock {
+            slot: 100,
+            unix_timestamp: 1_000,
+            ..Clock::default()
+        };
+        // 50 dollars a SOL
+        let mut price_feed_data = vec![0; PRICE_ACCOUNT_LEN];
+        PriceFeed {
+            exponent: -8,
+            timestamp: clock.unix_timestamp,
+            price: 5_000_000_000,
+            confidence: 1_000_000,
+            status: PRICE_STATUS_TRADING,
+            publish_slot: clock.slot,
+        }
+        .pack_into_slice(&mut price_feed_data);
+        let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&escrow.mint_key);
+        let mut metadata_data = Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: escrow.mint_key,
+            data: Data {
+                name: "name".to_string(),
+                symbol: "symbol".to_string(),
+                uri: "uri".to_string(),
+                seller_fee_basis_points: 0,
+                creators: None,
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .try_to_vec()
+        .unwrap();
+        metadata_data.resize(MAX_METADATA_LEN, 0);
+
+        let mut accounts = HashMap::new();
+        accounts.insert(escrow_address, program_account(escrow, &program_id));
+        accounts.insert(platform_address, program_account(platform, &program_id));
+        accounts.insert(usd_listing_address, program_account(usd_listing, &program_id));
+        accounts.insert(
+            price_feed_address,
+            Account {
+                lamports: 1_000_000,
+                data: price_feed_data,
+                owner: oracle_program_id(),
+                ..Account::default()
+            },
+        );
+        accounts.insert(sysvar::clock::id(), create_account_for_test(&clock));
+        accounts.insert(
+            metadata_key,
+            Account {
+                lamports: 1_000_000,
+                data: metadata_data,
+                owner: metaplex_token_metadata::id(),
+                ..Account::default()
+            },
+        );
+        let client = EscrowClient::from_rpc_client(
+            RpcClient::new_sender(
+                AccountsSender { accounts },
+                RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+            ),
+            program_id,
+        );
+
+        assert_eq!(client.get_price(&escrow_address, &escrow).unwrap(), 500_000_000);
+        let payout = client.get_payout(&escrow_address, &escrow, &platform, false).unwrap();
+        assert_eq!(payout.platform_fee, 12_500_000);
+        assert_eq!(payout.seller_proceeds, 487_500_000);
+
+        let transaction = client
+            .exchange_transaction(&taker, &escrow_address, &platform_address, ExchangeOptionalAccounts::default())
+            .unwrap();
+        transaction.verify().unwrap();
+        let instruction = &transaction.message.instructions[0];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(keys[10..], [treasury, usd_listing_address, price_feed_address]);
+        // the current value, and the rise the taker lets happen
+        assert_eq!(
+            EscrowInstruction::unpack(&instruction.data).unwrap(),
+            EscrowInstruction::ExchangeUsd {
+                amount: 1,
+                max_lamports: 505_000_000,
+                options: 0,
+            }
+        );
+    }
+}
@@ -0,0 +1,873 @@
+// Compute units the program consumes per instruction, measured on the BPF build
+// with `cargo test-bpf` and held against the baselines in `compute_units.json`.
+// Run it with `UPDATE_COMPUTE_UNITS=1` to record the current measurements as
+// the new baselines after an intended change. Printing editions runs the token
+// metadata program, dumped into `tests/fixtures/mpl_token_metadata.so`; without
+// it `BuyEdition` is not measured.
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    instruction::{self, ExchangeOptionalAccounts},
+    pda,
+    price_feed::{self, PriceFeed, PRICE_ACCOUNT_LEN, PRICE_STATUS_TRADING},
+    processor,
+    state::VaultAccount,
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{
+    Creator, Data, Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+};
+#[cfg(feature = "test-bpf")]
+use solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{BanksClient, ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, hash::Hash, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
+use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
+#[cfg(feature = "test-bpf")]
+use std::{collections::BTreeMap, path::Path, str::FromStr, sync::Mutex, time::{SystemTime, UNIX_EPOCH}};
+
+#[cfg(feature = "test-bpf")]
+const COMPUTE_UNIT_BASELINES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compute_units.json");
+
+#[cfg(feature = "test-bpf")]
+const METADATA_PROGRAM_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mpl_token_metadata.so");
+
+/// Percentage of its baseline an instruction may consume on top of it before the
+/// suite fails.
+#[cfg(feature = "test-bpf")]
+const COMPUTE_UNIT_TOLERANCE_PERCENT: u64 = 10;
+
+#[cfg(feature = "test-bpf")]
+static PROGRAM_LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "test-bpf")]
+struct ProgramLogCapture;
+
+#[cfg(feature = "test-bpf")]
+impl log::Log for ProgramLogCapture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "solana_runtime::message_processor::stable_log"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            PROGRAM_LOGS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "test-bpf")]
+static PROGRAM_LOG_CAPTURE: ProgramLogCapture = ProgramLogCapture;
+
+// the bank the instructions are measured on, and the measurements so far
+#[cfg(feature = "test-bpf")]
+struct Bench {
+    client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    measured: BTreeMap<&'static str, u64>,
+}
+
+#[cfg(feature = "test-bpf")]
+impl Bench {
+    // processes `instructions` paid for by the payer and signed by `signers`, and
+    // returns the compute units each escrow program instruction among them consumed,
+    // read from the `Program <id> consumed <units> of <limit>` log lines
+    async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Vec<u64> {
+        PROGRAM_LOGS.lock().unwrap().clear();
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        let mut keypairs = vec![&self.payer];
+        keypairs.extend_from_slice(signers);
+        transaction.sign(&keypairs, self.recent_blockhash);
+        self.client.process_transaction(transaction).await.unwrap();
+
+        let prefix = format!("Program {} consumed ", self.program_id);
+        let units: Vec<u64> = PROGRAM_LOGS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|line| line.strip_prefix(&prefix)?.split(' ').next()?.parse().ok())
+            .collect();
+        let program_instructions = instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == self.program_id)
+            .count();
+        assert_eq!(units.len(), program_instructions, "no compute units were logged, the program did not run as BPF");
+        units
+    }
+
+    // processes `instructions` and records the escrow program instructions among
+    // them as `names`, in order
+    async fn measure(&mut self, instructions: &[Instruction], signers: &[&Keypair], names: &[&'static str]) {
+        let units = self.process(instructions, signers).await;
+        assert_eq!(units.len(), names.len());
+        self.measured.extend(names.iter().copied().zip(units));
+    }
+
+    // mints a single token of a new mint into a new token account of `owner`
+    async fn mint_nft(
+        &mut self,
+        minter: &Keypair,
+        mint: &Keypair,
+        token_account: &Keypair,
+        owner: &Pubkey,
+        freeze_authority: Option<&Pubkey>,
+    ) {
+        let rent = Rent::default();
+        let payer = self.payer.pubkey();
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::id(),
+                ),
+                spl_token::instruction::initialize_mint(
+                    &spl_token::id(),
+                    &mint.pubkey(),
+                    &minter.pubkey(),
+                    freeze_authority,
+                    0
+                ).unwrap(),
+                system_instruction::create_account(
+                    &payer,
+                    &token_account.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::id(),
+                ),
+                spl_token::instruction::initialize_account(&spl_token::id(), &token_account.pubkey(), &mint.pubkey(), owner)
+                    .unwrap(),
+                spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &mint.pubkey(),
+                    &token_account.pubkey(),
+                    &minter.pubkey(),
+                    &[],
+                    1
+                ).unwrap(),
+            ],
+            &[minter, mint, token_account],
+        )
+        .await;
+    }
+
+    // creates a token account of `mint` for `owner`, funded with `amount` minted by `minter`
+    async fn token_account(&mut self, minter: &Keypair, mint: &Pubkey, account: &Keypair, owner: &Pubkey, amount: u64) {
+        let payer = self.payer.pubkey();
+        let mut instructions = vec![
+            system_instruction::create_account(
+                &payer,
+                &account.pubkey(),
+                Rent::default().minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(&spl_token::id(), &account.pubkey(), mint, owner).unwrap(),
+        ];
+        if amount == 0 {
+            self.process(&instructions, &[account]).await;
+            return;
+        }
+        instructions.push(
+            spl_token::instruction::mint_to(&spl_token::id(), mint, &account.pubkey(), &minter.pubkey(), &[], amount)
+                .unwrap(),
+        );
+        self.process(&instructions, &[minter, account]).await;
+    }
+}
+
+// adds the metadata of `mint`, paying 5% royalties to `creator`
+#[cfg(feature = "test-bpf")]
+fn add_metadata(program_test: &mut ProgramTest, mint: &Pubkey, update_authority: &Pubkey, creator: &Pubkey) {
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(mint);
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: *update_authority,
+        mint: *mint,
+        data: Data {
+            name: "Hello, world!".to_string(),
+            symbol: "Hello, world!".to_string(),
+            uri: "Hello, world!".to_string(),
+            seller_fee_basis_points: 500,
+            creators: Some(vec![Creator {
+                address: *creator,
+                verified: true,
+                share: 100,
+            }]),
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+}
+
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn compute_units_within_baselines() {
+    // `cargo test-bpf` points `BPF_OUT_DIR` at the BPF build, the native processor
+    // run by `cargo test` logs no compute units
+    if std::env::var("BPF_OUT_DIR").is_err() {
+        println!("compute units are only measured on the BPF build, run `cargo test-bpf`");
+        return;
+    }
+    let metadata_program_dumped = Path::new(METADATA_PROGRAM_FIXTURE).exists();
+
+    let escrow_program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+    let (escrow_authority, _bump_seed) = pda::find_escrow_authority(&escrow_program_id);
+
+    let minter = Keypair::new();
+    let admin = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let creator = Keypair::new();
+    let referrer = Keypair::new();
+    let treasury = Pubkey::new_unique();
+    let platform_account = Pubkey::new_unique();
+    let price_feed_key = price_feed::sol_usd_price_feed_id();
+    let (marketplace, _marketplace_bump_seed) =
+        pda::find_marketplace_address(&escrow_program_id, &admin.pubkey(), "bench");
+
+    // one NFT for every way of listing and closing a listing
+    let sold_mint = Keypair::new();
+    let cancelled_mint = Keypair::new();
+    let delegated_mint = Keypair::new();
+    let frozen_mint = Keypair::new();
+    let usd_mint = Keypair::new();
+    let master_mint = Keypair::new();
+
+    log::set_logger(&PROGRAM_LOG_CAPTURE).unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        escrow_program_id,
+        processor!(processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+    // creating the accounts of a print takes the BPF build of the metadata program
+    if metadata_program_dumped {
+        program_test.add_program("mpl_token_metadata", metaplex_token_metadata::id(), None);
+    } else {
+        println!("{} is missing, BuyEdition is not measured", METADATA_PROGRAM_FIXTURE);
+    }
+
+    for pubkey in [admin.pubkey(), seller.pubkey(), taker.pubkey(), referrer.pubkey()] {
+        program_test.add_account(
+            pubkey,
+            Account {
+                lamports: 10_000_000_000,
+                ..Account::default()
+            }
+        );
+    }
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    for pubkey in [creator.pubkey(), treasury] {
+        program_test.add_account(
+            pubkey,
+            Account {
+                lamports: rent_exempt_minimum,
+                ..Account::default()
+            }
+        );
+    }
+
+    // the admin's platform account, which pauses token swap fills
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: treasury,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform_account,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: escrow_program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    let mut native_mint_data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: spl_token::native_mint::DECIMALS,
+        is_initialized: true,
+        ..spl_token::state::Mint::default()
+    }
+    .pack_into_slice(&mut native_mint_data);
+    program_test.add_account(
+        spl_token::native_mint::id(),
+        Account {
+            lamports: Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+            owner: spl_token::id(),
+            data: native_mint_data,
+            ..Account::default()
+        }
+    );
+
+    // SOL at $25.00, published when the test starts
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let mut price_feed_data = vec![0; PRICE_ACCOUNT_LEN];
+    PriceFeed {
+        exponent: -8,
+        timestamp: now,
+        price: 2_500_000_000,
+        confidence: 1_000_000,
+        status: PRICE_STATUS_TRADING,
+        publish_slot: 0,
+    }
+    .pack_into_slice(&mut price_feed_data);
+    program_test.add_account(
+        price_feed_key,
+        Account {
+            lamports: Rent::default().minimum_balance(PRICE_ACCOUNT_LEN),
+            owner: price_feed::oracle_program_id(),
+            data: price_feed_data,
+            ..Account::default()
+        }
+    );
+
+    for mint in [&sold_mint, &cancelled_mint, &delegated_mint, &frozen_mint, &usd_mint, &master_mint] {
+        add_metadata(&mut program_test, &mint.pubkey(), &minter.pubkey(), &creator.pubkey());
+    }
+    let (master_edition, _master_edition_bump_seed) = pda::find_edition_address(&master_mint.pubkey());
+    let mut master_edition_data = MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply: 0,
+        max_supply: Some(10),
+    }
+    .try_to_vec()
+    .unwrap();
+    master_edition_data.resize(MAX_MASTER_EDITION_LEN, 0);
+    program_test.add_account(
+        master_edition,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_MASTER_EDITION_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: master_edition_data,
+            ..Account::default()
+        }
+    );
+
+    let (client, payer, recent_blockhash) = program_test.start().await;
+    let mut bench = Bench {
+        client,
+        payer,
+        recent_blockhash,
+        program_id: escrow_program_id,
+        measured: BTreeMap::new(),
+    };
+
+    // marketplace settings, on a marketplace of the admin
+    bench.measure(
+        &[instruction::create_marketplace(&escrow_program_id, &admin.pubkey(), &admin.pubkey(), &treasury, "bench", 250)
+            .unwrap()],
+        &[&admin],
+        &["CreateMarketplace"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::update_platform_account(&escrow_program_id, &admin.pubkey(), &marketplace, &treasury, 250)
+            .unwrap()],
+        &[&admin],
+        &["UpdatePlatformAccount"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::set_pass_discount(&escrow_program_id, &admin.pubkey(), &marketplace, &Pubkey::new_unique(), 100)
+            .unwrap()],
+        &[&admin],
+        &["SetPassDiscount"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::set_referral_share(&escrow_program_id, &admin.pubkey(), &marketplace, 1000).unwrap()],
+        &[&admin],
+        &["SetReferralShare"],
+    )
+    .await;
+
+    let sold_token_account = Keypair::new();
+    let cancelled_token_account = Keypair::new();
+    let delegated_token_account = Keypair::new();
+    let frozen_token_account = Keypair::new();
+    let usd_token_account = Keypair::new();
+    let master_token_account = Keypair::new();
+    for (mint, token_account, freeze_authority) in [
+        (&sold_mint, &sold_token_account, None),
+        (&cancelled_mint, &cancelled_token_account, None),
+        (&delegated_mint, &delegated_token_account, None),
+        (&frozen_mint, &frozen_token_account, Some(&escrow_authority)),
+        (&usd_mint, &usd_token_account, None),
+        (&master_mint, &master_token_account, None),
+    ] {
+        bench.mint_nft(&minter, mint, token_account, &seller.pubkey(), freeze_authority).await;
+    }
+
+    // a listing in custody, bought with the royalty paid to the creator
+    let sold_escrow_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_token(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &sold_token_account.pubkey(),
+            &sold_mint.pubkey(),
+            &sold_escrow_account.pubkey(),
+            &marketplace,
+            1_000_000
+        ).unwrap()],
+        &[&seller, &sold_escrow_account],
+        &["ListToken"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::exchange(
+            &escrow_program_id,
+            &taker.pubkey(),
+            &sold_token_account.pubkey(),
+            &seller.pubkey(),
+            &sold_mint.pubkey(),
+            &sold_escrow_account.pubkey(),
+            &marketplace,
+            &treasury,
+            &[creator.pubkey()],
+            1
+        ).unwrap()],
+        &[&taker],
+        &["Exchange"],
+    )
+    .await;
+
+    // a listing in custody, cancelled
+    let cancelled_escrow_account = Keypair::new();
+    bench.process(
+        &[instruction::list_token(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &cancelled_token_account.pubkey(),
+            &cancelled_mint.pubkey(),
+            &cancelled_escrow_account.pubkey(),
+            &marketplace,
+            1_000_000
+        ).unwrap()],
+        &[&seller, &cancelled_escrow_account],
+    )
+    .await;
+    bench.measure(
+        &[instruction::cancel(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &cancelled_token_account.pubkey(),
+            &cancelled_escrow_account.pubkey()
+        ).unwrap()],
+        &[&seller],
+        &["Cancel"],
+    )
+    .await;
+
+    // a listing priced in USD, bought at the price of the feed
+    let usd_escrow_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_token_usd(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &usd_token_account.pubkey(),
+            &usd_mint.pubkey(),
+            &usd_escrow_account.pubkey(),
+            &marketplace,
+            &price_feed_key,
+            5_000_000
+        ).unwrap()],
+        &[&seller, &usd_escrow_account],
+        &["ListTokenUsd"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::exchange_usd(
+            &escrow_program_id,
+            &taker.pubkey(),
+            &usd_token_account.pubkey(),
+            &seller.pubkey(),
+            &usd_mint.pubkey(),
+            &usd_escrow_account.pubkey(),
+            &marketplace,
+            &treasury,
+            &price_feed_key,
+            &ExchangeOptionalAccounts::default(),
+            &[creator.pubkey()],
+            1,
+            1_000_000_000
+        ).unwrap()],
+        &[&taker],
+        &["ExchangeUsd"],
+    )
+    .await;
+
+    // a delegated listing in proceeds-vault mode, bought through a registered referrer
+    // while the marketplace escrows royalties, which the creator and the seller then claim
+    bench.measure(
+        &[instruction::register_referrer(&escrow_program_id, &referrer.pubkey()).unwrap()],
+        &[&referrer],
+        &["RegisterReferrer"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::set_escrow_royalties(&escrow_program_id, &admin.pubkey(), &marketplace, true).unwrap()],
+        &[&admin],
+        &["SetEscrowRoyalties"],
+    )
+    .await;
+    let delegated_escrow_account = Keypair::new();
+    bench.measure(
+        &[
+            instruction::list_token_delegated(
+                &escrow_program_id,
+                &seller.pubkey(),
+                &delegated_token_account.pubkey(),
+                &delegated_mint.pubkey(),
+                &delegated_escrow_account.pubkey(),
+                &marketplace,
+                1_000_000
+            ).unwrap(),
+            instruction::set_proceeds_vault(
+                &escrow_program_id,
+                &seller.pubkey(),
+                &delegated_escrow_account.pubkey(),
+                true
+            ).unwrap(),
+        ],
+        &[&seller, &delegated_escrow_account],
+        &["ListTokenDelegated", "SetProceedsVault"],
+    )
+    .await;
+    let taker_token_account = get_associated_token_address(&taker.pubkey(), &delegated_mint.pubkey());
+    bench.measure(
+        &[
+            create_associated_token_account(&taker.pubkey(), &taker.pubkey(), &delegated_mint.pubkey()),
+            instruction::exchange_with_options(
+                &escrow_program_id,
+                &taker.pubkey(),
+                &delegated_token_account.pubkey(),
+                &seller.pubkey(),
+                &delegated_mint.pubkey(),
+                &delegated_escrow_account.pubkey(),
+                &marketplace,
+                &treasury,
+                &ExchangeOptionalAccounts {
+                    referrer: Some(referrer.pubkey()),
+                    buyer_token_account: Some(taker_token_account),
+                    seller_proceeds: true,
+                    ..ExchangeOptionalAccounts::default()
+                },
+                &instruction::royalty_accounts(&escrow_program_id, &[creator.pubkey()]),
+                1
+            ).unwrap(),
+        ],
+        &[&taker],
+        &["ExchangeWithOptions"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::claim_royalties(&escrow_program_id, &creator.pubkey(), &creator.pubkey()).unwrap()],
+        &[&creator],
+        &["ClaimRoyalties"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::withdraw_proceeds(&escrow_program_id, &seller.pubkey(), &seller.pubkey()).unwrap()],
+        &[&seller],
+        &["WithdrawProceeds"],
+    )
+    .await;
+
+    // a frozen listing, force delisted by the admin
+    let frozen_escrow_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_token_frozen(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &frozen_token_account.pubkey(),
+            &frozen_mint.pubkey(),
+            &frozen_escrow_account.pubkey(),
+            &marketplace,
+            1_000_000
+        ).unwrap()],
+        &[&seller, &frozen_escrow_account],
+        &["ListTokenFrozen"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::force_delist_frozen(
+            &escrow_program_id,
+            &admin.pubkey(),
+            &frozen_token_account.pubkey(),
+            &frozen_escrow_account.pubkey(),
+            &marketplace,
+            &frozen_mint.pubkey(),
+            3
+        ).unwrap()],
+        &[&admin],
+        &["ForceDelist"],
+    )
+    .await;
+
+    // a drop of prints of a master edition, one print bought before it is cancelled
+    let drop_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_edition_drop(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &master_token_account.pubkey(),
+            &master_mint.pubkey(),
+            &drop_account.pubkey(),
+            &marketplace,
+            1_000_000,
+            2
+        ).unwrap()],
+        &[&seller, &drop_account],
+        &["ListEditionDrop"],
+    )
+    .await;
+    if metadata_program_dumped {
+        let print_mint = Keypair::new();
+        let print_token_account = Keypair::new();
+        bench.mint_nft(&taker, &print_mint, &print_token_account, &taker.pubkey(), None).await;
+        bench.measure(
+            &[instruction::buy_edition(
+                &escrow_program_id,
+                &taker.pubkey(),
+                &drop_account.pubkey(),
+                &seller.pubkey(),
+                &master_mint.pubkey(),
+                &master_token_account.pubkey(),
+                &print_mint.pubkey(),
+                &minter.pubkey(),
+                &marketplace,
+                &treasury,
+                &instruction::royalty_accounts(&escrow_program_id, &[creator.pubkey()]),
+                1
+            ).unwrap()],
+            &[&taker],
+            &["BuyEdition"],
+        )
+        .await;
+    }
+    bench.measure(
+        &[instruction::cancel_edition_drop(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &master_token_account.pubkey(),
+            &drop_account.pubkey()
+        ).unwrap()],
+        &[&seller],
+        &["CancelEditionDrop"],
+    )
+    .await;
+
+    // 100 of a token swapped for 50 of another, filled in part and cancelled
+    let offered_mint = Keypair::new();
+    let expected_mint = Keypair::new();
+    let rent = Rent::default();
+    let payer = bench.payer.pubkey();
+    let mut instructions = Vec::new();
+    for mint in [&offered_mint, &expected_mint] {
+        instructions.push(system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ));
+        instructions.push(spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &minter.pubkey(),
+            None,
+            0
+        ).unwrap());
+    }
+    bench.process(&instructions, &[&offered_mint, &expected_mint]).await;
+    let sellers_offered_account = Keypair::new();
+    let sellers_receive_account = Keypair::new();
+    let takers_sending_account = Keypair::new();
+    let takers_receiving_account = Keypair::new();
+    for (account, mint, owner, amount) in [
+        (&sellers_offered_account, &offered_mint, &seller, 200),
+        (&sellers_receive_account, &expected_mint, &seller, 0),
+        (&takers_sending_account, &expected_mint, &taker, 100),
+        (&takers_receiving_account, &offered_mint, &taker, 0),
+    ] {
+        bench.token_account(&minter, &mint.pubkey(), account, &owner.pubkey(), amount).await;
+    }
+
+    let swap_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_token_swap(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &sellers_offered_account.pubkey(),
+            &sellers_receive_account.pubkey(),
+            &offered_mint.pubkey(),
+            &swap_account.pubkey(),
+            &platform_account,
+            100,
+            50
+        ).unwrap()],
+        &[&seller, &swap_account],
+        &["ListTokenSwap"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::exchange_token_swap(
+            &escrow_program_id,
+            &taker.pubkey(),
+            &takers_sending_account.pubkey(),
+            &takers_receiving_account.pubkey(),
+            &sellers_receive_account.pubkey(),
+            &seller.pubkey(),
+            &swap_account.pubkey(),
+            &platform_account,
+            40,
+            20
+        ).unwrap()],
+        &[&taker],
+        &["ExchangeTokenSwap"],
+    )
+    .await;
+    bench.measure(
+        &[instruction::cancel_token_swap(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &sellers_offered_account.pubkey(),
+            &swap_account.pubkey()
+        ).unwrap()],
+        &[&seller],
+        &["CancelTokenSwap"],
+    )
+    .await;
+
+    // 100 of the token for 50_000 lamports paid out as native SOL, filled in part
+    // with SOL the taker wraps in the same transaction
+    let sol_swap_account = Keypair::new();
+    bench.measure(
+        &[instruction::list_token_swap_for_sol(
+            &escrow_program_id,
+            &seller.pubkey(),
+            &sellers_offered_account.pubkey(),
+            &offered_mint.pubkey(),
+            &sol_swap_account.pubkey(),
+            &platform_account,
+            100,
+            50_000
+        ).unwrap()],
+        &[&seller, &sol_swap_account],
+        &["ListTokenSwapForSol"],
+    )
+    .await;
+    let temporary_wsol_account = Keypair::new();
+    bench.measure(
+        &[
+            system_instruction::create_account(
+                &taker.pubkey(),
+                &temporary_wsol_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &temporary_wsol_account.pubkey(),
+                &spl_token::native_mint::id(),
+                &taker.pubkey()
+            ).unwrap(),
+            instruction::exchange_token_swap_with_sol(
+                &escrow_program_id,
+                &taker.pubkey(),
+                &temporary_wsol_account.pubkey(),
+                &takers_receiving_account.pubkey(),
+                &seller.pubkey(),
+                &seller.pubkey(),
+                &sol_swap_account.pubkey(),
+                &platform_account,
+                40,
+                20_000
+            ).unwrap(),
+        ],
+        &[&taker, &temporary_wsol_account],
+        &["ExchangeTokenSwapWithSol"],
+    )
+    .await;
+
+    bench.measure(
+        &[instruction::set_paused(&escrow_program_id, &admin.pubkey(), &marketplace, true).unwrap()],
+        &[&admin],
+        &["SetPaused"],
+    )
+    .await;
+
+    let measured = bench.measured;
+    for (instruction, units) in &measured {
+        println!("{}: {} compute units", instruction, units);
+    }
+
+    let mut baselines: BTreeMap<String, u64> =
+        serde_json::from_str(&std::fs::read_to_string(COMPUTE_UNIT_BASELINES).unwrap()).unwrap();
+    if std::env::var("UPDATE_COMPUTE_UNITS").is_ok() {
+        // the baselines of instructions not measured in this run are kept
+        baselines.extend(measured.iter().map(|(instruction, units)| (instruction.to_string(), *units)));
+        let baselines = serde_json::to_string_pretty(&baselines).unwrap() + "\n";
+        std::fs::write(COMPUTE_UNIT_BASELINES, baselines).unwrap();
+        return;
+    }
+
+    // instructions without a recorded baseline are only reported, until the
+    // baselines are written by the measurements above
+    let mut regressions = Vec::new();
+    for (instruction, units) in &measured {
+        match baselines.get(*instruction) {
+            None => println!(
+                "{} consumed {} compute units, no baseline is recorded, run with UPDATE_COMPUTE_UNITS=1",
+                instruction, units
+            ),
+            Some(baseline) => {
+                let limit = baseline + baseline * COMPUTE_UNIT_TOLERANCE_PERCENT / 100;
+                if *units > limit {
+                    regressions.push(format!("{} consumed {} compute units, baseline {}", instruction, units, baseline));
+                }
+            }
+        }
+    }
+    assert!(regressions.is_empty(), "{}", regressions.join("\n"));
+}
@@ -4,7 +4,10 @@ mod tests {
         pubkey::Pubkey,
         program_pack::Pack
     };
-    use escrow_buy::state::Escrow;
+    use escrow_buy::state::{
+        account_type, AccountType, EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault, SellerProceeds,
+        TokenProgram, TokenSwap, UsdListing, VaultAccount, STATE_VERSION,
+    };
 
     // unit test for state pack unpack
     #[test]
@@ -15,7 +18,14 @@ mod tests {
             seller_pubkey: Pubkey::new(&[1; 32]),
             token_account_pubkey: Pubkey::new(&[2; 32]),
             mint_key: Pubkey::new(&[3; 32]),
-            expected_amount: 123
+            expected_amount: 123,
+            marketplace: Pubkey::new(&[4; 32]),
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::Token2022,
+            usd_priced: false,
+            escrow_authority_bump_seed: 254,
+            metadata_bump_seed: 253,
+            proceeds_vault: false,
         };
         // temp packed data vec
         let mut packed_data = vec![0; Escrow::get_packed_len()];
@@ -25,5 +35,398 @@ mod tests {
         let unpacked_data = Escrow::unpack(&packed_data).unwrap();
         // do an assert check on the test escrow state and unpacked escrow state
         assert_eq!(state, unpacked_data);
+
+        // listings made before the mode existed hold the token in custody
+        packed_data[144] = 0;
+        assert_eq!(Escrow::unpack(&packed_data).unwrap().listing_mode, ListingMode::Custody);
+
+        packed_data[144] = 2;
+        assert_eq!(Escrow::unpack(&packed_data).unwrap().listing_mode, ListingMode::Frozen);
+
+        packed_data[144] = 3;
+        assert!(Escrow::unpack(&packed_data).is_err());
+        packed_data[144] = 0;
+
+        // listings made before the token program was recorded are of SPL Token mints
+        packed_data[145] = 0;
+        assert_eq!(Escrow::unpack(&packed_data).unwrap().token_program, TokenProgram::SplToken);
+
+        packed_data[145] = 2;
+        assert!(Escrow::unpack(&packed_data).is_err());
+        packed_data[145] = 1;
+
+        // listings made before USD prices existed are priced in lamports
+        assert!(!Escrow::unpack(&packed_data).unwrap().usd_priced);
+        packed_data[146] = 1;
+        assert!(Escrow::unpack(&packed_data).unwrap().usd_priced);
+        packed_data[146] = 2;
+        assert!(Escrow::unpack(&packed_data).is_err());
+        packed_data[146] = 0;
+
+        // listings made before the bump seeds were recorded hold 0 for them
+        assert_eq!(&packed_data[147..149], &[254, 253]);
+        packed_data[147..149].fill(0);
+        let unpacked_data = Escrow::unpack(&packed_data).unwrap();
+        assert_eq!(unpacked_data.escrow_authority_bump_seed, 0);
+        assert_eq!(unpacked_data.metadata_bump_seed, 0);
+
+        // listings made before proceeds could be credited transfer them to the seller
+        assert!(!unpacked_data.proceeds_vault);
+        packed_data[149] = 1;
+        assert!(Escrow::unpack(&packed_data).unwrap().proceeds_vault);
+        packed_data[149] = 2;
+        assert!(Escrow::unpack(&packed_data).is_err());
+    }
+
+    // the header of a packed account is checked on every unpack
+    #[test]
+    fn state_header_test() {
+        let state = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let mut packed_data = vec![0; VaultAccount::get_packed_len()];
+        VaultAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(packed_data[0], AccountType::PlatformAccount as u8);
+        assert_eq!(packed_data[1], STATE_VERSION);
+        assert_eq!(VaultAccount::unpack(&packed_data).unwrap(), state);
+
+        // an account of another type is refused
+        let mut other_type = packed_data.clone();
+        other_type[0] = AccountType::Escrow as u8;
+        assert!(VaultAccount::unpack_unchecked(&other_type).is_err());
+
+        // and so is an unknown layout version
+        let mut other_version = packed_data.clone();
+        other_version[1] = STATE_VERSION + 1;
+        assert!(VaultAccount::unpack_unchecked(&other_version).is_err());
+
+        // a freshly created account reads as uninitialized
+        let zeroed = vec![0; Escrow::get_packed_len()];
+        assert!(!Escrow::unpack_unchecked(&zeroed).unwrap().is_initialized);
+        assert!(Escrow::unpack(&zeroed).is_err());
+    }
+
+    // the paused flag takes the first byte of what used to be padding
+    #[test]
+    fn platform_paused_test() {
+        let state = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: true,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let mut packed_data = vec![0; VaultAccount::get_packed_len()];
+        VaultAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(VaultAccount::unpack(&packed_data).unwrap(), state);
+
+        // accounts written before the flag existed are not paused
+        packed_data[48] = 0;
+        assert!(!VaultAccount::unpack(&packed_data).unwrap().paused);
+
+        packed_data[48] = 2;
+        assert!(VaultAccount::unpack(&packed_data).is_err());
+    }
+
+    #[test]
+    fn platform_pass_discount_test() {
+        let state = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::new(&[2; 32]),
+            pass_fee_basis_points: 100,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        };
+        let mut packed_data = vec![0; VaultAccount::get_packed_len()];
+        VaultAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(VaultAccount::unpack(&packed_data).unwrap(), state);
+        assert_eq!(state.fee_basis_points(true), 100);
+        assert_eq!(state.fee_basis_points(false), 250);
+
+        // accounts written before the discount existed have no pass collection
+        packed_data[49..89].fill(0);
+        let state = VaultAccount::unpack(&packed_data).unwrap();
+        assert_eq!(state.pass_collection, Pubkey::default());
+        assert_eq!(state.fee_basis_points(true), 250);
+    }
+
+    // the referral share is carved out of the platform fee
+    #[test]
+    fn platform_referral_share_test() {
+        let state = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 2000,
+            escrow_royalties: false,
+        };
+        let mut packed_data = vec![0; VaultAccount::get_packed_len()];
+        VaultAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(VaultAccount::unpack(&packed_data).unwrap(), state);
+        assert_eq!(state.referral_fee(250), Some(50));
+        assert_eq!(state.referral_fee(3), Some(0));
+
+        let state = VaultAccount {
+            referral_basis_points: 10001,
+            ..state
+        };
+        assert_eq!(state.referral_fee(250), None);
+    }
+
+    #[test]
+    fn referrer_pack_unpack_test() {
+        let state = ReferrerAccount {
+            is_initialized: true,
+            referrer: Pubkey::new(&[1; 32]),
+            total_earned: 1234,
+            sales: 5,
+        };
+        let mut packed_data = vec![0; ReferrerAccount::get_packed_len()];
+        ReferrerAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(packed_data[0], AccountType::Referrer as u8);
+        assert_eq!(ReferrerAccount::unpack(&packed_data).unwrap(), state);
+    }
+
+    // royalties of a platform that escrows them are held for the creators to claim
+    #[test]
+    fn platform_escrow_royalties_test() {
+        let state = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: true,
+        };
+        let mut packed_data = vec![0; VaultAccount::get_packed_len()];
+        VaultAccount::pack(state, &mut packed_data).unwrap();
+        assert_eq!(packed_data[97], 1);
+        assert_eq!(VaultAccount::unpack(&packed_data).unwrap(), state);
+
+        packed_data[97] = 2;
+        assert!(VaultAccount::unpack(&packed_data).is_err());
+    }
+
+    #[test]
+    fn royalty_vault_pack_unpack_test() {
+        let state = RoyaltyVault {
+            is_initialized: true,
+            creator: Pubkey::new(&[1; 32]),
+            total_deposited: 5000,
+            total_claimed: 3000,
+            sales: 4,
+        };
+        let mut packed_data = vec![0; RoyaltyVault::get_packed_len()];
+        RoyaltyVault::pack(state, &mut packed_data).unwrap();
+        assert_eq!(account_type(&packed_data), Some(AccountType::RoyaltyVault));
+        assert_eq!(RoyaltyVault::unpack(&packed_data).unwrap(), state);
+        assert_eq!(state.unclaimed(), 2000);
+    }
+
+    #[test]
+    fn seller_proceeds_pack_unpack_test() {
+        let state = SellerProceeds {
+            is_initialized: true,
+            seller: Pubkey::new(&[1; 32]),
+            total_credited: 9000,
+            total_withdrawn: 4000,
+            sales: 2,
+        };
+        let mut packed_data = vec![0; SellerProceeds::get_packed_len()];
+        SellerProceeds::pack(state, &mut packed_data).unwrap();
+        assert_eq!(account_type(&packed_data), Some(AccountType::SellerProceeds));
+        assert_eq!(SellerProceeds::unpack(&packed_data).unwrap(), state);
+        assert_eq!(state.balance(), 5000);
+    }
+
+    #[test]
+    fn marketplace_pack_unpack_test() {
+        let mut name = [0; 32];
+        name[..5].copy_from_slice(b"store");
+        let state = Marketplace {
+            is_initialized: true,
+            authority: Pubkey::new(&[1; 32]),
+            creator: Pubkey::new(&[2; 32]),
+            name,
+            platform: VaultAccount {
+                is_initialized: true,
+                treasury_account: Pubkey::new(&[3; 32]),
+                base_percentage: 150,
+                paused: false,
+                pass_collection: Pubkey::new(&[4; 32]),
+                pass_fee_basis_points: 50,
+                referral_basis_points: 1000,
+                escrow_royalties: true,
+            },
+        };
+        let mut packed_data = vec![0; Marketplace::get_packed_len()];
+        Marketplace::pack(state, &mut packed_data).unwrap();
+        assert_eq!(account_type(&packed_data), Some(AccountType::Marketplace));
+        assert_eq!(Marketplace::unpack(&packed_data).unwrap(), state);
+        assert_eq!(state.name(), b"store");
+
+        // a marketplace is not a platform account of the admin
+        assert!(VaultAccount::unpack(&packed_data[..VaultAccount::get_packed_len()]).is_err());
+        assert_eq!(account_type(&[0; 8]), None);
+    }
+
+    #[test]
+    fn edition_drop_pack_unpack_test() {
+        let state = EditionDrop {
+            is_initialized: true,
+            creator: Pubkey::new(&[1; 32]),
+            master_token_account: Pubkey::new(&[2; 32]),
+            master_mint: Pubkey::new(&[3; 32]),
+            marketplace: Pubkey::new(&[4; 32]),
+            price: 1000,
+            max_prints: 10,
+            prints_sold: 3,
+            escrow_authority_bump_seed: 253,
+        };
+        let mut packed_data = vec![0; EditionDrop::get_packed_len()];
+        EditionDrop::pack(state, &mut packed_data).unwrap();
+        assert_eq!(account_type(&packed_data), Some(AccountType::EditionDrop));
+        assert_eq!(EditionDrop::unpack(&packed_data).unwrap(), state);
+    }
+
+    #[test]
+    fn usd_listing_pack_unpack_test() {
+        let state = UsdListing {
+            is_initialized: true,
+            escrow: Pubkey::new(&[1; 32]),
+            price_feed: Pubkey::new(&[2; 32]),
+            bump_seed: 255,
+        };
+        let mut packed_data = vec![0; UsdListing::get_packed_len()];
+        UsdListing::pack(state, &mut packed_data).unwrap();
+        assert_eq!(account_type(&packed_data), Some(AccountType::UsdListing));
+        assert_eq!(UsdListing::unpack(&packed_data).unwrap(), state);
+    }
+
+    // the accounts written before the account header are read with the defaults of
+    // the fields they lack, and written back while they hold nothing else
+    #[test]
+    fn legacy_state_pack_unpack_test() {
+        let mut packed_data = vec![1];
+        packed_data.extend_from_slice(&[1; 32]);
+        packed_data.extend_from_slice(&[2; 32]);
+        packed_data.extend_from_slice(&[3; 32]);
+        packed_data.extend_from_slice(&123u64.to_le_bytes());
+        let state = Escrow::unpack_legacy(&packed_data).unwrap();
+        assert_eq!(
+            state,
+            Escrow {
+                is_initialized: true,
+                seller_pubkey: Pubkey::new(&[1; 32]),
+                token_account_pubkey: Pubkey::new(&[2; 32]),
+                mint_key: Pubkey::new(&[3; 32]),
+                expected_amount: 123,
+                marketplace: Pubkey::default(),
+                listing_mode: ListingMode::Custody,
+                token_program: TokenProgram::SplToken,
+                usd_priced: false,
+                escrow_authority_bump_seed: 0,
+                metadata_bump_seed: 0,
+                proceeds_vault: false,
+            }
+        );
+        assert!(Escrow::unpack(&packed_data).is_err());
+
+        let mut repacked_data = vec![0; Escrow::LEGACY_LEN];
+        Escrow { is_initialized: false, ..state }.pack_legacy(&mut repacked_data).unwrap();
+        assert_eq!(repacked_data[0], 0);
+        assert_eq!(&repacked_data[1..], &packed_data[1..]);
+        assert!(Escrow { listing_mode: ListingMode::Delegate, ..state }.pack_legacy(&mut repacked_data).is_err());
+        packed_data[0] = 2;
+        assert!(Escrow::unpack_legacy(&packed_data).is_err());
+
+        let mut packed_data = vec![1];
+        packed_data.extend_from_slice(&[4; 32]);
+        packed_data.extend_from_slice(&250u64.to_le_bytes());
+        let state = VaultAccount::unpack_legacy(&packed_data).unwrap();
+        assert!(state.is_initialized);
+        assert_eq!(state.treasury_account, Pubkey::new(&[4; 32]));
+        assert_eq!(state.base_percentage, 250);
+        assert!(!state.paused);
+        assert!(VaultAccount::unpack(&packed_data).is_err());
+
+        let mut repacked_data = vec![0; VaultAccount::LEGACY_LEN];
+        VaultAccount { base_percentage: 100, ..state }.pack_legacy(&mut repacked_data).unwrap();
+        assert_eq!(VaultAccount::unpack_legacy(&repacked_data).unwrap().base_percentage, 100);
+        assert!(VaultAccount { paused: true, ..state }.pack_legacy(&mut repacked_data).is_err());
+    }
+
+    // unit test for token swap state pack unpack
+    #[test]
+    fn token_swap_pack_unpack_test() {
+        let state = TokenSwap {
+            is_initialized: true,
+            seller_pubkey: Pubkey::new(&[1; 32]),
+            vault_pubkey: Pubkey::new(&[2; 32]),
+            seller_receive_pubkey: Pubkey::new(&[3; 32]),
+            offered_mint: Pubkey::new(&[4; 32]),
+            expected_mint: Pubkey::new(&[5; 32]),
+            offered_amount: 100,
+            expected_amount: 50,
+            native_payout: true,
+            escrow_authority_bump_seed: 254,
+        };
+        let mut packed_data = vec![0; TokenSwap::get_packed_len()];
+        TokenSwap::pack(state, &mut packed_data).unwrap();
+        let unpacked_data = TokenSwap::unpack(&packed_data).unwrap();
+        assert_eq!(state, unpacked_data);
+
+        // the native payout flag is a bool like every other one
+        packed_data[184] = 2;
+        assert!(TokenSwap::unpack(&packed_data).is_err());
+    }
+
+    // partial fills are rounded up in favour of the seller
+    #[test]
+    fn token_swap_price_test() {
+        let state = TokenSwap {
+            is_initialized: true,
+            seller_pubkey: Pubkey::new_unique(),
+            vault_pubkey: Pubkey::new_unique(),
+            seller_receive_pubkey: Pubkey::new_unique(),
+            offered_mint: Pubkey::new_unique(),
+            expected_mint: Pubkey::new_unique(),
+            offered_amount: 3,
+            expected_amount: 10,
+            native_payout: false,
+            escrow_authority_bump_seed: 0,
+        };
+        assert_eq!(state.price_for(1), Some(4));
+        assert_eq!(state.price_for(2), Some(7));
+        assert_eq!(state.price_for(3), Some(10));
+        assert_eq!(state.price_for(0), None);
+        assert_eq!(state.price_for(4), None);
+
+        // a partial fill may not take everything that is owed
+        let state = TokenSwap {
+            offered_amount: 100,
+            expected_amount: 1,
+            ..state
+        };
+        assert_eq!(state.price_for(1), None);
+        assert_eq!(state.price_for(100), Some(1));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+    use solana_program::pubkey::Pubkey;
+
+    use escrow_buy::events::{parse_logs, EscrowEvent, EVENT_LOG_PREFIX};
+
+    fn event_line(event: &EscrowEvent) -> String {
+        format!(
+            "Program log: {}{}",
+            EVENT_LOG_PREFIX,
+            base64::encode(event.try_to_vec().unwrap())
+        )
+    }
+
+    // unit test for decoding events out of transaction logs
+    #[test]
+    fn parse_logs_test() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+
+        let cancelled = EscrowEvent::Cancelled {
+            escrow: Pubkey::new_unique(),
+            seller: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+        };
+        let config_updated = EscrowEvent::ConfigUpdated {
+            platform_account: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            fee_basis_points: 250,
+        };
+        let not_ours = EscrowEvent::TokenSwapCancelled {
+            swap: Pubkey::new_unique(),
+            seller: Pubkey::new_unique(),
+            refunded_amount: 70,
+        };
+
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: Cancel".to_string(),
+            format!("Program {} invoke [2]", other_program_id),
+            // an event shaped line logged by another program is skipped
+            event_line(&not_ours),
+            format!("Program {} success", other_program_id),
+            event_line(&cancelled),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", program_id),
+            event_line(&config_updated),
+            // a line that is not an event at all
+            format!("Program log: {}not base64!", EVENT_LOG_PREFIX),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", other_program_id),
+            event_line(&not_ours),
+            format!("Program {} failed: custom program error: 0x1", other_program_id),
+        ];
+
+        assert_eq!(parse_logs(&program_id, &logs), vec![cancelled, config_updated]);
+        assert_eq!(parse_logs(&other_program_id, &logs), vec![not_ours.clone(), not_ours]);
+    }
+
+    #[test]
+    fn from_log_line_test() {
+        let event = EscrowEvent::TokenSwapFilled {
+            swap: Pubkey::new_unique(),
+            taker: Pubkey::new_unique(),
+            amount: 40,
+            paid: 20,
+            remaining_offered_amount: 60,
+        };
+        assert_eq!(EscrowEvent::from_log_line(&event_line(&event)), Some(event));
+        assert_eq!(EscrowEvent::from_log_line("Program log: Instruction: Exchange"), None);
+    }
+}
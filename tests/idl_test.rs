@@ -0,0 +1,283 @@
+#[cfg(all(test, feature = "idl"))]
+mod tests {
+    use serde_json::Value;
+    use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+    use escrow_buy::{
+        idl::{idl, IdlAccountLayout},
+        instruction::EscrowInstruction,
+        state::{EditionDrop, Escrow, ListingMode, Marketplace, ReferrerAccount, TokenProgram, TokenSwap, VaultAccount, STATE_VERSION},
+    };
+
+    fn field_bytes<'a>(layout: &IdlAccountLayout, data: &'a [u8], name: &str) -> &'a [u8] {
+        let field = layout
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap_or_else(|| panic!("{} has no field {}", layout.name, name));
+        &data[field.offset..field.offset + field.size]
+    }
+
+    fn layout(name: &str) -> IdlAccountLayout {
+        idl().accounts.into_iter().find(|layout| layout.name == name).unwrap()
+    }
+
+    #[test]
+    fn committed_idl_test() {
+        let committed: Value =
+            serde_json::from_str(include_str!("../idl/escrow_buy.json")).unwrap();
+        assert!(
+            committed == idl().to_json(),
+            "idl/escrow_buy.json is out of date, regenerate it with \
+             `cargo run --features idl --bin escrow-idl -- idl/escrow_buy.json`"
+        );
+    }
+
+    #[test]
+    fn account_layouts_test() {
+        let seller = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let escrow = Escrow {
+            is_initialized: true,
+            seller_pubkey: seller,
+            token_account_pubkey: Pubkey::new_unique(),
+            mint_key: mint,
+            expected_amount: 123,
+            marketplace: Pubkey::new_unique(),
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: true,
+        };
+        let mut data = vec![0; Escrow::LEN];
+        escrow.pack_into_slice(&mut data);
+        let escrow_layout = layout("Escrow");
+        assert_eq!(field_bytes(&escrow_layout, &data, "account_type"), &[escrow_layout.account_type]);
+        assert_eq!(field_bytes(&escrow_layout, &data, "version"), &[STATE_VERSION]);
+        assert_eq!(field_bytes(&escrow_layout, &data, "is_initialized"), &[1]);
+        assert_eq!(field_bytes(&escrow_layout, &data, "seller_pubkey"), seller.as_ref());
+        assert_eq!(
+            field_bytes(&escrow_layout, &data, "token_account_pubkey"),
+            escrow.token_account_pubkey.as_ref()
+        );
+        assert_eq!(field_bytes(&escrow_layout, &data, "mint_key"), mint.as_ref());
+        assert_eq!(field_bytes(&escrow_layout, &data, "expected_amount"), &123u64.to_le_bytes());
+        assert_eq!(field_bytes(&escrow_layout, &data, "marketplace"), escrow.marketplace.as_ref());
+        assert_eq!(field_bytes(&escrow_layout, &data, "listing_mode"), &[1]);
+        assert_eq!(field_bytes(&escrow_layout, &data, "usd_priced"), &[0]);
+        assert_eq!(field_bytes(&escrow_layout, &data, "proceeds_vault"), &[1]);
+
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: true,
+            pass_collection: Pubkey::new_unique(),
+            pass_fee_basis_points: 100,
+            referral_basis_points: 2000,
+            escrow_royalties: false,
+        };
+        let mut data = vec![0; VaultAccount::LEN];
+        platform.pack_into_slice(&mut data);
+        let platform_layout = layout("VaultAccount");
+        assert_eq!(field_bytes(&platform_layout, &data, "account_type"), &[platform_layout.account_type]);
+        assert_eq!(
+            field_bytes(&platform_layout, &data, "treasury_account"),
+            platform.treasury_account.as_ref()
+        );
+        assert_eq!(field_bytes(&platform_layout, &data, "base_percentage"), &250u64.to_le_bytes());
+        assert_eq!(field_bytes(&platform_layout, &data, "paused"), &[1]);
+        assert_eq!(
+            field_bytes(&platform_layout, &data, "pass_collection"),
+            platform.pass_collection.as_ref()
+        );
+        assert_eq!(field_bytes(&platform_layout, &data, "pass_fee_basis_points"), &100u64.to_le_bytes());
+        assert_eq!(field_bytes(&platform_layout, &data, "referral_basis_points"), &2000u64.to_le_bytes());
+
+        let mut name = [0; 32];
+        name[..6].copy_from_slice(b"market");
+        let marketplace = Marketplace {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            name,
+            platform,
+        };
+        let mut data = vec![0; Marketplace::LEN];
+        marketplace.pack_into_slice(&mut data);
+        let marketplace_layout = layout("Marketplace");
+        assert_eq!(field_bytes(&marketplace_layout, &data, "account_type"), &[marketplace_layout.account_type]);
+        assert_eq!(field_bytes(&marketplace_layout, &data, "authority"), marketplace.authority.as_ref());
+        assert_eq!(field_bytes(&marketplace_layout, &data, "creator"), marketplace.creator.as_ref());
+        assert_eq!(field_bytes(&marketplace_layout, &data, "name"), &name);
+        assert_eq!(
+            field_bytes(&marketplace_layout, &data, "treasury_account"),
+            platform.treasury_account.as_ref()
+        );
+        assert_eq!(field_bytes(&marketplace_layout, &data, "base_percentage"), &250u64.to_le_bytes());
+        assert_eq!(field_bytes(&marketplace_layout, &data, "paused"), &[1]);
+        assert_eq!(
+            field_bytes(&marketplace_layout, &data, "pass_collection"),
+            platform.pass_collection.as_ref()
+        );
+        assert_eq!(field_bytes(&marketplace_layout, &data, "pass_fee_basis_points"), &100u64.to_le_bytes());
+        assert_eq!(field_bytes(&marketplace_layout, &data, "referral_basis_points"), &2000u64.to_le_bytes());
+
+        let referrer = ReferrerAccount {
+            is_initialized: true,
+            referrer: Pubkey::new_unique(),
+            total_earned: 1234,
+            sales: 5,
+        };
+        let mut data = vec![0; ReferrerAccount::LEN];
+        referrer.pack_into_slice(&mut data);
+        let referrer_layout = layout("ReferrerAccount");
+        assert_eq!(field_bytes(&referrer_layout, &data, "account_type"), &[referrer_layout.account_type]);
+        assert_eq!(field_bytes(&referrer_layout, &data, "referrer"), referrer.referrer.as_ref());
+        assert_eq!(field_bytes(&referrer_layout, &data, "total_earned"), &1234u64.to_le_bytes());
+        assert_eq!(field_bytes(&referrer_layout, &data, "sales"), &5u64.to_le_bytes());
+
+        let swap = TokenSwap {
+            is_initialized: true,
+            seller_pubkey: seller,
+            vault_pubkey: Pubkey::new_unique(),
+            seller_receive_pubkey: Pubkey::new_unique(),
+            offered_mint: mint,
+            expected_mint: Pubkey::new_unique(),
+            offered_amount: 100,
+            expected_amount: 50,
+            native_payout: true,
+            escrow_authority_bump_seed: 0,
+        };
+        let mut data = vec![0; TokenSwap::LEN];
+        swap.pack_into_slice(&mut data);
+        let swap_layout = layout("TokenSwap");
+        assert_eq!(field_bytes(&swap_layout, &data, "account_type"), &[swap_layout.account_type]);
+        assert_eq!(field_bytes(&swap_layout, &data, "seller_pubkey"), seller.as_ref());
+        assert_eq!(field_bytes(&swap_layout, &data, "vault_pubkey"), swap.vault_pubkey.as_ref());
+        assert_eq!(
+            field_bytes(&swap_layout, &data, "seller_receive_pubkey"),
+            swap.seller_receive_pubkey.as_ref()
+        );
+        assert_eq!(field_bytes(&swap_layout, &data, "offered_mint"), mint.as_ref());
+        assert_eq!(field_bytes(&swap_layout, &data, "expected_mint"), swap.expected_mint.as_ref());
+        assert_eq!(field_bytes(&swap_layout, &data, "offered_amount"), &100u64.to_le_bytes());
+        assert_eq!(field_bytes(&swap_layout, &data, "expected_amount"), &50u64.to_le_bytes());
+        assert_eq!(field_bytes(&swap_layout, &data, "native_payout"), &[1]);
+
+        let drop = EditionDrop {
+            is_initialized: true,
+            creator: seller,
+            master_token_account: Pubkey::new_unique(),
+            master_mint: mint,
+            marketplace: Pubkey::new_unique(),
+            price: 1000,
+            max_prints: 10,
+            prints_sold: 3,
+            escrow_authority_bump_seed: 0,
+        };
+        let mut data = vec![0; EditionDrop::LEN];
+        drop.pack_into_slice(&mut data);
+        let drop_layout = layout("EditionDrop");
+        assert_eq!(field_bytes(&drop_layout, &data, "account_type"), &[drop_layout.account_type]);
+        assert_eq!(field_bytes(&drop_layout, &data, "creator"), seller.as_ref());
+        assert_eq!(
+            field_bytes(&drop_layout, &data, "master_token_account"),
+            drop.master_token_account.as_ref()
+        );
+        assert_eq!(field_bytes(&drop_layout, &data, "master_mint"), mint.as_ref());
+        assert_eq!(field_bytes(&drop_layout, &data, "marketplace"), drop.marketplace.as_ref());
+        assert_eq!(field_bytes(&drop_layout, &data, "price"), &1000u64.to_le_bytes());
+        assert_eq!(field_bytes(&drop_layout, &data, "max_prints"), &10u64.to_le_bytes());
+        assert_eq!(field_bytes(&drop_layout, &data, "prints_sold"), &3u64.to_le_bytes());
+    }
+
+    #[test]
+    fn instruction_args_test() {
+        let instructions = idl().instructions;
+        let samples = vec![
+            EscrowInstruction::ListToken { amount: 11 },
+            EscrowInstruction::Exchange { amount: 12 },
+            EscrowInstruction::Cancel,
+            EscrowInstruction::UpdatePlatformAccount { amount: 13 },
+            EscrowInstruction::ListTokenSwap {
+                offered_amount: 14,
+                expected_amount: 15,
+            },
+            EscrowInstruction::ExchangeTokenSwap {
+                amount: 16,
+                expected_amount: 17,
+            },
+            EscrowInstruction::CancelTokenSwap,
+            EscrowInstruction::SetPaused { paused: true },
+            EscrowInstruction::ForceDelist { reason: 3 },
+            EscrowInstruction::ExchangeWithOptions {
+                amount: 18,
+                options: 3,
+            },
+            EscrowInstruction::SetPassDiscount { pass_fee_basis_points: 19 },
+            EscrowInstruction::SetReferralShare { referral_basis_points: 20 },
+            EscrowInstruction::CreateMarketplace {
+                name: [3; 32],
+                fee_basis_points: 21,
+            },
+            EscrowInstruction::ListTokenDelegated { amount: 22 },
+            EscrowInstruction::ListTokenFrozen { amount: 23 },
+            EscrowInstruction::ListEditionDrop {
+                price: 24,
+                max_prints: 25,
+            },
+            EscrowInstruction::BuyEdition { edition: 26 },
+            EscrowInstruction::CancelEditionDrop,
+            EscrowInstruction::ListTokenSwapForSol {
+                offered_amount: 27,
+                expected_amount: 28,
+            },
+            EscrowInstruction::ExchangeTokenSwapWithSol {
+                amount: 29,
+                expected_amount: 30,
+            },
+            EscrowInstruction::ListTokenUsd { usd_price: 31 },
+            EscrowInstruction::ExchangeUsd {
+                amount: 32,
+                max_lamports: 33,
+                options: 3,
+            },
+            EscrowInstruction::SetEscrowRoyalties { escrow_royalties: true },
+            EscrowInstruction::ClaimRoyalties,
+            EscrowInstruction::SetProceedsVault { proceeds_vault: true },
+            EscrowInstruction::WithdrawProceeds,
+            EscrowInstruction::RegisterReferrer,
+        ];
+        assert_eq!(instructions.len(), samples.len());
+
+        let mut expected_value = 11u64;
+        for (instruction, sample) in instructions.iter().zip(&samples) {
+            let data = sample.pack();
+            assert_eq!(data[2], instruction.discriminant, "{}", instruction.name);
+            for arg in &instruction.args {
+                let bytes = &data[arg.offset..arg.offset + arg.size];
+                match arg.ty {
+                    "u64" => {
+                        assert_eq!(bytes, &expected_value.to_le_bytes(), "{}.{}", instruction.name, arg.name);
+                        expected_value += 1;
+                    }
+                    "bool" => assert_eq!(bytes, &[1], "{}.{}", instruction.name, arg.name),
+                    "u8" => assert_eq!(bytes, &[3], "{}.{}", instruction.name, arg.name),
+                    "[u8; 32]" => assert_eq!(bytes, &[3; 32], "{}.{}", instruction.name, arg.name),
+                    ty => panic!("unexpected argument type {}", ty),
+                }
+            }
+            // the legacy tag of the instructions older than the versioned
+            // encoding is the same as the discriminant
+            if instruction.discriminant <= 6 {
+                let mut legacy = vec![instruction.discriminant];
+                legacy.extend_from_slice(&data[3..]);
+                assert_eq!(&EscrowInstruction::unpack(&legacy).unwrap(), sample);
+            }
+        }
+    }
+}
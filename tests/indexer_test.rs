@@ -0,0 +1,272 @@
+#[cfg(all(test, feature = "indexer"))]
+mod tests {
+    use solana_program::pubkey::Pubkey;
+
+    use escrow_buy::{
+        events::{EscrowEvent, RoyaltyPayout},
+        indexer::{Index, ListingStatus},
+        state::{Escrow, ListingMode, TokenProgram},
+    };
+
+    fn escrow(seller: &Pubkey, mint: &Pubkey, price: u64) -> Escrow {
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: *seller,
+            token_account_pubkey: Pubkey::new_unique(),
+            mint_key: *mint,
+            expected_amount: price,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+    }
+
+    fn tombstone() -> Escrow {
+        Escrow {
+            is_initialized: false,
+            seller_pubkey: Pubkey::default(),
+            token_account_pubkey: Pubkey::default(),
+            mint_key: Pubkey::default(),
+            expected_amount: 0,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+    }
+
+    #[test]
+    fn floor_and_seller_listings_test() {
+        let index = Index::open_in_memory().unwrap();
+        let seller = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let other_collection = Pubkey::new_unique();
+
+        let cheap = Pubkey::new_unique();
+        let expensive = Pubkey::new_unique();
+        let elsewhere = Pubkey::new_unique();
+        index
+            .apply_account(&expensive, &escrow(&seller, &Pubkey::new_unique(), 300), Some(&collection))
+            .unwrap();
+        index
+            .apply_account(&cheap, &escrow(&seller, &Pubkey::new_unique(), 120), Some(&collection))
+            .unwrap();
+        index
+            .apply_account(&elsewhere, &escrow(&Pubkey::new_unique(), &Pubkey::new_unique(), 50), Some(&other_collection))
+            .unwrap();
+
+        assert_eq!(index.floor_price(&collection).unwrap(), Some(120));
+        let mut floors = vec![(collection, 120), (other_collection, 50)];
+        floors.sort_by_key(|(collection, _)| collection.to_string());
+        assert_eq!(index.floor_prices().unwrap(), floors);
+
+        let listings = index.seller_listings(&seller).unwrap();
+        assert_eq!(
+            listings.iter().map(|listing| listing.escrow).collect::<Vec<_>>(),
+            vec![cheap, expensive]
+        );
+
+        // the cheapest listing is closed, found uninitialized by an account scan
+        index.apply_account(&cheap, &tombstone(), None).unwrap();
+        assert_eq!(index.get_listing(&cheap).unwrap().unwrap().status, ListingStatus::Closed);
+        assert_eq!(index.floor_price(&collection).unwrap(), Some(300));
+        assert_eq!(index.seller_listings(&seller).unwrap().len(), 1);
+        // a tombstone of an unknown listing is not indexed
+        index.apply_account(&Pubkey::new_unique(), &tombstone(), None).unwrap();
+        assert_eq!(index.floor_prices().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn apply_events_test() {
+        let index = Index::open_in_memory().unwrap();
+        let seller = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let sold_escrow = Pubkey::new_unique();
+        let cancelled_escrow = Pubkey::new_unique();
+
+        index
+            .apply_event(
+                "list",
+                1,
+                &EscrowEvent::Listed {
+                    escrow: sold_escrow,
+                    seller,
+                    mint,
+                    token_account: Pubkey::new_unique(),
+                    price: 123,
+                },
+            )
+            .unwrap();
+        // the account scan resolves the collection of the listing
+        index
+            .apply_account(&sold_escrow, &escrow(&seller, &mint, 123), Some(&collection))
+            .unwrap();
+        // replaying the event does not forget it
+        index
+            .apply_event(
+                "list",
+                1,
+                &EscrowEvent::Listed {
+                    escrow: sold_escrow,
+                    seller,
+                    mint,
+                    token_account: Pubkey::new_unique(),
+                    price: 123,
+                },
+            )
+            .unwrap();
+        assert_eq!(index.mint_listings(&mint).unwrap()[0].collection, Some(collection));
+        assert_eq!(index.floor_price(&collection).unwrap(), Some(123));
+
+        let sold = EscrowEvent::Sold {
+            escrow: sold_escrow,
+            seller,
+            buyer,
+            mint,
+            price: 123,
+            royalties: vec![
+                RoyaltyPayout {
+                    creator: Pubkey::new_unique(),
+                    amount: 20,
+                },
+                RoyaltyPayout {
+                    creator: Pubkey::new_unique(),
+                    amount: 14,
+                },
+            ],
+            treasury: Pubkey::new_unique(),
+            platform_fee: 3,
+            seller_proceeds: 86,
+        };
+        index.apply_event("buy", 2, &sold).unwrap();
+        index.apply_event("buy", 2, &sold).unwrap();
+
+        let listing = index.get_listing(&sold_escrow).unwrap().unwrap();
+        assert_eq!(listing.status, ListingStatus::Sold);
+        // a tombstone found after the sale keeps it a sale
+        index.apply_account(&sold_escrow, &tombstone(), None).unwrap();
+        assert_eq!(index.get_listing(&sold_escrow).unwrap().unwrap().status, ListingStatus::Sold);
+        assert_eq!(index.floor_price(&collection).unwrap(), None);
+
+        let sales = index.mint_sales(&mint).unwrap();
+        assert_eq!(sales.len(), 1);
+        assert_eq!(sales[0].signature, "buy");
+        assert_eq!(sales[0].slot, 2);
+        assert_eq!(sales[0].buyer, buyer);
+        assert_eq!(sales[0].royalties, 34);
+        assert_eq!(sales[0].platform_fee, 3);
+        assert_eq!(sales[0].seller_proceeds, 86);
+
+        index
+            .apply_event(
+                "list again",
+                3,
+                &EscrowEvent::Listed {
+                    escrow: cancelled_escrow,
+                    seller: buyer,
+                    mint,
+                    token_account: Pubkey::new_unique(),
+                    price: 200,
+                },
+            )
+            .unwrap();
+        assert_eq!(index.seller_listings(&buyer).unwrap().len(), 1);
+        index
+            .apply_event(
+                "cancel",
+                4,
+                &EscrowEvent::Cancelled {
+                    escrow: cancelled_escrow,
+                    seller: buyer,
+                    mint,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            index.get_listing(&cancelled_escrow).unwrap().unwrap().status,
+            ListingStatus::Cancelled
+        );
+        assert!(index.mint_listings(&mint).unwrap().is_empty());
+
+        // a listing taken down by the admin
+        let delisted_escrow = Pubkey::new_unique();
+        index
+            .apply_account(&delisted_escrow, &escrow(&seller, &mint, 90), Some(&collection))
+            .unwrap();
+        assert_eq!(index.floor_price(&collection).unwrap(), Some(90));
+        index
+            .apply_event(
+                "delist",
+                5,
+                &EscrowEvent::ForceDelisted {
+                    escrow: delisted_escrow,
+                    seller,
+                    mint,
+                    reason: 2,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            index.get_listing(&delisted_escrow).unwrap().unwrap().status,
+            ListingStatus::Delisted
+        );
+        assert_eq!(index.floor_price(&collection).unwrap(), None);
+    }
+
+    // every print of a drop is a sale of its own mint, the drop has no listing
+    #[test]
+    fn apply_edition_sold_test() {
+        let index = Index::open_in_memory().unwrap();
+        let drop = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let print_mint = Pubkey::new_unique();
+
+        let sold = EscrowEvent::EditionSold {
+            drop,
+            seller: creator,
+            buyer,
+            mint: print_mint,
+            edition: 1,
+            price: 1000,
+            royalties: vec![RoyaltyPayout {
+                creator,
+                amount: 975,
+            }],
+            treasury: Pubkey::new_unique(),
+            platform_fee: 25,
+            seller_proceeds: 0,
+            remaining_prints: 9,
+        };
+        index.apply_event("print", 7, &sold).unwrap();
+        index.apply_event("print", 7, &sold).unwrap();
+
+        let sales = index.mint_sales(&print_mint).unwrap();
+        assert_eq!(sales.len(), 1);
+        assert_eq!(sales[0].escrow, drop);
+        assert_eq!(sales[0].seller, creator);
+        assert_eq!(sales[0].buyer, buyer);
+        assert_eq!(sales[0].price, 1000);
+        assert_eq!(sales[0].royalties, 975);
+        assert_eq!(sales[0].platform_fee, 25);
+        assert_eq!(index.get_listing(&drop).unwrap(), None);
+    }
+
+    #[test]
+    fn log_cursor_test() {
+        let index = Index::open_in_memory().unwrap();
+        assert_eq!(index.log_cursor().unwrap(), None);
+        index.set_log_cursor("first").unwrap();
+        index.set_log_cursor("second").unwrap();
+        assert_eq!(index.log_cursor().unwrap(), Some("second".to_string()));
+    }
+}
@@ -35,6 +35,57 @@ mod tests {
         assert_eq!(
             result,
             EscrowInstruction::Cancel
-        )
+        );
+
+        let swap_data = [
+            4, 100, 0, 0, 0, 0, 0, 0, 0,
+            50, 0, 0, 0, 0, 0, 0, 0
+        ];
+        result = EscrowInstruction::unpack(&swap_data).unwrap();
+        assert_eq!(
+            result,
+            EscrowInstruction::ListTokenSwap { offered_amount: 100, expected_amount: 50 }
+        );
+
+        // both amounts are required
+        assert!(EscrowInstruction::unpack(&swap_data[..9]).is_err());
+    }
+
+    // Instruction data pack test, every variant round-trips
+    // through the versioned format
+    #[test]
+    fn instruction_pack_test() {
+        let instructions = [
+            EscrowInstruction::ListToken { amount: 123 },
+            EscrowInstruction::Exchange { amount: 123 },
+            EscrowInstruction::Cancel,
+            EscrowInstruction::UpdatePlatformAccount { amount: 250 },
+            EscrowInstruction::ListTokenSwap { offered_amount: 100, expected_amount: 50 },
+            EscrowInstruction::ExchangeTokenSwap { amount: 40, expected_amount: 20 },
+            EscrowInstruction::CancelTokenSwap,
+            EscrowInstruction::SetPaused { paused: true },
+            EscrowInstruction::ForceDelist { reason: 3 },
+        ];
+        for instruction in instructions {
+            let packed = instruction.pack();
+            assert_eq!(packed[0], VERSIONED_INSTRUCTION_MARKER);
+            assert_eq!(packed[1], INSTRUCTION_VERSION);
+            assert_eq!(EscrowInstruction::unpack(&packed).unwrap(), instruction);
+        }
+
+        // the legacy encoding of a listing still decodes
+        let legacy = [0, 123, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            EscrowInstruction::unpack(&legacy).unwrap(),
+            EscrowInstruction::ListToken { amount: 123 }
+        );
+
+        // unknown versions and trailing bytes are rejected
+        let mut packed = EscrowInstruction::Cancel.pack();
+        packed[1] = INSTRUCTION_VERSION + 1;
+        assert!(EscrowInstruction::unpack(&packed).is_err());
+        let mut packed = EscrowInstruction::Cancel.pack();
+        packed.push(0);
+        assert!(EscrowInstruction::unpack(&packed).is_err());
     }
-}
\ No newline at end of file
+}
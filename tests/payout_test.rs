@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use metaplex_token_metadata::state::Creator;
+    use solana_program::pubkey::Pubkey;
+
+    use escrow_buy::{
+        events::RoyaltyPayout,
+        payout::{compute_payout, compute_primary_payout, Payout},
+    };
+
+    // unit test for splitting the price of a sale
+    #[test]
+    fn compute_payout_test() {
+        let creators = [
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: true,
+                share: 70,
+            },
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 30,
+            },
+        ];
+
+        // 4000 bps of 123 is 49, 70% of it 34 and 30% 14,
+        // 250 bps of 123 is 3 and the seller gets the rest
+        assert_eq!(
+            compute_payout(123, 4000, &creators, 250),
+            Some(Payout {
+                royalties: vec![
+                    RoyaltyPayout {
+                        creator: creators[0].address,
+                        amount: 34,
+                    },
+                    RoyaltyPayout {
+                        creator: creators[1].address,
+                        amount: 14,
+                    },
+                ],
+                platform_fee: 3,
+                seller_proceeds: 72,
+            })
+        );
+
+        // without creators the seller keeps the royalties
+        assert_eq!(
+            compute_payout(10_000, 500, &[], 100),
+            Some(Payout {
+                royalties: vec![],
+                platform_fee: 100,
+                seller_proceeds: 9_900,
+            })
+        );
+
+        // fees larger than the price are refused
+        assert_eq!(compute_payout(100, 10_000, &creators, 100), None);
+        assert_eq!(compute_payout(u64::MAX, 2, &creators, 0), None);
+    }
+
+    // unit test for splitting the price of a print edition
+    #[test]
+    fn compute_primary_payout_test() {
+        let creators = [
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: true,
+                share: 70,
+            },
+            Creator {
+                address: Pubkey::new_unique(),
+                verified: false,
+                share: 30,
+            },
+        ];
+
+        // 250 bps of 1001 is 25, 70% of the 976 left 683 and 30% 292,
+        // the seller gets what rounding leaves
+        assert_eq!(
+            compute_primary_payout(1001, &creators, 250),
+            Some(Payout {
+                royalties: vec![
+                    RoyaltyPayout {
+                        creator: creators[0].address,
+                        amount: 683,
+                    },
+                    RoyaltyPayout {
+                        creator: creators[1].address,
+                        amount: 292,
+                    },
+                ],
+                platform_fee: 25,
+                seller_proceeds: 1,
+            })
+        );
+
+        // without creators the seller gets everything but the fee
+        assert_eq!(
+            compute_primary_payout(10_000, &[], 100),
+            Some(Payout {
+                royalties: vec![],
+                platform_fee: 100,
+                seller_proceeds: 9_900,
+            })
+        );
+
+        // a fee larger than the price is refused
+        assert_eq!(compute_primary_payout(100, &creators, 20_000), None);
+    }
+}
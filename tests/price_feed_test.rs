@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use solana_program::{account_info::AccountInfo, clock::Clock, program_error::ProgramError, pubkey::Pubkey};
+
+    use escrow_buy::{
+        error::EscrowError,
+        price_feed::{oracle_program_id, sol_usd_price_feed_id, PriceFeed, PRICE_ACCOUNT_LEN, PRICE_STATUS_TRADING},
+    };
+
+    // $25.00 per SOL, published at slot 100 and second 1000
+    fn feed() -> PriceFeed {
+        PriceFeed {
+            exponent: -8,
+            timestamp: 1000,
+            price: 2_500_000_000,
+            confidence: 1_000_000,
+            status: PRICE_STATUS_TRADING,
+            publish_slot: 100,
+        }
+    }
+
+    fn clock(slot: u64, unix_timestamp: i64) -> Clock {
+        Clock {
+            slot,
+            unix_timestamp,
+            ..Clock::default()
+        }
+    }
+
+    #[test]
+    fn price_feed_pack_unpack_test() {
+        let mut data = vec![0; PRICE_ACCOUNT_LEN];
+        feed().pack_into_slice(&mut data);
+        assert_eq!(PriceFeed::unpack(&data).unwrap(), feed());
+
+        // only price accounts of the known version are read
+        let mut product_account = data.clone();
+        product_account[8] = 2;
+        assert_eq!(PriceFeed::unpack(&product_account), Err(ProgramError::InvalidAccountData));
+        let mut other_version = data.clone();
+        other_version[4] = 1;
+        assert_eq!(PriceFeed::unpack(&other_version), Err(ProgramError::InvalidAccountData));
+        assert_eq!(PriceFeed::unpack(&data[..200]), Err(ProgramError::InvalidAccountData));
+    }
+
+    // loads `feed()` from a price account at `key` owned by `owner`
+    fn load(key: &Pubkey, owner: &Pubkey) -> Result<PriceFeed, ProgramError> {
+        let mut lamports = 0;
+        let mut data = vec![0; PRICE_ACCOUNT_LEN];
+        feed().pack_into_slice(&mut data);
+        let account = AccountInfo::new(key, false, false, &mut lamports, &mut data, owner, false, 0);
+        PriceFeed::load(&account)
+    }
+
+    // listings are only priced with the SOL/USD feed, a feed of another asset of the
+    // same oracle program, BTC/USD say, would change what the buyer pays
+    #[test]
+    fn price_feed_load_test() {
+        assert_eq!(load(&sol_usd_price_feed_id(), &oracle_program_id()), Ok(feed()));
+        assert_eq!(
+            load(&Pubkey::new_unique(), &oracle_program_id()),
+            Err(EscrowError::UnsupportedPriceFeed.into())
+        );
+        assert_eq!(
+            load(&sol_usd_price_feed_id(), &Pubkey::new_unique()),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+
+    #[test]
+    fn price_feed_conversion_test() {
+        let now = clock(110, 1030);
+
+        // $50.00 is 2 SOL at $25.00
+        assert_eq!(feed().lamports_for(50_000_000, &now), Ok(2_000_000_000));
+        // rounded up to the next lamport
+        assert_eq!(feed().lamports_for(1, &now), Ok(40));
+        assert_eq!(
+            PriceFeed {
+                price: 3_000_000_000,
+                confidence: 0,
+                ..feed()
+            }
+            .lamports_for(1, &now),
+            Ok(34)
+        );
+
+        // the same price at another exponent
+        let coarse = PriceFeed {
+            exponent: 2,
+            price: 0,
+            confidence: 0,
+            ..feed()
+        };
+        assert_eq!(
+            PriceFeed { price: 1, ..coarse }.lamports_for(100_000_000, &now),
+            Ok(1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn price_feed_stale_test() {
+        let stale = Err(EscrowError::StalePriceFeed.into());
+        assert_eq!(feed().lamports_for(1_000_000, &clock(126, 1000)), stale);
+        assert_eq!(feed().lamports_for(1_000_000, &clock(100, 1061)), stale);
+        assert!(feed().lamports_for(1_000_000, &clock(125, 1060)).is_ok());
+        assert_eq!(
+            PriceFeed { status: 0, ..feed() }.lamports_for(1_000_000, &clock(100, 1000)),
+            stale
+        );
+    }
+
+    #[test]
+    fn price_feed_confidence_test() {
+        let now = clock(100, 1000);
+        let uncertain = Err(EscrowError::UncertainPriceFeed.into());
+
+        // up to 2% of the price
+        assert!(PriceFeed { confidence: 50_000_000, ..feed() }.lamports_for(1_000_000, &now).is_ok());
+        assert_eq!(
+            PriceFeed { confidence: 50_000_001, ..feed() }.lamports_for(1_000_000, &now),
+            uncertain
+        );
+        assert_eq!(
+            PriceFeed { price: 0, confidence: 0, ..feed() }.lamports_for(1_000_000, &now),
+            uncertain
+        );
+        assert_eq!(
+            PriceFeed { price: -1, confidence: 0, ..feed() }.lamports_for(1_000_000, &now),
+            uncertain
+        );
+    }
+}
@@ -0,0 +1,342 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        entrypoint::ProgramResult,
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_associated_token_account::get_associated_token_address;
+    use spl_token::state::{Account as TokenAccount, Mint};
+
+    use escrow_buy::{
+        error::EscrowError,
+        instruction::EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT,
+        pda::find_metadata_address,
+        processor::Processor,
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    fn escrow_authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], &escrow_program_id()).0
+    }
+
+    fn platform_test_account(treasury: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn escrow_test_account(seller: &Pubkey, token: &Pubkey, mint: &Pubkey, listing_mode: ListingMode) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; Escrow::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: *seller,
+            token_account_pubkey: *token,
+            mint_key: *mint,
+            expected_amount: 1000,
+            marketplace: Pubkey::default(),
+            listing_mode,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn token_test_account(token: TokenAccount) -> TestAccount {
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        token.pack_into_slice(&mut account.data);
+        account
+    }
+
+    // the seller's token account, with the escrow authority approved for the token
+    fn delegated_token(mint: &Pubkey, seller: &Pubkey) -> TokenAccount {
+        TokenAccount {
+            mint: *mint,
+            owner: *seller,
+            amount: 1,
+            delegate: COption::Some(escrow_authority()),
+            delegated_amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+    }
+
+    fn mint_test_account() -> TestAccount {
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; Mint::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Mint {
+            supply: 1,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn metadata_test_account(mint: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_METADATA_LEN],
+            ..TestAccount::default()
+        };
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: *mint,
+            data: Data {
+                name: String::from("name"),
+                symbol: String::from("symbol"),
+                uri: String::from("uri"),
+                seller_fee_basis_points: 0,
+                creators: Some(Vec::<Creator>::new()),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut account.data[..])
+        .unwrap();
+        account
+    }
+
+    // buys a listing in `listing_mode` whose token account holds `token`, sending
+    // the token to `buyer_token_account(taker, mint)` when it returns one
+    fn exchange(
+        listing_mode: ListingMode,
+        token: impl Fn(&Pubkey, &Pubkey) -> TokenAccount,
+        buyer_token_account: impl Fn(&Pubkey, &Pubkey) -> Option<Pubkey>,
+    ) -> ProgramResult {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let taker_pubkey = Pubkey::new_unique();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let treasury_pubkey = Pubkey::new_unique();
+        let (metadata_pubkey, _metadata_bump_seed) = find_metadata_address(&mint_pubkey);
+        let buyer_token_pubkey = buyer_token_account(&taker_pubkey, &mint_pubkey);
+
+        let mut escrow_account = escrow_test_account(&seller_pubkey, &token_pubkey, &mint_pubkey, listing_mode);
+        let mut token_account = token_test_account(token(&mint_pubkey, &seller_pubkey));
+        let mut metadata_account = metadata_test_account(&mint_pubkey);
+        let mut platform_account = platform_test_account(&treasury_pubkey);
+        let mut taker_account = TestAccount::default();
+        let mut seller_account = TestAccount::default();
+        let mut mint_account = mint_test_account();
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut treasury_account = TestAccount::default();
+        let mut buyer_token_account = TestAccount::default();
+
+        let mut accounts = vec![
+            (&taker_pubkey, true, &mut taker_account),
+            (&token_pubkey, false, &mut token_account),
+            (&seller_pubkey, false, &mut seller_account),
+            (&mint_pubkey, false, &mut mint_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::ID, false, &mut token_program_account),
+            (&system_program::ID, false, &mut system_program_account),
+            (&pda, false, &mut pda_account),
+            (&metadata_pubkey, false, &mut metadata_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&treasury_pubkey, false, &mut treasury_account),
+        ];
+        let options = match &buyer_token_pubkey {
+            Some(buyer_token_pubkey) => {
+                accounts.push((buyer_token_pubkey, false, &mut buyer_token_account));
+                EXCHANGE_OPTION_BUYER_TOKEN_ACCOUNT
+            }
+            None => 0,
+        };
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_exchange_with_options(&accounts, 1, options, &escrow_program_id)
+    }
+
+    fn associated_token_account(taker: &Pubkey, mint: &Pubkey) -> Option<Pubkey> {
+        Some(get_associated_token_address(taker, mint))
+    }
+
+    #[test]
+    fn exchange_delegated_listing_test() {
+        exchange(ListingMode::Delegate, delegated_token, associated_token_account).unwrap();
+    }
+
+    // the seller revoked the approval or moved the token after listing it
+    #[test]
+    fn exchange_invalidated_listing_test() {
+        let invalidated = Err(EscrowError::ListingInvalidated.into());
+        assert_eq!(
+            exchange(
+                ListingMode::Delegate,
+                |mint, seller| TokenAccount {
+                    delegate: COption::None,
+                    delegated_amount: 0,
+                    ..delegated_token(mint, seller)
+                },
+                associated_token_account,
+            ),
+            invalidated
+        );
+        assert_eq!(
+            exchange(
+                ListingMode::Delegate,
+                |mint, seller| TokenAccount {
+                    amount: 0,
+                    ..delegated_token(mint, seller)
+                },
+                associated_token_account,
+            ),
+            invalidated
+        );
+        assert_eq!(
+            exchange(
+                ListingMode::Delegate,
+                |mint, _seller| delegated_token(mint, &Pubkey::new_unique()),
+                associated_token_account,
+            ),
+            invalidated
+        );
+    }
+
+    // the token account of a frozen listing stays frozen until it is sold
+    #[test]
+    fn exchange_frozen_listing_test() {
+        let frozen_token = |mint: &Pubkey, seller: &Pubkey| TokenAccount {
+            state: spl_token::state::AccountState::Frozen,
+            ..delegated_token(mint, seller)
+        };
+        exchange(ListingMode::Frozen, frozen_token, associated_token_account).unwrap();
+
+        let invalidated = Err(EscrowError::ListingInvalidated.into());
+        assert_eq!(
+            exchange(ListingMode::Frozen, delegated_token, associated_token_account),
+            invalidated
+        );
+        assert_eq!(
+            exchange(ListingMode::Delegate, frozen_token, associated_token_account),
+            invalidated
+        );
+        assert_eq!(
+            exchange(ListingMode::Frozen, frozen_token, |_taker, _mint| None),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    // the token of a delegated listing only goes to the taker's associated token account
+    #[test]
+    fn exchange_buyer_token_account_test() {
+        assert_eq!(
+            exchange(ListingMode::Delegate, delegated_token, |_taker, _mint| None),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            exchange(ListingMode::Delegate, delegated_token, |_taker, mint| {
+                associated_token_account(&Pubkey::new_unique(), mint)
+            }),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // a listing in custody hands over its token account instead
+        let escrowed_token = |mint: &Pubkey, _seller: &Pubkey| TokenAccount {
+            mint: *mint,
+            owner: escrow_authority(),
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        exchange(ListingMode::Custody, escrowed_token, |_taker, _mint| None).unwrap();
+        assert_eq!(
+            exchange(ListingMode::Custody, escrowed_token, associated_token_account),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    fn cancel(token: TokenAccount, seller_pubkey: &Pubkey) -> Result<Escrow, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let token_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+
+        let mut seller_account = TestAccount::default();
+        let mut token_account = token_test_account(token);
+        let mut escrow_account =
+            escrow_test_account(seller_pubkey, &token_pubkey, &token.mint, ListingMode::Delegate);
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+
+        let mut accounts = [
+            (seller_pubkey, true, &mut seller_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::ID, false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_cancel(&accounts, &escrow_program_id)?;
+
+        let escrow = Escrow::unpack_unchecked(&accounts[2].data.borrow())?;
+        Ok(escrow)
+    }
+
+    // a delegated listing is closed even when the seller already revoked the approval
+    #[test]
+    fn cancel_delegated_listing_test() {
+        let seller = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        assert!(!cancel(delegated_token(&mint, &seller), &seller).unwrap().is_initialized);
+
+        let revoked = TokenAccount {
+            delegate: COption::None,
+            delegated_amount: 0,
+            ..delegated_token(&mint, &seller)
+        };
+        assert!(!cancel(revoked, &seller).unwrap().is_initialized);
+    }
+}
@@ -0,0 +1,434 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{
+        Creator, Data, Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+    };
+    use solana_program::{
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+        sysvar,
+    };
+    use solana_sdk::{
+        account::create_account_for_test,
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        error::EscrowError,
+        pda::{find_edition_address, find_edition_marker_address, find_metadata_address},
+        processor::Processor,
+        state::{EditionDrop, VaultAccount},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    fn escrow_authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], &escrow_program_id()).0
+    }
+
+    fn platform_test_account(treasury: &Pubkey, paused: bool) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn token_test_account(mint: &Pubkey, owner: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn master_edition_test_account(supply: u64, max_supply: Option<u64>) -> TestAccount {
+        let mut account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_MASTER_EDITION_LEN],
+            ..TestAccount::default()
+        };
+        MasterEditionV2 {
+            key: Key::MasterEditionV2,
+            supply,
+            max_supply,
+        }
+        .serialize(&mut &mut account.data[..])
+        .unwrap();
+        account
+    }
+
+    fn metadata_test_account(mint: &Pubkey, update_authority: &Pubkey, creators: Vec<Creator>) -> TestAccount {
+        let mut account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_METADATA_LEN],
+            ..TestAccount::default()
+        };
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: *update_authority,
+            mint: *mint,
+            data: Data {
+                name: String::from("name"),
+                symbol: String::from("symbol"),
+                uri: String::from("uri"),
+                seller_fee_basis_points: 500,
+                creators: Some(creators),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut account.data[..])
+        .unwrap();
+        account
+    }
+
+    fn drop_test_account(drop: EditionDrop) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; EditionDrop::get_packed_len()],
+            ..TestAccount::default()
+        };
+        drop.pack_into_slice(&mut account.data);
+        account
+    }
+
+    // lists a drop of `max_prints` prints of a master edition that already printed
+    // `supply` of at most `max_supply`
+    fn list(max_prints: u64, supply: u64, max_supply: Option<u64>) -> Result<EditionDrop, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let creator_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let drop_pubkey = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let (master_edition_pubkey, _master_edition_bump_seed) = find_edition_address(&mint_pubkey);
+
+        let mut creator_account = TestAccount::default();
+        let mut token_account = token_test_account(&mint_pubkey, &creator_pubkey);
+        let mut mint_account = TestAccount {
+            owner: spl_token::id(),
+            ..TestAccount::default()
+        };
+        // created by the system program, which is not run here
+        let mut drop_account = TestAccount::new(0, EditionDrop::get_packed_len(), &escrow_program_id);
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&Pubkey::new_unique(), false);
+        let mut master_edition_account = master_edition_test_account(supply, max_supply);
+
+        let mut accounts = [
+            (&creator_pubkey, true, &mut creator_account),
+            (&token_pubkey, false, &mut token_account),
+            (&mint_pubkey, false, &mut mint_account),
+            (&drop_pubkey, true, &mut drop_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&master_edition_pubkey, false, &mut master_edition_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_list_edition_drop(&accounts, 1000, max_prints, &escrow_program_id)?;
+
+        let drop = EditionDrop::unpack(&accounts[3].data.borrow())?;
+        assert_eq!(drop.creator, creator_pubkey);
+        assert_eq!(drop.master_token_account, token_pubkey);
+        assert_eq!(drop.master_mint, mint_pubkey);
+        assert_eq!(drop.marketplace, platform_pubkey);
+        Ok(drop)
+    }
+
+    #[test]
+    fn list_edition_drop_test() {
+        let drop = list(10, 0, None).unwrap();
+        assert_eq!(drop.price, 1000);
+        assert_eq!(drop.max_prints, 10);
+        assert_eq!(drop.prints_sold, 0);
+
+        // the prints of the drop have to fit in what the master edition has left
+        list(10, 5, Some(15)).unwrap();
+        assert_eq!(list(10, 6, Some(15)), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(list(0, 0, None), Err(ProgramError::InvalidInstructionData));
+    }
+
+    struct BuyAccounts {
+        drop: EditionDrop,
+        creators: Vec<Creator>,
+        // creator addresses passed to the instruction
+        passed_creators: Vec<Pubkey>,
+        paused: bool,
+        token_program: Pubkey,
+    }
+
+    // buys a print of `drop`, returning the drop afterwards
+    fn buy(test: BuyAccounts) -> Result<EditionDrop, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let buyer_pubkey = Pubkey::new_unique();
+        let drop_pubkey = Pubkey::new_unique();
+        let new_mint_pubkey = Pubkey::new_unique();
+        let update_authority_pubkey = Pubkey::new_unique();
+        let treasury_pubkey = Pubkey::new_unique();
+        let edition = test.drop.prints_sold + 1;
+        let (new_metadata_pubkey, _new_metadata_bump_seed) = find_metadata_address(&new_mint_pubkey);
+        let (new_edition_pubkey, _new_edition_bump_seed) = find_edition_address(&new_mint_pubkey);
+        let (master_edition_pubkey, _master_edition_bump_seed) = find_edition_address(&test.drop.master_mint);
+        let (edition_marker_pubkey, _edition_marker_bump_seed) =
+            find_edition_marker_address(&test.drop.master_mint, edition);
+        let (master_metadata_pubkey, _master_metadata_bump_seed) = find_metadata_address(&test.drop.master_mint);
+
+        let mut buyer_account = TestAccount::default();
+        let mut drop_account = drop_test_account(test.drop);
+        let mut seller_account = TestAccount::default();
+        let mut new_mint_account = TestAccount::default();
+        let mut new_metadata_account = TestAccount::default();
+        let mut new_edition_account = TestAccount::default();
+        let mut master_edition_account = master_edition_test_account(test.drop.prints_sold, None);
+        let mut edition_marker_account = TestAccount::default();
+        let mut master_token_account = token_test_account(&test.drop.master_mint, &pda);
+        let mut master_metadata_account =
+            metadata_test_account(&test.drop.master_mint, &update_authority_pubkey, test.creators);
+        let mut update_authority_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut metadata_program_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&treasury_pubkey, test.paused);
+        let mut treasury_account = TestAccount::default();
+        let mut creator_accounts = vec![TestAccount::default(); test.passed_creators.len()];
+
+        let metadata_program_id = metaplex_token_metadata::id();
+        let mut accounts = vec![
+            (&buyer_pubkey, true, &mut buyer_account),
+            (&drop_pubkey, false, &mut drop_account),
+            (&test.drop.creator, false, &mut seller_account),
+            (&new_mint_pubkey, false, &mut new_mint_account),
+            (&new_metadata_pubkey, false, &mut new_metadata_account),
+            (&new_edition_pubkey, false, &mut new_edition_account),
+            (&master_edition_pubkey, false, &mut master_edition_account),
+            (&edition_marker_pubkey, false, &mut edition_marker_account),
+            (&test.drop.master_token_account, false, &mut master_token_account),
+            (&master_metadata_pubkey, false, &mut master_metadata_account),
+            (&update_authority_pubkey, false, &mut update_authority_account),
+            (&pda, false, &mut pda_account),
+            (&test.token_program, false, &mut token_program_account),
+            (&system_program::ID, false, &mut system_program_account),
+            (&sysvar::rent::ID, false, &mut rent_account),
+            (&metadata_program_id, false, &mut metadata_program_account),
+            (&test.drop.marketplace, false, &mut platform_account),
+            (&treasury_pubkey, false, &mut treasury_account),
+        ];
+        for (creator, account) in test.passed_creators.iter().zip(creator_accounts.iter_mut()) {
+            accounts.push((creator, false, account));
+        }
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_buy_edition(&accounts, edition, &escrow_program_id)?;
+
+        let drop = EditionDrop::unpack_unchecked(&accounts[1].data.borrow())?;
+        Ok(drop)
+    }
+
+    fn running_drop(creator: &Pubkey, prints_sold: u64) -> EditionDrop {
+        EditionDrop {
+            is_initialized: true,
+            creator: *creator,
+            master_token_account: Pubkey::new_unique(),
+            master_mint: Pubkey::new_unique(),
+            marketplace: Pubkey::new_unique(),
+            price: 1000,
+            max_prints: 3,
+            prints_sold,
+            escrow_authority_bump_seed: 0,
+        }
+    }
+
+    fn creator(address: &Pubkey, share: u8) -> Creator {
+        Creator {
+            address: *address,
+            verified: true,
+            share,
+        }
+    }
+
+    #[test]
+    fn buy_edition_test() {
+        let artist = Pubkey::new_unique();
+        let collaborator = Pubkey::new_unique();
+        let creators = vec![creator(&artist, 60), creator(&collaborator, 40)];
+
+        let drop = buy(BuyAccounts {
+            drop: running_drop(&artist, 0),
+            creators: creators.clone(),
+            passed_creators: vec![artist, collaborator],
+            paused: false,
+            token_program: spl_token::id(),
+        })
+        .unwrap();
+        assert!(drop.is_initialized);
+        assert_eq!(drop.prints_sold, 1);
+
+        // the last print ends the drop
+        let drop = buy(BuyAccounts {
+            drop: running_drop(&artist, 2),
+            creators: creators.clone(),
+            passed_creators: vec![artist, collaborator],
+            paused: false,
+            token_program: spl_token::id(),
+        })
+        .unwrap();
+        assert!(!drop.is_initialized);
+        assert_eq!(drop.prints_sold, 3);
+
+        // the creator accounts are matched by key, in any order, and all of them are needed
+        let drop = buy(BuyAccounts {
+            drop: running_drop(&artist, 0),
+            creators: creators.clone(),
+            passed_creators: vec![collaborator, artist],
+            paused: false,
+            token_program: spl_token::id(),
+        })
+        .unwrap();
+        assert_eq!(drop.prints_sold, 1);
+        assert_eq!(
+            buy(BuyAccounts {
+                drop: running_drop(&artist, 0),
+                creators: creators.clone(),
+                passed_creators: vec![artist],
+                paused: false,
+                token_program: spl_token::id(),
+            }),
+            Err(EscrowError::MissingCreatorAccount.into())
+        );
+
+        assert_eq!(
+            buy(BuyAccounts {
+                drop: running_drop(&artist, 0),
+                creators: creators.clone(),
+                passed_creators: vec![artist, collaborator],
+                paused: true,
+                token_program: spl_token::id(),
+            }),
+            Err(EscrowError::MarketplacePaused.into())
+        );
+
+        // the last print releases the master edition through the SPL Token program only
+        assert_eq!(
+            buy(BuyAccounts {
+                drop: running_drop(&artist, 2),
+                creators,
+                passed_creators: vec![artist, collaborator],
+                paused: false,
+                token_program: Pubkey::new_unique(),
+            }),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+
+    // a sold out or cancelled drop prints no more
+    #[test]
+    fn buy_ended_edition_drop_test() {
+        let artist = Pubkey::new_unique();
+        assert_eq!(
+            buy(BuyAccounts {
+                drop: running_drop(&artist, 3),
+                creators: vec![creator(&artist, 100)],
+                passed_creators: vec![artist],
+                paused: false,
+                token_program: spl_token::id(),
+            }),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            buy(BuyAccounts {
+                drop: EditionDrop {
+                    is_initialized: false,
+                    ..running_drop(&artist, 1)
+                },
+                creators: vec![creator(&artist, 100)],
+                passed_creators: vec![artist],
+                paused: false,
+                token_program: spl_token::id(),
+            }),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    fn cancel(drop: EditionDrop, signer: &Pubkey, token_program: &Pubkey) -> Result<EditionDrop, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let drop_pubkey = Pubkey::new_unique();
+
+        let mut creator_account = TestAccount::default();
+        let mut master_token_account = token_test_account(&drop.master_mint, &pda);
+        let mut drop_account = drop_test_account(drop);
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+
+        let mut accounts = [
+            (signer, true, &mut creator_account),
+            (&drop.master_token_account, false, &mut master_token_account),
+            (&drop_pubkey, false, &mut drop_account),
+            (token_program, false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_cancel_edition_drop(&accounts, &escrow_program_id)?;
+
+        let drop = EditionDrop::unpack_unchecked(&accounts[2].data.borrow())?;
+        Ok(drop)
+    }
+
+    #[test]
+    fn cancel_edition_drop_test() {
+        let artist = Pubkey::new_unique();
+        let drop = cancel(running_drop(&artist, 1), &artist, &spl_token::id()).unwrap();
+        assert!(!drop.is_initialized);
+        assert_eq!(drop.prints_sold, 1);
+
+        assert_eq!(
+            cancel(running_drop(&artist, 1), &Pubkey::new_unique(), &spl_token::id()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // the escrow authority releases the master edition through the SPL Token program only
+        assert_eq!(
+            cancel(running_drop(&artist, 1), &artist, &Pubkey::new_unique()),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+}
@@ -1,11 +1,12 @@
 #[cfg(feature = "test-bpf")]
-use escrow_buy::{processor, state::Escrow};
+use escrow_buy::{instruction, processor, state::VaultAccount};
 #[cfg(feature = "test-bpf")]
-use solana_program::{instruction::{AccountMeta, Instruction}, program_pack::Pack, pubkey::Pubkey, rent::Rent, sysvar};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
 #[cfg(feature = "test-bpf")]
 use solana_program_test::{ProgramTest, processor};
 #[cfg(feature = "test-bpf")]
 use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
 use std::str::FromStr;
 
 
@@ -13,18 +14,12 @@ use std::str::FromStr;
 #[cfg(feature = "test-bpf")]
 #[should_panic]
 async fn process_escrow_init_fail() {
-    let mut data = [0u8; 9];
-    hex::decode_to_slice("007b00000000000000", &mut data as &mut [u8]).unwrap();
-
-    let amount: u64 = 123;
-    data[1] = 0;
+    // listing for nothing is refused
+    let amount: u64 = 0;
     let escrow_program_id = Pubkey::from_str(
-        &"escrowprogram111111111111111111111111111111"
+        "escrowprogram111111111111111111111111111111"
     ).unwrap();
 
-    // let sellers token
-    let sellers_token = Keypair::new();
-
     // minters key
     let minter = Keypair::new();
 
@@ -33,113 +28,121 @@ async fn process_escrow_init_fail() {
     let temp_seller_token_account = Keypair::new();
     let mint_key = Keypair::new();
     let escrow_account = Keypair::new();
+    let platform_account = Pubkey::new_unique();
 
     let mut program_test = ProgramTest::new(
         "escrow_buy",
         escrow_program_id,
         processor!(processor::Processor::process)
     );
-
-    // temp token account for the sell token
-    program_test.add_account(
-        temp_seller_token_account.pubkey(),
-        Account {
-            lamports: Rent::default().minimum_balance(
-                spl_token::state::Account::LEN
-            ),
-            owner: spl_token::id(),
-            data: vec![0; spl_token::state::Account::LEN],
-            ..Account::default()
-        }
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
     );
 
-    // initers buy token which will be recieved 
-    // in an exchange to the sell token
+    // the seller pays for the escrow account
     program_test.add_account(
-        mint_key.pubkey(),
+        initers_key.pubkey(),
         Account {
-            lamports: 123,
-            owner: spl_token::id(),
+            lamports: 1_000_000_000,
             ..Account::default()
         }
     );
 
-    // escrow account
+    // platform account listings are made on
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: Pubkey::new_unique(),
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
     program_test.add_account(
-        escrow_account.pubkey(), 
+        platform_account,
         Account {
-            lamports: Rent::default().minimum_balance(Escrow::get_packed_len()),
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
             owner: escrow_program_id,
-            data: vec![0; Escrow::get_packed_len()],
-            ..Account::default()
-        },
-    );
-    
-    // TEST
-    // this is a mint token account, this mint will
-    // be stored in the temp sell token account
-    program_test.add_account(
-        sellers_token.pubkey(), 
-        Account {
-            lamports: Rent::default().minimum_balance(spl_token::state::Mint::LEN),
-            owner: spl_token::id(), 
-            data: vec![0; spl_token::state::Mint::LEN],
+            data: platform_data,
             ..Account::default()
-        },
+        }
     );
 
     let (mut client, payer, recent_blockhash) = program_test.start().await;
     println!("Creating a mint and minting it to temp token account...");
 
+    let rent = Rent::default();
     let mut transaction = Transaction::new_with_payer(
         &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint_key.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+
             spl_token::instruction::initialize_mint(
                 &spl_token::id(),
-                &sellers_token.pubkey(),
+                &mint_key.pubkey(),
                 &minter.pubkey(),
                 None,
                 0
             ).unwrap(),
 
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+
             spl_token::instruction::initialize_account(
                 &spl_token::id(),
                 &temp_seller_token_account.pubkey(),
-                &sellers_token.pubkey(),
+                &mint_key.pubkey(),
                 &initers_key.pubkey()
             ).unwrap(),
 
             spl_token::instruction::mint_to(
                 &spl_token::id(),
-                &sellers_token.pubkey(),
+                &mint_key.pubkey(),
                 &temp_seller_token_account.pubkey(),
                 &minter.pubkey(),
                 &[],
-                amount
+                1
             ).unwrap()
         ],
         Some(&payer.pubkey())
     );
-    transaction.sign(&[&payer, &minter], recent_blockhash);
+    transaction.sign(
+        &[&payer, &minter, &mint_key, &temp_seller_token_account],
+        recent_blockhash
+    );
     client.process_transaction(transaction).await.unwrap();
     println!("Done...");
 
     let mut transaction = Transaction::new_with_payer(
         &[
-            Instruction::new_with_bytes(
-                escrow_program_id,
-                &data,
-                vec![
-                    AccountMeta::new(initers_key.pubkey(), true),
-                    AccountMeta::new(temp_seller_token_account.pubkey(), false),
-                    AccountMeta::new(mint_key.pubkey(), false),
-                    AccountMeta::new(escrow_account.pubkey(), false),
-                    AccountMeta::new(sysvar::rent::id(), false),
-                    AccountMeta::new(spl_token::id(), false)
-                ],
-            )
+            instruction::list_token(
+                &escrow_program_id,
+                &initers_key.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &mint_key.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                amount
+            ).unwrap()
         ],
         Some(&payer.pubkey())
     );
-    transaction.sign(&[&payer, &initers_key], recent_blockhash);
+    transaction.sign(&[&payer, &initers_key, &escrow_account], recent_blockhash);
     client.process_transaction(transaction).await.unwrap();
-}
\ No newline at end of file
+}
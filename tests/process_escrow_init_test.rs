@@ -4,21 +4,23 @@ mod tests {
     // this trait to generate pubkey from_str
     use std::str::FromStr;
     use solana_program::{
+        program_error::ProgramError,
         program_pack::Pack,
         rent::Rent,
         sysvar,
-        pubkey::Pubkey
+        pubkey::Pubkey,
+        system_program
     };
     use solana_sdk::{
         account::create_account_for_test,
         account::create_is_signer_account_infos,
-        account::Account as TestAccount,
-        account::WritableAccount
+        account::Account as TestAccount
     };
 
     use escrow_buy::{
+        error::EscrowError,
         processor::Processor,
-        state::Escrow
+        state::{Escrow, VaultAccount}
     };
 
     // Init escrow test
@@ -26,7 +28,7 @@ mod tests {
     fn init_escrow_test() {
         // create a program id for escrow program
         let escrow_program_id = Pubkey::from_str(
-            &"escrowprogram111111111111111111111111111111"
+            "escrowprogram111111111111111111111111111111"
         ).unwrap();
 
         // create rent account
@@ -48,13 +50,34 @@ mod tests {
         // initializers account
         let mut initializer_account = TestAccount::default();
 
-        // temp token account
-        let mut temp_token_account = TestAccount::default();
-
         // token recieve account
         // set its owner field to token program id
-        let mut mint_key = TestAccount::default();
-        mint_key.set_owner(token_program_id);
+        let mint_pubkey = Pubkey::new_unique();
+        let mut mint_key = TestAccount {
+            owner: token_program_id,
+            data: vec![0; spl_token::state::Mint::get_packed_len()],
+            ..TestAccount::default()
+        };
+        spl_token::state::Mint {
+            supply: 1,
+            is_initialized: true,
+            ..spl_token::state::Mint::default()
+        }
+        .pack_into_slice(&mut mint_key.data);
+
+        // temp token account holding the NFT
+        let mut temp_token_account = TestAccount {
+            owner: token_program_id,
+            data: vec![0; spl_token::state::Account::get_packed_len()],
+            ..TestAccount::default()
+        };
+        spl_token::state::Account {
+            mint: mint_pubkey,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        }
+        .pack_into_slice(&mut temp_token_account.data);
 
         // 3. escrow account with required lamports
         let mut escrow_account = TestAccount::new(
@@ -66,6 +89,12 @@ mod tests {
         // 5. token program account
         let mut token_program_account = TestAccount::default();
 
+        // 6. system program account
+        let mut system_program_account = TestAccount::default();
+
+        // 7. platform account, listing is allowed
+        let mut platform_account = platform_test_account(&escrow_program_id, false);
+
         // create accounts for calling the process_init_escrow
         let mut accounts = [
             (
@@ -79,7 +108,7 @@ mod tests {
                 &mut temp_token_account
             ),
             (
-                &Pubkey::new_unique(), 
+                &mint_pubkey, 
                 true, 
                 &mut mint_key
             ),
@@ -97,6 +126,16 @@ mod tests {
                 &token_program_id,
                 true,
                 &mut token_program_account
+            ),
+            (
+                &system_program::id(),
+                false,
+                &mut system_program_account
+            ),
+            (
+                &Pubkey::new_unique(),
+                false,
+                &mut platform_account
             )
         ];
 
@@ -113,16 +152,140 @@ mod tests {
         let temp_token_account = &accounts[1];
         let mint_key = &accounts[2];
         let escrow_account = &accounts[3];
+        let platform_account = &accounts[7];
 
         let escrow_state = Escrow::unpack(
             &escrow_account.data.borrow()
         ).unwrap();
         
         // assertion tests for escrow account state
-        assert_eq!(escrow_state.is_initialized, true);
+        assert!(escrow_state.is_initialized);
         assert_eq!(escrow_state.seller_pubkey, *initializer_account.key);
         assert_eq!(escrow_state.token_account_pubkey, *temp_token_account.key);
         assert_eq!(escrow_state.mint_key, *mint_key.key);
-        assert_eq!(escrow_state.expected_amount, 123 as u64);
+        assert_eq!(escrow_state.expected_amount, 123_u64);
+        // the listing is bought through the platform account it was made on
+        assert_eq!(escrow_state.marketplace, *platform_account.key);
+    }
+
+    // platform account owned by the escrow program
+    fn platform_test_account(escrow_program_id: &Pubkey, paused: bool) -> TestAccount {
+        let mut platform_account = TestAccount {
+            owner: *escrow_program_id,
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        platform_account
+    }
+
+    // every listing names its platform account, leaving it out cannot get a
+    // listing past a paused platform
+    #[test]
+    fn init_escrow_without_platform_account_test() {
+        let escrow_program_id = Pubkey::from_str(
+            "escrowprogram111111111111111111111111111111"
+        ).unwrap();
+        let mint_pubkey = Pubkey::new_unique();
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut initializer_account = TestAccount::default();
+        let mut temp_token_account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; spl_token::state::Account::get_packed_len()],
+            ..TestAccount::default()
+        };
+        spl_token::state::Account {
+            mint: mint_pubkey,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        }
+        .pack_into_slice(&mut temp_token_account.data);
+        let mut mint_key = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; spl_token::state::Mint::get_packed_len()],
+            ..TestAccount::default()
+        };
+        spl_token::state::Mint {
+            supply: 1,
+            is_initialized: true,
+            ..spl_token::state::Mint::default()
+        }
+        .pack_into_slice(&mut mint_key.data);
+        let mut escrow_account = TestAccount::new(0, Escrow::get_packed_len(), &escrow_program_id);
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&escrow_program_id, true);
+
+        let mut accounts = [
+            (&Pubkey::new_unique(), true, &mut initializer_account),
+            (&Pubkey::new_unique(), false, &mut temp_token_account),
+            (&mint_pubkey, false, &mut mint_key),
+            (&Pubkey::new_unique(), true, &mut escrow_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&Pubkey::new_unique(), false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_init_escrow(&accounts, 123, &escrow_program_id),
+            Err(EscrowError::MarketplacePaused.into())
+        );
+
+        // the paused platform account left out
+        let accounts = &accounts[..7];
+        assert_eq!(
+            Processor::process_init_escrow(accounts, 123, &escrow_program_id),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(
+            Processor::process_list_token_delegated(accounts, 123, &escrow_program_id),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        assert!(!Escrow::unpack_unchecked(&accounts[3].data.borrow()).unwrap().is_initialized);
+    }
+
+    // no listing while the platform is paused
+    #[test]
+    fn init_escrow_paused_test() {
+        let escrow_program_id = Pubkey::from_str(
+            "escrowprogram111111111111111111111111111111"
+        ).unwrap();
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut initializer_account = TestAccount::default();
+        let mut temp_token_account = TestAccount::default();
+        let mut mint_key = TestAccount::default();
+        let mut escrow_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&escrow_program_id, true);
+
+        let mut accounts = [
+            (&Pubkey::new_unique(), true, &mut initializer_account),
+            (&Pubkey::new_unique(), false, &mut temp_token_account),
+            (&Pubkey::new_unique(), false, &mut mint_key),
+            (&Pubkey::new_unique(), true, &mut escrow_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&Pubkey::new_unique(), false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+
+        assert_eq!(
+            Processor::process_init_escrow(&accounts, 123, &escrow_program_id),
+            Err(EscrowError::MarketplacePaused.into())
+        );
     }
 }
\ No newline at end of file
@@ -1,150 +1,91 @@
 #[cfg(feature = "test-bpf")]
-use escrow_buy::{processor, state::Escrow};
+use borsh::BorshSerialize;
 #[cfg(feature = "test-bpf")]
-use solana_program::{instruction::{AccountMeta, Instruction}, program_pack::Pack, pubkey::Pubkey, rent::Rent, sysvar, system_program};
+use escrow_buy::{events::{parse_logs, EscrowEvent, RoyaltyPayout}, instruction, pda, processor, state::{Escrow, VaultAccount}};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
 #[cfg(feature = "test-bpf")]
 use solana_program_test::{ProgramTest, processor};
 #[cfg(feature = "test-bpf")]
 use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::Transaction};
-use std::str::FromStr;
+#[cfg(feature = "test-bpf")]
+use std::{str::FromStr, sync::Mutex};
+
+// program-test writes the program logs of every transaction to the `log`
+// crate, this logger keeps them so the emitted events can be decoded
+#[cfg(feature = "test-bpf")]
+static PROGRAM_LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
+#[cfg(feature = "test-bpf")]
+struct ProgramLogCapture;
+
+#[cfg(feature = "test-bpf")]
+impl log::Log for ProgramLogCapture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "solana_runtime::message_processor::stable_log"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            PROGRAM_LOGS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
 
-#[tokio::test]
 #[cfg(feature = "test-bpf")]
-async fn process_escrow_init_success() {
-    let mut data = [0u8; 9];
-    hex::decode_to_slice("007b00000000000000", &mut data as &mut [u8]).unwrap();
+static PROGRAM_LOG_CAPTURE: ProgramLogCapture = ProgramLogCapture;
+
 
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_exchange_success() {
     let amount: u64 = 123;
     let escrow_program_id = Pubkey::from_str(
-        &"escrowprogram111111111111111111111111111111"
+        "escrowprogram111111111111111111111111111111"
     ).unwrap();
 
-    // // let sellers token
-    // let sellers_token = Keypair::new();
-
-    // // minters key
-    // let minter = Keypair::new();
+    // minters key
+    let minter = Keypair::new();
 
     // required accounts
     let initers_key = Keypair::new();
     let temp_seller_token_account = Keypair::new();
     let mint_key = Keypair::new();
-    let mint_key_pubkey = mint_key.pubkey();
     let escrow_account = Keypair::new();
-    // let metadata_account = Keypair::new();
+
     let taker_keypair = Keypair::new();
-    let taker_to_recieve_keypair = Keypair::new();
     let creators_pubkey = Pubkey::from_str(
-        &"metadatacreatorL5LYvXwxBNSaVkinzjzvTt1j3XsQ"
+        "metadatacreatorL5LYvXwxBNSaVkinzjzvTt1j3XsQ"
     ).unwrap();
     let valhalla_pubkey = Pubkey::from_str(
-        &"paXi61MzXmioYZL5LYvXwxBNSaVkinzjzvTt1j3XsQz"
+        "paXi61MzXmioYZL5LYvXwxBNSaVkinzjzvTt1j3XsQz"
     ).unwrap();
+    let platform_account = Pubkey::new_unique();
 
-    let (pda, _bump_seed) = Pubkey::find_program_address(
-        &[b"escrow"], 
-        &escrow_program_id
-    );
+    // installed before program-test sets up its own logger
+    log::set_logger(&PROGRAM_LOG_CAPTURE).unwrap();
 
     let mut program_test = ProgramTest::new(
         "escrow_buy",
         escrow_program_id,
         processor!(processor::Processor::process)
     );
-
-    // temp token account for the sell token
-    program_test.add_account(
-        temp_seller_token_account.pubkey(),
-        Account {
-            lamports: Rent::default().minimum_balance(
-                spl_token::state::Account::LEN
-            ),
-            owner: spl_token::id(),
-            data: vec![0; spl_token::state::Account::LEN],
-            ..Account::default()
-        }
-    );
-
-    // initers buy token which will be recieved 
-    // in an exchange to the sell token
-    program_test.add_account(
-        mint_key.pubkey(),
-        Account {
-            lamports: 123,
-            owner: spl_token::id(),
-            ..Account::default()
-        }
-    );
-
-    // escrow account
-    program_test.add_account(
-        escrow_account.pubkey(), 
-        Account {
-            lamports: Rent::default().minimum_balance(Escrow::get_packed_len()),
-            owner: escrow_program_id,
-            data: vec![0; Escrow::get_packed_len()],
-            ..Account::default()
-        },
-    );
-
-    const PREFIX: &str = "metadata";
-    // This is the program_id of the token_metadata program
-    let metadata_program_id =
-        Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").unwrap();
-
-    let metadata_seeds = &[
-        PREFIX.as_bytes(),
-        metadata_program_id.as_ref(),
-        mint_key_pubkey.as_ref(),
-    ];
-
-    let (metadata_key, _metadata_bump_seed) =
-        Pubkey::find_program_address(metadata_seeds, &metadata_program_id);
-
-    // token metadata account
-    program_test.add_account(
-        metadata_key,
-        Account {
-            lamports: 5616720,
-            owner: spl_token::id(),
-            data: vec![
-                4, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 13, 0, 0, 0, 72, 101, 108, 108, 111, 44, 
-                32, 119, 111, 114, 108, 100, 33, 13, 0, 0, 0, 72, 101, 108, 108, 111, 44, 32, 
-                119, 111, 114, 108, 100, 33, 13, 0, 0, 0, 72, 101, 108, 108, 111, 44, 32, 119, 
-                111, 114, 108, 100, 33, 160, 15, 1, 1, 0, 0, 0, 11, 112, 101, 173, 83, 76, 32, 
-                208, 98, 85, 112, 157, 31, 154, 198, 47, 233, 236, 44, 180, 235, 183, 174, 111, 
-                61, 22, 187, 135, 21, 148, 93, 83, 1, 70, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-            ],
-            ..Account::default()
-        }
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
     );
 
+    // the seller pays for the escrow account
     program_test.add_account(
         initers_key.pubkey(),
         Account {
-            lamports: 5616720,
+            lamports: 1_000_000_000,
             ..Account::default()
         }
     );
@@ -157,45 +98,107 @@ async fn process_escrow_init_success() {
         }
     );
 
-    // temp token account for token reciever
+    // creator and treasury start out rent exempt so that
+    // they can receive any share of the sale
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    for pubkey in [creators_pubkey, valhalla_pubkey] {
+        program_test.add_account(
+            pubkey,
+            Account {
+                lamports: rent_exempt_minimum,
+                ..Account::default()
+            }
+        );
+    }
+
+    // platform account taking a 250 basis points fee for the treasury
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: valhalla_pubkey,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
     program_test.add_account(
-        taker_to_recieve_keypair.pubkey(),
+        platform_account,
         Account {
-            lamports: Rent::default().minimum_balance(
-                spl_token::state::Account::LEN
-            ),
-            owner: spl_token::id(),
-            data: vec![0; spl_token::state::Account::LEN],
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: escrow_program_id,
+            data: platform_data,
             ..Account::default()
         }
     );
-    
-    // TEST
-    // this is a mint token account, this mint will
-    // be stored in the temp sell token account
+
+    // token metadata account, a single creator with a 70 share
+    // of a 4000 basis points royalty
+    let (metadata_key, metadata_bump_seed) = pda::find_metadata_address(&mint_key.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint_key.pubkey(),
+        data: Data {
+            name: "Hello, world!".to_string(),
+            symbol: "Hello, world!".to_string(),
+            uri: "Hello, world!".to_string(),
+            seller_fee_basis_points: 4000,
+            creators: Some(vec![Creator {
+                address: creators_pubkey,
+                verified: true,
+                share: 70,
+            }]),
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
     program_test.add_account(
-        mint_key.pubkey(), 
+        metadata_key,
         Account {
-            lamports: Rent::default().minimum_balance(spl_token::state::Mint::LEN),
-            owner: spl_token::id(), 
-            data: vec![0; spl_token::state::Mint::LEN],
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
             ..Account::default()
-        },
+        }
     );
 
     let (mut client, payer, recent_blockhash) = program_test.start().await;
     println!("Creating a mint and minting it to temp token account...");
 
+    let rent = Rent::default();
     let mut transaction = Transaction::new_with_payer(
         &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint_key.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+
             spl_token::instruction::initialize_mint(
                 &spl_token::id(),
                 &mint_key.pubkey(),
-                &initers_key.pubkey(),
+                &minter.pubkey(),
                 None,
                 0
             ).unwrap(),
 
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+
             spl_token::instruction::initialize_account(
                 &spl_token::id(),
                 &temp_seller_token_account.pubkey(),
@@ -207,121 +210,80 @@ async fn process_escrow_init_success() {
                 &spl_token::id(),
                 &mint_key.pubkey(),
                 &temp_seller_token_account.pubkey(),
-                &initers_key.pubkey(),
+                &minter.pubkey(),
                 &[],
-                amount
-            ).unwrap(),
-
-            // initialize token account for token reciever
-            spl_token::instruction::initialize_account(
-                &spl_token::id(),
-                &taker_to_recieve_keypair.pubkey(),
-                &mint_key.pubkey(),
-                &taker_keypair.pubkey()
-            ).unwrap(),
+                1
+            ).unwrap()
         ],
         Some(&payer.pubkey())
     );
-    transaction.sign(&[&payer, &initers_key], recent_blockhash);
+    transaction.sign(
+        &[&payer, &minter, &mint_key, &temp_seller_token_account],
+        recent_blockhash
+    );
     client.process_transaction(transaction).await.unwrap();
     println!("Done...");
 
     let mut transaction = Transaction::new_with_payer(
         &[
-            Instruction::new_with_bytes(
-                escrow_program_id,
-                &data,
-                vec![
-                    AccountMeta::new(initers_key.pubkey(), true),
-                    AccountMeta::new(temp_seller_token_account.pubkey(), false),
-                    AccountMeta::new(mint_key.pubkey(), false),
-                    AccountMeta::new(escrow_account.pubkey(), false),
-                    AccountMeta::new(sysvar::rent::id(), false),
-                    AccountMeta::new(spl_token::id(), false)
-                ],
-            )
+            instruction::list_token(
+                &escrow_program_id,
+                &initers_key.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &mint_key.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                amount
+            ).unwrap()
         ],
         Some(&payer.pubkey())
     );
-
-    let temp_seller_token_account_test = client.get_account(
-        temp_seller_token_account.pubkey()
-    ).await
-    .expect("Some Error happened")
-    .expect("Some Error happened");
-
-    // to assert after exchange if token is transferred
-    // to the reciever's token account
-    let mint = spl_token::state::Account::unpack(
-        &temp_seller_token_account_test.data
-    ).unwrap().mint;
-
-    transaction.sign(&[&payer, &initers_key], recent_blockhash);
+    transaction.sign(&[&payer, &initers_key, &escrow_account], recent_blockhash);
     client.process_transaction(transaction).await.unwrap();
 
-    // assert tests after the process_escrow_init has been completed
     let escrow_account_test = client.get_account(
         escrow_account.pubkey()
     ).await
     .expect("Unable to find escrow account")
     .expect("Unable to find escrow account");
 
-    let temp_token_account = client.get_account(
-        temp_seller_token_account.pubkey()
-    ).await
-    .expect("Unable to find escrow account")
-    .expect("Unable to find escrow account");
-
     let escrow_account_state = Escrow::unpack(
         &escrow_account_test.data
     ).unwrap();
-
-    let token_account_state = spl_token::state::Account::unpack(
-        &temp_token_account.data
-    ).unwrap();
-
-    // check if temp token account owner is now the pda
-    // and not the initializer
-    assert_ne!(token_account_state.owner, initers_key.pubkey());
-
+    assert!(escrow_account_state.is_initialized);
+    // the listing records the bump seeds the sale recreates its pdas from
     assert_eq!(
-        escrow_account_state.is_initialized, 
-        true
-    );
-
-    data[0] = 1;
-    let mut transaction = Transaction::new_with_payer(
-        &[
-            Instruction::new_with_bytes(
-                escrow_program_id,
-                &data,
-                vec![
-                    AccountMeta::new(taker_keypair.pubkey(), true),
-                    AccountMeta::new(taker_to_recieve_keypair.pubkey(), false),
-                    AccountMeta::new(temp_seller_token_account.pubkey(), false),
-                    AccountMeta::new(initers_key.pubkey(), false),
-                    AccountMeta::new(mint_key.pubkey(), false),
-                    AccountMeta::new(escrow_account.pubkey(), false),
-                    AccountMeta::new(spl_token::id(), false),
-                    AccountMeta::new(system_program::id(), false),
-                    AccountMeta::new(pda, false),
-                    AccountMeta::new(metadata_key, false),
-                    AccountMeta::new(valhalla_pubkey, false),
-                    AccountMeta::new(creators_pubkey, false)
-                ],
-            )
-        ],
-        Some(&payer.pubkey())
+        escrow_account_state.escrow_authority_bump_seed,
+        pda::find_escrow_authority(&escrow_program_id).1
     );
+    assert_eq!(escrow_account_state.metadata_bump_seed, metadata_bump_seed);
 
     let initers_test_before = client.get_account(
         initers_key.pubkey()
     ).await
-    .expect("Error while finding creators account")
-    .expect("Error while finding creators account");
+    .expect("Error while finding initers account")
+    .expect("Error while finding initers account");
 
     println!("Initers account {:?}", initers_test_before);
 
+    // the taker takes the single NFT of the token account
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::exchange(
+                &escrow_program_id,
+                &taker_keypair.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &initers_key.pubkey(),
+                &mint_key.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                &valhalla_pubkey,
+                &[creators_pubkey],
+                1
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
     transaction.sign(&[&payer, &taker_keypair], recent_blockhash);
     client.process_transaction(transaction).await.unwrap();
 
@@ -336,14 +298,14 @@ async fn process_escrow_init_success() {
         &escrow_account_test.data
     ).unwrap();
 
-    let taker_token_recieve_account_test = client.get_account(
-        taker_to_recieve_keypair.pubkey()
+    let temp_token_account = client.get_account(
+        temp_seller_token_account.pubkey()
     ).await
-    .expect("Error while finding taker token to recieve account")
-    .expect("Error while finding taker token to recieve account");
+    .expect("Error while finding the escrowed token account")
+    .expect("Error while finding the escrowed token account");
 
-    let taker_token_recieve_account_test_state = spl_token::state::Account::unpack(
-        &taker_token_recieve_account_test.data
+    let token_account_state = spl_token::state::Account::unpack(
+        &temp_token_account.data
     ).unwrap();
 
     let creators_test = client.get_account(
@@ -355,34 +317,31 @@ async fn process_escrow_init_success() {
     let valhalla_test = client.get_account(
         valhalla_pubkey
     ).await
-    .expect("Error while finding creators account")
-    .expect("Error while finding creators account");
+    .expect("Error while finding treasury account")
+    .expect("Error while finding treasury account");
 
     let initers_test = client.get_account(
         initers_key.pubkey()
     ).await
-    .expect("Error while finding creators account")
-    .expect("Error while finding creators account");
+    .expect("Error while finding initers account")
+    .expect("Error while finding initers account");
 
     let taker_test = client.get_account(
         taker_keypair.pubkey()
     ).await
-    .expect("Error while finding creators account")
-    .expect("Error while finding creators account");
+    .expect("Error while finding taker account")
+    .expect("Error while finding taker account");
 
     // assertion tests
 
     // assert if escrow state is_initialized false
-    assert_eq!(
-        escrow_account_state.is_initialized, 
-        false
-    );
+    assert!(!escrow_account_state.is_initialized);
 
-    // assert of token reciever has got the token into 
-    // their account after the exchange
-    assert_eq!(mint, taker_token_recieve_account_test_state.mint);
+    // the taker now owns the token account holding the NFT
+    assert_eq!(token_account_state.owner, taker_keypair.pubkey());
+    assert_eq!(token_account_state.mint, mint_key.pubkey());
+    assert_eq!(token_account_state.amount, 1);
 
-    // initial creator account lamport was 0
     // escrow amount - 123
     // seller_fee_basis_points - 4000
     // creator share - 70
@@ -390,33 +349,55 @@ async fn process_escrow_init_success() {
     // formula used in process Exchange
     // (4000 * 123) / 10000 = 49.2
     // (70*49.2) / 100 = 34.44 round down to 34
-    assert_eq!(creators_test.lamports, 34);
+    assert_eq!(creators_test.lamports, rent_exempt_minimum + 34);
 
     // size = 123
     // val share = 250
     // amount to be sent to valhalla - (123*250) / 10000 = 3.075 round down to 3
     // i think decimals are stripped out because lamports
     // are itself the smallest unit of SOL
-
-    assert_eq!(valhalla_test.lamports, 3);
+    assert_eq!(valhalla_test.lamports, rent_exempt_minimum + 3);
 
     // assert if takers lamports are debited by 123
     // taker account initial lamports are 5616720
-    assert_eq!(taker_test.lamports, 5616720-123);
-
-    // seller paid 2039280 lamports for creating a 
-    // associated temp token account while escrow init
-    // now that the exchange is done, the temp token amount
-    // will be closed, and the lamports needed to rent_exempt
-    // the account will be refunded to sellers account
-    // so the seller's account's lamport will be now
-    // seller account initial lamports are 5616720
-    // 5616720 + 2039280 + 86 (86 as the share he will get, after the sale)
-    assert_eq!(initers_test.lamports, 5616720 + 2039280 + 86);
+    assert_eq!(taker_test.lamports, 5616720 - 123);
+
+    // the seller gets what is left after the royalties
+    // and the platform fee
+    assert_eq!(initers_test.lamports, initers_test_before.lamports + 86);
 
     // we have initialized the listing of NFT with 123 lamports
     // 86 - sellers share
     // 3 - valhalla's share
     // 34 - creators share
     // 86 + 3 + 34 = 123
-}
\ No newline at end of file
+
+    // the listing and the sale can be rebuilt from the program logs
+    let events = parse_logs(&escrow_program_id, &PROGRAM_LOGS.lock().unwrap());
+    assert_eq!(
+        events,
+        vec![
+            EscrowEvent::Listed {
+                escrow: escrow_account.pubkey(),
+                seller: initers_key.pubkey(),
+                mint: mint_key.pubkey(),
+                token_account: temp_seller_token_account.pubkey(),
+                price: amount,
+            },
+            EscrowEvent::Sold {
+                escrow: escrow_account.pubkey(),
+                seller: initers_key.pubkey(),
+                buyer: taker_keypair.pubkey(),
+                mint: mint_key.pubkey(),
+                price: amount,
+                royalties: vec![RoyaltyPayout {
+                    creator: creators_pubkey,
+                    amount: 34,
+                }],
+                treasury: valhalla_pubkey,
+                platform_fee: 3,
+                seller_proceeds: 86,
+            },
+        ]
+    );
+}
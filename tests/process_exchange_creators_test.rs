@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use solana_program::{
+        entrypoint::ProgramResult,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        error::EscrowError,
+        pda::{find_escrow_authority, find_metadata_address},
+        processor::Processor,
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+    };
+
+    // sells a listing of a mint with `creators` in its metadata, passing the
+    // accounts of `creator_accounts` after the treasury
+    fn exchange(creators: &[(Pubkey, u8)], creator_accounts: &[Pubkey]) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let price = 1_000_000;
+        let seller = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let platform_account = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let (escrow_authority, escrow_authority_bump_seed) = find_escrow_authority(&program_id);
+        let (metadata_key, metadata_bump_seed) = find_metadata_address(&mint);
+
+        let mut escrow_account = TestAccount {
+            owner: program_id,
+            data: vec![0; Escrow::LEN],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: seller,
+            token_account_pubkey: token_account,
+            mint_key: mint,
+            expected_amount: price,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed,
+            metadata_bump_seed,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut escrow_account.data);
+
+        let mut token_account_data = TestAccount {
+            owner: escrow_authority,
+            data: vec![0; TokenAccount::LEN],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut token_account_data.data);
+
+        let mut platform_account_data = TestAccount {
+            owner: program_id,
+            data: vec![0; VaultAccount::LEN],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account_data.data);
+
+        let mut metadata_account = TestAccount::new(0, MAX_METADATA_LEN, &Pubkey::new_unique());
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint,
+            data: Data {
+                name: String::from("Hello, world!"),
+                symbol: String::from("Hello, world!"),
+                uri: String::from("Hello, world!"),
+                seller_fee_basis_points: 1000,
+                creators: Some(
+                    creators
+                        .iter()
+                        .map(|(address, share)| Creator {
+                            address: *address,
+                            verified: true,
+                            share: *share,
+                        })
+                        .collect(),
+                ),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut metadata_account.data[..])
+        .unwrap();
+
+        let taker = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let system_program_id = system_program::id();
+        let mut keyed_accounts = vec![
+            (taker, TestAccount::default()),
+            (token_account, token_account_data),
+            (seller, TestAccount::default()),
+            (mint, TestAccount::default()),
+            (Pubkey::new_unique(), escrow_account),
+            (token_program_id, TestAccount::default()),
+            (system_program_id, TestAccount::default()),
+            (escrow_authority, TestAccount::default()),
+            (metadata_key, metadata_account),
+            (platform_account, platform_account_data),
+            (treasury, TestAccount::default()),
+        ];
+        keyed_accounts.extend(creator_accounts.iter().map(|creator| (*creator, TestAccount::default())));
+
+        let mut accounts: Vec<_> = keyed_accounts
+            .iter_mut()
+            .map(|(key, account)| (&*key, *key == taker, account))
+            .collect();
+        let accounts = create_is_signer_account_infos(&mut accounts);
+
+        Processor::process_exchange(&accounts, 1, &program_id)
+    }
+
+    // creators are matched by key, not by their position in the metadata
+    #[test]
+    fn exchange_creators_in_any_order() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        exchange(&[(first, 60), (second, 40)], &[first, second]).unwrap();
+        exchange(&[(first, 60), (second, 40)], &[second, first]).unwrap();
+    }
+
+    // a creator without a share is owed nothing and needs no account
+    #[test]
+    fn exchange_without_zero_share_creator() {
+        let candy_machine = Pubkey::new_unique();
+        let artist = Pubkey::new_unique();
+        exchange(&[(candy_machine, 0), (artist, 100)], &[artist]).unwrap();
+        exchange(&[(candy_machine, 0), (artist, 100)], &[artist, candy_machine]).unwrap();
+    }
+
+    #[test]
+    fn exchange_missing_creator() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        assert_eq!(
+            exchange(&[(first, 60), (second, 40)], &[second]),
+            Err(EscrowError::MissingCreatorAccount.into())
+        );
+        assert_eq!(
+            exchange(&[(first, 60), (second, 40)], &[second, Pubkey::new_unique()]),
+            Err(EscrowError::MissingCreatorAccount.into())
+        );
+    }
+
+    #[test]
+    fn exchange_duplicate_creator() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        assert_eq!(
+            exchange(&[(first, 60), (second, 40)], &[first, second, first]),
+            Err(EscrowError::DuplicateCreatorAccount.into())
+        );
+    }
+}
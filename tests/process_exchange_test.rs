@@ -25,15 +25,16 @@ mod tests {
     use spl_token::state::Account as TokenAccount;
     
     use escrow_buy::{
+        pda::find_metadata_address,
         processor::Processor,
-        state::Escrow
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount}
     };
     
     // escrow exchange test
     #[test]
     fn process_process_exchange() {
         let escrow_program_id = Pubkey::from_str(
-            &"escrowprogram111111111111111111111111111111"
+            "escrowprogram111111111111111111111111111111"
         ).unwrap();
 
         let initializer_pubkey = Pubkey::new_unique();
@@ -48,13 +49,13 @@ mod tests {
         let system_program_id = system_program::id();
         let mut system_program_account = TestAccount::default();
 
-        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id); // temp_token_account owner pubkey
+        let (pda, escrow_authority_bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id); // temp_token_account owner pubkey
 
         let amount = 123;
 
         // setup escrow account
         let mut escrow_account = TestAccount {
-            owner: pda,
+            owner: escrow_program_id,
             data: vec![0; Escrow::get_packed_len()],
             ..TestAccount::default()
         };
@@ -64,6 +65,14 @@ mod tests {
             token_account_pubkey: temp_token_pubkey,
             mint_key: mint_key_pubkey,
             expected_amount: amount,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            // the sale recreates the pdas from the bump seeds the listing recorded
+            escrow_authority_bump_seed,
+            metadata_bump_seed: find_metadata_address(&mint_key_pubkey).1,
+            proceeds_vault: false,
         }
         .pack_into_slice(&mut escrow_account.data);
 
@@ -82,13 +91,31 @@ mod tests {
         .pack_into_slice(&mut temp_token_account.data);
 
         let mut taker_account = TestAccount::default();
-        let mut taker_token_receive_account = TestAccount::default();
         let mut initializer_account = TestAccount::default();
         let mut mint_key = TestAccount::default();
         let mut creators_account = TestAccount::default();
-        let mut val_account = TestAccount::default();
+        let mut treasury_account = TestAccount::default();
         let mut pda_temp_account = TestAccount::default();
 
+        // platform state account, owned by the escrow program
+        let treasury_pubkey = Pubkey::new_unique();
+        let mut val_account = TestAccount {
+            owner: escrow_program_id,
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: treasury_pubkey,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut val_account.data);
+
         let rent = Rent::default();
 
         // size of all the fields to be stored
@@ -130,13 +157,12 @@ mod tests {
         );
 
         let taker_pubkey = Pubkey::new_unique();
-        let taker_token_receive_pubkey = Pubkey::new_unique();
         let escrow_pubkey = Pubkey::new_unique();
         let creators_pubkey = Pubkey::from_str(
-            &"metadatacreatorL5LYvXwxBNSaVkinzjzvTt1j3XsQ"
+            "metadatacreatorL5LYvXwxBNSaVkinzjzvTt1j3XsQ"
         ).unwrap();
         let val_pubkey = Pubkey::from_str(
-            &"paXi61MzXmioYZL5LYvXwxBNSaVkinzjzvTt1j3XsQz"
+            "paXi61MzXmioYZL5LYvXwxBNSaVkinzjzvTt1j3XsQz"
         ).unwrap();
     
         const PREFIX: &str = "metadata";
@@ -159,11 +185,6 @@ mod tests {
                 true, 
                 &mut taker_account
             ),
-            (
-                &taker_token_receive_pubkey,
-                false,
-                &mut taker_token_receive_account,
-            ),
             (
                 &temp_token_pubkey, 
                 false, 
@@ -209,6 +230,11 @@ mod tests {
                 false, 
                 &mut val_account
             ),
+            (
+                &treasury_pubkey,
+                false,
+                &mut treasury_account
+            ),
             (
                 &creators_pubkey, 
                 false, 
@@ -240,30 +266,30 @@ mod tests {
             edition_nonce: None
         };
 
-        let metadata_accountinfo = &accounts[9];
+        let metadata_accountinfo = &accounts[8];
         metadata.serialize(
             &mut &mut metadata_accountinfo.data.borrow_mut()[..]
         ).unwrap();
 
-        let escrow_account_test = accounts[5].clone();
+        let escrow_account_test = accounts[4].clone();
         let escrow_state_test = Escrow::unpack_from_slice(
             &escrow_account_test.data.borrow()
         ).unwrap();
 
         // assertion tests before escrow exchange
         // check if is_initialized is set to true
-        assert_eq!(escrow_state_test.is_initialized, true);
+        assert!(escrow_state_test.is_initialized);
 
         Processor::process_exchange(&accounts, amount, &escrow_program_id)
             .expect("error: process_exchange()");
 
-        let escrow_account_test = accounts[5].clone();
+        let escrow_account_test = accounts[4].clone();
         let escrow_state_test = Escrow::unpack_from_slice(
             &escrow_account_test.data.borrow()
         ).unwrap();
 
         // assertion tests after escrow exchange
         // check if is_initialized is set to false
-        assert_eq!(escrow_state_test.is_initialized, false);
+        assert!(!escrow_state_test.is_initialized);
     }
 }
\ No newline at end of file
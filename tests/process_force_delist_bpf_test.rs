@@ -0,0 +1,178 @@
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    instruction, pda, processor,
+    state::{Escrow, ListingMode, Marketplace, TokenProgram, VaultAccount, MARKETPLACE_NAME_LEN},
+};
+#[cfg(feature = "test-bpf")]
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
+use spl_token_2022::state::{Account as TokenAccount, AccountState, Mint};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// force delists a Token-2022 listing of `listing_mode` made on a marketplace of
+// `admin`, and returns the seller's token account afterwards
+#[cfg(feature = "test-bpf")]
+async fn force_delist_token_2022(listing_mode: ListingMode) -> TokenAccount {
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+    let (escrow_authority, _bump_seed) = pda::find_escrow_authority(&program_id);
+    let admin = Keypair::new();
+    let seller = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let escrow_account = Pubkey::new_unique();
+    let platform = Pubkey::new_unique();
+    let frozen = listing_mode == ListingMode::Frozen;
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_token_2022",
+        spl_token_2022::id(),
+        processor!(spl_token_2022::processor::Processor::process)
+    );
+
+    let mut mint_data = vec![0; Mint::LEN];
+    Mint {
+        supply: 1,
+        is_initialized: true,
+        freeze_authority: COption::Some(escrow_authority),
+        ..Mint::default()
+    }
+    .pack_into_slice(&mut mint_data);
+    program_test.add_account(
+        mint,
+        Account {
+            lamports: Rent::default().minimum_balance(Mint::LEN),
+            owner: spl_token_2022::id(),
+            data: mint_data,
+            ..Account::default()
+        }
+    );
+
+    // the seller's token account, approved to the escrow authority when listed
+    let mut token_data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner: seller,
+        amount: 1,
+        delegate: COption::Some(escrow_authority),
+        delegated_amount: 1,
+        state: if frozen { AccountState::Frozen } else { AccountState::Initialized },
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut token_data);
+    program_test.add_account(
+        token_account,
+        Account {
+            lamports: Rent::default().minimum_balance(TokenAccount::LEN),
+            owner: spl_token_2022::id(),
+            data: token_data,
+            ..Account::default()
+        }
+    );
+
+    let mut escrow_data = vec![0; Escrow::LEN];
+    Escrow {
+        is_initialized: true,
+        seller_pubkey: seller,
+        token_account_pubkey: token_account,
+        mint_key: mint,
+        expected_amount: 1000,
+        marketplace: platform,
+        listing_mode,
+        token_program: TokenProgram::Token2022,
+        usd_priced: false,
+        escrow_authority_bump_seed: 0,
+        metadata_bump_seed: 0,
+        proceeds_vault: false,
+    }
+    .pack_into_slice(&mut escrow_data);
+    program_test.add_account(
+        escrow_account,
+        Account {
+            lamports: Rent::default().minimum_balance(Escrow::LEN),
+            owner: program_id,
+            data: escrow_data,
+            ..Account::default()
+        }
+    );
+
+    let mut platform_data = vec![0; Marketplace::LEN];
+    Marketplace {
+        is_initialized: true,
+        authority: admin.pubkey(),
+        creator: admin.pubkey(),
+        name: [0; MARKETPLACE_NAME_LEN],
+        platform: VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        },
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform,
+        Account {
+            lamports: Rent::default().minimum_balance(Marketplace::LEN),
+            owner: program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    let force_delist = if frozen {
+        instruction::force_delist_frozen(&program_id, &admin.pubkey(), &token_account, &escrow_account, &platform, &mint, 3)
+    } else {
+        instruction::force_delist_delegated(&program_id, &admin.pubkey(), &token_account, &escrow_account, &platform, &mint, 3)
+    }
+    .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::with_token_program(force_delist, &spl_token_2022::id())],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let escrow = client.get_account(escrow_account).await.unwrap().unwrap();
+    assert!(!Escrow::unpack_unchecked(&escrow.data).unwrap().is_initialized);
+    let token_account = client.get_account(token_account).await.unwrap().unwrap();
+    TokenAccount::unpack(&token_account.data).unwrap()
+}
+
+// the escrow authority gives up its approval of a force delisted token
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_force_delist_revokes_delegated_listing() {
+    let token_account = force_delist_token_2022(ListingMode::Delegate).await;
+    assert_eq!(token_account.amount, 1);
+    assert_eq!(token_account.delegate, COption::None);
+    assert_eq!(token_account.delegated_amount, 0);
+}
+
+// and thaws a frozen one before
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_force_delist_revokes_frozen_listing() {
+    let token_account = force_delist_token_2022(ListingMode::Frozen).await;
+    assert_eq!(token_account.amount, 1);
+    assert_eq!(token_account.state, AccountState::Initialized);
+    assert_eq!(token_account.delegate, COption::None);
+    assert_eq!(token_account.delegated_amount, 0);
+}
@@ -0,0 +1,304 @@
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Once};
+
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::{ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        program_utils::limited_deserialize,
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction::SystemInstruction,
+        system_program,
+        sysvar,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::create_account_for_test,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+    use escrow_buy::{
+        pda::find_vault_address,
+        processor::Processor,
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+    };
+
+    // hands the processor the default rent and runs the SPL Token instructions it
+    // invokes, along with the system instructions creating an account whose data
+    // the test already sized
+    struct TokenProgramStubs;
+
+    impl SyscallStubs for TokenProgramStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            let accounts: Vec<AccountInfo> = instruction
+                .accounts
+                .iter()
+                .map(|meta| {
+                    let mut account_info = account_infos
+                        .iter()
+                        .find(|account_info| *account_info.key == meta.pubkey)
+                        .unwrap()
+                        .clone();
+                    account_info.is_signer = meta.is_signer;
+                    account_info
+                })
+                .collect();
+            if instruction.program_id == spl_token::id() {
+                return spl_token::processor::Processor::process(&instruction.program_id, &accounts, &instruction.data);
+            }
+            match limited_deserialize(&instruction.data, 1024).unwrap() {
+                SystemInstruction::Transfer { lamports } => {
+                    **accounts[0].try_borrow_mut_lamports()? -= lamports;
+                    **accounts[1].try_borrow_mut_lamports()? += lamports;
+                }
+                SystemInstruction::Allocate { space } => assert_eq!(accounts[0].data_len() as u64, space),
+                SystemInstruction::Assign { owner } => accounts[0].assign(&owner),
+                _ => unimplemented!(),
+            }
+            Ok(())
+        }
+    }
+
+    // the platform admin takes a listing down without the seller
+    #[test]
+    fn force_delist_test() {
+        let escrow_program_id = Pubkey::from_str(
+            "escrowprogram111111111111111111111111111111"
+        ).unwrap();
+        let admin_pubkey = Pubkey::from_str(
+            "J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq"
+        ).unwrap();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+
+        let mut escrow_account = TestAccount {
+            owner: escrow_program_id,
+            data: vec![0; Escrow::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey,
+            token_account_pubkey: token_pubkey,
+            mint_key: Pubkey::new_unique(),
+            expected_amount: 123,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut escrow_account.data);
+
+        let mut token_account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            owner: pda,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut token_account.data);
+
+        let mut admin_account = TestAccount::default();
+        let mut seller_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+
+        // the seller cannot use it in place of the admin
+        let mut accounts = [
+            (&seller_pubkey, true, &mut seller_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_force_delist(&accounts, 2, &escrow_program_id),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut accounts = [
+            (&admin_pubkey, true, &mut admin_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_force_delist(&accounts, 2, &escrow_program_id)
+            .expect("error: process_force_delist()");
+
+        let escrow_state = Escrow::unpack_unchecked(&accounts[2].data.borrow()).unwrap();
+        assert!(!escrow_state.is_initialized);
+        assert_eq!(escrow_state.seller_pubkey, seller_pubkey);
+
+        // a closed listing cannot be taken down again
+        assert_eq!(
+            Processor::process_force_delist(&accounts, 2, &escrow_program_id),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    // taking down a delegated SPL Token listing leaves the seller's token account
+    // without the approval of the escrow authority, which the owner alone could revoke
+    #[test]
+    fn force_delist_delegated_listing_test() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(TokenProgramStubs));
+        });
+
+        let escrow_program_id = Pubkey::from_str(
+            "escrowprogram111111111111111111111111111111"
+        ).unwrap();
+        let admin_pubkey = Pubkey::from_str(
+            "J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq"
+        ).unwrap();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+        let (vault_pubkey, _vault_bump_seed) = find_vault_address(&escrow_program_id, &escrow_pubkey);
+        let rent = Rent::default();
+
+        let mut escrow_account = TestAccount {
+            owner: escrow_program_id,
+            data: vec![0; Escrow::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey,
+            token_account_pubkey: token_pubkey,
+            mint_key: mint_pubkey,
+            expected_amount: 123,
+            marketplace: platform_pubkey,
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut escrow_account.data);
+
+        let mut platform_account = TestAccount {
+            owner: escrow_program_id,
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+
+        let mut mint_account = TestAccount {
+            owner: spl_token::id(),
+            lamports: rent.minimum_balance(Mint::LEN),
+            data: vec![0; Mint::LEN],
+            ..TestAccount::default()
+        };
+        Mint {
+            supply: 1,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut mint_account.data);
+
+        let mut token_account = TestAccount {
+            owner: spl_token::id(),
+            lamports: rent.minimum_balance(TokenAccount::LEN),
+            data: vec![0; TokenAccount::LEN],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            mint: mint_pubkey,
+            owner: seller_pubkey,
+            amount: 1,
+            delegate: COption::Some(pda),
+            delegated_amount: 1,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut token_account.data);
+
+        // allocated by the system program, which is not run here
+        let mut vault_account = TestAccount {
+            data: vec![0; TokenAccount::LEN],
+            ..TestAccount::default()
+        };
+        let mut admin_account = TestAccount {
+            lamports: 1_000_000_000,
+            ..TestAccount::default()
+        };
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut rent_account = create_account_for_test(&rent);
+
+        let mut accounts = [
+            (&admin_pubkey, true, &mut admin_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&mint_pubkey, false, &mut mint_account),
+            (&vault_pubkey, false, &mut vault_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_force_delist(&accounts, 2, &escrow_program_id)
+            .expect("error: process_force_delist()");
+
+        let escrow_state = Escrow::unpack_unchecked(&accounts[2].data.borrow()).unwrap();
+        assert!(!escrow_state.is_initialized);
+
+        // the token stays with the seller, no longer approved to the escrow authority
+        let token_state = TokenAccount::unpack(&accounts[1].data.borrow()).unwrap();
+        assert_eq!(token_state.owner, seller_pubkey);
+        assert_eq!(token_state.amount, 1);
+        assert_eq!(token_state.delegate, COption::None);
+        assert_eq!(token_state.delegated_amount, 0);
+
+        // the vault it moved through is closed and its rent is back with the admin
+        assert_eq!(accounts[7].lamports(), 0);
+        assert_eq!(accounts[0].lamports(), 1_000_000_000);
+    }
+}
@@ -0,0 +1,351 @@
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    instruction::{self, ExchangeOptionalAccounts},
+    pda,
+    processor,
+    state::{Escrow, ListingMode, VaultAccount},
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{
+    instruction::InstructionError, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+    system_instruction,
+};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError}};
+#[cfg(feature = "test-bpf")]
+use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account};
+#[cfg(feature = "test-bpf")]
+use spl_token::state::Account as TokenAccount;
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// a frozen listing keeps the NFT in the seller's wallet, frozen by the program
+// until it is sold or the listing is cancelled
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_frozen_listing() {
+    let price: u64 = 100_000;
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+    let (escrow_authority, _nonce) = Pubkey::find_program_address(&[b"escrow"], &program_id);
+
+    let minter = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let token_account = Keypair::new();
+    let mint = Keypair::new();
+    let escrow_account = Keypair::new();
+    let relist_escrow_account = Keypair::new();
+    let other_mint = Keypair::new();
+    let other_token_account = Keypair::new();
+    let platform = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    // run the token programs natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_associated_token_account",
+        spl_associated_token_account::id(),
+        processor!(spl_associated_token_account::processor::process_instruction)
+    );
+
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    program_test.add_account(
+        treasury,
+        Account {
+            lamports: rent_exempt_minimum,
+            ..Account::default()
+        }
+    );
+
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: treasury,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    // token metadata without royalties
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&mint.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint.pubkey(),
+        data: Data {
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    // mint the NFT to the seller, the program is the freeze authority of the mint
+    let rent = Rent::default();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+            system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &minter.pubkey(),
+                Some(&escrow_authority),
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &mint, &token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // a mint the program cannot freeze is refused
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &other_mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &other_mint.pubkey(),
+                &minter.pubkey(),
+                Some(&minter.pubkey()),
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &other_token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &other_token_account.pubkey(),
+                &other_mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &other_mint.pubkey(),
+                &other_token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &other_mint, &other_token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token_frozen(
+                &program_id,
+                &seller.pubkey(),
+                &other_token_account.pubkey(),
+                &other_mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform,
+                price
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidAccountData)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token_frozen(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform,
+                price
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // the seller keeps the token account, frozen with the program as delegate
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    assert_eq!(Escrow::unpack(&escrow.data).unwrap().listing_mode, ListingMode::Frozen);
+    let listed_token = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    let listed_token = TokenAccount::unpack(&listed_token.data).unwrap();
+    assert_eq!(listed_token.owner, seller.pubkey());
+    assert_eq!(listed_token.delegate, COption::Some(escrow_authority));
+    assert!(listed_token.is_frozen());
+
+    // so the approval cannot be taken back while listed
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            spl_token::instruction::revoke(
+                &spl_token::id(),
+                &token_account.pubkey(),
+                &seller.pubkey(),
+                &[]
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller], recent_blockhash);
+    assert!(client.process_transaction(transaction).await.is_err());
+
+    // the NFT is sent to the taker's associated token account, the seller's one is thawed
+    let taker_token_account = get_associated_token_address(&taker.pubkey(), &mint.pubkey());
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            create_associated_token_account(&payer.pubkey(), &taker.pubkey(), &mint.pubkey()),
+            instruction::exchange_with_options(
+                &program_id,
+                &taker.pubkey(),
+                &token_account.pubkey(),
+                &seller.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform,
+                &treasury,
+                &ExchangeOptionalAccounts {
+                    buyer_token_account: Some(taker_token_account),
+                    ..ExchangeOptionalAccounts::default()
+                },
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let bought_token = client.get_account(taker_token_account).await.unwrap().unwrap();
+    let bought_token = TokenAccount::unpack(&bought_token.data).unwrap();
+    assert_eq!(bought_token.amount, 1);
+    assert!(!bought_token.is_frozen());
+    let sold_token = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    let sold_token = TokenAccount::unpack(&sold_token.data).unwrap();
+    assert_eq!(sold_token.amount, 0);
+    assert!(!sold_token.is_frozen());
+
+    // cancelling a frozen listing thaws the token account and revokes the approval
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token_frozen(
+                &program_id,
+                &taker.pubkey(),
+                &taker_token_account,
+                &mint.pubkey(),
+                &relist_escrow_account.pubkey(),
+                &platform,
+                price
+            ).unwrap(),
+            instruction::cancel_frozen(
+                &program_id,
+                &taker.pubkey(),
+                &taker_token_account,
+                &relist_escrow_account.pubkey(),
+                &mint.pubkey()
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker, &relist_escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let cancelled_token = client.get_account(taker_token_account).await.unwrap().unwrap();
+    let cancelled_token = TokenAccount::unpack(&cancelled_token.data).unwrap();
+    assert_eq!(cancelled_token.amount, 1);
+    assert!(!cancelled_token.is_frozen());
+    assert_eq!(cancelled_token.delegate, COption::None);
+    let escrow = client.get_account(relist_escrow_account.pubkey()).await.unwrap().unwrap();
+    assert!(!Escrow::unpack_unchecked(&escrow.data).unwrap().is_initialized);
+}
@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        pda::find_metadata_address,
+        processor::Processor,
+        state::{Escrow, VaultAccount},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    fn escrow_authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], &escrow_program_id()).0
+    }
+
+    // an escrow account as the program wrote it before the account header: the
+    // is_initialized flag, the seller, the token account, the mint and the price
+    fn legacy_escrow_test_account(seller: &Pubkey, token: &Pubkey, mint: &Pubkey) -> TestAccount {
+        let mut data = vec![1];
+        data.extend_from_slice(seller.as_ref());
+        data.extend_from_slice(token.as_ref());
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(&1000u64.to_le_bytes());
+        assert_eq!(data.len(), Escrow::LEGACY_LEN);
+        TestAccount {
+            owner: escrow_program_id(),
+            data,
+            ..TestAccount::default()
+        }
+    }
+
+    // a platform account as the admin set it up before the account header
+    fn legacy_platform_test_account(treasury: &Pubkey) -> TestAccount {
+        let mut data = vec![1];
+        data.extend_from_slice(treasury.as_ref());
+        data.extend_from_slice(&250u64.to_le_bytes());
+        assert_eq!(data.len(), VaultAccount::LEGACY_LEN);
+        TestAccount {
+            owner: escrow_program_id(),
+            data,
+            ..TestAccount::default()
+        }
+    }
+
+    // the token account the escrow authority took over when the token was listed
+    fn escrowed_token_test_account(mint: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            mint: *mint,
+            owner: escrow_authority(),
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn metadata_test_account(mint: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_METADATA_LEN],
+            ..TestAccount::default()
+        };
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: *mint,
+            data: Data {
+                name: String::from("name"),
+                symbol: String::from("symbol"),
+                uri: String::from("uri"),
+                seller_fee_basis_points: 0,
+                creators: Some(Vec::<Creator>::new()),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut account.data[..])
+        .unwrap();
+        account
+    }
+
+    // the seller of a listing made before the account header gets the token back
+    #[test]
+    fn cancel_legacy_listing_test() {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+
+        let mut seller_account = TestAccount::default();
+        let mut token_account = escrowed_token_test_account(&mint_pubkey);
+        let mut escrow_account = legacy_escrow_test_account(&seller_pubkey, &token_pubkey, &mint_pubkey);
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+
+        let mut accounts = [
+            (&seller_pubkey, true, &mut seller_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_cancel(&accounts, &escrow_program_id).expect("error: process_cancel()");
+
+        // the account keeps its layout, closed
+        assert_eq!(accounts[2].data_len(), Escrow::LEGACY_LEN);
+        let escrow = Escrow::unpack_legacy(&accounts[2].data.borrow()).unwrap();
+        assert!(!escrow.is_initialized);
+        assert_eq!(escrow.seller_pubkey, seller_pubkey);
+        assert_eq!(escrow.token_account_pubkey, token_pubkey);
+        assert_eq!(escrow.expected_amount, 1000);
+
+        // and cannot be cancelled again
+        assert_eq!(
+            Processor::process_cancel(&accounts, &escrow_program_id),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    // a listing made before the account header is sold through the admin's platform
+    // account, of either layout
+    #[test]
+    fn exchange_legacy_listing_test() {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let taker_pubkey = Pubkey::new_unique();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let treasury_pubkey = Pubkey::new_unique();
+        let (metadata_pubkey, _metadata_bump_seed) = find_metadata_address(&mint_pubkey);
+
+        let mut escrow_account = legacy_escrow_test_account(&seller_pubkey, &token_pubkey, &mint_pubkey);
+        let mut token_account = escrowed_token_test_account(&mint_pubkey);
+        let mut metadata_account = metadata_test_account(&mint_pubkey);
+        let mut platform_account = legacy_platform_test_account(&treasury_pubkey);
+        let mut taker_account = TestAccount::default();
+        let mut seller_account = TestAccount::default();
+        let mut mint_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut treasury_account = TestAccount::default();
+
+        let mut accounts = [
+            (&taker_pubkey, true, &mut taker_account),
+            (&token_pubkey, false, &mut token_account),
+            (&seller_pubkey, false, &mut seller_account),
+            (&mint_pubkey, false, &mut mint_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::ID, false, &mut token_program_account),
+            (&system_program::ID, false, &mut system_program_account),
+            (&pda, false, &mut pda_account),
+            (&metadata_pubkey, false, &mut metadata_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&treasury_pubkey, false, &mut treasury_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_exchange(&accounts, 1, &escrow_program_id).expect("error: process_exchange()");
+
+        let escrow = Escrow::unpack_legacy(&accounts[4].data.borrow()).unwrap();
+        assert!(!escrow.is_initialized);
+        assert_eq!(escrow.mint_key, mint_pubkey);
+    }
+}
@@ -0,0 +1,234 @@
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    instruction,
+    pda,
+    processor,
+    state::{Escrow, Marketplace},
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// anyone creates a marketplace, listings made on it pay its treasury
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_marketplace_exchange() {
+    let price: u64 = 100_000;
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let minter = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let creator = Keypair::new();
+    let token_account = Keypair::new();
+    let mint = Keypair::new();
+    let escrow_account = Keypair::new();
+    let authority = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let (marketplace, _marketplace_bump_seed) =
+        pda::find_marketplace_address(&program_id, &creator.pubkey(), "partner store");
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+
+    // the treasury starts out rent exempt so that it can receive any fee
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    program_test.add_account(
+        treasury,
+        Account {
+            lamports: rent_exempt_minimum,
+            ..Account::default()
+        }
+    );
+
+    // token metadata without royalties
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&mint.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint.pubkey(),
+        data: Data {
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    // a 100 basis points marketplace
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &creator.pubkey(), 1_000_000_000),
+            instruction::create_marketplace(
+                &program_id,
+                &creator.pubkey(),
+                &authority,
+                &treasury,
+                "partner store",
+                100
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &creator], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let marketplace_account = client.get_account(marketplace).await.unwrap().unwrap();
+    assert_eq!(marketplace_account.owner, program_id);
+    let marketplace_state = Marketplace::unpack(&marketplace_account.data).unwrap();
+    assert_eq!(marketplace_state.authority, authority);
+    assert_eq!(marketplace_state.creator, creator.pubkey());
+    assert_eq!(marketplace_state.name(), b"partner store");
+    assert_eq!(marketplace_state.platform.treasury_account, treasury);
+    assert_eq!(marketplace_state.platform.base_percentage, 100);
+
+    // mint the NFT to the seller
+    let rent = Rent::default();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+            system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &minter.pubkey(),
+                None,
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &mint, &token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &marketplace,
+                price
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    assert_eq!(Escrow::unpack(&escrow.data).unwrap().marketplace, marketplace);
+
+    let seller_before = client.get_account(seller.pubkey()).await.unwrap().unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::exchange(
+                &program_id,
+                &taker.pubkey(),
+                &token_account.pubkey(),
+                &seller.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &marketplace,
+                &treasury,
+                &[],
+                1
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // the marketplace fee of 1000 lamports goes to its treasury
+    let treasury_after = client.get_account(treasury).await.unwrap().unwrap();
+    assert_eq!(treasury_after.lamports, rent_exempt_minimum + 1000);
+    let seller_after = client.get_account(seller.pubkey()).await.unwrap().unwrap();
+    assert_eq!(seller_after.lamports, seller_before.lamports + price - 1000);
+
+    // the same creator cannot take the name twice
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::create_marketplace(
+                &program_id,
+                &creator.pubkey(),
+                &creator.pubkey(),
+                &treasury,
+                "partner store",
+                0
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &creator], recent_blockhash);
+    assert!(client.process_transaction(transaction).await.is_err());
+}
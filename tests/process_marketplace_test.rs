@@ -0,0 +1,446 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, str::FromStr, sync::Once};
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::{ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction,
+        system_program,
+        sysvar,
+    };
+    use solana_sdk::{
+        account::create_account_for_test,
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        pda::{find_marketplace_address, find_metadata_address},
+        processor::Processor,
+        state::{Escrow, ListingMode, Marketplace, TokenProgram, VaultAccount, MARKETPLACE_NAME_LEN},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    thread_local! {
+        static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // hands the processor the default rent and records the instructions it invokes
+    struct RecordingStubs;
+
+    impl SyscallStubs for RecordingStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+            Ok(())
+        }
+    }
+
+    fn record_invoked() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(RecordingStubs));
+        });
+        INVOKED.with(|invoked| invoked.borrow_mut().clear());
+    }
+
+    fn invoked() -> Vec<Instruction> {
+        INVOKED.with(|invoked| invoked.borrow().clone())
+    }
+
+    fn admin() -> Pubkey {
+        Pubkey::from_str("J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq").unwrap()
+    }
+
+    fn platform(treasury: &Pubkey) -> VaultAccount {
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+    }
+
+    fn marketplace_test_account(authority: &Pubkey, treasury: &Pubkey) -> TestAccount {
+        let mut name = [0; 32];
+        name[..5].copy_from_slice(b"store");
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; Marketplace::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Marketplace {
+            is_initialized: true,
+            authority: *authority,
+            creator: Pubkey::new_unique(),
+            name,
+            platform: platform(treasury),
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn vault_test_account(treasury: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        platform(treasury).pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn escrow_test_account(seller: &Pubkey, token: &Pubkey, mint: &Pubkey, marketplace: &Pubkey) -> TestAccount {
+        let mut account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; Escrow::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: *seller,
+            token_account_pubkey: *token,
+            mint_key: *mint,
+            expected_amount: 1000,
+            marketplace: *marketplace,
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn escrowed_token_account(mint: &Pubkey) -> TestAccount {
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id());
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            mint: *mint,
+            owner: pda,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    // buys a listing recorded on `listed_on` through `platform_pubkey`, paying `treasury_pubkey`
+    fn exchange(
+        listed_on: &Pubkey,
+        platform_pubkey: &Pubkey,
+        treasury_pubkey: &Pubkey,
+        mut platform_account: TestAccount,
+    ) -> ProgramResult {
+        let escrow_program_id = escrow_program_id();
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+        let taker_pubkey = Pubkey::new_unique();
+        let seller_pubkey = Pubkey::new_unique();
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let (metadata_pubkey, _metadata_bump_seed) = find_metadata_address(&mint_pubkey);
+
+        let mut escrow_account = escrow_test_account(&seller_pubkey, &token_pubkey, &mint_pubkey, listed_on);
+        let mut token_account = escrowed_token_account(&mint_pubkey);
+
+        // no royalties
+        let mut metadata_account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_METADATA_LEN],
+            ..TestAccount::default()
+        };
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: mint_pubkey,
+            data: Data {
+                name: String::from("name"),
+                symbol: String::from("symbol"),
+                uri: String::from("uri"),
+                seller_fee_basis_points: 0,
+                creators: Some(Vec::<Creator>::new()),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut metadata_account.data[..])
+        .unwrap();
+
+        let mut taker_account = TestAccount::default();
+        let mut seller_account = TestAccount::default();
+        let mut mint_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut treasury_account = TestAccount::default();
+
+        let mut accounts = [
+            (&taker_pubkey, true, &mut taker_account),
+            (&token_pubkey, false, &mut token_account),
+            (&seller_pubkey, false, &mut seller_account),
+            (&mint_pubkey, false, &mut mint_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::ID, false, &mut token_program_account),
+            (&system_program::ID, false, &mut system_program_account),
+            (&pda, false, &mut pda_account),
+            (&metadata_pubkey, false, &mut metadata_account),
+            (platform_pubkey, false, &mut platform_account),
+            (treasury_pubkey, false, &mut treasury_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_exchange(&accounts, 1, &escrow_program_id)
+    }
+
+    // a listing is bought through the marketplace it was made on
+    #[test]
+    fn exchange_on_marketplace_test() {
+        let marketplace = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        exchange(&marketplace, &marketplace, &treasury, marketplace_test_account(&authority, &treasury)).unwrap();
+
+        // the treasury of the marketplace is paid
+        assert_eq!(
+            exchange(
+                &marketplace,
+                &marketplace,
+                &Pubkey::new_unique(),
+                marketplace_test_account(&authority, &treasury)
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // not through another one, with lower fees
+        let other = Pubkey::new_unique();
+        assert_eq!(
+            exchange(&marketplace, &other, &treasury, marketplace_test_account(&authority, &treasury)),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // nor through the admin's platform account
+        let vault = Pubkey::new_unique();
+        assert_eq!(
+            exchange(&marketplace, &vault, &treasury, vault_test_account(&treasury)),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    // listings made before the marketplace was recorded stay on the admin's platform account
+    #[test]
+    fn exchange_legacy_listing_test() {
+        let vault = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        exchange(&Pubkey::default(), &vault, &treasury, vault_test_account(&treasury)).unwrap();
+
+        let marketplace = Pubkey::new_unique();
+        assert_eq!(
+            exchange(
+                &Pubkey::default(),
+                &marketplace,
+                &treasury,
+                marketplace_test_account(&Pubkey::new_unique(), &treasury)
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    fn set_paused(signer: &Pubkey, authority: &Pubkey) -> Result<Marketplace, ProgramError> {
+        let marketplace_pubkey = Pubkey::new_unique();
+        let mut signer_account = TestAccount::default();
+        let mut marketplace_account = marketplace_test_account(authority, &Pubkey::new_unique());
+        let mut accounts = [
+            (signer, true, &mut signer_account),
+            (&marketplace_pubkey, false, &mut marketplace_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_paused(&accounts, true, &escrow_program_id())?;
+
+        let marketplace = Marketplace::unpack(&accounts[1].data.borrow())?;
+        Ok(marketplace)
+    }
+
+    // a marketplace is configured by its own authority, not by the platform admin
+    #[test]
+    fn marketplace_authority_test() {
+        let authority = Pubkey::new_unique();
+        let marketplace = set_paused(&authority, &authority).unwrap();
+        assert!(marketplace.platform.paused);
+        assert_eq!(marketplace.authority, authority);
+        assert_eq!(marketplace.name(), b"store");
+
+        assert_eq!(
+            set_paused(&admin(), &authority),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    // lamports sent to the marketplace address ahead of its creation only lower
+    // what the creator pays for it
+    #[test]
+    fn create_prefunded_marketplace_test() {
+        let rent_exempt_minimum = Rent::default().minimum_balance(Marketplace::get_packed_len());
+        for prefunded in [1, rent_exempt_minimum, rent_exempt_minimum + 1] {
+            let creator = Pubkey::new_unique();
+            let authority = Pubkey::new_unique();
+            let treasury = Pubkey::new_unique();
+            let (marketplace_pubkey, _marketplace_bump_seed) =
+                find_marketplace_address(&escrow_program_id(), &creator, "market");
+            let mut name = [0; MARKETPLACE_NAME_LEN];
+            name[..6].copy_from_slice(b"market");
+
+            let mut creator_account = TestAccount::default();
+            // allocated by the system program, which is not run here
+            let mut marketplace_account = TestAccount {
+                lamports: prefunded,
+                data: vec![0; Marketplace::get_packed_len()],
+                ..TestAccount::default()
+            };
+            let mut authority_account = TestAccount::default();
+            let mut treasury_account = TestAccount::default();
+            let mut system_program_account = TestAccount::default();
+            let mut accounts = [
+                (&creator, true, &mut creator_account),
+                (&marketplace_pubkey, false, &mut marketplace_account),
+                (&authority, false, &mut authority_account),
+                (&treasury, false, &mut treasury_account),
+                (&system_program::id(), false, &mut system_program_account),
+            ];
+            let accounts = create_is_signer_account_infos(&mut accounts);
+            record_invoked();
+            Processor::process_create_marketplace(&accounts, name, 300, &escrow_program_id())
+                .expect("error: process_create_marketplace()");
+
+            let marketplace = Marketplace::unpack(&accounts[1].data.borrow()).unwrap();
+            assert_eq!(marketplace.authority, authority);
+            assert_eq!(marketplace.name(), b"market");
+            assert_eq!(marketplace.platform.base_percentage, 300);
+
+            let mut created = Vec::new();
+            if prefunded < rent_exempt_minimum {
+                created.push(system_instruction::transfer(
+                    &creator,
+                    &marketplace_pubkey,
+                    rent_exempt_minimum - prefunded,
+                ));
+            }
+            created.push(system_instruction::allocate(
+                &marketplace_pubkey,
+                Marketplace::get_packed_len() as u64,
+            ));
+            created.push(system_instruction::assign(&marketplace_pubkey, &escrow_program_id()));
+            assert_eq!(invoked(), created);
+        }
+    }
+
+    fn update_fee(fee_basis_points: u64) -> Result<Marketplace, ProgramError> {
+        let authority = Pubkey::new_unique();
+        let marketplace_pubkey = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let mut authority_account = TestAccount::default();
+        let mut marketplace_account = TestAccount {
+            lamports: Rent::default().minimum_balance(Marketplace::get_packed_len()),
+            ..marketplace_test_account(&authority, &treasury)
+        };
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut treasury_account = TestAccount::default();
+        let mut accounts = [
+            (&authority, true, &mut authority_account),
+            (&marketplace_pubkey, false, &mut marketplace_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&treasury, false, &mut treasury_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_val_accounts(&accounts, fee_basis_points, &escrow_program_id())?;
+
+        let marketplace = Marketplace::unpack(&accounts[1].data.borrow())?;
+        Ok(marketplace)
+    }
+
+    // the authority sets the fee of its marketplace, at most the whole price
+    #[test]
+    fn update_marketplace_fee_test() {
+        assert_eq!(update_fee(500).unwrap().platform.base_percentage, 500);
+        assert_eq!(update_fee(10000).unwrap().platform.base_percentage, 10000);
+        assert_eq!(update_fee(10001), Err(ProgramError::InvalidInstructionData));
+    }
+
+    fn force_delist(signer: &Pubkey, authority: &Pubkey, with_platform_account: bool) -> ProgramResult {
+        let escrow_program_id = escrow_program_id();
+        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+        let token_pubkey = Pubkey::new_unique();
+        let mint_pubkey = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let marketplace_pubkey = Pubkey::new_unique();
+
+        let mut signer_account = TestAccount::default();
+        let mut token_account = escrowed_token_account(&mint_pubkey);
+        let mut escrow_account =
+            escrow_test_account(&Pubkey::new_unique(), &token_pubkey, &mint_pubkey, &marketplace_pubkey);
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut marketplace_account = marketplace_test_account(authority, &Pubkey::new_unique());
+
+        let mut accounts = vec![
+            (signer, true, &mut signer_account),
+            (&token_pubkey, false, &mut token_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&spl_token::ID, false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        if with_platform_account {
+            accounts.push((&marketplace_pubkey, false, &mut marketplace_account));
+        }
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_force_delist(&accounts, 1, &escrow_program_id)
+    }
+
+    // listings of a marketplace are moderated by its authority
+    #[test]
+    fn force_delist_on_marketplace_test() {
+        let authority = Pubkey::new_unique();
+        force_delist(&authority, &authority, true).unwrap();
+        assert_eq!(
+            force_delist(&admin(), &authority, true),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            force_delist(&authority, &authority, false),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        entrypoint::ProgramResult,
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        instruction::EXCHANGE_OPTION_MEMBERSHIP_PASS,
+        pda::find_metadata_address,
+        processor::Processor,
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+    };
+
+    const PRICE: u64 = 123;
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    fn metadata_test_account(creators: Vec<Creator>) -> TestAccount {
+        let mut account = TestAccount {
+            owner: metaplex_token_metadata::id(),
+            data: vec![0; MAX_METADATA_LEN],
+            ..TestAccount::default()
+        };
+        Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            data: Data {
+                name: String::from("name"),
+                symbol: String::from("symbol"),
+                uri: String::from("uri"),
+                seller_fee_basis_points: 500,
+                creators: Some(creators),
+            },
+            primary_sale_happened: true,
+            is_mutable: false,
+            edition_nonce: None,
+        }
+        .serialize(&mut &mut account.data[..])
+        .unwrap();
+        account
+    }
+
+    fn platform_test_account(treasury: &Pubkey, pass_collection: &Pubkey) -> TestAccount {
+        let mut platform_account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: *pass_collection,
+            pass_fee_basis_points: 100,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        platform_account
+    }
+
+    // a listing bought with a membership pass
+    struct PassExchange {
+        seller: Pubkey,
+        taker: Pubkey,
+        // membership pass collection of the platform account
+        platform_pass_collection: Pubkey,
+        pass_owner: Pubkey,
+        pass_amount: u64,
+        pass_creator: Creator,
+    }
+
+    impl PassExchange {
+        fn new() -> Self {
+            let pass_collection = Pubkey::new_unique();
+            let taker = Pubkey::new_unique();
+            PassExchange {
+                seller: Pubkey::new_unique(),
+                taker,
+                platform_pass_collection: pass_collection,
+                pass_owner: taker,
+                pass_amount: 1,
+                pass_creator: Creator {
+                    address: pass_collection,
+                    verified: true,
+                    share: 100,
+                },
+            }
+        }
+
+        fn process(&self, options: u8) -> ProgramResult {
+            let escrow_program_id = escrow_program_id();
+            let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+            let token_pubkey = Pubkey::new_unique();
+            let mint_pubkey = Pubkey::new_unique();
+            let escrow_pubkey = Pubkey::new_unique();
+            let platform_pubkey = Pubkey::new_unique();
+            let treasury_pubkey = Pubkey::new_unique();
+            let creator_pubkey = Pubkey::new_unique();
+            let pass_token_pubkey = Pubkey::new_unique();
+            let pass_mint_pubkey = Pubkey::new_unique();
+            let (metadata_pubkey, _metadata_bump_seed) = find_metadata_address(&mint_pubkey);
+            let (pass_metadata_pubkey, _pass_metadata_bump_seed) = find_metadata_address(&pass_mint_pubkey);
+
+            let mut escrow_account = TestAccount {
+                owner: escrow_program_id,
+                data: vec![0; Escrow::get_packed_len()],
+                ..TestAccount::default()
+            };
+            Escrow {
+                is_initialized: true,
+                seller_pubkey: self.seller,
+                token_account_pubkey: token_pubkey,
+                mint_key: mint_pubkey,
+                expected_amount: PRICE,
+                marketplace: Pubkey::default(),
+                listing_mode: ListingMode::Custody,
+                token_program: TokenProgram::SplToken,
+                usd_priced: false,
+                escrow_authority_bump_seed: 0,
+                metadata_bump_seed: 0,
+                proceeds_vault: false,
+            }
+            .pack_into_slice(&mut escrow_account.data);
+
+            let mut token_account = TestAccount {
+                owner: spl_token::id(),
+                data: vec![0; TokenAccount::get_packed_len()],
+                ..TestAccount::default()
+            };
+            TokenAccount {
+                mint: mint_pubkey,
+                owner: pda,
+                amount: 1,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            }
+            .pack_into_slice(&mut token_account.data);
+
+            let mut pass_token_account = TestAccount {
+                owner: spl_token::id(),
+                data: vec![0; TokenAccount::get_packed_len()],
+                ..TestAccount::default()
+            };
+            TokenAccount {
+                mint: pass_mint_pubkey,
+                owner: self.pass_owner,
+                amount: self.pass_amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            }
+            .pack_into_slice(&mut pass_token_account.data);
+
+            let mut taker_account = TestAccount::default();
+            let mut seller_account = TestAccount::default();
+            let mut mint_account = TestAccount::default();
+            let mut token_program_account = TestAccount::default();
+            let mut system_program_account = TestAccount::default();
+            let mut pda_account = TestAccount::default();
+            let mut metadata_account = metadata_test_account(vec![Creator {
+                address: creator_pubkey,
+                verified: true,
+                share: 100,
+            }]);
+            let mut platform_account = platform_test_account(&treasury_pubkey, &self.platform_pass_collection);
+            let mut treasury_account = TestAccount::default();
+            let mut pass_metadata_account = metadata_test_account(vec![self.pass_creator.clone()]);
+            let mut creator_account = TestAccount::default();
+
+            let mut accounts = vec![
+                (&self.taker, true, &mut taker_account),
+                (&token_pubkey, false, &mut token_account),
+                (&self.seller, false, &mut seller_account),
+                (&mint_pubkey, false, &mut mint_account),
+                (&escrow_pubkey, false, &mut escrow_account),
+                (&spl_token::ID, false, &mut token_program_account),
+                (&system_program::ID, false, &mut system_program_account),
+                (&pda, false, &mut pda_account),
+                (&metadata_pubkey, false, &mut metadata_account),
+                (&platform_pubkey, false, &mut platform_account),
+                (&treasury_pubkey, false, &mut treasury_account),
+            ];
+            if options & EXCHANGE_OPTION_MEMBERSHIP_PASS != 0 {
+                accounts.push((&pass_token_pubkey, false, &mut pass_token_account));
+                accounts.push((&pass_metadata_pubkey, false, &mut pass_metadata_account));
+            }
+            accounts.push((&creator_pubkey, false, &mut creator_account));
+
+            let accounts = create_is_signer_account_infos(&mut accounts);
+            Processor::process_exchange_with_options(&accounts, 1, options, &escrow_program_id)
+        }
+    }
+
+    // a pass held by the taker or by the seller is accepted
+    #[test]
+    fn exchange_with_pass_test() {
+        let exchange = PassExchange::new();
+        exchange.process(EXCHANGE_OPTION_MEMBERSHIP_PASS).unwrap();
+
+        let exchange = PassExchange {
+            pass_owner: exchange.seller,
+            ..exchange
+        };
+        exchange.process(EXCHANGE_OPTION_MEMBERSHIP_PASS).unwrap();
+
+        // without the option the pass is not looked at
+        exchange.process(0).unwrap();
+    }
+
+    #[test]
+    fn exchange_with_invalid_pass_test() {
+        let exchange = PassExchange::new();
+        assert_eq!(
+            exchange.process(0x80),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        // the pass of someone else
+        let invalid = PassExchange {
+            pass_owner: Pubkey::new_unique(),
+            ..PassExchange::new()
+        };
+        assert_eq!(
+            invalid.process(EXCHANGE_OPTION_MEMBERSHIP_PASS),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // an emptied pass token account
+        let invalid = PassExchange {
+            pass_amount: 0,
+            ..PassExchange::new()
+        };
+        assert_eq!(
+            invalid.process(EXCHANGE_OPTION_MEMBERSHIP_PASS),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // an NFT claiming the collection without its signature
+        let mut invalid = PassExchange::new();
+        invalid.pass_creator.verified = false;
+        assert_eq!(
+            invalid.process(EXCHANGE_OPTION_MEMBERSHIP_PASS),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // an NFT of another collection
+        let invalid = PassExchange {
+            platform_pass_collection: Pubkey::new_unique(),
+            ..PassExchange::new()
+        };
+        assert_eq!(
+            invalid.process(EXCHANGE_OPTION_MEMBERSHIP_PASS),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // a platform without membership pass
+        let invalid = PassExchange {
+            platform_pass_collection: Pubkey::default(),
+            ..PassExchange::new()
+        };
+        assert_eq!(
+            invalid.process(EXCHANGE_OPTION_MEMBERSHIP_PASS),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    // only the platform admin sets the membership pass discount
+    #[test]
+    fn set_pass_discount_test() {
+        let escrow_program_id = escrow_program_id();
+        let admin_pubkey = Pubkey::from_str(
+            "J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq"
+        ).unwrap();
+        let other_pubkey = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let pass_collection = Pubkey::new_unique();
+
+        let mut other_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&Pubkey::new_unique(), &Pubkey::default());
+        let mut pass_collection_account = TestAccount::default();
+        let mut accounts = [
+            (&other_pubkey, true, &mut other_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&pass_collection, false, &mut pass_collection_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_set_pass_discount(&accounts, 50, &escrow_program_id),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut admin_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&Pubkey::new_unique(), &Pubkey::default());
+        let mut accounts = [
+            (&admin_pubkey, true, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&pass_collection, false, &mut pass_collection_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_pass_discount(&accounts, 50, &escrow_program_id).unwrap();
+
+        let platform = VaultAccount::unpack(&accounts[1].data.borrow()).unwrap();
+        assert_eq!(platform.pass_collection, pass_collection);
+        assert_eq!(platform.pass_fee_basis_points, 50);
+        assert_eq!(platform.base_percentage, 250);
+        assert_eq!(platform.fee_basis_points(true), 50);
+        assert_eq!(platform.fee_basis_points(false), 250);
+
+        // a pass fee above the base fee is no discount
+        let mut platform_account = platform_test_account(&Pubkey::new_unique(), &Pubkey::default());
+        let mut accounts = [
+            (&admin_pubkey, true, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&pass_collection, false, &mut pass_collection_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_set_pass_discount(&accounts, 251, &escrow_program_id),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        Processor::process_set_pass_discount(&accounts, 250, &escrow_program_id).unwrap();
+
+        // nor does it become one when the base fee is lowered under it
+        let platform = VaultAccount {
+            base_percentage: 200,
+            ..VaultAccount::unpack(&accounts[1].data.borrow()).unwrap()
+        };
+        assert_eq!(platform.fee_basis_points(true), 200);
+    }
+}
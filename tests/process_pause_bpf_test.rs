@@ -0,0 +1,207 @@
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{error::EscrowError, instruction, processor, state::{Escrow, VaultAccount}};
+#[cfg(feature = "test-bpf")]
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError}};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// a platform account owned by the escrow program
+#[cfg(feature = "test-bpf")]
+fn platform_account(program_id: &Pubkey, treasury: &Pubkey, paused: bool) -> Account {
+    let mut data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: *treasury,
+        base_percentage: 250,
+        paused,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+        owner: *program_id,
+        data,
+        ..Account::default()
+    }
+}
+
+// listing and buying are refused on a paused platform account,
+// cancelling is not
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_paused_platform() {
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let minter = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let token_account = Keypair::new();
+    let mint = Keypair::new();
+    let escrow_account = Keypair::new();
+    let treasury = Pubkey::new_unique();
+    let open_platform = Pubkey::new_unique();
+    let paused_platform = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+    program_test.add_account(open_platform, platform_account(&program_id, &treasury, false));
+    program_test.add_account(paused_platform, platform_account(&program_id, &treasury, true));
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    // mint the NFT to the seller
+    let rent = Rent::default();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+            system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &minter.pubkey(),
+                None,
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &mint, &token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let paused_error = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(EscrowError::MarketplacePaused as u32),
+    );
+
+    // listing on the paused platform account fails
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &paused_platform,
+                123
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        paused_error
+    );
+
+    // listing on the open one works
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &open_platform,
+                123
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // buying through the paused platform account fails
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::exchange(
+                &program_id,
+                &taker.pubkey(),
+                &token_account.pubkey(),
+                &seller.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &paused_platform,
+                &treasury,
+                &[],
+                1
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        paused_error
+    );
+
+    // the seller can still take the NFT back
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::cancel(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &escrow_account.pubkey()
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    assert!(!Escrow::unpack_unchecked(&escrow.data).unwrap().is_initialized);
+    let token_account = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&token_account.data).unwrap().owner,
+        seller.pubkey()
+    );
+}
@@ -0,0 +1,250 @@
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    instruction::{self, ExchangeOptionalAccounts},
+    pda,
+    processor,
+    state::{ReferrerAccount, VaultAccount},
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// a referred sale pays the registered referrer its share of the platform fee
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_referred_exchange() {
+    let price: u64 = 100_000;
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let minter = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let token_account = Keypair::new();
+    let mint = Keypair::new();
+    let escrow_account = Keypair::new();
+    let treasury = Pubkey::new_unique();
+    let referrer = Keypair::new();
+    let platform_account = Pubkey::new_unique();
+    let (referrer_account, _referrer_bump_seed) = pda::find_referrer_address(&program_id, &referrer.pubkey());
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+
+    // treasury and referrer start out rent exempt so that
+    // they can receive any share of the sale, the referrer
+    // also holds the rent of the earnings account it registers
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    let earnings_rent = Rent::default().minimum_balance(ReferrerAccount::LEN);
+    for (pubkey, lamports) in [
+        (treasury, rent_exempt_minimum),
+        (referrer.pubkey(), rent_exempt_minimum + earnings_rent),
+    ] {
+        program_test.add_account(
+            pubkey,
+            Account {
+                lamports,
+                ..Account::default()
+            }
+        );
+    }
+
+    // a 250 basis points platform fee, 2000 basis points of it for referrers
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: treasury,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 2000,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform_account,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    // token metadata without royalties
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&mint.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint.pubkey(),
+        data: Data {
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    // mint the NFT to the seller
+    let rent = Rent::default();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+            system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &minter.pubkey(),
+                None,
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &mint, &token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                price
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let seller_before = client.get_account(seller.pubkey()).await.unwrap().unwrap();
+
+    // a sale naming a referrer that has not registered fails
+    let referred_exchange = instruction::exchange_with_options(
+        &program_id,
+        &taker.pubkey(),
+        &token_account.pubkey(),
+        &seller.pubkey(),
+        &mint.pubkey(),
+        &escrow_account.pubkey(),
+        &platform_account,
+        &treasury,
+        &ExchangeOptionalAccounts {
+            referrer: Some(referrer.pubkey()),
+            ..ExchangeOptionalAccounts::default()
+        },
+        &[],
+        1
+    ).unwrap();
+    let mut transaction = Transaction::new_with_payer(std::slice::from_ref(&referred_exchange), Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    assert!(client.process_transaction(transaction).await.is_err());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::register_referrer(&program_id, &referrer.pubkey()).unwrap()],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &referrer], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(&[referred_exchange], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // the platform fee of 2500 lamports is split 2000 / 500
+    // between the treasury and the referrer
+    let treasury_after = client.get_account(treasury).await.unwrap().unwrap();
+    assert_eq!(treasury_after.lamports, rent_exempt_minimum + 2000);
+    let referrer_after = client.get_account(referrer.pubkey()).await.unwrap().unwrap();
+    assert_eq!(referrer_after.lamports, rent_exempt_minimum + 500);
+    let seller_after = client.get_account(seller.pubkey()).await.unwrap().unwrap();
+    assert_eq!(seller_after.lamports, seller_before.lamports + price - 2500);
+
+    // the referrer paid for the earnings account, the taker only the price
+    let earnings_account = client.get_account(referrer_account).await.unwrap().unwrap();
+    assert_eq!(earnings_account.owner, program_id);
+    assert_eq!(earnings_account.lamports, earnings_rent);
+    let taker_after = client.get_account(taker.pubkey()).await.unwrap().unwrap();
+    assert_eq!(taker_after.lamports, 1_000_000_000 - price);
+
+    let earnings = ReferrerAccount::unpack(&earnings_account.data).unwrap();
+    assert_eq!(
+        earnings,
+        ReferrerAccount {
+            is_initialized: true,
+            referrer: referrer.pubkey(),
+            total_earned: 500,
+            sales: 1,
+        }
+    );
+}
@@ -0,0 +1,424 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, str::FromStr, sync::Once};
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::{ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        error::EscrowError,
+        instruction::EXCHANGE_OPTION_REFERRER,
+        pda::{find_metadata_address, find_referrer_address},
+        processor::Processor,
+        state::{Escrow, ListingMode, ReferrerAccount, TokenProgram, VaultAccount},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    thread_local! {
+        static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // hands the processor the default rent and records the instructions it invokes
+    struct RecordingStubs;
+
+    impl SyscallStubs for RecordingStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+            Ok(())
+        }
+    }
+
+    fn record_invoked() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(RecordingStubs));
+        });
+        INVOKED.with(|invoked| invoked.borrow_mut().clear());
+    }
+
+    fn invoked() -> Vec<Instruction> {
+        INVOKED.with(|invoked| invoked.borrow().clone())
+    }
+
+    fn platform_test_account(treasury: &Pubkey, referral_basis_points: u64) -> TestAccount {
+        let mut platform_account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        platform_account
+    }
+
+    // a listing of 1000 lamports bought through a referrer
+    struct ReferredExchange {
+        seller: Pubkey,
+        taker: Pubkey,
+        referrer: Pubkey,
+        referrer_account: Pubkey,
+        earnings: ReferrerAccount,
+        // whether the referrer created its PDA with RegisterReferrer
+        registered: bool,
+    }
+
+    impl ReferredExchange {
+        fn new() -> Self {
+            let referrer = Pubkey::new_unique();
+            ReferredExchange {
+                seller: Pubkey::new_unique(),
+                taker: Pubkey::new_unique(),
+                referrer,
+                referrer_account: find_referrer_address(&escrow_program_id(), &referrer).0,
+                earnings: ReferrerAccount {
+                    is_initialized: true,
+                    referrer,
+                    total_earned: 100,
+                    sales: 2,
+                },
+                registered: true,
+            }
+        }
+
+        // processes the exchange and returns the referrer's earnings after it
+        fn process(&self) -> Result<ReferrerAccount, ProgramError> {
+            let escrow_program_id = escrow_program_id();
+            let (pda, _bump_seed) = Pubkey::find_program_address(&[b"escrow"], &escrow_program_id);
+            let token_pubkey = Pubkey::new_unique();
+            let mint_pubkey = Pubkey::new_unique();
+            let escrow_pubkey = Pubkey::new_unique();
+            let platform_pubkey = Pubkey::new_unique();
+            let treasury_pubkey = Pubkey::new_unique();
+            let (metadata_pubkey, _metadata_bump_seed) = find_metadata_address(&mint_pubkey);
+
+            let mut escrow_account = TestAccount {
+                owner: escrow_program_id,
+                data: vec![0; Escrow::get_packed_len()],
+                ..TestAccount::default()
+            };
+            Escrow {
+                is_initialized: true,
+                seller_pubkey: self.seller,
+                token_account_pubkey: token_pubkey,
+                mint_key: mint_pubkey,
+                expected_amount: 1000,
+                marketplace: Pubkey::default(),
+                listing_mode: ListingMode::Custody,
+                token_program: TokenProgram::SplToken,
+                usd_priced: false,
+                escrow_authority_bump_seed: 0,
+                metadata_bump_seed: 0,
+                proceeds_vault: false,
+            }
+            .pack_into_slice(&mut escrow_account.data);
+
+            let mut token_account = TestAccount {
+                owner: spl_token::id(),
+                data: vec![0; TokenAccount::get_packed_len()],
+                ..TestAccount::default()
+            };
+            TokenAccount {
+                mint: mint_pubkey,
+                owner: pda,
+                amount: 1,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            }
+            .pack_into_slice(&mut token_account.data);
+
+            // no royalties, the whole platform fee is 25 lamports
+            let mut metadata_account = TestAccount {
+                owner: metaplex_token_metadata::id(),
+                data: vec![0; MAX_METADATA_LEN],
+                ..TestAccount::default()
+            };
+            Metadata {
+                key: Key::MetadataV1,
+                update_authority: Pubkey::new_unique(),
+                mint: mint_pubkey,
+                data: Data {
+                    name: String::from("name"),
+                    symbol: String::from("symbol"),
+                    uri: String::from("uri"),
+                    seller_fee_basis_points: 0,
+                    creators: Some(Vec::<Creator>::new()),
+                },
+                primary_sale_happened: true,
+                is_mutable: false,
+                edition_nonce: None,
+            }
+            .serialize(&mut &mut metadata_account.data[..])
+            .unwrap();
+
+            let mut referrer_account = if self.registered {
+                let mut referrer_account = TestAccount {
+                    owner: escrow_program_id,
+                    data: vec![0; ReferrerAccount::get_packed_len()],
+                    ..TestAccount::default()
+                };
+                self.earnings.pack_into_slice(&mut referrer_account.data);
+                referrer_account
+            } else {
+                TestAccount::default()
+            };
+
+            let mut taker_account = TestAccount::default();
+            let mut seller_account = TestAccount::default();
+            let mut mint_account = TestAccount::default();
+            let mut token_program_account = TestAccount::default();
+            let mut system_program_account = TestAccount::default();
+            let mut pda_account = TestAccount::default();
+            let mut platform_account = platform_test_account(&treasury_pubkey, 2000);
+            let mut treasury_account = TestAccount::default();
+            let mut referrer_main_account = TestAccount::default();
+
+            let mut accounts = [
+                (&self.taker, true, &mut taker_account),
+                (&token_pubkey, false, &mut token_account),
+                (&self.seller, false, &mut seller_account),
+                (&mint_pubkey, false, &mut mint_account),
+                (&escrow_pubkey, false, &mut escrow_account),
+                (&spl_token::ID, false, &mut token_program_account),
+                (&system_program::ID, false, &mut system_program_account),
+                (&pda, false, &mut pda_account),
+                (&metadata_pubkey, false, &mut metadata_account),
+                (&platform_pubkey, false, &mut platform_account),
+                (&treasury_pubkey, false, &mut treasury_account),
+                (&self.referrer, false, &mut referrer_main_account),
+                (&self.referrer_account, false, &mut referrer_account),
+            ];
+            let accounts = create_is_signer_account_infos(&mut accounts);
+            Processor::process_exchange_with_options(&accounts, 1, EXCHANGE_OPTION_REFERRER, &escrow_program_id)?;
+
+            let earnings = ReferrerAccount::unpack(&accounts[12].data.borrow())?;
+            Ok(earnings)
+        }
+    }
+
+    // the referrer's share of the fee is added to its earnings
+    #[test]
+    fn exchange_with_referrer_test() {
+        let exchange = ReferredExchange::new();
+        let earnings = exchange.process().unwrap();
+        assert_eq!(earnings.referrer, exchange.referrer);
+        // 2000 basis points of the 25 lamports platform fee
+        assert_eq!(earnings.total_earned, 105);
+        assert_eq!(earnings.sales, 3);
+    }
+
+    #[test]
+    fn exchange_with_invalid_referrer_test() {
+        // the taker cannot refer itself
+        let exchange = ReferredExchange::new();
+        let invalid = ReferredExchange {
+            referrer: exchange.taker,
+            referrer_account: find_referrer_address(&escrow_program_id(), &exchange.taker).0,
+            ..exchange
+        };
+        assert_eq!(invalid.process(), Err(ProgramError::InvalidAccountData));
+
+        // nor can the seller
+        let exchange = ReferredExchange::new();
+        let invalid = ReferredExchange {
+            referrer: exchange.seller,
+            referrer_account: find_referrer_address(&escrow_program_id(), &exchange.seller).0,
+            ..exchange
+        };
+        assert_eq!(invalid.process(), Err(ProgramError::InvalidAccountData));
+
+        // the earnings are kept at the referrer's PDA
+        let invalid = ReferredExchange {
+            referrer_account: Pubkey::new_unique(),
+            ..ReferredExchange::new()
+        };
+        assert_eq!(invalid.process(), Err(ProgramError::InvalidAccountData));
+    }
+
+    // the taker cannot name a referrer that has not registered, such as a second
+    // wallet of its own, to take the referrer's share of the fee
+    #[test]
+    fn exchange_with_unregistered_referrer_test() {
+        let exchange = ReferredExchange {
+            registered: false,
+            ..ReferredExchange::new()
+        };
+        record_invoked();
+        assert_eq!(
+            exchange.process(),
+            Err(EscrowError::UnregisteredReferrer.into())
+        );
+        assert!(!invoked().contains(&system_instruction::transfer(&exchange.taker, &exchange.referrer, 5)));
+
+        // nor pass the earnings account of another referrer
+        let exchange = ReferredExchange::new();
+        let invalid = ReferredExchange {
+            earnings: ReferrerAccount {
+                referrer: Pubkey::new_unique(),
+                ..exchange.earnings
+            },
+            ..exchange
+        };
+        assert_eq!(invalid.process(), Err(ProgramError::InvalidAccountData));
+    }
+
+    fn register_referrer(
+        referrer: &Pubkey,
+        referrer_account_pubkey: &Pubkey,
+        referrer_account: &mut TestAccount,
+        is_signer: bool,
+    ) -> Result<ReferrerAccount, ProgramError> {
+        let mut referrer_main_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut accounts = [
+            (referrer, is_signer, &mut referrer_main_account),
+            (referrer_account_pubkey, false, referrer_account),
+            (&system_program::ID, false, &mut system_program_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_register_referrer(&accounts, &escrow_program_id())?;
+
+        let earnings = ReferrerAccount::unpack(&accounts[1].data.borrow())?;
+        Ok(earnings)
+    }
+
+    // the referrer signs for and pays its earnings account, once; lamports sent to
+    // the address beforehand only lower what it pays
+    #[test]
+    fn register_referrer_test() {
+        let rent_exempt_minimum = Rent::default().minimum_balance(ReferrerAccount::get_packed_len());
+        for prefunded in [0, 1, rent_exempt_minimum, rent_exempt_minimum + 1] {
+            let referrer = Pubkey::new_unique();
+            let (referrer_account_pubkey, _bump_seed) = find_referrer_address(&escrow_program_id(), &referrer);
+            // allocated by the system program, which is not run here
+            let mut referrer_account = TestAccount {
+                lamports: prefunded,
+                data: vec![0; ReferrerAccount::get_packed_len()],
+                ..TestAccount::default()
+            };
+            record_invoked();
+            let earnings = register_referrer(&referrer, &referrer_account_pubkey, &mut referrer_account, true).unwrap();
+            assert_eq!(
+                earnings,
+                ReferrerAccount {
+                    is_initialized: true,
+                    referrer,
+                    total_earned: 0,
+                    sales: 0,
+                }
+            );
+
+            let mut created = Vec::new();
+            if prefunded < rent_exempt_minimum {
+                created.push(system_instruction::transfer(
+                    &referrer,
+                    &referrer_account_pubkey,
+                    rent_exempt_minimum - prefunded,
+                ));
+            }
+            created.push(system_instruction::allocate(
+                &referrer_account_pubkey,
+                ReferrerAccount::get_packed_len() as u64,
+            ));
+            created.push(system_instruction::assign(&referrer_account_pubkey, &escrow_program_id()));
+            assert_eq!(invoked(), created);
+        }
+
+        let referrer = Pubkey::new_unique();
+        let (referrer_account_pubkey, _bump_seed) = find_referrer_address(&escrow_program_id(), &referrer);
+        let mut referrer_account = TestAccount {
+            data: vec![0; ReferrerAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        assert_eq!(
+            register_referrer(&referrer, &referrer_account_pubkey, &mut referrer_account, false),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            register_referrer(&referrer, &Pubkey::new_unique(), &mut referrer_account, true),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // a registered referrer keeps its earnings
+        let mut referrer_account = TestAccount {
+            owner: escrow_program_id(),
+            data: vec![0; ReferrerAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        ReferredExchange::new().earnings.pack_into_slice(&mut referrer_account.data);
+        assert_eq!(
+            register_referrer(&referrer, &referrer_account_pubkey, &mut referrer_account, true),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+    }
+
+    fn set_referral_share(admin: &Pubkey, referral_basis_points: u64) -> ProgramResult {
+        let platform_pubkey = Pubkey::new_unique();
+        let mut admin_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&Pubkey::new_unique(), 0);
+        let mut accounts = [
+            (admin, true, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_referral_share(&accounts, referral_basis_points, &escrow_program_id())?;
+
+        let platform = VaultAccount::unpack(&accounts[1].data.borrow())?;
+        assert_eq!(platform.referral_basis_points, referral_basis_points);
+        Ok(())
+    }
+
+    // only the platform admin sets the referral share, of at most the whole fee
+    #[test]
+    fn set_referral_share_test() {
+        let admin = Pubkey::from_str("J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq").unwrap();
+        set_referral_share(&admin, 2000).unwrap();
+        set_referral_share(&admin, 10000).unwrap();
+        assert_eq!(
+            set_referral_share(&admin, 10001),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            set_referral_share(&Pubkey::new_unique(), 2000),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
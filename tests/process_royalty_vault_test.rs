@@ -0,0 +1,438 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, str::FromStr, sync::Once};
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::{ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction,
+        system_program,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        error::EscrowError,
+        pda::{find_escrow_authority, find_metadata_address, find_royalty_vault_address},
+        processor::Processor,
+        state::{Escrow, ListingMode, RoyaltyVault, TokenProgram, VaultAccount},
+    };
+
+    thread_local! {
+        static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // hands the processor the default rent and records the instructions it invokes
+    struct RecordingStubs;
+
+    impl SyscallStubs for RecordingStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+            Ok(())
+        }
+    }
+
+    fn record_invoked() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(RecordingStubs));
+        });
+        INVOKED.with(|invoked| invoked.borrow_mut().clear());
+    }
+
+    fn invoked() -> Vec<Instruction> {
+        INVOKED.with(|invoked| invoked.borrow().clone())
+    }
+
+    fn platform_test_account(program_id: &Pubkey, treasury: &Pubkey, escrow_royalties: bool) -> TestAccount {
+        let mut platform_account = TestAccount {
+            owner: *program_id,
+            data: vec![0; VaultAccount::LEN],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: *treasury,
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        platform_account
+    }
+
+    // a royalty vault the program already created, holding the unclaimed royalties
+    // of `vault` on top of its own balance of 1000
+    fn royalty_vault_test_account(program_id: &Pubkey, vault: Option<RoyaltyVault>) -> TestAccount {
+        let mut royalty_vault_account = TestAccount {
+            lamports: 1000 + vault.map_or(0, |vault| vault.unclaimed()),
+            owner: *program_id,
+            data: vec![0; RoyaltyVault::LEN],
+            ..TestAccount::default()
+        };
+        if let Some(vault) = vault {
+            vault.pack_into_slice(&mut royalty_vault_account.data);
+        }
+        royalty_vault_account
+    }
+
+    // a sale of 1_000_000 lamports with 1000 bps of royalties, all of them owed
+    // to a single creator
+    struct RoyaltyExchange {
+        program_id: Pubkey,
+        escrow_royalties: bool,
+        taker: Pubkey,
+        creator: Pubkey,
+        creator_account: TestAccount,
+        royalty_vault: Option<TestAccount>,
+    }
+
+    impl RoyaltyExchange {
+        fn new() -> Self {
+            let program_id = Pubkey::new_unique();
+            RoyaltyExchange {
+                program_id,
+                escrow_royalties: false,
+                taker: Pubkey::new_unique(),
+                creator: Pubkey::new_unique(),
+                // a creator the system program cannot credit, such as a PDA of another program
+                creator_account: TestAccount::new(0, 0, &Pubkey::new_unique()),
+                royalty_vault: Some(royalty_vault_test_account(&program_id, None)),
+            }
+        }
+
+        // runs the exchange and returns the royalty vault afterwards
+        fn process(self) -> Result<Option<RoyaltyVault>, ProgramError> {
+            let program_id = self.program_id;
+            let price = 1_000_000;
+            let seller = Pubkey::new_unique();
+            let token_account = Pubkey::new_unique();
+            let mint = Pubkey::new_unique();
+            let platform_account = Pubkey::new_unique();
+            let treasury = Pubkey::new_unique();
+            let (escrow_authority, escrow_authority_bump_seed) = find_escrow_authority(&program_id);
+            let (metadata_key, metadata_bump_seed) = find_metadata_address(&mint);
+
+            let mut escrow_account = TestAccount {
+                owner: program_id,
+                data: vec![0; Escrow::LEN],
+                ..TestAccount::default()
+            };
+            Escrow {
+                is_initialized: true,
+                seller_pubkey: seller,
+                token_account_pubkey: token_account,
+                mint_key: mint,
+                expected_amount: price,
+                marketplace: Pubkey::default(),
+                listing_mode: ListingMode::Custody,
+                token_program: TokenProgram::SplToken,
+                usd_priced: false,
+                escrow_authority_bump_seed,
+                metadata_bump_seed,
+                proceeds_vault: false,
+            }
+            .pack_into_slice(&mut escrow_account.data);
+
+            let mut token_account_data = TestAccount {
+                owner: escrow_authority,
+                data: vec![0; TokenAccount::LEN],
+                ..TestAccount::default()
+            };
+            TokenAccount {
+                amount: 1,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            }
+            .pack_into_slice(&mut token_account_data.data);
+
+            let mut metadata_account = TestAccount::new(0, MAX_METADATA_LEN, &Pubkey::new_unique());
+            Metadata {
+                key: Key::MetadataV1,
+                update_authority: Pubkey::new_unique(),
+                mint,
+                data: Data {
+                    name: String::from("Hello, world!"),
+                    symbol: String::from("Hello, world!"),
+                    uri: String::from("Hello, world!"),
+                    seller_fee_basis_points: 1000,
+                    creators: Some(vec![Creator {
+                        address: self.creator,
+                        verified: true,
+                        share: 100,
+                    }]),
+                },
+                primary_sale_happened: true,
+                is_mutable: false,
+                edition_nonce: None,
+            }
+            .serialize(&mut &mut metadata_account.data[..])
+            .unwrap();
+
+            let taker = self.taker;
+            let mut keyed_accounts = vec![
+                (taker, TestAccount::default()),
+                (token_account, token_account_data),
+                (seller, TestAccount::default()),
+                (mint, TestAccount::default()),
+                (Pubkey::new_unique(), escrow_account),
+                (spl_token::id(), TestAccount::default()),
+                (system_program::id(), TestAccount::default()),
+                (escrow_authority, TestAccount::default()),
+                (metadata_key, metadata_account),
+                (
+                    platform_account,
+                    platform_test_account(&program_id, &treasury, self.escrow_royalties),
+                ),
+                (treasury, TestAccount::default()),
+                (self.creator, self.creator_account),
+            ];
+            if let Some(royalty_vault) = self.royalty_vault {
+                keyed_accounts.push((find_royalty_vault_address(&program_id, &self.creator).0, royalty_vault));
+            }
+
+            let mut accounts: Vec<_> = keyed_accounts
+                .iter_mut()
+                .map(|(key, account)| (&*key, *key == taker, account))
+                .collect();
+            let accounts = create_is_signer_account_infos(&mut accounts);
+
+            Processor::process_exchange(&accounts, 1, &program_id)?;
+
+            accounts
+                .get(12)
+                .map(|royalty_vault| RoyaltyVault::unpack(&royalty_vault.data.borrow()))
+                .transpose()
+        }
+    }
+
+    // a creator the system program cannot credit is paid into its vault
+    #[test]
+    fn exchange_deposits_royalty_test() {
+        let exchange = RoyaltyExchange::new();
+        let creator = exchange.creator;
+        assert_eq!(
+            exchange.process().unwrap(),
+            Some(RoyaltyVault {
+                is_initialized: true,
+                creator,
+                total_deposited: 100_000,
+                total_claimed: 0,
+                sales: 1,
+            })
+        );
+
+        // later sales add up in the same vault
+        let exchange = RoyaltyExchange::new();
+        let previous = RoyaltyVault {
+            is_initialized: true,
+            creator: exchange.creator,
+            total_deposited: 5000,
+            total_claimed: 2000,
+            sales: 3,
+        };
+        let exchange = RoyaltyExchange {
+            royalty_vault: Some(royalty_vault_test_account(&exchange.program_id, Some(previous))),
+            ..exchange
+        };
+        assert_eq!(
+            exchange.process().unwrap(),
+            Some(RoyaltyVault {
+                total_deposited: 105_000,
+                sales: 4,
+                ..previous
+            })
+        );
+    }
+
+    // lamports sent to the vault address ahead of the first royalty only lower what
+    // the taker pays to create it
+    #[test]
+    fn exchange_prefunded_royalty_vault_test() {
+        let rent_exempt_minimum = Rent::default().minimum_balance(RoyaltyVault::LEN);
+        for prefunded in [1, rent_exempt_minimum, rent_exempt_minimum + 1] {
+            let exchange = RoyaltyExchange {
+                // allocated by the system program, which is not run here
+                royalty_vault: Some(TestAccount {
+                    lamports: prefunded,
+                    data: vec![0; RoyaltyVault::LEN],
+                    ..TestAccount::default()
+                }),
+                ..RoyaltyExchange::new()
+            };
+            let program_id = exchange.program_id;
+            let taker = exchange.taker;
+            let creator = exchange.creator;
+            let (royalty_vault, _royalty_vault_bump_seed) = find_royalty_vault_address(&program_id, &creator);
+            record_invoked();
+            let vault = exchange.process().unwrap().unwrap();
+            assert_eq!(vault.creator, creator);
+            assert_eq!(vault.total_deposited, 100_000);
+            assert_eq!(vault.sales, 1);
+
+            // the taker creates the vault and deposits the royalty
+            let mut created = Vec::new();
+            if prefunded < rent_exempt_minimum {
+                created.push(system_instruction::transfer(&taker, &royalty_vault, rent_exempt_minimum - prefunded));
+            }
+            created.push(system_instruction::allocate(&royalty_vault, RoyaltyVault::LEN as u64));
+            created.push(system_instruction::assign(&royalty_vault, &program_id));
+            created.push(system_instruction::transfer(&taker, &royalty_vault, 100_000));
+            assert!(invoked().windows(created.len()).any(|invoked| invoked == created));
+        }
+    }
+
+    // a platform escrowing royalties pays every creator into its vault
+    #[test]
+    fn exchange_escrow_royalties_test() {
+        let exchange = RoyaltyExchange {
+            escrow_royalties: true,
+            creator_account: TestAccount::default(),
+            ..RoyaltyExchange::new()
+        };
+        assert_eq!(exchange.process().unwrap().unwrap().total_deposited, 100_000);
+
+        let exchange = RoyaltyExchange {
+            escrow_royalties: true,
+            creator_account: TestAccount::default(),
+            royalty_vault: None,
+            ..RoyaltyExchange::new()
+        };
+        assert_eq!(exchange.process(), Err(EscrowError::MissingRoyaltyVault.into()));
+    }
+
+    #[test]
+    fn exchange_missing_royalty_vault_test() {
+        // the creator cannot be paid directly and has no vault to fall back on
+        let exchange = RoyaltyExchange {
+            royalty_vault: None,
+            ..RoyaltyExchange::new()
+        };
+        assert_eq!(exchange.process(), Err(EscrowError::MissingRoyaltyVault.into()));
+
+        // without a vault a creator of the system program is paid directly, as before
+        let exchange = RoyaltyExchange {
+            creator_account: TestAccount::default(),
+            royalty_vault: None,
+            ..RoyaltyExchange::new()
+        };
+        assert_eq!(exchange.process(), Ok(None));
+    }
+
+    fn claim_royalties(
+        signer: &Pubkey,
+        vault: RoyaltyVault,
+        vault_lamports: u64,
+    ) -> Result<(RoyaltyVault, u64, u64), ProgramError> {
+        let program_id = Pubkey::new_unique();
+        let royalty_vault_pubkey = find_royalty_vault_address(&program_id, &vault.creator).0;
+        let destination = Pubkey::new_unique();
+        let mut signer_account = TestAccount::default();
+        let mut royalty_vault_account = TestAccount {
+            lamports: vault_lamports,
+            ..royalty_vault_test_account(&program_id, Some(vault))
+        };
+        let mut destination_account = TestAccount::new(10, 0, &system_program::id());
+        let mut accounts = [
+            (signer, true, &mut signer_account),
+            (&royalty_vault_pubkey, false, &mut royalty_vault_account),
+            (&destination, false, &mut destination_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_claim_royalties(&accounts, &program_id)?;
+
+        let vault = RoyaltyVault::unpack(&accounts[1].data.borrow())?;
+        Ok((vault, accounts[1].lamports(), accounts[2].lamports()))
+    }
+
+    // the creator takes what was deposited since the last claim, the vault keeps
+    // its own balance
+    #[test]
+    fn claim_royalties_test() {
+        let creator = Pubkey::new_unique();
+        let vault = RoyaltyVault {
+            is_initialized: true,
+            creator,
+            total_deposited: 5000,
+            total_claimed: 2000,
+            sales: 3,
+        };
+        assert_eq!(
+            claim_royalties(&creator, vault, 4000).unwrap(),
+            (
+                RoyaltyVault {
+                    total_claimed: 5000,
+                    ..vault
+                },
+                1000,
+                3010
+            )
+        );
+
+        // nothing left to claim
+        let claimed = RoyaltyVault {
+            total_claimed: 5000,
+            ..vault
+        };
+        assert_eq!(claim_royalties(&creator, claimed, 1000), Err(ProgramError::InsufficientFunds));
+
+        // only the creator claims its royalties
+        assert_eq!(
+            claim_royalties(&Pubkey::new_unique(), vault, 4000),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    fn set_escrow_royalties(admin: &Pubkey, escrow_royalties: bool) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let platform_pubkey = Pubkey::new_unique();
+        let mut admin_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&program_id, &Pubkey::new_unique(), !escrow_royalties);
+        let mut accounts = [
+            (admin, true, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_escrow_royalties(&accounts, escrow_royalties, &program_id)?;
+
+        let platform = VaultAccount::unpack(&accounts[1].data.borrow())?;
+        assert_eq!(platform.escrow_royalties, escrow_royalties);
+        Ok(())
+    }
+
+    // only the platform admin decides whether royalties are escrowed
+    #[test]
+    fn set_escrow_royalties_test() {
+        let admin = Pubkey::from_str("J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq").unwrap();
+        set_escrow_royalties(&admin, true).unwrap();
+        set_escrow_royalties(&admin, false).unwrap();
+        assert_eq!(
+            set_escrow_royalties(&Pubkey::new_unique(), true),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -0,0 +1,536 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, sync::Once};
+
+    use borsh::BorshSerialize;
+    use metaplex_token_metadata::state::{Data, Key, Metadata, MAX_METADATA_LEN};
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::{ProgramResult, SUCCESS},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_instruction,
+        system_program,
+        sysvar::{
+            self,
+            instructions::{construct_instructions_data, store_current_index, BorrowedAccountMeta, BorrowedInstruction},
+        },
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::state::Account as TokenAccount;
+
+    use escrow_buy::{
+        error::EscrowError,
+        instruction::{self, ExchangeOptionalAccounts, EXCHANGE_OPTION_SELLER_PROCEEDS},
+        pda::{find_escrow_authority, find_metadata_address, find_seller_proceeds_address},
+        processor::Processor,
+        state::{Escrow, ListingMode, SellerProceeds, TokenProgram, VaultAccount},
+    };
+
+    thread_local! {
+        static INVOKED: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // hands the processor the default rent and records the instructions it invokes
+    struct RecordingStubs;
+
+    impl SyscallStubs for RecordingStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+            Ok(())
+        }
+    }
+
+    fn record_invoked() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(RecordingStubs));
+        });
+        INVOKED.with(|invoked| invoked.borrow_mut().clear());
+    }
+
+    fn invoked() -> Vec<Instruction> {
+        INVOKED.with(|invoked| invoked.borrow().clone())
+    }
+
+    // a seller proceeds account the program already created, holding the balance
+    // of `proceeds` on top of its own balance of 1000
+    fn seller_proceeds_test_account(program_id: &Pubkey, proceeds: Option<SellerProceeds>) -> TestAccount {
+        let mut seller_proceeds_account = TestAccount {
+            lamports: 1000 + proceeds.map_or(0, |proceeds| proceeds.balance()),
+            owner: *program_id,
+            data: vec![0; SellerProceeds::LEN],
+            ..TestAccount::default()
+        };
+        if let Some(proceeds) = proceeds {
+            proceeds.pack_into_slice(&mut seller_proceeds_account.data);
+        }
+        seller_proceeds_account
+    }
+
+    // a sale of 1_000_000 lamports without royalties, of which the seller gets
+    // 975_000 after the platform fee of 250 bps
+    struct ProceedsExchange {
+        program_id: Pubkey,
+        taker: Pubkey,
+        seller: Pubkey,
+        proceeds_vault: bool,
+        seller_proceeds: Option<(Pubkey, TestAccount)>,
+    }
+
+    impl ProceedsExchange {
+        fn new() -> Self {
+            let program_id = Pubkey::new_unique();
+            let seller = Pubkey::new_unique();
+            ProceedsExchange {
+                program_id,
+                taker: Pubkey::new_unique(),
+                seller,
+                proceeds_vault: true,
+                seller_proceeds: Some((
+                    find_seller_proceeds_address(&program_id, &seller).0,
+                    seller_proceeds_test_account(&program_id, None),
+                )),
+            }
+        }
+
+        // runs the exchange and returns the seller proceeds afterwards
+        fn process(self) -> Result<Option<SellerProceeds>, ProgramError> {
+            let program_id = self.program_id;
+            let price = 1_000_000;
+            let token_account = Pubkey::new_unique();
+            let mint = Pubkey::new_unique();
+            let platform_account = Pubkey::new_unique();
+            let treasury = Pubkey::new_unique();
+            let (escrow_authority, escrow_authority_bump_seed) = find_escrow_authority(&program_id);
+            let (metadata_key, metadata_bump_seed) = find_metadata_address(&mint);
+
+            let mut escrow_account = TestAccount {
+                owner: program_id,
+                data: vec![0; Escrow::LEN],
+                ..TestAccount::default()
+            };
+            Escrow {
+                is_initialized: true,
+                seller_pubkey: self.seller,
+                token_account_pubkey: token_account,
+                mint_key: mint,
+                expected_amount: price,
+                marketplace: Pubkey::default(),
+                listing_mode: ListingMode::Custody,
+                token_program: TokenProgram::SplToken,
+                usd_priced: false,
+                escrow_authority_bump_seed,
+                metadata_bump_seed,
+                proceeds_vault: self.proceeds_vault,
+            }
+            .pack_into_slice(&mut escrow_account.data);
+
+            let mut token_account_data = TestAccount {
+                owner: escrow_authority,
+                data: vec![0; TokenAccount::LEN],
+                ..TestAccount::default()
+            };
+            TokenAccount {
+                amount: 1,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            }
+            .pack_into_slice(&mut token_account_data.data);
+
+            let mut platform_account_data = TestAccount {
+                owner: program_id,
+                data: vec![0; VaultAccount::LEN],
+                ..TestAccount::default()
+            };
+            VaultAccount {
+                is_initialized: true,
+                treasury_account: treasury,
+                base_percentage: 250,
+                paused: false,
+                pass_collection: Pubkey::default(),
+                pass_fee_basis_points: 0,
+                referral_basis_points: 0,
+                escrow_royalties: false,
+            }
+            .pack_into_slice(&mut platform_account_data.data);
+
+            let mut metadata_account = TestAccount::new(0, MAX_METADATA_LEN, &Pubkey::new_unique());
+            Metadata {
+                key: Key::MetadataV1,
+                update_authority: Pubkey::new_unique(),
+                mint,
+                data: Data {
+                    name: String::from("Hello, world!"),
+                    symbol: String::from("Hello, world!"),
+                    uri: String::from("Hello, world!"),
+                    seller_fee_basis_points: 0,
+                    creators: None,
+                },
+                primary_sale_happened: true,
+                is_mutable: false,
+                edition_nonce: None,
+            }
+            .serialize(&mut &mut metadata_account.data[..])
+            .unwrap();
+
+            let taker = self.taker;
+            let mut keyed_accounts = vec![
+                (taker, TestAccount::default()),
+                (token_account, token_account_data),
+                (self.seller, TestAccount::default()),
+                (mint, TestAccount::default()),
+                (Pubkey::new_unique(), escrow_account),
+                (spl_token::id(), TestAccount::default()),
+                (system_program::id(), TestAccount::default()),
+                (escrow_authority, TestAccount::default()),
+                (metadata_key, metadata_account),
+                (platform_account, platform_account_data),
+                (treasury, TestAccount::default()),
+            ];
+            let options = match self.seller_proceeds {
+                Some(seller_proceeds) => {
+                    keyed_accounts.push(seller_proceeds);
+                    EXCHANGE_OPTION_SELLER_PROCEEDS
+                }
+                None => 0,
+            };
+
+            let mut accounts: Vec<_> = keyed_accounts
+                .iter_mut()
+                .map(|(key, account)| (&*key, *key == taker, account))
+                .collect();
+            let accounts = create_is_signer_account_infos(&mut accounts);
+
+            Processor::process_exchange_with_options(&accounts, 1, options, &program_id)?;
+
+            accounts
+                .get(11)
+                .map(|seller_proceeds| SellerProceeds::unpack(&seller_proceeds.data.borrow()))
+                .transpose()
+        }
+    }
+
+    // the proceeds of a listing in proceeds-vault mode add up in the seller's account
+    #[test]
+    fn exchange_credits_proceeds_test() {
+        let exchange = ProceedsExchange::new();
+        let seller = exchange.seller;
+        assert_eq!(
+            exchange.process().unwrap(),
+            Some(SellerProceeds {
+                is_initialized: true,
+                seller,
+                total_credited: 975_000,
+                total_withdrawn: 0,
+                sales: 1,
+            })
+        );
+
+        let exchange = ProceedsExchange::new();
+        let previous = SellerProceeds {
+            is_initialized: true,
+            seller: exchange.seller,
+            total_credited: 5000,
+            total_withdrawn: 5000,
+            sales: 1,
+        };
+        let seller_proceeds = exchange.seller_proceeds.as_ref().unwrap().0;
+        let exchange = ProceedsExchange {
+            seller_proceeds: Some((
+                seller_proceeds,
+                seller_proceeds_test_account(&exchange.program_id, Some(previous)),
+            )),
+            ..exchange
+        };
+        assert_eq!(
+            exchange.process().unwrap(),
+            Some(SellerProceeds {
+                total_credited: 980_000,
+                sales: 2,
+                ..previous
+            })
+        );
+    }
+
+    // lamports sent to the proceeds address ahead of the first credited sale only
+    // lower what the taker pays to create it
+    #[test]
+    fn exchange_prefunded_seller_proceeds_test() {
+        let rent_exempt_minimum = Rent::default().minimum_balance(SellerProceeds::LEN);
+        for prefunded in [1, rent_exempt_minimum, rent_exempt_minimum + 1] {
+            let exchange = ProceedsExchange::new();
+            let program_id = exchange.program_id;
+            let taker = exchange.taker;
+            let seller = exchange.seller;
+            let seller_proceeds = exchange.seller_proceeds.as_ref().unwrap().0;
+            let exchange = ProceedsExchange {
+                // allocated by the system program, which is not run here
+                seller_proceeds: Some((
+                    seller_proceeds,
+                    TestAccount {
+                        lamports: prefunded,
+                        data: vec![0; SellerProceeds::LEN],
+                        ..TestAccount::default()
+                    },
+                )),
+                ..exchange
+            };
+            record_invoked();
+            let proceeds = exchange.process().unwrap().unwrap();
+            assert_eq!(proceeds.seller, seller);
+            assert_eq!(proceeds.total_credited, 975_000);
+            assert_eq!(proceeds.sales, 1);
+
+            // the taker creates the account and credits the proceeds
+            let mut created = Vec::new();
+            if prefunded < rent_exempt_minimum {
+                created.push(system_instruction::transfer(&taker, &seller_proceeds, rent_exempt_minimum - prefunded));
+            }
+            created.push(system_instruction::allocate(&seller_proceeds, SellerProceeds::LEN as u64));
+            created.push(system_instruction::assign(&seller_proceeds, &program_id));
+            created.push(system_instruction::transfer(&taker, &seller_proceeds, 975_000));
+            assert!(invoked().windows(created.len()).any(|invoked| invoked == created));
+        }
+    }
+
+    // the seller proceeds account is passed exactly for listings in proceeds-vault mode
+    #[test]
+    fn exchange_seller_proceeds_option_test() {
+        let exchange = ProceedsExchange {
+            seller_proceeds: None,
+            ..ProceedsExchange::new()
+        };
+        assert_eq!(exchange.process(), Err(ProgramError::InvalidAccountData));
+
+        let exchange = ProceedsExchange {
+            proceeds_vault: false,
+            ..ProceedsExchange::new()
+        };
+        assert_eq!(exchange.process(), Err(ProgramError::InvalidAccountData));
+
+        let exchange = ProceedsExchange {
+            proceeds_vault: false,
+            seller_proceeds: None,
+            ..ProceedsExchange::new()
+        };
+        assert_eq!(exchange.process(), Ok(None));
+    }
+
+    // the proceeds account of someone else
+    #[test]
+    fn exchange_invalid_seller_proceeds_test() {
+        let exchange = ProceedsExchange::new();
+        let other = find_seller_proceeds_address(&exchange.program_id, &Pubkey::new_unique()).0;
+        let exchange = ProceedsExchange {
+            seller_proceeds: Some((other, seller_proceeds_test_account(&exchange.program_id, None))),
+            ..exchange
+        };
+        assert_eq!(exchange.process(), Err(ProgramError::InvalidAccountData));
+    }
+
+    // batch sales of such a listing leave the seller account read-only
+    #[test]
+    fn exchange_with_seller_proceeds_instruction_test() {
+        let program_id = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let instruction = instruction::exchange_with_options(
+            &program_id,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &seller,
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &ExchangeOptionalAccounts {
+                seller_proceeds: true,
+                ..ExchangeOptionalAccounts::default()
+            },
+            &[],
+            1,
+        )
+        .unwrap();
+        assert_eq!(instruction.accounts[2].pubkey, seller);
+        assert!(!instruction.accounts[2].is_writable);
+        assert_eq!(instruction.accounts[11].pubkey, find_seller_proceeds_address(&program_id, &seller).0);
+        assert!(instruction.accounts[11].is_writable);
+    }
+
+    // the instructions sysvar of a transaction of `instructions`, running the last one
+    fn instructions_sysvar(instructions: &[Instruction]) -> TestAccount {
+        let borrowed: Vec<BorrowedInstruction> = instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|account| BorrowedAccountMeta {
+                        pubkey: &account.pubkey,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                    .collect(),
+                data: &instruction.data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        store_current_index(&mut data, instructions.len() as u16 - 1);
+        TestAccount {
+            owner: sysvar::id(),
+            data,
+            ..TestAccount::default()
+        }
+    }
+
+    // sets the mode of a listing by `seller`, in the transaction listing it when `listing`
+    fn set_proceeds_vault(signer: &Pubkey, seller: &Pubkey, proceeds_vault: bool, listing: bool) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let escrow_pubkey = Pubkey::new_unique();
+        let mut escrow_account = TestAccount {
+            owner: program_id,
+            data: vec![0; Escrow::LEN],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: *seller,
+            token_account_pubkey: Pubkey::new_unique(),
+            mint_key: Pubkey::new_unique(),
+            expected_amount: 100,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Custody,
+            token_program: TokenProgram::SplToken,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: !proceeds_vault,
+        }
+        .pack_into_slice(&mut escrow_account.data);
+        let mut instructions = vec![instruction::set_proceeds_vault(&program_id, signer, &escrow_pubkey, proceeds_vault).unwrap()];
+        if listing {
+            let list = instruction::list_token(
+                &program_id,
+                seller,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &escrow_pubkey,
+                &Pubkey::new_unique(),
+                100,
+            )
+            .unwrap();
+            instructions.insert(0, list);
+        }
+        let mut signer_account = TestAccount::default();
+        let mut instructions_account = instructions_sysvar(&instructions);
+        let mut accounts = [
+            (signer, true, &mut signer_account),
+            (&escrow_pubkey, false, &mut escrow_account),
+            (&sysvar::instructions::id(), false, &mut instructions_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_proceeds_vault(&accounts, proceeds_vault, &program_id)?;
+
+        let escrow = Escrow::unpack(&accounts[1].data.borrow())?;
+        assert_eq!(escrow.proceeds_vault, proceeds_vault);
+        Ok(())
+    }
+
+    // only the seller decides where the proceeds of a listing go, and only when
+    // listing it, so that the accounts of a sale on its way stay the same
+    #[test]
+    fn set_proceeds_vault_test() {
+        let seller = Pubkey::new_unique();
+        set_proceeds_vault(&seller, &seller, true, true).unwrap();
+        set_proceeds_vault(&seller, &seller, false, true).unwrap();
+        assert_eq!(
+            set_proceeds_vault(&Pubkey::new_unique(), &seller, true, true),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            set_proceeds_vault(&seller, &seller, true, false),
+            Err(EscrowError::ProceedsVaultAfterListing.into())
+        );
+    }
+
+    fn withdraw_proceeds(
+        signer: &Pubkey,
+        proceeds: SellerProceeds,
+        proceeds_lamports: u64,
+    ) -> Result<(SellerProceeds, u64, u64), ProgramError> {
+        let program_id = Pubkey::new_unique();
+        let seller_proceeds_pubkey = find_seller_proceeds_address(&program_id, &proceeds.seller).0;
+        let destination = Pubkey::new_unique();
+        let mut signer_account = TestAccount::default();
+        let mut seller_proceeds_account = TestAccount {
+            lamports: proceeds_lamports,
+            ..seller_proceeds_test_account(&program_id, Some(proceeds))
+        };
+        let mut destination_account = TestAccount::new(10, 0, &system_program::id());
+        let mut accounts = [
+            (signer, true, &mut signer_account),
+            (&seller_proceeds_pubkey, false, &mut seller_proceeds_account),
+            (&destination, false, &mut destination_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_withdraw_proceeds(&accounts, &program_id)?;
+
+        let proceeds = SellerProceeds::unpack(&accounts[1].data.borrow())?;
+        Ok((proceeds, accounts[1].lamports(), accounts[2].lamports()))
+    }
+
+    // the seller takes the balance, the account keeps its own lamports
+    #[test]
+    fn withdraw_proceeds_test() {
+        let seller = Pubkey::new_unique();
+        let proceeds = SellerProceeds {
+            is_initialized: true,
+            seller,
+            total_credited: 9000,
+            total_withdrawn: 4000,
+            sales: 2,
+        };
+        assert_eq!(
+            withdraw_proceeds(&seller, proceeds, 6000).unwrap(),
+            (
+                SellerProceeds {
+                    total_withdrawn: 9000,
+                    ..proceeds
+                },
+                1000,
+                5010
+            )
+        );
+
+        // nothing left to withdraw
+        let withdrawn = SellerProceeds {
+            total_withdrawn: 9000,
+            ..proceeds
+        };
+        assert_eq!(withdraw_proceeds(&seller, withdrawn, 1000), Err(ProgramError::InsufficientFunds));
+
+        // only the seller withdraws its proceeds
+        assert_eq!(
+            withdraw_proceeds(&Pubkey::new_unique(), proceeds, 6000),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
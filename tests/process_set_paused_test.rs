@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use solana_program::{
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+    };
+    use solana_sdk::{
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+
+    use escrow_buy::{
+        processor::Processor,
+        state::VaultAccount,
+    };
+
+    // platform account owned by the escrow program, not paused
+    fn platform_test_account(escrow_program_id: &Pubkey) -> TestAccount {
+        let mut platform_account = TestAccount {
+            owner: *escrow_program_id,
+            data: vec![0; VaultAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        platform_account
+    }
+
+    // only the platform admin, signing, can pause the platform
+    #[test]
+    fn set_paused_test() {
+        let escrow_program_id = Pubkey::from_str(
+            "escrowprogram111111111111111111111111111111"
+        ).unwrap();
+        let admin_pubkey = Pubkey::from_str(
+            "J7A8AeFaPNxe3w7jCxnE2xHVWZz2GgjAF9LWky5AG2Jq"
+        ).unwrap();
+        let platform_pubkey = Pubkey::new_unique();
+
+        let mut admin_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&escrow_program_id);
+
+        // the admin has to sign
+        let mut accounts = [
+            (&admin_pubkey, false, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_set_paused(&accounts, true, &escrow_program_id),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        // and nobody else can
+        let other_pubkey = Pubkey::new_unique();
+        let mut other_account = TestAccount::default();
+        let mut platform_account = platform_test_account(&escrow_program_id);
+        let mut accounts = [
+            (&other_pubkey, true, &mut other_account),
+            (&platform_pubkey, false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_set_paused(&accounts, true, &escrow_program_id),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut platform_account = platform_test_account(&escrow_program_id);
+        let mut accounts = [
+            (&admin_pubkey, true, &mut admin_account),
+            (&platform_pubkey, false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_set_paused(&accounts, true, &escrow_program_id).unwrap();
+        assert!(VaultAccount::unpack(&accounts[1].data.borrow()).unwrap().paused);
+
+        Processor::process_set_paused(&accounts, false, &escrow_program_id).unwrap();
+        assert!(!VaultAccount::unpack(&accounts[1].data.borrow()).unwrap().paused);
+    }
+}
@@ -0,0 +1,432 @@
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    error::EscrowError,
+    instruction::{self, ExchangeOptionalAccounts},
+    pda,
+    processor,
+    state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{
+    instruction::InstructionError, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+    system_instruction,
+};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{BanksClient, ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{
+    account::Account, hash::Hash, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError},
+};
+#[cfg(feature = "test-bpf")]
+use spl_token_2022::{
+    extension::{
+        immutable_owner::ImmutableOwner, transfer_fee::TransferFeeAmount, ExtensionType, StateWithExtensions,
+        StateWithExtensionsMut,
+    },
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+#[cfg(feature = "test-bpf")]
+const PRICE: u64 = 100_000;
+
+// a Token-2022 NFT minted to the seller, whose mint charges a 1% transfer fee
+// of at most `maximum_fee`
+#[cfg(feature = "test-bpf")]
+struct Token2022Nft {
+    client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    seller: Keypair,
+    taker: Keypair,
+    token_account: Keypair,
+    mint: Keypair,
+    escrow_account: Keypair,
+    platform: Pubkey,
+    treasury: Pubkey,
+    taker_token_account: Pubkey,
+}
+
+#[cfg(feature = "test-bpf")]
+async fn mint_token_2022_nft(maximum_fee: u64) -> Token2022Nft {
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let minter = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let token_account = Keypair::new();
+    let mint = Keypair::new();
+    let escrow_account = Keypair::new();
+    let platform = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_token_2022",
+        spl_token_2022::id(),
+        processor!(spl_token_2022::processor::Processor::process)
+    );
+
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    program_test.add_account(
+        treasury,
+        Account {
+            lamports: rent_exempt_minimum,
+            ..Account::default()
+        }
+    );
+
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: treasury,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    // token metadata without royalties
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&mint.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint.pubkey(),
+        data: Data {
+            name: "name".to_string(),
+            symbol: "symbol".to_string(),
+            uri: "uri".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+
+    // the associated token account program of this version only creates SPL Token
+    // accounts, the taker's Token-2022 one is set up directly
+    let taker_token_account =
+        pda::find_associated_token_address(&taker.pubkey(), &mint.pubkey(), &spl_token_2022::id());
+    let token_account_len = ExtensionType::get_account_len::<TokenAccount>(&[
+        ExtensionType::ImmutableOwner,
+        ExtensionType::TransferFeeAmount,
+    ]);
+    let mut taker_token_data = vec![0; token_account_len];
+    let mut state = StateWithExtensionsMut::<TokenAccount>::unpack_uninitialized(&mut taker_token_data).unwrap();
+    state.base = TokenAccount {
+        mint: mint.pubkey(),
+        owner: taker.pubkey(),
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    state.init_extension::<ImmutableOwner>().unwrap();
+    state.init_extension::<TransferFeeAmount>().unwrap();
+    program_test.add_account(
+        taker_token_account,
+        Account {
+            lamports: Rent::default().minimum_balance(token_account_len),
+            owner: spl_token_2022::id(),
+            data: taker_token_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    // mint the NFT to the seller
+    let rent = Rent::default();
+    let mint_len = ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]);
+    let seller_token_len = ExtensionType::get_account_len::<TokenAccount>(&[ExtensionType::TransferFeeAmount]);
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+            system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(mint_len),
+                mint_len as u64,
+                &spl_token_2022::id(),
+            ),
+            spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                None,
+                None,
+                100,
+                maximum_fee
+            ).unwrap(),
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &minter.pubkey(),
+                None,
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(seller_token_len),
+                seller_token_len as u64,
+                &spl_token_2022::id(),
+            ),
+            spl_token_2022::instruction::initialize_account(
+                &spl_token_2022::id(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &seller.pubkey()
+            ).unwrap(),
+            spl_token_2022::instruction::mint_to(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &minter, &mint, &token_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    Token2022Nft {
+        client,
+        payer,
+        recent_blockhash,
+        program_id,
+        seller,
+        taker,
+        token_account,
+        mint,
+        escrow_account,
+        platform,
+        treasury,
+        taker_token_account,
+    }
+}
+
+// a Token-2022 NFT whose mint charges a transfer fee capped at nothing is listed
+// and bought like any other, the token program of the listing is recorded and checked
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_2022_listing() {
+    let Token2022Nft {
+        mut client,
+        payer,
+        recent_blockhash,
+        program_id,
+        seller,
+        taker,
+        token_account,
+        mint,
+        escrow_account,
+        platform,
+        treasury,
+        taker_token_account,
+    } = mint_token_2022_nft(0).await;
+    let (escrow_authority, _nonce) = Pubkey::find_program_address(&[b"escrow"], &program_id);
+
+    // listing with the SPL Token program fails, the mint belongs to Token-2022
+    let list = || instruction::list_token_delegated(
+        &program_id,
+        &seller.pubkey(),
+        &token_account.pubkey(),
+        &mint.pubkey(),
+        &escrow_account.pubkey(),
+        &platform,
+        PRICE
+    ).unwrap();
+    let mut transaction = Transaction::new_with_payer(&[list()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::IncorrectProgramId)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::with_token_program(list(), &spl_token_2022::id())],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    let escrow = Escrow::unpack(&escrow.data).unwrap();
+    assert_eq!(escrow.listing_mode, ListingMode::Delegate);
+    assert_eq!(escrow.token_program, TokenProgram::Token2022);
+    let listed_token = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    let listed_token = StateWithExtensions::<TokenAccount>::unpack(&listed_token.data).unwrap().base;
+    assert_eq!(listed_token.delegate, COption::Some(escrow_authority));
+
+    // the fee on a single token rounds to the capped nothing, the taker gets it whole
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::with_token_program(
+                instruction::exchange_with_options(
+                    &program_id,
+                    &taker.pubkey(),
+                    &token_account.pubkey(),
+                    &seller.pubkey(),
+                    &mint.pubkey(),
+                    &escrow_account.pubkey(),
+                    &platform,
+                    &treasury,
+                    &ExchangeOptionalAccounts {
+                        buyer_token_account: Some(taker_token_account),
+                        ..ExchangeOptionalAccounts::default()
+                    },
+                    &[],
+                    1
+                ).unwrap(),
+                &spl_token_2022::id()
+            ),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let bought_token = client.get_account(taker_token_account).await.unwrap().unwrap();
+    assert_eq!(StateWithExtensions::<TokenAccount>::unpack(&bought_token.data).unwrap().base.amount, 1);
+    let sold_token = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    assert_eq!(StateWithExtensions::<TokenAccount>::unpack(&sold_token.data).unwrap().base.amount, 0);
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    assert!(!Escrow::unpack_unchecked(&escrow.data).unwrap().is_initialized);
+}
+
+// the fee a mint charges on the transfer of a single token is the whole token, such
+// an NFT is only listed in custody, whose sale hands over the token account
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_2022_transfer_fee_listing() {
+    let Token2022Nft {
+        mut client,
+        payer,
+        recent_blockhash,
+        program_id,
+        seller,
+        taker,
+        token_account,
+        mint,
+        escrow_account,
+        platform,
+        treasury,
+        taker_token_account: _,
+    } = mint_token_2022_nft(1).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::with_token_program(
+            instruction::list_token_delegated(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform,
+                PRICE
+            ).unwrap(),
+            &spl_token_2022::id()
+        )],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EscrowError::TransferFeeWithholdsToken as u32),
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::with_token_program(
+            instruction::list_token(
+                &program_id,
+                &seller.pubkey(),
+                &token_account.pubkey(),
+                &mint.pubkey(),
+                &escrow_account.pubkey(),
+                &platform,
+                PRICE
+            ).unwrap(),
+            &spl_token_2022::id()
+        )],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let seller_lamports = client.get_balance(seller.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::with_token_program(
+                instruction::exchange(
+                    &program_id,
+                    &taker.pubkey(),
+                    &token_account.pubkey(),
+                    &seller.pubkey(),
+                    &mint.pubkey(),
+                    &escrow_account.pubkey(),
+                    &platform,
+                    &treasury,
+                    &[],
+                    1
+                ).unwrap(),
+                &spl_token_2022::id()
+            ),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    // the taker owns the whole token and the seller is paid the price minus the
+    // platform fee of 250 bps
+    let bought_token = client.get_account(token_account.pubkey()).await.unwrap().unwrap();
+    let bought_token = StateWithExtensions::<TokenAccount>::unpack(&bought_token.data).unwrap().base;
+    assert_eq!(bought_token.owner, taker.pubkey());
+    assert_eq!(bought_token.amount, 1);
+    assert_eq!(client.get_balance(seller.pubkey()).await.unwrap(), seller_lamports + 97_500);
+    let escrow = client.get_account(escrow_account.pubkey()).await.unwrap().unwrap();
+    assert!(!Escrow::unpack_unchecked(&escrow.data).unwrap().is_initialized);
+}
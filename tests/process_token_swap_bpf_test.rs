@@ -0,0 +1,530 @@
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{instruction, pda, processor, state::{TokenSwap, VaultAccount}};
+#[cfg(feature = "test-bpf")]
+use solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{BanksClient, ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, hash::Hash, signature::{Keypair, Signer}, transaction::Transaction};
+#[cfg(feature = "test-bpf")]
+use std::str::FromStr;
+
+// accounts and mints shared by the token swap tests
+#[cfg(feature = "test-bpf")]
+struct SwapSetup {
+    client: BanksClient,
+    payer: Keypair,
+    recent_blockhash: Hash,
+    program_id: Pubkey,
+    seller: Keypair,
+    taker: Keypair,
+    sellers_offered_account: Pubkey,
+    sellers_receive_account: Pubkey,
+    takers_sending_account: Pubkey,
+    takers_receiving_account: Pubkey,
+    swap_account: Keypair,
+    vault: Pubkey,
+    platform: Pubkey,
+}
+
+// the admin's platform account, which pauses token swap fills
+#[cfg(feature = "test-bpf")]
+fn add_platform_account(program_test: &mut ProgramTest, program_id: Pubkey) -> Pubkey {
+    let platform = Pubkey::new_unique();
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: Pubkey::new_unique(),
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+    platform
+}
+
+// creates the offered (A) and expected (B) mints, funds the seller with
+// 100 A and the taker with 100 B, and lists 100 A for 50 B
+#[cfg(feature = "test-bpf")]
+async fn list_token_swap() -> SwapSetup {
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    // run the token program natively alongside the escrow program
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+    let platform = add_platform_account(&mut program_test, program_id);
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    let mint_authority = Keypair::new();
+    let offered_mint = Keypair::new();
+    let expected_mint = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let sellers_offered_account = Keypair::new();
+    let sellers_receive_account = Keypair::new();
+    let takers_sending_account = Keypair::new();
+    let takers_receiving_account = Keypair::new();
+
+    let rent = Rent::default();
+    let mut instructions = vec![
+        system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+        system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+    ];
+    for mint in [&offered_mint, &expected_mint] {
+        instructions.push(system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ));
+        instructions.push(spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &mint_authority.pubkey(),
+            None,
+            0
+        ).unwrap());
+    }
+    for (account, mint, owner) in [
+        (&sellers_offered_account, &offered_mint, &seller),
+        (&sellers_receive_account, &expected_mint, &seller),
+        (&takers_sending_account, &expected_mint, &taker),
+        (&takers_receiving_account, &offered_mint, &taker),
+    ] {
+        instructions.push(system_instruction::create_account(
+            &payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ));
+        instructions.push(spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account.pubkey(),
+            &mint.pubkey(),
+            &owner.pubkey()
+        ).unwrap());
+    }
+    for (account, mint) in [
+        (&sellers_offered_account, &offered_mint),
+        (&takers_sending_account, &expected_mint),
+    ] {
+        instructions.push(spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &account.pubkey(),
+            &mint_authority.pubkey(),
+            &[],
+            100
+        ).unwrap());
+    }
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &vec![
+            &payer, &mint_authority, &offered_mint, &expected_mint,
+            &sellers_offered_account, &sellers_receive_account,
+            &takers_sending_account, &takers_receiving_account,
+        ],
+        recent_blockhash
+    );
+    client.process_transaction(transaction).await.unwrap();
+
+    let swap_account = Keypair::new();
+    let (vault, _vault_bump) = pda::find_vault_address(&program_id, &swap_account.pubkey());
+
+    // list 100 of the offered token for 50 of the expected token
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token_swap(
+                &program_id,
+                &seller.pubkey(),
+                &sellers_offered_account.pubkey(),
+                &sellers_receive_account.pubkey(),
+                &offered_mint.pubkey(),
+                &swap_account.pubkey(),
+                &platform,
+                100,
+                50
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &seller, &swap_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    SwapSetup {
+        client,
+        payer,
+        recent_blockhash,
+        program_id,
+        seller,
+        taker,
+        sellers_offered_account: sellers_offered_account.pubkey(),
+        sellers_receive_account: sellers_receive_account.pubkey(),
+        takers_sending_account: takers_sending_account.pubkey(),
+        takers_receiving_account: takers_receiving_account.pubkey(),
+        swap_account,
+        vault,
+        platform,
+    }
+}
+
+#[cfg(feature = "test-bpf")]
+fn exchange_instruction(setup: &SwapSetup, amount: u64, expected_amount: u64) -> Instruction {
+    instruction::exchange_token_swap(
+        &setup.program_id,
+        &setup.taker.pubkey(),
+        &setup.takers_sending_account,
+        &setup.takers_receiving_account,
+        &setup.sellers_receive_account,
+        &setup.seller.pubkey(),
+        &setup.swap_account.pubkey(),
+        &setup.platform,
+        amount,
+        expected_amount
+    ).unwrap()
+}
+
+#[cfg(feature = "test-bpf")]
+async fn token_balance(client: &mut BanksClient, account: Pubkey) -> u64 {
+    let account = client.get_account(account).await
+        .expect("Unable to find token account")
+        .expect("Unable to find token account");
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_swap_partial_fills() {
+    let mut setup = list_token_swap().await;
+
+    let swap_account = setup.client.get_account(setup.swap_account.pubkey()).await
+        .expect("Unable to find swap account")
+        .expect("Unable to find swap account");
+    let swap_state = TokenSwap::unpack(&swap_account.data).unwrap();
+    assert_eq!(swap_state.offered_amount, 100);
+    assert_eq!(swap_state.expected_amount, 50);
+    assert_eq!(token_balance(&mut setup.client, setup.vault).await, 100);
+    assert_eq!(token_balance(&mut setup.client, setup.sellers_offered_account).await, 0);
+
+    // taking 40 at a price the taker did not expect is refused
+    let mut transaction = Transaction::new_with_payer(
+        &[exchange_instruction(&setup, 40, 19)],
+        Some(&setup.payer.pubkey())
+    );
+    transaction.sign(&[&setup.payer, &setup.taker], setup.recent_blockhash);
+    assert!(setup.client.process_transaction(transaction).await.is_err());
+
+    // take 40 of the offered token for 20 of the expected token
+    let mut transaction = Transaction::new_with_payer(
+        &[exchange_instruction(&setup, 40, 20)],
+        Some(&setup.payer.pubkey())
+    );
+    transaction.sign(&[&setup.payer, &setup.taker], setup.recent_blockhash);
+    setup.client.process_transaction(transaction).await.unwrap();
+
+    let swap_account = setup.client.get_account(setup.swap_account.pubkey()).await
+        .expect("Unable to find swap account")
+        .expect("Unable to find swap account");
+    let swap_state = TokenSwap::unpack(&swap_account.data).unwrap();
+    assert_eq!(swap_state.offered_amount, 60);
+    assert_eq!(swap_state.expected_amount, 30);
+    assert_eq!(token_balance(&mut setup.client, setup.takers_receiving_account).await, 40);
+    assert_eq!(token_balance(&mut setup.client, setup.sellers_receive_account).await, 20);
+
+    // take the rest, which closes the vault and the swap
+    let mut transaction = Transaction::new_with_payer(
+        &[exchange_instruction(&setup, 60, 30)],
+        Some(&setup.payer.pubkey())
+    );
+    transaction.sign(&[&setup.payer, &setup.taker], setup.recent_blockhash);
+    setup.client.process_transaction(transaction).await.unwrap();
+
+    let swap_account = setup.client.get_account(setup.swap_account.pubkey()).await
+        .expect("Unable to find swap account")
+        .expect("Unable to find swap account");
+    let swap_state = TokenSwap::unpack_unchecked(&swap_account.data).unwrap();
+    assert!(!swap_state.is_initialized);
+    assert_eq!(token_balance(&mut setup.client, setup.takers_receiving_account).await, 100);
+    assert_eq!(token_balance(&mut setup.client, setup.takers_sending_account).await, 50);
+    assert_eq!(token_balance(&mut setup.client, setup.sellers_receive_account).await, 50);
+    assert!(setup.client.get_account(setup.vault).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_swap_cancel() {
+    let mut setup = list_token_swap().await;
+
+    // take a part first, the seller gets back the rest
+    let mut transaction = Transaction::new_with_payer(
+        &[exchange_instruction(&setup, 30, 15)],
+        Some(&setup.payer.pubkey())
+    );
+    transaction.sign(&[&setup.payer, &setup.taker], setup.recent_blockhash);
+    setup.client.process_transaction(transaction).await.unwrap();
+
+    // only the seller can cancel
+    let not_the_seller = instruction::cancel_token_swap(
+        &setup.program_id,
+        &setup.taker.pubkey(),
+        &setup.sellers_offered_account,
+        &setup.swap_account.pubkey()
+    ).unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[not_the_seller],
+        Some(&setup.payer.pubkey())
+    );
+    transaction.sign(&[&setup.payer, &setup.taker], setup.recent_blockhash);
+    assert!(setup.client.process_transaction(transaction).await.is_err());
+
+    let cancel = instruction::cancel_token_swap(
+        &setup.program_id,
+        &setup.seller.pubkey(),
+        &setup.sellers_offered_account,
+        &setup.swap_account.pubkey()
+    ).unwrap();
+    let mut transaction = Transaction::new_with_payer(&[cancel], Some(&setup.payer.pubkey()));
+    transaction.sign(&[&setup.payer, &setup.seller], setup.recent_blockhash);
+    setup.client.process_transaction(transaction).await.unwrap();
+
+    let swap_account = setup.client.get_account(setup.swap_account.pubkey()).await
+        .expect("Unable to find swap account")
+        .expect("Unable to find swap account");
+    let swap_state = TokenSwap::unpack_unchecked(&swap_account.data).unwrap();
+    assert!(!swap_state.is_initialized);
+    assert_eq!(token_balance(&mut setup.client, setup.sellers_offered_account).await, 70);
+    assert_eq!(token_balance(&mut setup.client, setup.sellers_receive_account).await, 15);
+    assert!(setup.client.get_account(setup.vault).await.unwrap().is_none());
+}
+
+// lists 100 of a token for 50_000 lamports of wrapped SOL, paid out as native SOL
+// or to a wrapped SOL account of the seller, and fills it with the taker's SOL
+#[cfg(feature = "test-bpf")]
+async fn exchange_token_swap_with_sol(native_payout: bool) {
+    let program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        program_id,
+        processor!(processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+    let platform = add_platform_account(&mut program_test, program_id);
+    let mut native_mint_data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: spl_token::native_mint::DECIMALS,
+        is_initialized: true,
+        ..spl_token::state::Mint::default()
+    }
+    .pack_into_slice(&mut native_mint_data);
+    program_test.add_account(
+        spl_token::native_mint::id(),
+        Account {
+            lamports: Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+            owner: spl_token::id(),
+            data: native_mint_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    let mint_authority = Keypair::new();
+    let offered_mint = Keypair::new();
+    let seller = Keypair::new();
+    let taker = Keypair::new();
+    let sellers_offered_account = Keypair::new();
+    let sellers_wsol_account = Keypair::new();
+    let takers_receiving_account = Keypair::new();
+    let temporary_wsol_account = Keypair::new();
+    let swap_account = Keypair::new();
+
+    let rent = Rent::default();
+    let token_account_rent = rent.minimum_balance(spl_token::state::Account::LEN);
+    let mut instructions = vec![
+        system_instruction::transfer(&payer.pubkey(), &seller.pubkey(), 1_000_000_000),
+        system_instruction::transfer(&payer.pubkey(), &taker.pubkey(), 1_000_000_000),
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &offered_mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &offered_mint.pubkey(),
+            &mint_authority.pubkey(),
+            None,
+            0
+        ).unwrap(),
+    ];
+    for (account, mint, owner) in [
+        (&sellers_offered_account, offered_mint.pubkey(), &seller),
+        (&sellers_wsol_account, spl_token::native_mint::id(), &seller),
+        (&takers_receiving_account, offered_mint.pubkey(), &taker),
+    ] {
+        instructions.push(system_instruction::create_account(
+            &payer.pubkey(),
+            &account.pubkey(),
+            token_account_rent,
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ));
+        instructions.push(spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account.pubkey(),
+            &mint,
+            &owner.pubkey()
+        ).unwrap());
+    }
+    instructions.push(spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &offered_mint.pubkey(),
+        &sellers_offered_account.pubkey(),
+        &mint_authority.pubkey(),
+        &[],
+        100
+    ).unwrap());
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &vec![
+            &payer, &mint_authority, &offered_mint, &sellers_offered_account,
+            &sellers_wsol_account, &takers_receiving_account,
+        ],
+        recent_blockhash
+    );
+    client.process_transaction(transaction).await.unwrap();
+
+    let list = if native_payout {
+        instruction::list_token_swap_for_sol(
+            &program_id,
+            &seller.pubkey(),
+            &sellers_offered_account.pubkey(),
+            &offered_mint.pubkey(),
+            &swap_account.pubkey(),
+            &platform,
+            100,
+            50_000
+        ).unwrap()
+    } else {
+        instruction::list_token_swap(
+            &program_id,
+            &seller.pubkey(),
+            &sellers_offered_account.pubkey(),
+            &sellers_wsol_account.pubkey(),
+            &offered_mint.pubkey(),
+            &swap_account.pubkey(),
+            &platform,
+            100,
+            50_000
+        ).unwrap()
+    };
+    let mut transaction = Transaction::new_with_payer(&[list], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &seller, &swap_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let seller_receive_account = if native_payout {
+        seller.pubkey()
+    } else {
+        sellers_wsol_account.pubkey()
+    };
+    let sellers_lamports = client.get_balance(seller.pubkey()).await.unwrap();
+    let takers_lamports = client.get_balance(taker.pubkey()).await.unwrap();
+
+    // the taker wraps its SOL in a temporary account created in the same transaction
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &taker.pubkey(),
+                &temporary_wsol_account.pubkey(),
+                token_account_rent,
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &temporary_wsol_account.pubkey(),
+                &spl_token::native_mint::id(),
+                &taker.pubkey()
+            ).unwrap(),
+            instruction::exchange_token_swap_with_sol(
+                &program_id,
+                &taker.pubkey(),
+                &temporary_wsol_account.pubkey(),
+                &takers_receiving_account.pubkey(),
+                &seller_receive_account,
+                &seller.pubkey(),
+                &swap_account.pubkey(),
+                &platform,
+                40,
+                20_000
+            ).unwrap(),
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker, &temporary_wsol_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(token_balance(&mut client, takers_receiving_account.pubkey()).await, 40);
+    assert!(client.get_account(temporary_wsol_account.pubkey()).await.unwrap().is_none());
+    assert_eq!(client.get_balance(taker.pubkey()).await.unwrap(), takers_lamports - 20_000);
+    if native_payout {
+        assert_eq!(client.get_balance(seller.pubkey()).await.unwrap(), sellers_lamports + 20_000);
+    } else {
+        assert_eq!(client.get_balance(seller.pubkey()).await.unwrap(), sellers_lamports);
+        assert_eq!(token_balance(&mut client, sellers_wsol_account.pubkey()).await, 20_000);
+    }
+
+    let swap_account = client.get_account(swap_account.pubkey()).await
+        .expect("Unable to find swap account")
+        .expect("Unable to find swap account");
+    let swap_state = TokenSwap::unpack(&swap_account.data).unwrap();
+    assert_eq!(swap_state.native_payout, native_payout);
+    assert_eq!(swap_state.offered_amount, 60);
+    assert_eq!(swap_state.expected_amount, 30_000);
+}
+
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_swap_with_sol_native_payout() {
+    exchange_token_swap_with_sol(true).await;
+}
+
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_token_swap_with_sol_wrapped_payout() {
+    exchange_token_swap_with_sol(false).await;
+}
@@ -0,0 +1,340 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use solana_program::{
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+        sysvar,
+    };
+    use solana_sdk::{
+        account::create_account_for_test,
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token::{native_mint, state::Account as TokenAccount};
+
+    use escrow_buy::{
+        error::EscrowError,
+        pda::find_vault_address,
+        processor::Processor,
+        state::{TokenSwap, VaultAccount},
+    };
+
+    fn escrow_program_id() -> Pubkey {
+        Pubkey::from_str("escrowprogram111111111111111111111111111111").unwrap()
+    }
+
+    fn escrow_authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], &escrow_program_id()).0
+    }
+
+    fn token_test_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> TestAccount {
+        let mut account = TestAccount {
+            owner: spl_token::id(),
+            data: vec![0; TokenAccount::get_packed_len()],
+            ..TestAccount::default()
+        };
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn platform_test_account(paused: bool) -> TestAccount {
+        let mut account = TestAccount::new(0, VaultAccount::get_packed_len(), &escrow_program_id());
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut account.data);
+        account
+    }
+
+    // lists 100 of a token for 50 lamports, paid to the seller's wallet unless the
+    // receive account is another one, on a platform account that is `paused` or not
+    fn list_for_sol(receive_to_seller: bool, paused: bool) -> Result<TokenSwap, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let seller_pubkey = Pubkey::new_unique();
+        let offered_token_pubkey = Pubkey::new_unique();
+        let offered_mint_pubkey = Pubkey::new_unique();
+        let swap_pubkey = Pubkey::new_unique();
+        let (vault_pubkey, _vault_bump_seed) = find_vault_address(&escrow_program_id, &swap_pubkey);
+        let receive_pubkey = if receive_to_seller { seller_pubkey } else { Pubkey::new_unique() };
+
+        let mut seller_account = TestAccount::default();
+        let mut offered_token_account = token_test_account(&offered_mint_pubkey, &seller_pubkey, 100);
+        let mut receive_account = TestAccount::default();
+        let mut offered_mint_account = TestAccount {
+            owner: spl_token::id(),
+            ..TestAccount::default()
+        };
+        // created by the system program, which is not run here
+        let mut swap_account = TestAccount::new(0, TokenSwap::get_packed_len(), &escrow_program_id);
+        let mut vault_account = TestAccount::default();
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut platform_account = platform_test_account(paused);
+
+        let mut accounts = [
+            (&seller_pubkey, true, &mut seller_account),
+            (&offered_token_pubkey, false, &mut offered_token_account),
+            (&receive_pubkey, false, &mut receive_account),
+            (&offered_mint_pubkey, false, &mut offered_mint_account),
+            (&swap_pubkey, true, &mut swap_account),
+            (&vault_pubkey, false, &mut vault_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&spl_token::id(), false, &mut token_program_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&Pubkey::new_unique(), false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_list_token_swap_for_sol(&accounts, 100, 50, &escrow_program_id)?;
+
+        let swap = TokenSwap::unpack(&accounts[4].data.borrow())?;
+        assert_eq!(swap.seller_pubkey, seller_pubkey);
+        assert_eq!(swap.seller_receive_pubkey, seller_pubkey);
+        Ok(swap)
+    }
+
+    #[test]
+    fn list_token_swap_for_sol_test() {
+        let swap = list_for_sol(true, false).unwrap();
+        assert_eq!(swap.expected_mint, native_mint::id());
+        assert_eq!(swap.offered_amount, 100);
+        assert_eq!(swap.expected_amount, 50);
+        assert!(swap.native_payout);
+        // along with the bump seed the fills and the cancellation sign with
+        assert_eq!(
+            swap.escrow_authority_bump_seed,
+            Pubkey::find_program_address(&[b"escrow"], &escrow_program_id()).1
+        );
+
+        // the payments of such a swap only go to the seller's wallet
+        assert_eq!(list_for_sol(false, false), Err(ProgramError::InvalidAccountData));
+
+        // and no swap is listed while the admin's platform account is paused
+        assert_eq!(list_for_sol(true, true), Err(EscrowError::MarketplacePaused.into()));
+    }
+
+    // takes 40 of a swap of 100 for 50 of `expected_mint`, paying through
+    // `sending_token` when `with_sol` is set
+    fn exchange(
+        expected_mint: &Pubkey,
+        native_payout: bool,
+        with_sol: bool,
+        sending_token: impl Fn(&Pubkey) -> TestAccount,
+    ) -> Result<TokenSwap, ProgramError> {
+        exchange_through(&spl_token::id(), false, expected_mint, native_payout, with_sol, sending_token)
+    }
+
+    // like `exchange`, passing `token_program` as the token program and a platform
+    // account that is `paused` or not
+    fn exchange_through(
+        token_program: &Pubkey,
+        paused: bool,
+        expected_mint: &Pubkey,
+        native_payout: bool,
+        with_sol: bool,
+        sending_token: impl Fn(&Pubkey) -> TestAccount,
+    ) -> Result<TokenSwap, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let taker_pubkey = Pubkey::new_unique();
+        let sending_pubkey = Pubkey::new_unique();
+        let receiving_pubkey = Pubkey::new_unique();
+        let seller_pubkey = Pubkey::new_unique();
+        let swap_pubkey = Pubkey::new_unique();
+        let (vault_pubkey, _vault_bump_seed) = find_vault_address(&escrow_program_id, &swap_pubkey);
+        let seller_receive_pubkey = if native_payout { seller_pubkey } else { Pubkey::new_unique() };
+        let platform_pubkey = Pubkey::new_unique();
+
+        let mut swap_account = TestAccount::new(0, TokenSwap::get_packed_len(), &escrow_program_id);
+        TokenSwap {
+            is_initialized: true,
+            seller_pubkey,
+            vault_pubkey,
+            seller_receive_pubkey,
+            offered_mint: Pubkey::new_unique(),
+            expected_mint: *expected_mint,
+            offered_amount: 100,
+            expected_amount: 50,
+            native_payout,
+            escrow_authority_bump_seed: 0,
+        }
+        .pack_into_slice(&mut swap_account.data);
+        let mut taker_account = TestAccount::default();
+        let mut sending_account = sending_token(&taker_pubkey);
+        let mut receiving_account = TestAccount::default();
+        let mut seller_receive_account = TestAccount::default();
+        let mut seller_account = TestAccount::default();
+        let mut vault_account = TestAccount::default();
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+        let mut platform_account = platform_test_account(paused);
+        let mut system_program_account = TestAccount::default();
+
+        let mut accounts = [
+            (&taker_pubkey, true, &mut taker_account),
+            (&sending_pubkey, false, &mut sending_account),
+            (&receiving_pubkey, false, &mut receiving_account),
+            (&seller_receive_pubkey, false, &mut seller_receive_account),
+            (&seller_pubkey, false, &mut seller_account),
+            (&swap_pubkey, false, &mut swap_account),
+            (&vault_pubkey, false, &mut vault_account),
+            (token_program, false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+            (&platform_pubkey, false, &mut platform_account),
+            (&system_program::id(), false, &mut system_program_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        if with_sol {
+            Processor::process_exchange_token_swap_with_sol(&accounts, 40, 20, &escrow_program_id)?;
+        } else {
+            Processor::process_token_swap_exchange(&accounts, 40, 20, &escrow_program_id)?;
+        }
+
+        let swap = TokenSwap::unpack(&accounts[5].data.borrow())?;
+        Ok(swap)
+    }
+
+    fn temporary_wsol(taker: &Pubkey) -> TestAccount {
+        token_test_account(&native_mint::id(), taker, 0)
+    }
+
+    #[test]
+    fn exchange_token_swap_with_sol_test() {
+        let swap = exchange(&native_mint::id(), true, true, temporary_wsol).unwrap();
+        assert_eq!(swap.offered_amount, 60);
+        assert_eq!(swap.expected_amount, 30);
+
+        let swap = exchange(&native_mint::id(), false, true, temporary_wsol).unwrap();
+        assert_eq!(swap.offered_amount, 60);
+        assert_eq!(swap.expected_amount, 30);
+
+        // a swap paid out in native SOL is not paid in wrapped SOL tokens
+        assert_eq!(
+            exchange(&native_mint::id(), true, false, temporary_wsol),
+            Err(ProgramError::InvalidAccountData)
+        );
+        exchange(&native_mint::id(), false, false, temporary_wsol).unwrap();
+    }
+
+    // the taker's SOL only pays for swaps expecting wrapped SOL, wrapped in a
+    // token account of the taker
+    #[test]
+    fn exchange_token_swap_with_sol_accounts_test() {
+        assert_eq!(
+            exchange(&Pubkey::new_unique(), false, true, temporary_wsol),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            exchange(&native_mint::id(), false, true, |_taker| {
+                temporary_wsol(&Pubkey::new_unique())
+            }),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            exchange(&native_mint::id(), false, true, |taker| {
+                token_test_account(&Pubkey::new_unique(), taker, 0)
+            }),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    // the escrow authority signs for the vault only through the SPL Token program
+    #[test]
+    fn exchange_token_swap_token_program_test() {
+        let fake_token_program = Pubkey::new_unique();
+        assert_eq!(
+            exchange_through(&fake_token_program, false, &Pubkey::new_unique(), false, false, |taker| {
+                token_test_account(&Pubkey::new_unique(), taker, 50)
+            }),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(
+            exchange_through(&fake_token_program, false, &native_mint::id(), false, true, temporary_wsol),
+            Err(ProgramError::IncorrectProgramId)
+        );
+    }
+
+    // pausing the admin's platform account stops token swap fills
+    #[test]
+    fn exchange_token_swap_paused_test() {
+        assert_eq!(
+            exchange_through(&spl_token::id(), true, &Pubkey::new_unique(), false, false, |taker| {
+                token_test_account(&Pubkey::new_unique(), taker, 50)
+            }),
+            Err(EscrowError::MarketplacePaused.into())
+        );
+        assert_eq!(
+            exchange_through(&spl_token::id(), true, &native_mint::id(), false, true, temporary_wsol),
+            Err(EscrowError::MarketplacePaused.into())
+        );
+    }
+
+    fn cancel(token_program: &Pubkey) -> Result<TokenSwap, ProgramError> {
+        let escrow_program_id = escrow_program_id();
+        let pda = escrow_authority();
+        let seller_pubkey = Pubkey::new_unique();
+        let refund_pubkey = Pubkey::new_unique();
+        let swap_pubkey = Pubkey::new_unique();
+        let (vault_pubkey, _vault_bump_seed) = find_vault_address(&escrow_program_id, &swap_pubkey);
+        let offered_mint = Pubkey::new_unique();
+
+        let mut swap_account = TestAccount::new(0, TokenSwap::get_packed_len(), &escrow_program_id);
+        TokenSwap {
+            is_initialized: true,
+            seller_pubkey,
+            vault_pubkey,
+            seller_receive_pubkey: Pubkey::new_unique(),
+            offered_mint,
+            expected_mint: Pubkey::new_unique(),
+            offered_amount: 100,
+            expected_amount: 50,
+            native_payout: false,
+            escrow_authority_bump_seed: 0,
+        }
+        .pack_into_slice(&mut swap_account.data);
+        let mut seller_account = TestAccount::default();
+        let mut refund_account = token_test_account(&offered_mint, &seller_pubkey, 0);
+        let mut vault_account = token_test_account(&offered_mint, &pda, 100);
+        let mut token_program_account = TestAccount::default();
+        let mut pda_account = TestAccount::default();
+
+        let mut accounts = [
+            (&seller_pubkey, true, &mut seller_account),
+            (&refund_pubkey, false, &mut refund_account),
+            (&swap_pubkey, false, &mut swap_account),
+            (&vault_pubkey, false, &mut vault_account),
+            (token_program, false, &mut token_program_account),
+            (&pda, false, &mut pda_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        Processor::process_cancel_token_swap(&accounts, &escrow_program_id)?;
+
+        let swap = TokenSwap::unpack_unchecked(&accounts[2].data.borrow())?;
+        Ok(swap)
+    }
+
+    #[test]
+    fn cancel_token_swap_token_program_test() {
+        assert!(!cancel(&spl_token::id()).unwrap().is_initialized);
+        assert_eq!(cancel(&Pubkey::new_unique()), Err(ProgramError::IncorrectProgramId));
+    }
+}
@@ -0,0 +1,381 @@
+#[cfg(feature = "test-bpf")]
+use borsh::BorshSerialize;
+#[cfg(feature = "test-bpf")]
+use escrow_buy::{
+    error::EscrowError,
+    events::{parse_logs, EscrowEvent, RoyaltyPayout},
+    instruction::{self, ExchangeOptionalAccounts},
+    pda,
+    price_feed::{self, PriceFeed, PRICE_ACCOUNT_LEN, PRICE_STATUS_TRADING},
+    processor,
+    state::{Escrow, UsdListing, VaultAccount},
+};
+#[cfg(feature = "test-bpf")]
+use metaplex_token_metadata::state::{Creator, Data, Key, Metadata, MAX_METADATA_LEN};
+#[cfg(feature = "test-bpf")]
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
+#[cfg(feature = "test-bpf")]
+use solana_program_test::{ProgramTest, processor};
+#[cfg(feature = "test-bpf")]
+use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError}};
+#[cfg(feature = "test-bpf")]
+use std::{str::FromStr, sync::Mutex, time::{SystemTime, UNIX_EPOCH}};
+
+// program-test writes the program logs of every transaction to the `log`
+// crate, this logger keeps them so the emitted events can be decoded
+#[cfg(feature = "test-bpf")]
+static PROGRAM_LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "test-bpf")]
+struct ProgramLogCapture;
+
+#[cfg(feature = "test-bpf")]
+impl log::Log for ProgramLogCapture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "solana_runtime::message_processor::stable_log"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            PROGRAM_LOGS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "test-bpf")]
+static PROGRAM_LOG_CAPTURE: ProgramLogCapture = ProgramLogCapture;
+
+// a listing priced in USD is paid at the lamport value of its price on the
+// feed, as long as that is within what the taker is willing to pay
+#[tokio::test]
+#[cfg(feature = "test-bpf")]
+async fn process_exchange_usd_success() {
+    // 5 millionths of a dollar
+    let usd_price: u64 = 5;
+    let escrow_program_id = Pubkey::from_str(
+        "escrowprogram111111111111111111111111111111"
+    ).unwrap();
+
+    let minter = Keypair::new();
+    let initers_key = Keypair::new();
+    let temp_seller_token_account = Keypair::new();
+    let mint_key = Keypair::new();
+    let escrow_account = Keypair::new();
+
+    let taker_keypair = Keypair::new();
+    let creators_pubkey = Pubkey::from_str(
+        "metadatacreatorL5LYvXwxBNSaVkinzjzvTt1j3XsQ"
+    ).unwrap();
+    let valhalla_pubkey = Pubkey::from_str(
+        "paXi61MzXmioYZL5LYvXwxBNSaVkinzjzvTt1j3XsQz"
+    ).unwrap();
+    let platform_account = Pubkey::new_unique();
+    let price_feed_key = price_feed::sol_usd_price_feed_id();
+
+    // installed before program-test sets up its own logger
+    log::set_logger(&PROGRAM_LOG_CAPTURE).unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "escrow_buy",
+        escrow_program_id,
+        processor!(processor::Processor::process)
+    );
+    program_test.add_program(
+        "spl_token",
+        spl_token::id(),
+        processor!(spl_token::processor::Processor::process)
+    );
+
+    // the seller pays for the escrow account and the usd listing account
+    program_test.add_account(
+        initers_key.pubkey(),
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        }
+    );
+
+    program_test.add_account(
+        taker_keypair.pubkey(),
+        Account {
+            lamports: 5616720,
+            ..Account::default()
+        }
+    );
+
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    for pubkey in [creators_pubkey, valhalla_pubkey] {
+        program_test.add_account(
+            pubkey,
+            Account {
+                lamports: rent_exempt_minimum,
+                ..Account::default()
+            }
+        );
+    }
+
+    let mut platform_data = vec![0; VaultAccount::LEN];
+    VaultAccount {
+        is_initialized: true,
+        treasury_account: valhalla_pubkey,
+        base_percentage: 250,
+        paused: false,
+        pass_collection: Pubkey::default(),
+        pass_fee_basis_points: 0,
+        referral_basis_points: 0,
+        escrow_royalties: false,
+    }
+    .pack_into_slice(&mut platform_data);
+    program_test.add_account(
+        platform_account,
+        Account {
+            lamports: Rent::default().minimum_balance(VaultAccount::LEN),
+            owner: escrow_program_id,
+            data: platform_data,
+            ..Account::default()
+        }
+    );
+
+    // SOL at $25.00, published when the test starts
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let mut price_feed_data = vec![0; PRICE_ACCOUNT_LEN];
+    PriceFeed {
+        exponent: -8,
+        timestamp: now,
+        price: 2_500_000_000,
+        confidence: 1_000_000,
+        status: PRICE_STATUS_TRADING,
+        publish_slot: 0,
+    }
+    .pack_into_slice(&mut price_feed_data);
+    program_test.add_account(
+        price_feed_key,
+        Account {
+            lamports: Rent::default().minimum_balance(PRICE_ACCOUNT_LEN),
+            owner: price_feed::oracle_program_id(),
+            data: price_feed_data,
+            ..Account::default()
+        }
+    );
+
+    let (metadata_key, _metadata_bump_seed) = pda::find_metadata_address(&mint_key.pubkey());
+    let mut metadata_data = Metadata {
+        key: Key::MetadataV1,
+        update_authority: minter.pubkey(),
+        mint: mint_key.pubkey(),
+        data: Data {
+            name: "Hello, world!".to_string(),
+            symbol: "Hello, world!".to_string(),
+            uri: "Hello, world!".to_string(),
+            seller_fee_basis_points: 4000,
+            creators: Some(vec![Creator {
+                address: creators_pubkey,
+                verified: true,
+                share: 70,
+            }]),
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+    }
+    .try_to_vec()
+    .unwrap();
+    metadata_data.resize(MAX_METADATA_LEN, 0);
+    program_test.add_account(
+        metadata_key,
+        Account {
+            lamports: Rent::default().minimum_balance(MAX_METADATA_LEN),
+            owner: metaplex_token_metadata::id(),
+            data: metadata_data,
+            ..Account::default()
+        }
+    );
+
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+
+    let rent = Rent::default();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint_key.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint_key.pubkey(),
+                &minter.pubkey(),
+                None,
+                0
+            ).unwrap(),
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &temp_seller_token_account.pubkey(),
+                &mint_key.pubkey(),
+                &initers_key.pubkey()
+            ).unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint_key.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &minter.pubkey(),
+                &[],
+                1
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(
+        &[&payer, &minter, &mint_key, &temp_seller_token_account],
+        recent_blockhash
+    );
+    client.process_transaction(transaction).await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::list_token_usd(
+                &escrow_program_id,
+                &initers_key.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &mint_key.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                &price_feed_key,
+                usd_price
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &initers_key, &escrow_account], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let escrow_account_state = Escrow::unpack(
+        &client.get_account(escrow_account.pubkey()).await.unwrap().unwrap().data
+    ).unwrap();
+    assert!(escrow_account_state.usd_priced);
+    assert_eq!(escrow_account_state.expected_amount, usd_price);
+
+    let (usd_listing_key, _usd_listing_bump_seed) =
+        pda::find_usd_listing_address(&escrow_program_id, &escrow_account.pubkey());
+    let usd_listing = UsdListing::unpack(
+        &client.get_account(usd_listing_key).await.unwrap().unwrap().data
+    ).unwrap();
+    assert_eq!(usd_listing.escrow, escrow_account.pubkey());
+    assert_eq!(usd_listing.price_feed, price_feed_key);
+
+    let initers_test_before = client.get_account(initers_key.pubkey()).await.unwrap().unwrap();
+
+    // $0.000005 at $25.00 is 200 lamports
+    let exchange_usd = |max_lamports| {
+        instruction::exchange_usd(
+            &escrow_program_id,
+            &taker_keypair.pubkey(),
+            &temp_seller_token_account.pubkey(),
+            &initers_key.pubkey(),
+            &mint_key.pubkey(),
+            &escrow_account.pubkey(),
+            &platform_account,
+            &valhalla_pubkey,
+            &price_feed_key,
+            &ExchangeOptionalAccounts::default(),
+            &[creators_pubkey],
+            1,
+            max_lamports
+        ).unwrap()
+    };
+
+    let mut transaction = Transaction::new_with_payer(&[exchange_usd(199)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &taker_keypair], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(EscrowError::PriceAboveMaximum as u32),
+        )
+    );
+
+    // a plain exchange cannot buy the listing at its USD price in lamports
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            instruction::exchange(
+                &escrow_program_id,
+                &taker_keypair.pubkey(),
+                &temp_seller_token_account.pubkey(),
+                &initers_key.pubkey(),
+                &mint_key.pubkey(),
+                &escrow_account.pubkey(),
+                &platform_account,
+                &valhalla_pubkey,
+                &[creators_pubkey],
+                1
+            ).unwrap()
+        ],
+        Some(&payer.pubkey())
+    );
+    transaction.sign(&[&payer, &taker_keypair], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)
+    );
+
+    let mut transaction = Transaction::new_with_payer(&[exchange_usd(200)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &taker_keypair], recent_blockhash);
+    client.process_transaction(transaction).await.unwrap();
+
+    let token_account_state = spl_token::state::Account::unpack(
+        &client.get_account(temp_seller_token_account.pubkey()).await.unwrap().unwrap().data
+    ).unwrap();
+    assert_eq!(token_account_state.owner, taker_keypair.pubkey());
+
+    // (4000 * 200) / 10000 = 80 of royalties, 70% of which is 56
+    // (250 * 200) / 10000 = 5 of platform fee
+    // 200 - 56 - 5 = 139 for the seller
+    let creators_test = client.get_account(creators_pubkey).await.unwrap().unwrap();
+    let valhalla_test = client.get_account(valhalla_pubkey).await.unwrap().unwrap();
+    let initers_test = client.get_account(initers_key.pubkey()).await.unwrap().unwrap();
+    let taker_test = client.get_account(taker_keypair.pubkey()).await.unwrap().unwrap();
+    assert_eq!(creators_test.lamports, rent_exempt_minimum + 56);
+    assert_eq!(valhalla_test.lamports, rent_exempt_minimum + 5);
+    assert_eq!(initers_test.lamports, initers_test_before.lamports + 139);
+    assert_eq!(taker_test.lamports, 5616720 - 200);
+
+    let events = parse_logs(&escrow_program_id, &PROGRAM_LOGS.lock().unwrap());
+    assert_eq!(
+        events,
+        vec![
+            EscrowEvent::UsdListed {
+                escrow: escrow_account.pubkey(),
+                seller: initers_key.pubkey(),
+                mint: mint_key.pubkey(),
+                token_account: temp_seller_token_account.pubkey(),
+                usd_price,
+                price_feed: price_feed_key,
+            },
+            EscrowEvent::Sold {
+                escrow: escrow_account.pubkey(),
+                seller: initers_key.pubkey(),
+                buyer: taker_keypair.pubkey(),
+                mint: mint_key.pubkey(),
+                price: 200,
+                royalties: vec![RoyaltyPayout {
+                    creator: creators_pubkey,
+                    amount: 56,
+                }],
+                treasury: valhalla_pubkey,
+                platform_fee: 5,
+                seller_proceeds: 139,
+            },
+        ]
+    );
+}
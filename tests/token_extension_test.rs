@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tests {
+    use solana_program::{
+        account_info::AccountInfo,
+        program_error::ProgramError,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+        sysvar,
+    };
+    use solana_sdk::{
+        account::create_account_for_test,
+        account::create_is_signer_account_infos,
+        account::Account as TestAccount,
+    };
+    use spl_token_2022::{
+        extension::{
+            confidential_transfer::ConfidentialTransferAccount, immutable_owner::ImmutableOwner,
+            transfer_fee::TransferFeeConfig, ExtensionType, StateWithExtensionsMut,
+        },
+        state::{Account, AccountState, Mint},
+    };
+
+    use escrow_buy::{
+        error::EscrowError,
+        processor::Processor,
+        state::{Escrow, ListingMode, TokenProgram, VaultAccount},
+        token,
+    };
+
+    fn token_2022_account(data: Vec<u8>) -> TestAccount {
+        TestAccount {
+            owner: spl_token_2022::id(),
+            data,
+            ..TestAccount::default()
+        }
+    }
+
+    // a Token-2022 token account holding one token, with `extension` initialized
+    fn extended_token_account<V: spl_token_2022::extension::Extension>(extension: ExtensionType) -> Vec<u8> {
+        let mut data = vec![0; ExtensionType::get_account_len::<Account>(&[extension])];
+        let mut state = StateWithExtensionsMut::<Account>::unpack_uninitialized(&mut data).unwrap();
+        state.base = Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 1,
+            state: AccountState::Initialized,
+            ..Account::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        state.init_extension::<V>().unwrap();
+        data
+    }
+
+    // a Token-2022 mint of a single token followed by the raw extension entries `tlv`
+    fn extended_mint(tlv: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Mint {
+            supply: 1,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut data[..Mint::LEN]);
+        data.push(1); // mint account type
+        data.extend_from_slice(tlv);
+        data
+    }
+
+    fn with_account_info<T>(account: &mut TestAccount, f: impl FnOnce(&AccountInfo) -> T) -> T {
+        let key = Pubkey::new_unique();
+        let mut accounts = [(&key, false, account)];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        f(&accounts[0])
+    }
+
+    #[test]
+    fn unpack_token_account_test() {
+        let mut account = token_2022_account(extended_token_account::<ImmutableOwner>(ExtensionType::ImmutableOwner));
+        assert_eq!(with_account_info(&mut account, token::unpack_token_account).unwrap().amount, 1);
+
+        // the balance of an account with confidential transfer state is not all visible
+        let mut account = token_2022_account(extended_token_account::<ConfidentialTransferAccount>(
+            ExtensionType::ConfidentialTransferAccount,
+        ));
+        assert_eq!(
+            with_account_info(&mut account, token::unpack_token_account),
+            Err(EscrowError::UnsupportedTokenExtension.into())
+        );
+    }
+
+    #[test]
+    fn unpack_mint_test() {
+        // mint close authority, unset
+        let mut tlv = vec![3, 0, 32, 0];
+        tlv.extend_from_slice(&[0; 32]);
+        let mut account = token_2022_account(extended_mint(&tlv));
+        assert_eq!(with_account_info(&mut account, token::unpack_mint).unwrap().supply, 1);
+
+        // non-transferable, unknown to the spl-token-2022 crate the program is built with
+        tlv.extend_from_slice(&[9, 0, 0, 0]);
+        let mut account = token_2022_account(extended_mint(&tlv));
+        assert_eq!(
+            with_account_info(&mut account, token::unpack_mint),
+            Err(EscrowError::UnsupportedTokenExtension.into())
+        );
+    }
+
+    // a Token-2022 mint of a single token whose transfer fee, from the next epoch on,
+    // is `basis_points` of at most `maximum_fee`
+    fn transfer_fee_mint(basis_points: u16, maximum_fee: u64) -> Vec<u8> {
+        let mut data = vec![0; ExtensionType::get_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        state.base = Mint {
+            supply: 1,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        let config = state.init_extension::<TransferFeeConfig>().unwrap();
+        config.newer_transfer_fee.transfer_fee_basis_points = basis_points.into();
+        config.newer_transfer_fee.maximum_fee = maximum_fee.into();
+        data
+    }
+
+    #[test]
+    fn transfer_fee_config_test() {
+        let data = transfer_fee_mint(100, 1);
+        let config = token::transfer_fee_config(&data).unwrap().unwrap();
+        assert_eq!(config.calculate_epoch_fee(0, 1), Some(1));
+        assert_eq!(config.calculate_epoch_fee(0, 0), Some(0));
+        assert_eq!(token::transfer_fee_config(&extended_mint(&[])).unwrap(), None);
+
+        // an entry running past the end of the account
+        assert_eq!(
+            token::transfer_fee_config(&extended_mint(&[1, 0, 108, 0])),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn has_immutable_owner_test() {
+        let mut account = token_2022_account(extended_token_account::<ImmutableOwner>(ExtensionType::ImmutableOwner));
+        assert_eq!(with_account_info(&mut account, token::has_immutable_owner), Ok(true));
+        let mut account = token_2022_account(extended_token_account::<ConfidentialTransferAccount>(
+            ExtensionType::ConfidentialTransferAccount,
+        ));
+        assert_eq!(with_account_info(&mut account, token::has_immutable_owner), Ok(false));
+    }
+
+    // the owner of an associated token account of Token-2022 cannot be handed over
+    // to the escrow authority, as a custody listing would
+    #[test]
+    fn list_immutable_owner_test() {
+        let program_id = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let mut token_data = extended_token_account::<ImmutableOwner>(ExtensionType::ImmutableOwner);
+        let mut state = StateWithExtensionsMut::<Account>::unpack(&mut token_data).unwrap();
+        state.base.mint = mint;
+        state.base.owner = seller;
+        state.pack_base();
+
+        let mut seller_account = TestAccount::default();
+        let mut token_account = token_2022_account(token_data);
+        let mut mint_data = vec![0; Mint::LEN];
+        Mint {
+            supply: 1,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut mint_data);
+        let mut mint_account = token_2022_account(mint_data);
+        let mut escrow_account = TestAccount::new(
+            Rent::default().minimum_balance(Escrow::LEN),
+            Escrow::LEN,
+            &program_id,
+        );
+        let mut rent_account = create_account_for_test(&Rent::default());
+        let mut token_program_account = TestAccount::default();
+        let mut system_program_account = TestAccount::default();
+        let mut platform_account = TestAccount::new(0, VaultAccount::LEN, &program_id);
+        VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new_unique(),
+            base_percentage: 250,
+            paused: false,
+            pass_collection: Pubkey::default(),
+            pass_fee_basis_points: 0,
+            referral_basis_points: 0,
+            escrow_royalties: false,
+        }
+        .pack_into_slice(&mut platform_account.data);
+        let mut accounts = [
+            (&seller, true, &mut seller_account),
+            (&Pubkey::new_unique(), false, &mut token_account),
+            (&mint, false, &mut mint_account),
+            (&Pubkey::new_unique(), true, &mut escrow_account),
+            (&sysvar::rent::id(), false, &mut rent_account),
+            (&spl_token_2022::id(), false, &mut token_program_account),
+            (&system_program::id(), false, &mut system_program_account),
+            (&Pubkey::new_unique(), false, &mut platform_account),
+        ];
+        let accounts = create_is_signer_account_infos(&mut accounts);
+        assert_eq!(
+            Processor::process_init_escrow(&accounts, 1000, &program_id),
+            Err(EscrowError::ImmutableOwner.into())
+        );
+    }
+
+    // the token program passed to a listing's instructions is the one it was listed with
+    #[test]
+    fn cancel_token_program_test() {
+        let program_id = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let token = Pubkey::new_unique();
+        let escrow = Pubkey::new_unique();
+        let pda = Pubkey::find_program_address(&[b"escrow"], &program_id).0;
+
+        let mut escrow_account = TestAccount {
+            owner: program_id,
+            data: vec![0; Escrow::get_packed_len()],
+            ..TestAccount::default()
+        };
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: seller,
+            token_account_pubkey: token,
+            mint_key: Pubkey::new_unique(),
+            expected_amount: 1000,
+            marketplace: Pubkey::default(),
+            listing_mode: ListingMode::Delegate,
+            token_program: TokenProgram::Token2022,
+            usd_priced: false,
+            escrow_authority_bump_seed: 0,
+            metadata_bump_seed: 0,
+            proceeds_vault: false,
+        }
+        .pack_into_slice(&mut escrow_account.data);
+
+        let cancel = |token_program: &Pubkey, escrow_account: TestAccount| {
+            let mut escrow_account = escrow_account;
+            let mut seller_account = TestAccount::default();
+            let mut token_account = TestAccount::default();
+            let mut token_program_account = TestAccount::default();
+            let mut pda_account = TestAccount::default();
+            let mut accounts = [
+                (&seller, true, &mut seller_account),
+                (&token, false, &mut token_account),
+                (&escrow, false, &mut escrow_account),
+                (token_program, false, &mut token_program_account),
+                (&pda, false, &mut pda_account),
+            ];
+            let accounts = create_is_signer_account_infos(&mut accounts);
+            Processor::process_cancel(&accounts, &program_id)
+        };
+        assert_eq!(
+            cancel(&spl_token::id(), escrow_account.clone()),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        cancel(&spl_token_2022::id(), escrow_account).unwrap();
+    }
+}
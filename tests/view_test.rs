@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod tests {
+    use solana_program::{program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+    use escrow_buy::{
+        state::{
+            AccountType, Escrow, ListingMode, Marketplace, ReferrerAccount, RoyaltyVault, TokenProgram, TokenSwap,
+            VaultAccount,
+        },
+        view::{
+            self, AccountView, EscrowView, MarketplaceView, ReferrerAccountView, RoyaltyVaultView, TokenSwapView,
+            VaultAccountView,
+        },
+    };
+
+    fn escrow() -> Escrow {
+        Escrow {
+            is_initialized: true,
+            seller_pubkey: Pubkey::new(&[1; 32]),
+            token_account_pubkey: Pubkey::new(&[2; 32]),
+            mint_key: Pubkey::new(&[3; 32]),
+            expected_amount: 123,
+            marketplace: Pubkey::new(&[4; 32]),
+            listing_mode: ListingMode::Frozen,
+            token_program: TokenProgram::Token2022,
+            usd_priced: true,
+            escrow_authority_bump_seed: 254,
+            metadata_bump_seed: 253,
+            proceeds_vault: true,
+        }
+    }
+
+    // a view reads the fields where the packed account has them
+    #[test]
+    fn escrow_view_test() {
+        let mut data = vec![0; Escrow::LEN];
+        Escrow::pack(escrow(), &mut data).unwrap();
+
+        let view = view::load::<EscrowView>(&data).unwrap();
+        assert!(view.header().is_initialized());
+        assert_eq!(view.header().account_type, AccountType::Escrow as u8);
+        assert_eq!(view.seller_pubkey, escrow().seller_pubkey);
+        assert_eq!(view.token_account_pubkey, escrow().token_account_pubkey);
+        assert_eq!(view.mint_key, escrow().mint_key);
+        assert_eq!(view.expected_amount(), 123);
+        assert_eq!(view.marketplace, escrow().marketplace);
+        assert_eq!(view.listing_mode(), ListingMode::Frozen);
+        assert_eq!(view.token_program(), TokenProgram::Token2022);
+        assert!(view.usd_priced());
+        assert_eq!(view.escrow_authority_bump_seed, 254);
+        assert_eq!(view.metadata_bump_seed, 253);
+        assert!(view.proceeds_vault());
+
+        // and changes them in place
+        let view = view::load_mut::<EscrowView>(&mut data).unwrap();
+        view.set_expected_amount(456);
+        view.set_listing_mode(ListingMode::Custody);
+        view.set_proceeds_vault(false);
+        view.header_mut().set_initialized(false);
+        let escrow = Escrow::unpack_unchecked(&data).unwrap();
+        assert!(!escrow.is_initialized);
+        assert_eq!(escrow.expected_amount, 456);
+        assert_eq!(escrow.listing_mode, ListingMode::Custody);
+        assert!(!escrow.proceeds_vault);
+        assert_eq!(view::load::<EscrowView>(&data), Err(ProgramError::UninitializedAccount));
+        assert!(view::load_unchecked::<EscrowView>(&data).is_ok());
+    }
+
+    // the same bytes are refused as by unpacking the account
+    #[test]
+    fn escrow_view_check_test() {
+        let mut data = vec![0; Escrow::LEN];
+        Escrow::pack(escrow(), &mut data).unwrap();
+
+        for (offset, value) in [(0, AccountType::TokenSwap as u8), (1, 2), (2, 2), (144, 3), (145, 2), (146, 2), (149, 2)] {
+            let mut invalid = data.clone();
+            invalid[offset] = value;
+            assert!(Escrow::unpack_unchecked(&invalid).is_err());
+            assert_eq!(view::load_unchecked::<EscrowView>(&invalid), Err(ProgramError::InvalidAccountData));
+        }
+        assert_eq!(view::load::<EscrowView>(&data[..100]), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn view_init_test() {
+        let mut data = vec![7; ReferrerAccount::LEN];
+        // only zeroed or uninitialized accounts of the type are initialized
+        assert!(view::init::<ReferrerAccountView>(&mut data).is_err());
+
+        data.fill(0);
+        let earnings = view::init::<ReferrerAccountView>(&mut data).unwrap();
+        earnings.referrer = Pubkey::new(&[1; 32]);
+        earnings.set_total_earned(1234);
+        earnings.set_sales(5);
+        assert_eq!(
+            ReferrerAccount::unpack(&data).unwrap(),
+            ReferrerAccount {
+                is_initialized: true,
+                referrer: Pubkey::new(&[1; 32]),
+                total_earned: 1234,
+                sales: 5,
+            }
+        );
+        assert_eq!(
+            view::init::<ReferrerAccountView>(&mut data).map(|_| ()),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+    }
+
+    // fields carved out of the padding sit at odd offsets, which views read all the same
+    #[test]
+    fn platform_views_test() {
+        let platform = VaultAccount {
+            is_initialized: true,
+            treasury_account: Pubkey::new(&[1; 32]),
+            base_percentage: 250,
+            paused: true,
+            pass_collection: Pubkey::new(&[2; 32]),
+            pass_fee_basis_points: 100,
+            referral_basis_points: 2000,
+            escrow_royalties: false,
+        };
+        let mut data = vec![0; VaultAccount::LEN];
+        VaultAccount::pack(platform, &mut data).unwrap();
+        let view = view::load::<VaultAccountView>(&data).unwrap();
+        assert_eq!(view.treasury_account, platform.treasury_account);
+        assert_eq!(view.base_percentage(), 250);
+        assert!(view.paused());
+        assert_eq!(view.pass_collection, platform.pass_collection);
+        assert_eq!(view.pass_fee_basis_points(), 100);
+        assert_eq!(view.referral_basis_points(), 2000);
+        assert!(!view.escrow_royalties());
+        // a platform account is no marketplace
+        assert!(view::load::<MarketplaceView>(&data).is_err());
+
+        let mut name = [0; 32];
+        name[..5].copy_from_slice(b"store");
+        let marketplace = Marketplace {
+            is_initialized: true,
+            authority: Pubkey::new(&[3; 32]),
+            creator: Pubkey::new(&[4; 32]),
+            name,
+            platform,
+        };
+        let mut data = vec![0; Marketplace::LEN];
+        Marketplace::pack(marketplace, &mut data).unwrap();
+        let view = view::load_mut::<MarketplaceView>(&mut data).unwrap();
+        assert_eq!(view.authority, marketplace.authority);
+        assert_eq!(view.name, name);
+        assert_eq!(view.referral_basis_points(), 2000);
+        view.set_paused(false);
+        view.set_base_percentage(150);
+        view.set_escrow_royalties(true);
+        let unpacked = Marketplace::unpack(&data).unwrap();
+        assert!(!unpacked.platform.paused);
+        assert_eq!(unpacked.platform.base_percentage, 150);
+        assert!(unpacked.platform.escrow_royalties);
+    }
+
+    #[test]
+    fn token_swap_view_test() {
+        let swap = TokenSwap {
+            is_initialized: true,
+            seller_pubkey: Pubkey::new(&[1; 32]),
+            vault_pubkey: Pubkey::new(&[2; 32]),
+            seller_receive_pubkey: Pubkey::new(&[3; 32]),
+            offered_mint: Pubkey::new(&[4; 32]),
+            expected_mint: Pubkey::new(&[5; 32]),
+            offered_amount: 100,
+            expected_amount: 50,
+            native_payout: true,
+            escrow_authority_bump_seed: 0,
+        };
+        let mut data = vec![0; TokenSwap::LEN];
+        TokenSwap::pack(swap, &mut data).unwrap();
+        let view = view::load_mut::<TokenSwapView>(&mut data).unwrap();
+        assert_eq!(view.expected_mint, swap.expected_mint);
+        assert!(view.native_payout());
+        view.set_offered_amount(60);
+        view.set_expected_amount(30);
+        assert_eq!(
+            TokenSwap::unpack(&data).unwrap(),
+            TokenSwap {
+                offered_amount: 60,
+                expected_amount: 30,
+                ..swap
+            }
+        );
+
+        // and refuses the same native payout flags as unpacking it
+        data[184] = 2;
+        assert!(TokenSwap::unpack(&data).is_err());
+        assert_eq!(view::load::<TokenSwapView>(&data), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn royalty_vault_view_test() {
+        let mut data = vec![0; RoyaltyVault::LEN];
+        let vault = view::init::<RoyaltyVaultView>(&mut data).unwrap();
+        vault.creator = Pubkey::new(&[1; 32]);
+        vault.set_total_deposited(900);
+        vault.set_sales(2);
+        vault.set_total_claimed(400);
+        assert_eq!(
+            RoyaltyVault::unpack(&data).unwrap(),
+            RoyaltyVault {
+                is_initialized: true,
+                creator: Pubkey::new(&[1; 32]),
+                total_deposited: 900,
+                total_claimed: 400,
+                sales: 2,
+            }
+        );
+        // a royalty vault is no referrer account
+        assert!(view::load::<ReferrerAccountView>(&data).is_err());
+    }
+}